@@ -0,0 +1,67 @@
+// Bootloader for ota builds
+//
+// Runs first on every power-up, before the curer firmware:
+// 1. If new firmware was uploaded to the DFU slot and marked ready, swap it
+//    into the ACTIVE slot (the old firmware moves to DFU)
+// 2. If the last swap's firmware never reported "booted OK" (it crashed or
+//    hung before getting there), swap the old firmware back - rollback
+// 3. Start whatever is in the ACTIVE slot
+//
+// The swap is done page by page and can survive a power cut part-way
+// through. The watchdog runs the whole time and stays running when the
+// firmware starts - a firmware that hangs gets reset, and then rolled back.
+//
+// Flash it once with `cargo run --release` from this folder; after that,
+// firmware updates go over Wi-Fi (see the README).
+
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+
+use cortex_m_rt::{entry, exception};
+#[cfg(feature = "debug")]
+use defmt_rtt as _;
+use embassy_boot_rp::*;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_time::Duration;
+
+/// Total flash size of the Pico's W25Q16 chip (2 MB)
+const FLASH_SIZE: usize = 2 * 1024 * 1024;
+
+/// The firmware must feed the watchdog within this long or be reset
+const WATCHDOG_TIMEOUT_SECONDS: u64 = 8;
+
+#[entry]
+fn main() -> ! {
+    let p = embassy_rp::init(Default::default());
+
+    // Flash access that keeps the watchdog fed while swapping (erasing and
+    // copying ~900K takes a while)
+    let flash = WatchdogFlash::<FLASH_SIZE>::start(p.FLASH, p.WATCHDOG, Duration::from_secs(WATCHDOG_TIMEOUT_SECONDS));
+    let flash = Mutex::new(RefCell::new(flash));
+
+    // Slot addresses come from memory.x
+    let config = BootLoaderConfig::from_linkerfile_blocking(&flash);
+    let active_offset = config.active.offset();
+    let bootloader: BootLoader = BootLoader::prepare(config);
+
+    unsafe { bootloader.load(embassy_rp::flash::FLASH_BASE as u32 + active_offset) }
+}
+
+/// Any crash in the bootloader: start over
+#[no_mangle]
+#[cfg_attr(target_os = "none", link_section = ".HardFault.user")]
+unsafe extern "C" fn HardFault() {
+    cortex_m::peripheral::SCB::sys_reset();
+}
+
+#[exception]
+unsafe fn DefaultHandler(_: i16) -> ! {
+    cortex_m::peripheral::SCB::sys_reset();
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    cortex_m::asm::udf();
+}
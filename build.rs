@@ -0,0 +1,112 @@
+//! Picks the linker memory layout, and reads the optional curer.toml
+//!
+//! Normal builds use memory/standard.x (the firmware starts right after the
+//! second-stage boot code). ota builds use memory/ota.x, where the firmware
+//! lives in the bootloader's ACTIVE slot. Whichever it is gets copied to
+//! memory.x in the build output folder, where the linker finds it.
+//!
+//! curer.toml (next to Cargo.toml, see curer.example.toml) lets you change
+//! the common settings without touching any .rs file, and environment
+//! variables such as `CURER_DURATION_SECS=45` override it for one build
+//! (CI, or firmware for several stations from one tree). Each setting found
+//! is handed to the compiler as a `CURER_CONFIG_<CONSTANT>` environment
+//! variable, which config.rs picks up with `option_env!` - so the
+//! compile-time checks in config.rs still apply to whatever was set.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// The settings that can be changed without editing config.rs:
+/// (curer.toml section, key, environment variable, config.rs constant)
+const SETTINGS: &[(&str, &str, &str, &str)] = &[
+    ("cure", "duration_seconds", "CURER_DURATION_SECS", "CURING_DURATION_SECONDS"),
+    ("cure", "max_duration_seconds", "CURER_MAX_DURATION_SECS", "MAX_CURING_DURATION_SECONDS"),
+    ("cure", "auto_start_countdown_seconds", "CURER_AUTO_START_COUNTDOWN_SECS", "AUTO_START_COUNTDOWN_SECONDS"),
+    ("cure", "duty_cycle_on_seconds", "CURER_DUTY_ON_SECS", "DUTY_CYCLE_ON_SECONDS"),
+    ("cure", "duty_cycle_off_seconds", "CURER_DUTY_OFF_SECS", "DUTY_CYCLE_OFF_SECONDS"),
+    ("cure", "cooldown_ms", "CURER_COOLDOWN_MS", "CYCLE_COOLDOWN_MS"),
+    ("cure", "add_time_seconds", "CURER_ADD_TIME_SECS", "ADD_TIME_SECONDS"),
+    ("button", "debounce_ms", "CURER_DEBOUNCE_MS", "BUTTON_DEBOUNCE_MS"),
+    ("button", "double_tap_window_ms", "CURER_DOUBLE_TAP_MS", "DOUBLE_TAP_WINDOW_MS"),
+    ("button", "abort_hold_ms", "CURER_ABORT_HOLD_MS", "ABORT_HOLD_MS"),
+    ("beeps", "completion", "CURER_BEEPS", "COMPLETION_BEEPS"),
+    ("beeps", "duration_ms", "CURER_BEEP_MS", "BEEP_DURATION_MS"),
+    ("beeps", "pause_ms", "CURER_BEEP_PAUSE_MS", "BEEP_PAUSE_MS"),
+    ("beeps", "countdown", "CURER_COUNTDOWN_BEEPS", "COUNTDOWN_BEEPS"),
+    ("beeps", "minute_chirp_ms", "CURER_MINUTE_CHIRP_MS", "MINUTE_CHIRP_MS"),
+    ("beeps", "abort", "CURER_ABORT_BEEPS", "ABORT_BEEPS"),
+    ("beeps", "abort_duration_ms", "CURER_ABORT_BEEP_MS", "ABORT_BEEP_DURATION_MS"),
+    ("beeps", "tone_hz", "CURER_TONE_HZ", "BUZZER_TONE_HZ"),
+    ("beeps", "volume_percent", "CURER_VOLUME_PERCENT", "BUZZER_VOLUME_PERCENT"),
+    ("relay", "settle_ms", "CURER_RELAY_SETTLE_MS", "RELAY_SETTLE_TIME_MS"),
+];
+
+fn main() {
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    let layout = if env::var_os("CARGO_FEATURE_OTA").is_some() { "memory/ota.x" } else { "memory/standard.x" };
+    fs::copy(layout, out.join("memory.x")).unwrap();
+
+    println!("cargo:rustc-link-search={}", out.display());
+    println!("cargo:rerun-if-changed=memory/standard.x");
+    println!("cargo:rerun-if-changed=memory/ota.x");
+
+    // curer.toml first, then the environment variables on top
+    let mut settings = BTreeMap::new();
+    println!("cargo:rerun-if-changed=curer.toml");
+    if let Ok(toml) = fs::read_to_string("curer.toml") {
+        settings.extend(read_settings(&toml));
+    }
+    for &(_, _, variable, constant) in SETTINGS {
+        println!("cargo:rerun-if-env-changed={}", variable);
+        if let Ok(value) = env::var(variable) {
+            let Some(value) = whole_number(&value) else {
+                panic!("{}=`{}` should be a whole number", variable, value);
+            };
+            settings.insert(constant, value);
+        }
+    }
+    for (constant, value) in settings {
+        println!("cargo:rustc-env=CURER_CONFIG_{}={}", constant, value);
+    }
+}
+
+/// A whole number like `300` or `1_000`
+fn whole_number(value: &str) -> Option<u32> {
+    value.trim().replace('_', "").parse().ok()
+}
+
+/// The settings in curer.toml as (constant, value). Only what curer.toml
+/// needs of TOML is understood: `[section]` headers, `key = number` lines
+/// and `#` comments. Anything else stops the build with the line number.
+fn read_settings(toml: &str) -> Vec<(&'static str, u32)> {
+    let mut settings = vec![];
+    let mut section = "";
+    for (number, line) in toml.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+        let problem = |what: &str| -> ! { panic!("curer.toml line {}: {}", number + 1, what) };
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+            section = name.trim();
+            if !SETTINGS.iter().any(|&(s, ..)| s == section) {
+                problem(&format!("unknown section [{}]", section));
+            }
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            problem("expected `key = value`");
+        };
+        let (key, value) = (key.trim(), value.trim());
+        let Some(&(.., constant)) = SETTINGS.iter().find(|&&(s, k, ..)| s == section && k == key) else {
+            problem(&format!("unknown setting `{}` in [{}]", key, section));
+        };
+        let Some(value) = whole_number(value) else {
+            problem(&format!("`{}` should be a whole number", value));
+        };
+        settings.push((constant, value));
+    }
+    settings
+}
@@ -0,0 +1,364 @@
+// Cure Simulator (host only: `--features std`)
+//
+// Runs the cure state machine (cycle.rs) on a virtual clock and prints what
+// the relay, status LED and buzzer would do, and when - so a new timing or
+// cure profile can be checked in seconds instead of flashing the Pico and
+// sitting through a real cure. The timing comes from the firmware's own
+// config.rs, so edit that and run:
+//
+//   cd curing-core
+//   cargo run --features std --bin simulate -- tap@60 tap@90
+//
+// A cure starts at 0 s. Each argument is something that happens, at a time
+// in seconds:
+//
+//   tap@S         button tapped: pause, or resume a button pause
+//   hold@S        button held: abort
+//   lid-open@S    lid opened (pauses the cure)
+//   lid-close@S   lid closed (resumes it)
+//   switch-off@S  latching start switch turned OFF
+//   warm@S        chamber past the soft limit (pauses the cure)
+//   overheat@S    chamber too hot
+//   cool@S        chamber cooled down: resumes a heat pause, or (with the
+//                 button pressed) ends the fault
+//   start@S       start another cure (once idle) - to see the cooldown grow
+//
+// `--seconds N` overrides CURING_DURATION_SECONDS, `--profile
+// uv:30,rest:60,uv:30` overrides CURE_PROFILE, and `--duty 20:10` runs a
+// duty cycle (20 s on, 10 s off) for the curing time. Like the host tests this is
+// the standard build: one momentary button, an active buzzer, no heater or
+// thermistor. The feature-only parts of config.rs are left out.
+
+use std::collections::VecDeque;
+use std::env;
+use std::process;
+
+use curing_core::cure::{CurePhase, PhaseEnd, PhaseKind};
+use curing_core::cycle::{CureEvent, CureState, Pause, Plan};
+use curing_core::fault::FaultCode;
+use curing_core::heat::{HeatEstimate, HeatModel};
+use curing_core::signals::{AudioEvent, Note};
+
+#[allow(dead_code, unexpected_cfgs)]
+#[path = "../../../src/config.rs"]
+mod config;
+
+use config::*;
+
+/// Something scripted on the command line
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Input {
+    Start,
+    Tap,
+    Hold,
+    LidOpen,
+    LidClose,
+    SwitchOff,
+    Warm,
+    Overheat,
+    Cool,
+}
+
+/// What the status LED is doing (status_led.rs)
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum LedShows {
+    Off,
+    On,
+    Blinking,
+}
+
+/// Everything printed, in the order it happens
+struct Trace {
+    lines: Vec<(u64, String)>,
+    uv: bool,
+    led: LedShows,
+}
+
+impl Trace {
+    fn log(&mut self, at_ms: u64, what: impl Into<String>) {
+        self.lines.push((at_ms, what.into()));
+    }
+
+    /// Log the relay and LED if the new state changes them (like
+    /// `enter_state()` in main.rs)
+    fn outputs(&mut self, at_ms: u64, state: &CureState<'_>, heat: &mut HeatEstimate) {
+        if state.uv_on() != self.uv {
+            self.uv = state.uv_on();
+            if self.uv {
+                heat.uv_on(at_ms);
+            } else {
+                heat.uv_off(at_ms);
+            }
+            self.log(at_ms, if self.uv { "relay  UV ON" } else { "relay  UV OFF" });
+        }
+        let led = match state {
+            CureState::Curing { paused: Some(Pause::Button), .. } => LedShows::Blinking,
+            _ if state.uv_on() => LedShows::On,
+            _ => LedShows::Off,
+        };
+        if led != self.led {
+            self.led = led;
+            self.log(
+                at_ms,
+                match led {
+                    LedShows::Off => "led    off".to_string(),
+                    LedShows::On => "led    on".to_string(),
+                    LedShows::Blinking => format!("led    blinking ({} ms on, {} ms off)", PAUSE_BLINK_MS, PAUSE_BLINK_MS),
+                },
+            );
+        }
+    }
+
+    /// Log each beep of a signal that starts at `at_ms`
+    fn buzzer(&mut self, at_ms: u64, steps: impl Iterator<Item = Note>) {
+        let mut at = at_ms;
+        for note in steps {
+            if note.hz != 0 {
+                self.log(at, format!("buzzer {} Hz for {} ms", note.hz, note.ms));
+            }
+            at += note.ms;
+        }
+    }
+
+    fn print(mut self) {
+        self.lines.sort_by_key(|(at, _)| *at); // Stable: same-time lines keep their order
+        for (at_ms, what) in self.lines {
+            println!("{:>4}:{:02}.{:03}  {}", at_ms / 60_000, at_ms / 1000 % 60, at_ms % 1000, what);
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut seconds = CURING_DURATION_SECONDS;
+    let mut profile: Vec<CurePhase> = CURE_PROFILE.to_vec();
+    let mut duty = DUTY_CYCLE_ENABLED.then_some((DUTY_CYCLE_ON_SECONDS, DUTY_CYCLE_OFF_SECONDS));
+    let mut script = vec![(0, Input::Start)];
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seconds" => seconds = args.next().and_then(|s| s.parse().ok()).unwrap_or_else(|| usage("--seconds needs a number")),
+            "--profile" => profile = parse_profile(args.next().unwrap_or_else(|| usage("--profile needs phases"))),
+            "--duty" => duty = Some(parse_duty(args.next().unwrap_or_else(|| usage("--duty needs ON:OFF seconds")))),
+            "-h" | "--help" => usage(""),
+            _ => script.push(parse_input(arg)),
+        }
+    }
+    script.sort_by_key(|(at, _)| *at);
+    let mut script: VecDeque<(u64, Input)> = script.into();
+
+    let plan = match duty {
+        _ if !profile.is_empty() => Plan::Profile(&profile),
+        Some((on_seconds, off_seconds)) => Plan::DutyCycle { seconds, on_seconds, off_seconds },
+        None => Plan::Single(seconds),
+    };
+    let heat_model = HeatModel {
+        budget_seconds: HEAT_BUDGET_SECONDS,
+        cooling_ratio: PANEL_COOLING_RATIO,
+        max_extra_cooldown_seconds: MAX_EXTRA_COOLDOWN_SECONDS,
+    };
+    let mut heat = HeatEstimate::new(heat_model, 0);
+    let mut trace = Trace { lines: vec![], uv: false, led: LedShows::Off };
+    let mut state = CureState::Idle;
+    let mut now = 0;
+    let mut lid_open = false;
+    let mut uv_on_ms_before = 0;        // For each cure's UV time
+
+    loop {
+        // What happens next, and when
+        let (at, event) = match state {
+            CureState::Idle | CureState::Fault(_) => {
+                let wanted = if state == CureState::Idle { Input::Start } else { Input::Cool };
+                match next_input(&mut script, &mut trace, &mut lid_open, u64::MAX, |input| input == wanted) {
+                    Some((at, Input::Start)) => {
+                        uv_on_ms_before = heat.total_on_ms();
+                        (at, CureEvent::Start(plan))
+                    }
+                    Some((at, _)) => (at, CureEvent::Cleared),
+                    None => break,
+                }
+            }
+
+            // Never start the UV with the lid open
+            CureState::Arming { .. } if lid_open => {
+                trace.buzzer(now, AUDIO_PATTERNS.pattern(AudioEvent::LidOpen).steps());
+                (now, CureEvent::Refused)
+            }
+            CureState::Arming { .. } => (now, CureEvent::Armed),
+
+            // Running: the phase ends, unless something happens first
+            CureState::Curing { remaining, paused: None, .. } => {
+                match next_input(&mut script, &mut trace, &mut lid_open, remaining.end_ms(), |input| input != Input::Start) {
+                    Some((at, input)) => (at, cure_event(input)),
+                    None => (remaining.end_ms(), CureEvent::Tick),
+                }
+            }
+
+            // Paused - only the button (or the lid) can carry on
+            CureState::Curing { paused: Some(pause), .. } => {
+                let resumes = |input| match pause {
+                    Pause::Button => input == Input::Tap || input == Input::Hold,
+                    Pause::Lid => input == Input::LidClose,
+                    Pause::Heat => input == Input::Cool || input == Input::Hold,
+                };
+                if pause == Pause::Lid {
+                    let warning = AUDIO_PATTERNS.pattern(AudioEvent::LidWarning);
+                    trace.log(now, format!("buzzer {} Hz for {} ms, every {} ms until the lid closes", warning.hz, warning.on_ms, warning.on_ms + warning.off_ms));
+                }
+                match next_input(&mut script, &mut trace, &mut lid_open, u64::MAX, resumes) {
+                    Some((at, input)) => (at, cure_event(input)),
+                    None => {
+                        trace.log(now, "(still paused - nothing left in the script)");
+                        break;
+                    }
+                }
+            }
+
+            // UV off, wait for the relay, then signal and cool down
+            CureState::Finishing { end } => {
+                let settled = now + RELAY_SETTLE_TIME_MS;
+                trace.log(settled, format!("cure   {} ({} s of UV)", end.name(), (heat.total_on_ms() - uv_on_ms_before) / 1000));
+                match end {
+                    PhaseEnd::Fault(fault) => trace.log(settled, format!("buzzer fault alarm - led blinks code {} ({})", fault.code(), fault.name())),
+                    PhaseEnd::Aborted => trace.buzzer(settled, AUDIO_PATTERNS.pattern(AudioEvent::Abort).steps()),
+                    _ => trace.buzzer(settled, AUDIO_PATTERNS.pattern(AudioEvent::Complete).steps()),
+                }
+                let extra_seconds = heat.extra_cooldown_seconds();
+                if extra_seconds > 0 {
+                    trace.log(settled, format!("cool   UV panel is hot - cooldown extended by {} s", extra_seconds));
+                }
+                let ready = settled + CYCLE_COOLDOWN_MS + extra_seconds * 1000;
+                // Nothing is listening until then
+                next_input(&mut script, &mut trace, &mut lid_open, ready, |_| false);
+                (ready, CureEvent::Finished)
+            }
+        };
+
+        now = at;
+        let new = state.transition(event, now);
+        if new != state {
+            trace.log(now, format!("state  {}", describe(&new, now)));
+            trace.outputs(now, &new, &mut heat);
+            if matches!(state, CureState::Arming { .. }) && matches!(new, CureState::Curing { .. }) {
+                trace.buzzer(now, AUDIO_PATTERNS.pattern(AudioEvent::Start).steps());
+            }
+        }
+        state = new;
+    }
+
+    trace.print();
+}
+
+/// Take the next scripted input before `before_ms` that `wanted` accepts.
+/// Inputs on the way that nothing is waiting for are logged as ignored (the
+/// lid still opens and closes, though).
+fn next_input(
+    script: &mut VecDeque<(u64, Input)>,
+    trace: &mut Trace,
+    lid_open: &mut bool,
+    before_ms: u64,
+    wanted: impl Fn(Input) -> bool,
+) -> Option<(u64, Input)> {
+    while let Some(&(at, input)) = script.front() {
+        if at >= before_ms {
+            return None;
+        }
+        script.pop_front();
+        match input {
+            Input::LidOpen => *lid_open = true,
+            Input::LidClose => *lid_open = false,
+            _ => {}
+        }
+        if wanted(input) {
+            return Some((at, input));
+        }
+        trace.log(at, format!("input  {:?} ignored", input));
+    }
+    None
+}
+
+/// What a scripted input means mid-cure
+fn cure_event<'a>(input: Input) -> CureEvent<'a> {
+    match input {
+        Input::Tap => CureEvent::Tap,
+        Input::Hold => CureEvent::Abort,
+        Input::LidOpen => CureEvent::LidOpened,
+        Input::LidClose => CureEvent::LidClosed,
+        Input::SwitchOff => CureEvent::SwitchedOff,
+        Input::Warm => CureEvent::TooWarm,
+        Input::Overheat => CureEvent::Fault(FaultCode::OverTemp),
+        Input::Cool => CureEvent::CooledDown,
+        Input::Start => unreachable!("not waited for mid-cure"),
+    }
+}
+
+fn describe(state: &CureState<'_>, now_ms: u64) -> String {
+    match state {
+        CureState::Idle => "idle - ready for a start".to_string(),
+        CureState::Arming { .. } => "arming".to_string(),
+        CureState::Curing { plan, phase, remaining, paused: None } => {
+            let kind = if state.uv_on() { "UV" } else { "rest" };
+            let left = remaining.remaining_ms(now_ms);
+            format!("phase {}/{}: {}, {}.{:03} s to go", phase + 1, plan.phase_count(), kind, left / 1000, left % 1000)
+        }
+        CureState::Curing { remaining, paused: Some(pause), .. } => {
+            format!("paused ({:?}) with {} s to go", pause, remaining.remaining_seconds(now_ms))
+        }
+        CureState::Finishing { end } => format!("finishing ({})", end.name()),
+        CureState::Fault(fault) => format!("fault {} ({}) - waiting for the chamber to cool and a button press", fault.code(), fault.name()),
+    }
+}
+
+/// `tap@60`, `lid-open@12.5`...
+fn parse_input(arg: &str) -> (u64, Input) {
+    let (name, seconds) = arg.split_once('@').unwrap_or_else(|| usage(&format!("`{}` isn't an input@seconds", arg)));
+    let input = match name {
+        "start" => Input::Start,
+        "tap" => Input::Tap,
+        "hold" => Input::Hold,
+        "lid-open" => Input::LidOpen,
+        "lid-close" => Input::LidClose,
+        "switch-off" => Input::SwitchOff,
+        "warm" => Input::Warm,
+        "overheat" => Input::Overheat,
+        "cool" => Input::Cool,
+        _ => usage(&format!("unknown input `{}`", name)),
+    };
+    let seconds: f64 = seconds.parse().unwrap_or_else(|_| usage(&format!("`{}` isn't a time in seconds", seconds)));
+    ((seconds * 1000.0).round() as u64, input)
+}
+
+/// `uv:30,rest:60,uv:30`
+fn parse_profile(arg: &str) -> Vec<CurePhase> {
+    arg.split(',')
+        .map(|phase| {
+            let (kind, seconds) = phase.split_once(':').unwrap_or_else(|| usage(&format!("`{}` isn't uv:S or rest:S", phase)));
+            let kind = match kind {
+                "uv" => PhaseKind::UvOn,
+                "rest" => PhaseKind::Rest,
+                _ => usage(&format!("unknown phase `{}`", kind)),
+            };
+            let seconds = seconds.parse().unwrap_or_else(|_| usage(&format!("`{}` isn't a number of seconds", seconds)));
+            CurePhase { kind, seconds }
+        })
+        .collect()
+}
+
+fn parse_duty(arg: &str) -> (u64, u64) {
+    let seconds = |s: &str| match s.parse() {
+        Ok(0) | Err(_) => usage(&format!("`{}` isn't a number of seconds above 0", s)),
+        Ok(seconds) => seconds,
+    };
+    let (on, off) = arg.split_once(':').unwrap_or_else(|| usage(&format!("`{}` isn't ON:OFF", arg)));
+    (seconds(on), seconds(off))
+}
+
+fn usage(problem: &str) -> ! {
+    if !problem.is_empty() {
+        eprintln!("simulate: {}\n", problem);
+    }
+    eprintln!("usage: simulate [--seconds N] [--profile uv:S,rest:S,...] [--duty ON:OFF] [input@seconds...]");
+    eprintln!("inputs: start tap hold lid-open lid-close switch-off warm overheat cool");
+    process::exit(if problem.is_empty() { 0 } else { 2 });
+}
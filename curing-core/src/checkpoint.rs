@@ -0,0 +1,137 @@
+// Cure Checkpoints
+//
+// If the power goes mid-cure (a brownout, the plug knocked out) the print is
+// left half cured, and nothing says so. So while a cure runs, the firmware
+// saves a `Checkpoint` to flash every so often - which plan, which phase and
+// how much of it was left - and at the next power-on it offers to finish the
+// cure from there.
+//
+// Flash wears out, so checkpoints are rate limited (CHECKPOINT_INTERVAL_SECONDS
+// in config.rs): a power cut loses up to one interval, which the resumed cure
+// runs again - a little too much UV rather than too little.
+//
+// A checkpoint belongs to one cure. `cure_number` is how many cures had
+// finished before it started (the cure statistics): once that cure has
+// finished, or been given up at power-on, the count has moved on and the
+// checkpoint is out of date.
+
+use crate::cure::CurePhase;
+use crate::cycle::{CureState, Plan, ResumePoint};
+
+/// A cure's plan as it can be saved. A profile is always CURE_PROFILE from
+/// config.rs, so only the fact that it was the profile is kept.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SavedPlan {
+    /// `Plan::Single`
+    Single(u64),
+    /// `Plan::Profile` with the firmware's profile
+    Profile,
+    /// `Plan::DutyCycle`
+    DutyCycle { seconds: u64, on_seconds: u64, off_seconds: u64 },
+}
+
+/// How far a cure had got
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// What the cure was running
+    pub plan: SavedPlan,
+    /// Where to pick it up
+    pub from: ResumePoint,
+    /// Seconds the UV had been on so far (for the cure statistics)
+    pub uv_on_seconds: u64,
+    /// Cures finished before this one started
+    pub cure_number: u32,
+}
+
+/// Plan kinds as saved - 0 is never used, so blank bytes aren't a checkpoint
+const SINGLE: u8 = 1;
+const PROFILE: u8 = 2;
+const DUTY_CYCLE: u8 = 3;
+
+impl Checkpoint {
+    /// Length of a saved checkpoint in bytes
+    pub const SIZE: usize = 23;
+
+    /// Where a running cure has got to at `now_ms` (the time left rounded up
+    /// to the second) - None unless curing
+    pub fn of(state: &CureState<'_>, now_ms: u64, cure_number: u32) -> Option<Self> {
+        let CureState::Curing { plan, phase, remaining, .. } = *state else {
+            return None;
+        };
+        let remaining_seconds = remaining.remaining_ms(now_ms).div_ceil(1000);
+
+        // UV time: the UV phases already done, and this one's share
+        let uv_done: u64 = (0..phase).filter_map(|i| plan.phase(i)).filter(|p| p.is_uv()).map(|p| p.seconds).sum();
+        let uv_now = plan.phase(phase).filter(|p| p.is_uv()).map_or(0, |p| p.seconds.saturating_sub(remaining_seconds));
+
+        let saved = match plan {
+            Plan::Single(seconds) => SavedPlan::Single(seconds),
+            Plan::Profile(_) => SavedPlan::Profile,
+            Plan::DutyCycle { seconds, on_seconds, off_seconds } => SavedPlan::DutyCycle { seconds, on_seconds, off_seconds },
+        };
+        Some(Self {
+            plan: saved,
+            from: ResumePoint { phase, remaining_seconds },
+            uv_on_seconds: uv_done + uv_now,
+            cure_number,
+        })
+    }
+
+    /// The plan to resume, with `profile` for a profile - None if it has no
+    /// phase to resume in (e.g. CURE_PROFILE has changed since)
+    pub fn plan<'a>(&self, profile: &'a [CurePhase]) -> Option<Plan<'a>> {
+        let plan = match self.plan {
+            SavedPlan::Single(seconds) => Plan::Single(seconds),
+            SavedPlan::Profile => Plan::Profile(profile),
+            SavedPlan::DutyCycle { seconds, on_seconds, off_seconds } => Plan::DutyCycle { seconds, on_seconds, off_seconds },
+        };
+        plan.phase(self.from.phase).is_some().then_some(plan)
+    }
+
+    /// Seconds of `plan` still to run from the checkpoint, rest phases included
+    pub fn seconds_to_go(&self, plan: &Plan<'_>) -> u64 {
+        let later: u64 = (self.from.phase + 1..plan.phase_count()).filter_map(|i| plan.phase(i)).map(|p| p.seconds).sum();
+        self.from.remaining_seconds + later
+    }
+
+    /// The checkpoint as bytes for flash (cures are short enough that every
+    /// time fits in 32 bits, and every phase number in 16)
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0u8; Self::SIZE];
+        let (kind, seconds, on_seconds, off_seconds) = match self.plan {
+            SavedPlan::Single(seconds) => (SINGLE, seconds, 0, 0),
+            SavedPlan::Profile => (PROFILE, 0, 0, 0),
+            SavedPlan::DutyCycle { seconds, on_seconds, off_seconds } => (DUTY_CYCLE, seconds, on_seconds, off_seconds),
+        };
+        bytes[0] = kind;
+        bytes[1..5].copy_from_slice(&(seconds as u32).to_le_bytes());
+        bytes[5..7].copy_from_slice(&(on_seconds as u16).to_le_bytes());
+        bytes[7..9].copy_from_slice(&(off_seconds as u16).to_le_bytes());
+        bytes[9..11].copy_from_slice(&(self.from.phase as u16).to_le_bytes());
+        bytes[11..15].copy_from_slice(&(self.from.remaining_seconds as u32).to_le_bytes());
+        bytes[15..19].copy_from_slice(&(self.uv_on_seconds as u32).to_le_bytes());
+        bytes[19..23].copy_from_slice(&self.cure_number.to_le_bytes());
+        bytes
+    }
+
+    /// A checkpoint read back from flash - None if `bytes` isn't one
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < Self::SIZE {
+            return None;
+        }
+        let u16_at = |at: usize| u16::from_le_bytes([bytes[at], bytes[at + 1]]) as u64;
+        let u32_at = |at: usize| u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]);
+        let plan = match bytes[0] {
+            SINGLE => SavedPlan::Single(u32_at(1) as u64),
+            PROFILE => SavedPlan::Profile,
+            DUTY_CYCLE => SavedPlan::DutyCycle { seconds: u32_at(1) as u64, on_seconds: u16_at(5), off_seconds: u16_at(7) },
+            _ => return None,
+        };
+        Some(Self {
+            plan,
+            from: ResumePoint { phase: u16_at(9) as usize, remaining_seconds: u32_at(11) as u64 },
+            uv_on_seconds: u32_at(15) as u64,
+            cure_number: u32_at(19),
+        })
+    }
+}
@@ -0,0 +1,141 @@
+// Cure Phases and Timing
+//
+// A cure is a list of phases - normally one UV-on phase, or a multi-stage
+// CURE_PROFILE (config.rs) that alternates UV and rest. Each phase is timed
+// with a PhaseTimer, which fixes the end of the phase as an absolute time
+// when it starts: whatever the firmware does meanwhile (logging, beeping)
+// can't stretch it. Pausing remembers how much was left, and resuming sets
+// a new end that far in the future.
+
+use crate::fault::FaultCode;
+
+/// What the UV does during one phase of a cure profile
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PhaseKind {
+    /// Relay closed - UV LEDs on
+    UvOn,
+    /// Relay open - let the print (and panel) cool down
+    Rest,
+}
+
+/// One step of a multi-stage cure profile
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CurePhase {
+    pub kind: PhaseKind,
+    pub seconds: u64,
+}
+
+impl CurePhase {
+    /// Is the UV on during this phase?
+    pub fn is_uv(&self) -> bool {
+        matches!(self.kind, PhaseKind::UvOn)
+    }
+}
+
+/// Total planned length of a cure in seconds (all phases, UV and rest)
+pub fn planned_seconds(phases: &[CurePhase]) -> u64 {
+    phases.iter().map(|phase| phase.seconds).sum()
+}
+
+/// How a cure phase (and so the cure) ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PhaseEnd {
+    /// Ran for its full time
+    Finished,
+    /// Button held down (or a remote stop) - stop the whole cure
+    Aborted,
+    /// Latching start switch turned OFF - stop the whole cure
+    SwitchedOff,
+    /// A hardware fault (too hot, lamp or relay trouble) - stop the whole cure
+    Fault(FaultCode),
+}
+
+impl PhaseEnd {
+    /// Counts as a completed cure in the statistics? A latching switch
+    /// turned OFF is how that kind of cure normally ends.
+    pub fn completed(&self) -> bool {
+        matches!(self, PhaseEnd::Finished | PhaseEnd::SwitchedOff)
+    }
+
+    /// Name for logs: completed, switched_off, aborted, or the fault's name
+    pub fn name(&self) -> &'static str {
+        match self {
+            PhaseEnd::Finished => "completed",
+            PhaseEnd::SwitchedOff => "switched_off",
+            PhaseEnd::Aborted => "aborted",
+            PhaseEnd::Fault(fault) => fault.name(),
+        }
+    }
+}
+
+/// The end of a running phase, and what's left of it while paused
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PhaseTimer {
+    /// When the phase ends (only meaningful while running)
+    end_ms: u64,
+    /// Time that was left when it was paused - None while running
+    paused_with_ms: Option<u64>,
+}
+
+impl PhaseTimer {
+    /// Start timing a phase of `seconds`
+    pub fn start(now_ms: u64, seconds: u64) -> Self {
+        Self { end_ms: now_ms + seconds * 1000, paused_with_ms: None }
+    }
+
+    /// When the phase ends, if it isn't paused
+    pub fn end_ms(&self) -> u64 {
+        self.end_ms
+    }
+
+    /// Milliseconds left (frozen while paused)
+    pub fn remaining_ms(&self, now_ms: u64) -> u64 {
+        self.paused_with_ms.unwrap_or(self.end_ms.saturating_sub(now_ms))
+    }
+
+    /// Whole seconds left, rounded down
+    pub fn remaining_seconds(&self, now_ms: u64) -> u64 {
+        self.remaining_ms(now_ms) / 1000
+    }
+
+    /// Has the phase run for its full time?
+    pub fn is_finished(&self, now_ms: u64) -> bool {
+        self.paused_with_ms.is_none() && now_ms >= self.end_ms
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_with_ms.is_some()
+    }
+
+    /// Stop the clock - returns the milliseconds left
+    pub fn pause(&mut self, now_ms: u64) -> u64 {
+        let remaining = self.remaining_ms(now_ms);
+        self.paused_with_ms = Some(remaining);
+        remaining
+    }
+
+    /// Start the clock again with whatever was left when it was paused
+    pub fn resume(&mut self, now_ms: u64) {
+        if let Some(remaining) = self.paused_with_ms.take() {
+            self.end_ms = now_ms + remaining;
+        }
+    }
+
+    /// Make the phase `ms` longer, running or paused
+    pub fn extend(&mut self, ms: u64) {
+        match &mut self.paused_with_ms {
+            Some(remaining) => *remaining += ms,
+            None => self.end_ms += ms,
+        }
+    }
+}
+
+/// One step of a dial (rotary encoder): the curing time `step` seconds
+/// longer or shorter, kept between one step and `max`
+pub fn dial(seconds: u64, clockwise: bool, step: u64, max: u64) -> u64 {
+    if clockwise {
+        (seconds + step).min(max)
+    } else {
+        seconds.saturating_sub(step).max(step)
+    }
+}
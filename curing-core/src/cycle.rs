@@ -0,0 +1,291 @@
+// Cure Cycle State Machine
+//
+// The whole life of a cure as a handful of states, and one function that
+// decides what happens next:
+//
+//   Idle ──Start──▶ Arming ──Armed──▶ Curing ──(last phase done)──▶ Finishing ──Finished──▶ Idle
+//                     │                 │  ▲                            │
+//                  Refused       Tap / lid / heat: pause, resume       └─(fault)──▶ Fault ──Cleared──▶ Idle
+//                     │             AddTime: same phase, longer
+//                     ▼             Abort / SwitchedOff / Fault ──▶ Finishing
+//                   Idle
+//
+// Resume (after a power cut, see checkpoint.rs) goes the same way as Start,
+// except that the cure picks up part way through its plan.
+//
+// The firmware waits for whatever can happen in the current state (a
+// button gesture, the phase timer, the lid...), turns it into a CureEvent,
+// and calls `transition()`. The outputs follow from the new state - e.g.
+// the UV is on exactly when `uv_on()` says so - so there's one place to
+// look for what each event does, whichever state it arrives in.
+
+use crate::cure::{CurePhase, PhaseEnd, PhaseKind, PhaseTimer};
+use crate::fault::FaultCode;
+
+/// The phases a cure will run
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Plan<'a> {
+    /// A single UV phase of this many seconds
+    Single(u64),
+    /// A multi-stage profile (CURE_PROFILE in config.rs)
+    Profile(&'a [CurePhase]),
+    /// UV on for `on_seconds`, off for `off_seconds`, over and over until
+    /// the UV has been on for `seconds` in all (DUTY_CYCLE_ENABLED in
+    /// config.rs). The last UV phase is whatever UV time is left, and a rest
+    /// is never the last phase
+    DutyCycle { seconds: u64, on_seconds: u64, off_seconds: u64 },
+}
+
+impl Plan<'_> {
+    /// Phase `index` (0 = first), if there is one
+    pub fn phase(&self, index: usize) -> Option<CurePhase> {
+        match self {
+            Plan::Single(seconds) => (index == 0).then_some(CurePhase { kind: PhaseKind::UvOn, seconds: *seconds }),
+            Plan::Profile(phases) => phases.get(index).copied(),
+            Plan::DutyCycle { seconds, on_seconds, off_seconds } => {
+                // Even phases are UV, odd ones rest. UV still to come at
+                // the start of this on/off pair:
+                let uv_left = seconds.saturating_sub((index / 2) as u64 * on_seconds);
+                if index.is_multiple_of(2) {
+                    (uv_left > 0).then_some(CurePhase { kind: PhaseKind::UvOn, seconds: (*on_seconds).min(uv_left) })
+                } else {
+                    // Only a rest if there's more UV after it
+                    (uv_left > *on_seconds).then_some(CurePhase { kind: PhaseKind::Rest, seconds: *off_seconds })
+                }
+            }
+        }
+    }
+
+    /// How many phases there are
+    pub fn phase_count(&self) -> usize {
+        match self {
+            Plan::Single(_) => 1,
+            Plan::Profile(phases) => phases.len(),
+            Plan::DutyCycle { .. } => (0..).take_while(|&i| self.phase(i).is_some()).count(),
+        }
+    }
+
+    /// Total planned length in seconds (all phases, UV and rest)
+    pub fn planned_seconds(&self) -> u64 {
+        match self {
+            Plan::Single(seconds) => *seconds,
+            Plan::Profile(phases) => crate::cure::planned_seconds(phases),
+            Plan::DutyCycle { .. } => (0..self.phase_count()).filter_map(|i| self.phase(i)).map(|p| p.seconds).sum(),
+        }
+    }
+
+    /// The same plan `seconds` longer, if it can be stretched and stays
+    /// within `max_seconds` in all - a profile's phases are fixed, so it can't
+    pub fn extended(&self, seconds: u64, max_seconds: u64) -> Option<Self> {
+        let longer = match *self {
+            Plan::Single(planned) => Plan::Single(planned + seconds),
+            Plan::Profile(_) => return None,
+            Plan::DutyCycle { seconds: planned, on_seconds, off_seconds } => {
+                Plan::DutyCycle { seconds: planned + seconds, on_seconds, off_seconds }
+            }
+        };
+        (longer.planned_seconds() <= max_seconds).then_some(longer)
+    }
+}
+
+/// Where a cure cut off by a power cut picks up again (see checkpoint.rs)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResumePoint {
+    /// The phase it was in (0 = first)
+    pub phase: usize,
+    /// Seconds of that phase still to run
+    pub remaining_seconds: u64,
+}
+
+/// Why a running phase is on hold
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pause {
+    /// Button tapped - another tap resumes
+    Button,
+    /// Lid opened - closing it resumes
+    Lid,
+    /// Chamber too warm (above the soft limit) - resumes once it has cooled
+    Heat,
+}
+
+/// Where the cure cycle is
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CureState<'a> {
+    /// Waiting for a start
+    Idle,
+    /// Start accepted, UV not on yet: the lid is checked, and the chamber
+    /// preheated in heater builds. `from` is set when resuming a cure
+    Arming { plan: Plan<'a>, from: Option<ResumePoint> },
+    /// Phase `phase` (0 = first) of the plan is running, or on hold
+    Curing { plan: Plan<'a>, phase: usize, remaining: PhaseTimer, paused: Option<Pause> },
+    /// The cure is over: UV off, result saved and signalled, cooldown
+    Finishing { end: PhaseEnd },
+    /// Something's wrong with the hardware - no new cure until it's cleared
+    Fault(FaultCode),
+}
+
+/// Something that happened, as far as the cure cycle is concerned
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CureEvent<'a> {
+    /// Start a cure (button, start switch, remote or auto-start)
+    Start(Plan<'a>),
+    /// Pick up a cure cut off by a power cut, part way through its plan
+    Resume { plan: Plan<'a>, from: ResumePoint },
+    /// Can't start after all (e.g. the lid is open)
+    Refused,
+    /// Lid closed and preheat done - UV can come on
+    Armed,
+    /// Time has passed - ends a phase whose time is up
+    Tick,
+    /// Button tapped: pause, or resume from a button pause
+    Tap,
+    /// Make the running cure `seconds` longer, keeping it within
+    /// `max_seconds` - nothing happens if it can't be (see `Plan::extended`)
+    AddTime { seconds: u64, max_seconds: u64 },
+    /// Lid opened mid-phase
+    LidOpened,
+    /// Lid closed again
+    LidClosed,
+    /// The chamber has passed the soft temperature limit mid-phase
+    TooWarm,
+    /// ...and has cooled down again
+    CooledDown,
+    /// Button held, or a remote stop
+    Abort,
+    /// Latching start switch turned OFF
+    SwitchedOff,
+    /// A hardware fault: too hot, lamp or relay trouble
+    Fault(FaultCode),
+    /// Finishing is done (signals played, cooldown over)
+    Finished,
+    /// The fault is over: its cause has gone (e.g. the chamber has cooled)
+    /// and the button has been pressed to say it's been seen
+    Cleared,
+}
+
+impl<'a> CureState<'a> {
+    /// The state after `event` at `now_ms` - events that mean nothing in the
+    /// current state leave it unchanged
+    pub fn transition(self, event: CureEvent<'a>, now_ms: u64) -> Self {
+        use CureEvent as E;
+        use CureState as S;
+
+        match (self, event) {
+            (S::Idle, E::Start(plan)) => S::Arming { plan, from: None },
+            (S::Idle, E::Resume { plan, from }) => S::Arming { plan, from: Some(from) },
+
+            (S::Arming { .. }, E::Refused) => S::Idle,
+            (S::Arming { plan, from: None }, E::Armed) => start_phase(plan, 0, now_ms),
+            (S::Arming { plan, from: Some(from) }, E::Armed) => resume_phase(plan, from, now_ms),
+
+            // The same three ways out from arming or curing
+            (S::Arming { .. } | S::Curing { .. }, E::Abort) => S::Finishing { end: PhaseEnd::Aborted },
+            (S::Arming { .. } | S::Curing { .. }, E::SwitchedOff) => S::Finishing { end: PhaseEnd::SwitchedOff },
+            (S::Arming { .. } | S::Curing { .. }, E::Fault(fault)) => S::Finishing { end: PhaseEnd::Fault(fault) },
+
+            (S::Curing { plan, phase, remaining, paused: None }, E::Tick) if remaining.is_finished(now_ms) => {
+                start_phase(plan, phase + 1, now_ms)
+            }
+            (S::Curing { plan, phase, mut remaining, paused: None }, E::Tap) => {
+                remaining.pause(now_ms);
+                S::Curing { plan, phase, remaining, paused: Some(Pause::Button) }
+            }
+            (S::Curing { plan, phase, mut remaining, paused: None }, E::AddTime { seconds, max_seconds }) => {
+                match plan.extended(seconds, max_seconds) {
+                    Some(longer) => {
+                        // Only the running phase's share of the extra time
+                        // is added to its timer - the rest is in later phases
+                        let length = |plan: Plan<'_>| plan.phase(phase).map_or(0, |p| p.seconds);
+                        remaining.extend((length(longer) - length(plan)) * 1000);
+                        S::Curing { plan: longer, phase, remaining, paused: None }
+                    }
+                    None => self,
+                }
+            }
+            (S::Curing { plan, phase, mut remaining, paused: None }, E::LidOpened) => {
+                remaining.pause(now_ms);
+                S::Curing { plan, phase, remaining, paused: Some(Pause::Lid) }
+            }
+            (S::Curing { plan, phase, mut remaining, paused: None }, E::TooWarm) => {
+                remaining.pause(now_ms);
+                S::Curing { plan, phase, remaining, paused: Some(Pause::Heat) }
+            }
+            (S::Curing { plan, phase, mut remaining, paused: Some(Pause::Button) }, E::Tap)
+            | (S::Curing { plan, phase, mut remaining, paused: Some(Pause::Lid) }, E::LidClosed)
+            | (S::Curing { plan, phase, mut remaining, paused: Some(Pause::Heat) }, E::CooledDown) => {
+                remaining.resume(now_ms);
+                S::Curing { plan, phase, remaining, paused: None }
+            }
+
+            (S::Finishing { end: PhaseEnd::Fault(fault) }, E::Finished) => S::Fault(fault),
+            (S::Finishing { .. }, E::Finished) => S::Idle,
+            (S::Fault(_), E::Cleared) => S::Idle,
+
+            (state, _) => state,
+        }
+    }
+
+    /// How far through its plan the cure is: (milliseconds done, planned
+    /// milliseconds), rest phases included - None unless curing
+    pub fn progress_ms(&self, now_ms: u64) -> Option<(u64, u64)> {
+        match self {
+            CureState::Curing { plan, phase, remaining, .. } => {
+                let before: u64 = (0..*phase).filter_map(|i| plan.phase(i)).map(|p| p.seconds * 1000).sum();
+                let this = plan.phase(*phase).map_or(0, |p| p.seconds * 1000);
+                let done = before + this.saturating_sub(remaining.remaining_ms(now_ms));
+                Some((done, plan.planned_seconds() * 1000))
+            }
+            _ => None,
+        }
+    }
+
+    /// Milliseconds left to count down: the whole cure for a duty cycle
+    /// (whose phases are too short to be worth showing), the running phase
+    /// otherwise - None unless curing
+    pub fn countdown_ms(&self, now_ms: u64) -> Option<u64> {
+        match self {
+            CureState::Curing { plan: Plan::DutyCycle { .. }, .. } => {
+                self.progress_ms(now_ms).map(|(done, planned)| planned - done)
+            }
+            CureState::Curing { remaining, .. } => Some(remaining.remaining_ms(now_ms)),
+            _ => None,
+        }
+    }
+
+    /// Should the UV LEDs be on?
+    pub fn uv_on(&self) -> bool {
+        match self {
+            CureState::Curing { plan, phase, paused: None, .. } => plan.phase(*phase).is_some_and(|p| p.is_uv()),
+            _ => false,
+        }
+    }
+}
+
+/// Phase `index` of the plan, timed from now - or the end of the cure if
+/// there are no phases left
+fn start_phase(plan: Plan<'_>, index: usize, now_ms: u64) -> CureState<'_> {
+    match plan.phase(index) {
+        Some(phase) => CureState::Curing {
+            plan,
+            phase: index,
+            remaining: PhaseTimer::start(now_ms, phase.seconds),
+            paused: None,
+        },
+        None => CureState::Finishing { end: PhaseEnd::Finished },
+    }
+}
+
+/// Phase `from.phase` of the plan with only `from.remaining_seconds` of it
+/// left (never more than the whole phase) - or the end of the cure if the
+/// plan has no such phase
+fn resume_phase(plan: Plan<'_>, from: ResumePoint, now_ms: u64) -> CureState<'_> {
+    match plan.phase(from.phase) {
+        Some(phase) => CureState::Curing {
+            plan,
+            phase: from.phase,
+            remaining: PhaseTimer::start(now_ms, from.remaining_seconds.min(phase.seconds)),
+            paused: None,
+        },
+        None => CureState::Finishing { end: PhaseEnd::Finished },
+    }
+}
@@ -0,0 +1,193 @@
+// Generic Pin Drivers
+//
+// The relay, button, buzzer and status LED drivers, written against the
+// embedded-hal 1.0 traits instead of a particular chip's pin types. The
+// RP2040 firmware hands them its embassy-rp pins; an STM32 or ESP32 build
+// would hand them its own. Anything a board does that the traits can't
+// describe stays with that board: on the RP2040 that's the "pin kill"
+// relay driver (a pin switched between output and input) and the PWM
+// outputs (piezo tones, MOSFET dimming), which implement `UvSwitch` and
+// `Tone` themselves.
+//
+// Pins must be infallible (`Error = Infallible`), as GPIO pins are on the
+// RP2040, STM32 and ESP32 HALs - a UV relay whose "off" can fail would
+// need a different design.
+
+use core::convert::Infallible;
+
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal::pwm::SetDutyCycle;
+use embedded_hal_async::digital::Wait;
+
+/// Which level a pin is at to mean "on" or "pressed"
+pub use embedded_hal::digital::PinState;
+
+/// Something that can switch the UV LEDs on and off
+pub trait UvSwitch {
+    /// UV LEDs ON
+    fn on(&mut self);
+
+    /// UV LEDs OFF
+    fn off(&mut self);
+
+    /// How long to wait after `off()` before the LEDs are guaranteed dark
+    ///
+    /// Mechanical relays need a moment for their contacts to open;
+    /// electronic switches are instant.
+    fn settle_time_ms(&self) -> u64;
+}
+
+/// Something that can sound a tone
+pub trait Tone {
+    /// Start sounding at `hz` (0 = silence)
+    fn tone(&mut self, hz: u32);
+}
+
+/// A relay, SSR or MOSFET switched by one output pin
+pub struct PinRelay<P> {
+    pin: P,
+    /// The pin level that turns the UV on
+    on_state: PinState,
+    settle_time_ms: u64,
+}
+
+impl<P: OutputPin<Error = Infallible>> PinRelay<P> {
+    /// Take the pin and switch the UV off straight away
+    pub fn new(mut pin: P, on_state: PinState, settle_time_ms: u64) -> Self {
+        infallible(pin.set_state(!on_state));
+        Self { pin, on_state, settle_time_ms }
+    }
+}
+
+impl<P: OutputPin<Error = Infallible>> UvSwitch for PinRelay<P> {
+    fn on(&mut self) {
+        infallible(self.pin.set_state(self.on_state));
+    }
+
+    fn off(&mut self) {
+        infallible(self.pin.set_state(!self.on_state));
+    }
+
+    fn settle_time_ms(&self) -> u64 {
+        self.settle_time_ms
+    }
+}
+
+/// An active buzzer (one with its own oscillator) on an output pin: any
+/// tone switches it on, at its own fixed pitch
+pub struct ActiveBuzzer<P> {
+    pin: P,
+}
+
+impl<P: OutputPin<Error = Infallible>> ActiveBuzzer<P> {
+    /// Take the pin, starting silent
+    pub fn new(mut pin: P) -> Self {
+        infallible(pin.set_low());
+        Self { pin }
+    }
+}
+
+impl<P: OutputPin<Error = Infallible>> Tone for ActiveBuzzer<P> {
+    fn tone(&mut self, hz: u32) {
+        infallible(self.pin.set_state(PinState::from(hz != 0)));
+    }
+}
+
+/// An LED on an output pin (HIGH = lit)
+pub struct Led<P> {
+    pin: P,
+}
+
+impl<P: OutputPin<Error = Infallible>> Led<P> {
+    /// Take the pin, starting dark
+    pub fn new(mut pin: P) -> Self {
+        infallible(pin.set_low());
+        Self { pin }
+    }
+
+    pub fn on(&mut self) {
+        self.set(true);
+    }
+
+    pub fn off(&mut self) {
+        self.set(false);
+    }
+
+    /// Light it or not
+    pub fn set(&mut self, lit: bool) {
+        infallible(self.pin.set_state(PinState::from(lit)));
+    }
+}
+
+/// An LED on a PWM output, so it can be dimmed as well as switched
+pub struct DimmableLed<P> {
+    pwm: P,
+}
+
+impl<P: SetDutyCycle<Error = Infallible>> DimmableLed<P> {
+    /// Take the PWM output, starting dark
+    pub fn new(mut pwm: P) -> Self {
+        infallible(pwm.set_duty_cycle_fully_off());
+        Self { pwm }
+    }
+
+    pub fn on(&mut self) {
+        self.set(true);
+    }
+
+    pub fn off(&mut self) {
+        self.set(false);
+    }
+
+    /// Fully lit or dark
+    pub fn set(&mut self, lit: bool) {
+        self.set_percent(if lit { 100 } else { 0 });
+    }
+
+    /// Lit to `percent` (0-100) of full brightness
+    pub fn set_percent(&mut self, percent: u8) {
+        infallible(self.pwm.set_duty_cycle_percent(percent.min(100)));
+    }
+}
+
+/// A push button or switch on an input pin
+pub struct Button<P> {
+    pin: P,
+    /// The pin level while pressed (LOW for a switch to GND with a pull-up)
+    pressed_state: PinState,
+}
+
+impl<P: InputPin<Error = Infallible> + Wait> Button<P> {
+    pub fn new(pin: P, pressed_state: PinState) -> Self {
+        Self { pin, pressed_state }
+    }
+
+    /// Is it down right now? (the raw level - no debouncing)
+    pub fn is_pressed(&mut self) -> bool {
+        let high = infallible(self.pin.is_high());
+        high == (self.pressed_state == PinState::High)
+    }
+
+    /// The pin itself, for board-specific extras (e.g. waking from sleep)
+    pub fn pin_mut(&mut self) -> &mut P {
+        &mut self.pin
+    }
+
+    /// Wait until the level is no longer `pressed`
+    pub async fn wait_for_change(&mut self, pressed: bool) {
+        // Pressed goes to released and back, whichever level that is
+        let wait_for_high = pressed != (self.pressed_state == PinState::High);
+        if wait_for_high {
+            infallible(self.pin.wait_for_high().await);
+        } else {
+            infallible(self.pin.wait_for_low().await);
+        }
+    }
+}
+
+fn infallible<T>(result: Result<T, Infallible>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(never) => match never {},
+    }
+}
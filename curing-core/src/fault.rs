@@ -0,0 +1,59 @@
+// Fault Codes
+//
+// Every hardware fault the curer can find has a number, so it can be told
+// apart without a debug probe: the firmware blinks it on the status LED
+// (and beeps it once) - 4 flashes, pause, 4 flashes... = relay stuck.
+// 1 is left out on purpose: a single flash is too easy to miss.
+//
+// An open lid isn't a fault - it only pauses the cure (or refuses a start)
+// and carries on when closed.
+
+/// What's wrong
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaultCode {
+    /// Too little lamp current - an LED or wire is open (ina219 builds)
+    LampOpen,
+    /// Too much lamp current - something is shorted (ina219 builds)
+    LampShorted,
+    /// The relay doesn't follow its coil (relay-feedback builds)
+    RelayStuck,
+    /// The UV sensor sees no light with the UV on (uv-sensor builds)
+    LampDark,
+    /// The chamber is too hot (thermistor builds)
+    OverTemp,
+    /// The temperature sensor is disconnected or shorted (thermistor builds)
+    SensorMissing,
+}
+
+impl FaultCode {
+    /// The number that's blinked and beeped
+    pub const fn code(self) -> u32 {
+        match self {
+            FaultCode::LampOpen => 2,
+            FaultCode::LampShorted => 3,
+            FaultCode::RelayStuck => 4,
+            FaultCode::LampDark => 5,
+            FaultCode::OverTemp => 6,
+            FaultCode::SensorMissing => 7,
+        }
+    }
+
+    /// Name for logs (over-temperature keeps the "overheated" of older logs)
+    pub const fn name(self) -> &'static str {
+        match self {
+            FaultCode::LampOpen => "lamp_open",
+            FaultCode::LampShorted => "lamp_shorted",
+            FaultCode::RelayStuck => "relay_stuck",
+            FaultCode::LampDark => "lamp_dark",
+            FaultCode::OverTemp => "overheated",
+            FaultCode::SensorMissing => "sensor_missing",
+        }
+    }
+
+    /// Does the fault clear itself? Over-temperature and the sensor come
+    /// right when the chamber cools (or the sensor is plugged back in) - the
+    /// rest are checked again at the next cure
+    pub const fn waits_for_sensor(self) -> bool {
+        matches!(self, FaultCode::OverTemp | FaultCode::SensorMissing)
+    }
+}
@@ -0,0 +1,165 @@
+// Button Gesture Recognition
+//
+// Works out taps, holds, double, triple and five taps from the button level alone, so the
+// same rules apply whatever is reading the pin. The caller feeds in the
+// button level and the time whenever either could matter:
+// - after the level changes
+// - at `wake_at()`, even if nothing changed (that's how a hold is noticed
+//   while the button is still down)
+// - once `settled_at()` has passed (a change during contact bounce is ignored)
+//
+// Debouncing: after each accepted change the level is ignored for
+// `debounce_ms`, so the bounce of a press or release can't look like a
+// second one.
+
+/// What the user did with the button
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Gesture {
+    /// Pressed and released before the hold threshold
+    Tap,
+    /// Held down for at least the hold threshold (button may still be down)
+    Hold,
+    /// Two taps in quick succession (only when a double-tap window is set)
+    DoubleTap,
+    /// Three taps in quick succession (only when triple taps are asked for)
+    TripleTap,
+    /// Five taps in quick succession (only when five taps are asked for)
+    FiveTaps,
+}
+
+/// How long presses, holds and the gaps between taps are
+#[derive(Clone, Copy, Debug)]
+pub struct GestureTiming {
+    /// Level changes closer together than this are contact bounce
+    pub debounce_ms: u64,
+    /// Down for this long = a hold
+    pub hold_ms: u64,
+    /// After a tap, wait this long for a second one. None = report taps
+    /// straight away and never report a double tap.
+    pub double_tap_ms: Option<u64>,
+    /// After a double tap, wait the same time again for a third tap. Only
+    /// with a double-tap window; a double tap then takes that much longer.
+    pub triple_tap: bool,
+    /// After a triple tap, wait for a fourth and fifth tap too (only with
+    /// triple taps). Four taps aren't a gesture - they're ignored.
+    pub five_taps: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum State {
+    /// Up - waiting for a press
+    Released,
+    /// Down since this time
+    Pressed { since: u64 },
+    /// Down, but not part of a gesture (a hold already reported, or down
+    /// before we started looking) - waiting for it to come up
+    WaitingForRelease,
+    /// Tapped `taps` times - that's the gesture unless there's another
+    /// press before `until`
+    TapPending { until: u64, taps: u8 },
+    /// Another press after `taps` taps
+    NextPress { taps: u8 },
+    /// Released - report the gesture once the release bounce has died down
+    Settling { gesture: Gesture, at: u64 },
+}
+
+/// Turns button levels over time into gestures
+pub struct GestureDetector {
+    timing: GestureTiming,
+    state: State,
+    /// Debounced level: true = pressed
+    pressed: bool,
+    /// Level changes are ignored until this time
+    settle_until: u64,
+}
+
+impl GestureDetector {
+    /// Start watching the button. If it's already down, that press doesn't
+    /// count - the next gesture starts with a fresh press.
+    pub fn new(timing: GestureTiming, now_ms: u64, pressed: bool) -> Self {
+        Self {
+            timing,
+            state: if pressed { State::WaitingForRelease } else { State::Released },
+            pressed,
+            settle_until: now_ms,
+        }
+    }
+
+    /// Tell the detector the button level at `now_ms` - returns a gesture
+    /// once one is complete
+    pub fn update(&mut self, now_ms: u64, pressed: bool) -> Option<Gesture> {
+        // Deadlines first: a tap whose window ran out is a tap, even if the
+        // button has just gone down again
+        match self.state {
+            State::Pressed { since } if now_ms >= since + self.timing.hold_ms => {
+                self.state = State::WaitingForRelease;
+                return Some(Gesture::Hold);
+            }
+            State::TapPending { until, taps } if now_ms >= until => {
+                self.state = State::Released;
+                match taps {
+                    1 => return Some(Gesture::Tap),
+                    2 => return Some(Gesture::DoubleTap),
+                    3 => return Some(Gesture::TripleTap),
+                    _ => {}                // Four taps: nothing
+                }
+            }
+            State::Settling { gesture, at } if now_ms >= at => {
+                self.state = State::Released;
+                return Some(gesture);
+            }
+            _ => {}
+        }
+
+        if pressed == self.pressed || now_ms < self.settle_until {
+            return None;              // No change, or still bouncing
+        }
+        self.pressed = pressed;
+        self.settle_until = now_ms + self.timing.debounce_ms;
+
+        self.state = match (self.state, pressed) {
+            (State::Released, true) => State::Pressed { since: now_ms },
+            (State::Pressed { .. }, false) => match self.timing.double_tap_ms {
+                Some(window) => State::TapPending { until: self.settle_until + window, taps: 1 },
+                None => State::Settling { gesture: Gesture::Tap, at: self.settle_until },
+            },
+            (State::TapPending { taps, .. }, true) => State::NextPress { taps },
+            (State::NextPress { taps: 1 }, false) => match self.timing.double_tap_ms {
+                Some(window) if self.timing.triple_tap => State::TapPending { until: self.settle_until + window, taps: 2 },
+                _ => State::Settling { gesture: Gesture::DoubleTap, at: self.settle_until },
+            },
+            (State::NextPress { taps: 2 }, false) => match self.timing.double_tap_ms {
+                Some(window) if self.timing.five_taps => State::TapPending { until: self.settle_until + window, taps: 3 },
+                _ => State::Settling { gesture: Gesture::TripleTap, at: self.settle_until },
+            },
+            // Only reached with a double-tap window
+            (State::NextPress { taps: 3 }, false) => State::TapPending { until: self.settle_until + self.timing.double_tap_ms.unwrap_or(0), taps: 4 },
+            (State::NextPress { .. }, false) => State::Settling { gesture: Gesture::FiveTaps, at: self.settle_until },
+            (State::WaitingForRelease, false) => State::Released,
+            (state, _) => state,
+        };
+
+        // A hold threshold shorter than the debounce time is already up
+        self.update(now_ms, pressed)
+    }
+
+    /// When `update` must next be called even if the level doesn't change
+    pub fn wake_at(&self) -> Option<u64> {
+        match self.state {
+            State::Pressed { since } => Some(since + self.timing.hold_ms),
+            State::TapPending { until, .. } => Some(until),
+            State::Settling { at, .. } => Some(at),
+            _ => None,
+        }
+    }
+
+    /// Level changes before this time are ignored (contact bounce)
+    pub fn settled_at(&self) -> u64 {
+        self.settle_until
+    }
+
+    /// The debounced button level: true = pressed
+    pub fn is_pressed(&self) -> bool {
+        self.pressed
+    }
+}
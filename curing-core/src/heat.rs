@@ -0,0 +1,80 @@
+// UV Panel Heat Estimate
+//
+// We don't have a temperature sensor, so we estimate how hot the UV panel is
+// from how long it has been on recently:
+// - every second the UV is ON adds one second of "heat"
+// - every `cooling_ratio` seconds the UV is OFF sheds one second of heat
+//
+// A single cure stays within `budget_seconds` and gets the normal cooldown.
+// Back-to-back long cures pile up heat beyond the budget, and the cooldown
+// after them is stretched until the panel has shed the excess.
+
+/// How the panel heats up and cools down (HEAT_BUDGET_SECONDS and friends in config.rs)
+#[derive(Clone, Copy, Debug)]
+pub struct HeatModel {
+    /// Heat a single cure may build up without a longer cooldown, in seconds of UV-on time
+    pub budget_seconds: u64,
+    /// Seconds of UV off needed to shed one second of heat (at least 1)
+    pub cooling_ratio: u64,
+    /// Never stretch the cooldown by more than this, in seconds
+    pub max_extra_cooldown_seconds: u64,
+}
+
+/// Running estimate of accumulated UV panel heat
+pub struct HeatEstimate {
+    model: HeatModel,
+    /// Accumulated heat, in seconds of UV-on time
+    heat_seconds: u64,
+    /// When the UV last switched on or off
+    last_change_ms: u64,
+    /// Whether the UV is currently on
+    is_on: bool,
+    /// All UV-on time since power-on, in milliseconds (for the cure statistics)
+    total_on_ms: u64,
+}
+
+impl HeatEstimate {
+    /// Start with a cold panel
+    pub fn new(model: HeatModel, now_ms: u64) -> Self {
+        Self { model, heat_seconds: 0, last_change_ms: now_ms, is_on: false, total_on_ms: 0 }
+    }
+
+    /// Call when the UV switches ON - sheds heat for the time it was off
+    pub fn uv_on(&mut self, now_ms: u64) {
+        if self.is_on {
+            return;
+        }
+        self.is_on = true;
+        let off_seconds = now_ms.saturating_sub(self.last_change_ms) / 1000;
+        self.heat_seconds = self.heat_seconds.saturating_sub(off_seconds / self.model.cooling_ratio);
+        self.last_change_ms = now_ms;
+    }
+
+    /// Call when the UV switches OFF - adds heat for the time it was on
+    pub fn uv_off(&mut self, now_ms: u64) {
+        if !self.is_on {
+            return;
+        }
+        self.is_on = false;
+        let on_ms = now_ms.saturating_sub(self.last_change_ms);
+        self.heat_seconds += on_ms / 1000;
+        self.total_on_ms += on_ms;
+        self.last_change_ms = now_ms;
+    }
+
+    /// Current heat estimate in seconds of UV-on time
+    pub fn heat_seconds(&self) -> u64 {
+        self.heat_seconds
+    }
+
+    /// All UV-on time since power-on, in milliseconds (up to the last switch-off)
+    pub fn total_on_ms(&self) -> u64 {
+        self.total_on_ms
+    }
+
+    /// Seconds of cooldown to add on top of the normal one for the current heat level
+    pub fn extra_cooldown_seconds(&self) -> u64 {
+        let excess = self.heat_seconds.saturating_sub(self.model.budget_seconds);
+        (excess * self.model.cooling_ratio).min(self.model.max_extra_cooldown_seconds)
+    }
+}
@@ -0,0 +1,89 @@
+// Keypad Duration Entry
+//
+// Typing a curing time on a 4x4 keypad, like setting a microwave: digits
+// build up a number of seconds, `*` rubs out the last digit, `#` starts.
+// `#` with nothing typed starts the last time used again. The letter keys
+// A-D do nothing (yet).
+//
+// The firmware scans the keypad and passes each key press in; what comes
+// back says what happened, so it can beep and show the number.
+
+/// The keys of a standard 4x4 membrane keypad, by row and column
+pub const KEYS: [[char; 4]; 4] = [
+    ['1', '2', '3', 'A'],
+    ['4', '5', '6', 'B'],
+    ['7', '8', '9', 'C'],
+    ['*', '0', '#', 'D'],
+];
+
+/// Longest number that can be typed (9999 seconds is already far past any
+/// safety maximum)
+const MAX_DIGITS: u32 = 4;
+
+/// What a key press did
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntryUpdate {
+    /// A digit was added or rubbed out - the number typed so far (0 when empty)
+    Typed(u64),
+    /// `#`: start a cure of this many seconds
+    Start(u64),
+    /// Not accepted: too many digits, or `#` with a time of 0 or over the
+    /// maximum (which also clears what was typed)
+    Rejected,
+}
+
+/// A curing time being typed in
+pub struct DurationEntry {
+    /// Typed so far
+    seconds: u64,
+    digits: u32,
+    /// The time `#` starts when nothing has been typed
+    last_seconds: u64,
+    max_seconds: u64,
+}
+
+impl DurationEntry {
+    /// Nothing typed yet; an empty `#` starts `default_seconds`
+    pub fn new(default_seconds: u64, max_seconds: u64) -> Self {
+        Self { seconds: 0, digits: 0, last_seconds: default_seconds, max_seconds }
+    }
+
+    /// The time an empty `#` would start
+    pub fn last_seconds(&self) -> u64 {
+        self.last_seconds
+    }
+
+    /// Handle one key press - None for keys that do nothing
+    pub fn key(&mut self, key: char) -> Option<EntryUpdate> {
+        match key {
+            '0'..='9' if self.digits == MAX_DIGITS => Some(EntryUpdate::Rejected),
+            '0'..='9' => {
+                self.seconds = self.seconds * 10 + u64::from(key as u8 - b'0');
+                self.digits += 1;
+                Some(EntryUpdate::Typed(self.seconds))
+            }
+            '*' => {
+                self.seconds /= 10;
+                self.digits = self.digits.saturating_sub(1);
+                Some(EntryUpdate::Typed(self.seconds))
+            }
+            '#' => {
+                let seconds = if self.digits == 0 { self.last_seconds } else { self.seconds };
+                self.seconds = 0;
+                self.digits = 0;
+                if seconds == 0 || seconds > self.max_seconds {
+                    return Some(EntryUpdate::Rejected);
+                }
+                self.last_seconds = seconds;
+                Some(EntryUpdate::Start(seconds))
+            }
+            _ => None,
+        }
+    }
+
+    /// Throw away anything typed
+    pub fn clear(&mut self) {
+        self.seconds = 0;
+        self.digits = 0;
+    }
+}
@@ -0,0 +1,47 @@
+//! Cure sequencing logic for the UV resin curing timer
+//!
+//! Everything in here is plain Rust with no hardware and no async: it is
+//! told what happened (a button level, the time in milliseconds) and
+//! decides what that means. The firmware in `src/` reads the pins and the
+//! clock, feeds them in, and switches the outputs - so this part can be
+//! reasoned about (and run) without a Pico.
+//!
+//! The one exception is `drivers`: the relay, button, buzzer and LED drivers,
+//! written against the embedded-hal 1.0 traits rather than RP2040 pins, so
+//! the same control code can run on an STM32 or ESP32 board.
+//!
+//! - `gesture`: tap / hold / double-, triple- and five-tap from raw button levels, with debouncing
+//! - `cure`: cure phases, phase timing with pause/resume, how a cure ended
+//! - `cycle`: the cure cycle state machine (idle, arming, curing, finishing, fault)
+//! - `checkpoint`: where a running cure has got to, saved so it can be resumed after a power cut
+//! - `record_log`: the append-only record log the firmware keeps its settings and checkpoints in
+//! - `fault`: the numbered fault codes blinked on the status LED
+//! - `relay_timing`: how long the relay takes to switch, and whether it's slowing down
+//! - `heat`: UV panel heat estimate and the extra cooldown it calls for
+//! - `presets`: which duration preset is selected (multi-duration builds)
+//! - `signals`: the beeps and melodies of each buzzer signal, step by step, and which event gets which
+//! - `seven_segment`: digits and MM:SS for 4-digit 7-segment displays
+//! - `nec`: NEC infrared remote messages from receiver pulse timings
+//! - `keypad`: typing a curing time on a 4x4 keypad (keypad builds)
+//! - `menu`: the on-device settings menu (cure time, beeps, cooldown) for one button
+//! - `drivers`: UV switch, button, buzzer and LED drivers over any embedded-hal pins
+//!
+//! Times are milliseconds since power-on (`u64`), like `Instant::as_millis()`.
+
+#![no_std]
+
+pub mod checkpoint;
+pub mod cure;
+pub mod cycle;
+pub mod drivers;
+pub mod fault;
+pub mod gesture;
+pub mod heat;
+pub mod keypad;
+pub mod menu;
+pub mod nec;
+pub mod presets;
+pub mod record_log;
+pub mod relay_timing;
+pub mod seven_segment;
+pub mod signals;
@@ -0,0 +1,164 @@
+// Settings Menu
+//
+// Changing the main settings on the curer itself, with just the button:
+// - tap: the next choice for the setting being changed (after the last
+//   one it wraps round to the first)
+// - hold: on to the next setting - after the last one, the menu is done
+//
+// The settings come in a fixed order (cure time, completion beeps,
+// cooldown, sound), and each can only be set to one of a short list of
+// choices (the sound just has two: on, then off),
+// so the firmware can say which one is picked with a few LED flashes or
+// beeps. A value that isn't on the list (e.g. from config.rs) stays as it
+// is until the first tap, which moves on to the next choice above it.
+
+/// The settings in the menu, in the order they come
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuItem {
+    /// How long a cure runs
+    CureTime,
+    /// How many beeps say "cure finished"
+    CompletionBeeps,
+    /// Pause after a cure before the next can start
+    Cooldown,
+    /// Buzzer on, or silent mode
+    Sound,
+}
+
+impl MenuItem {
+    /// Position in the menu, from 1 (for flashing or beeping it)
+    pub fn number(&self) -> u32 {
+        match self {
+            MenuItem::CureTime => 1,
+            MenuItem::CompletionBeeps => 2,
+            MenuItem::Cooldown => 3,
+            MenuItem::Sound => 4,
+        }
+    }
+
+    /// The setting after this one, if there is one
+    fn next(&self) -> Option<MenuItem> {
+        match self {
+            MenuItem::CureTime => Some(MenuItem::CompletionBeeps),
+            MenuItem::CompletionBeeps => Some(MenuItem::Cooldown),
+            MenuItem::Cooldown => Some(MenuItem::Sound),
+            MenuItem::Sound => None,
+        }
+    }
+}
+
+/// The values the menu changes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MenuSettings {
+    pub cure_seconds: u64,
+    pub completion_beeps: u32,
+    pub cooldown_ms: u64,
+    /// Silent mode: no beeps at all
+    pub muted: bool,
+}
+
+/// What each setting can be set to (each list in ascending order)
+#[derive(Clone, Copy, Debug)]
+pub struct MenuChoices<'a> {
+    pub cure_seconds: &'a [u64],
+    pub completion_beeps: &'a [u64],
+    pub cooldown_ms: &'a [u64],
+}
+
+/// What a tap or hold did
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuUpdate {
+    /// The setting changed to choice number `choice` (from 1) on its list
+    Changed { item: MenuItem, choice: u32 },
+    /// Now changing this setting
+    NextItem(MenuItem),
+    /// Past the last setting - these are the new settings
+    Done(MenuSettings),
+}
+
+/// The settings menu, open
+pub struct SettingsMenu<'a> {
+    choices: MenuChoices<'a>,
+    item: MenuItem,
+    settings: MenuSettings,
+}
+
+impl<'a> SettingsMenu<'a> {
+    /// Open the menu on the first setting (the cure time)
+    pub fn new(settings: MenuSettings, choices: MenuChoices<'a>) -> Self {
+        Self { choices, item: MenuItem::CureTime, settings }
+    }
+
+    /// The setting being changed
+    pub fn item(&self) -> MenuItem {
+        self.item
+    }
+
+    /// The settings as they stand
+    pub fn settings(&self) -> MenuSettings {
+        self.settings
+    }
+
+    /// Which choice (from 1) the current setting is on - the first choice
+    /// at or above it, if it isn't one of them exactly
+    pub fn choice(&self) -> u32 {
+        if self.item == MenuItem::Sound {
+            return if self.settings.muted { 2 } else { 1 };
+        }
+        let (choices, value) = self.current();
+        choice_number(choices, value)
+    }
+
+    /// Button tapped: the next choice for the current setting
+    pub fn tap(&mut self) -> MenuUpdate {
+        if self.item == MenuItem::Sound {
+            self.settings.muted = !self.settings.muted;
+            return MenuUpdate::Changed { item: self.item, choice: self.choice() };
+        }
+        let (choices, value) = self.current();
+        let value = next_choice(choices, value);
+        match self.item {
+            MenuItem::CureTime => self.settings.cure_seconds = value,
+            MenuItem::CompletionBeeps => self.settings.completion_beeps = value as u32,
+            MenuItem::Cooldown => self.settings.cooldown_ms = value,
+            MenuItem::Sound => {}
+        }
+        MenuUpdate::Changed { item: self.item, choice: self.choice() }
+    }
+
+    /// Button held: on to the next setting, or done after the last
+    pub fn hold(&mut self) -> MenuUpdate {
+        match self.item.next() {
+            Some(item) => {
+                self.item = item;
+                MenuUpdate::NextItem(item)
+            }
+            None => MenuUpdate::Done(self.settings),
+        }
+    }
+
+    /// The choices for the current setting, and its value (the sound has
+    /// no list - it's just on or off)
+    fn current(&self) -> (&'a [u64], u64) {
+        match self.item {
+            MenuItem::CureTime => (self.choices.cure_seconds, self.settings.cure_seconds),
+            MenuItem::CompletionBeeps => (self.choices.completion_beeps, self.settings.completion_beeps as u64),
+            MenuItem::Cooldown => (self.choices.cooldown_ms, self.settings.cooldown_ms),
+            MenuItem::Sound => (&[], 0),
+        }
+    }
+}
+
+/// The first choice above `value`, or the first choice of all after the last
+fn next_choice(choices: &[u64], value: u64) -> u64 {
+    match choices.iter().find(|&&choice| choice > value) {
+        Some(&choice) => choice,
+        None => choices.first().copied().unwrap_or(value),
+    }
+}
+
+/// Position (from 1) of the first choice at or above `value`
+fn choice_number(choices: &[u64], value: u64) -> u32 {
+    let below = choices.iter().filter(|&&choice| choice < value).count();
+    (below.min(choices.len().saturating_sub(1)) + 1) as u32
+}
@@ -0,0 +1,103 @@
+// NEC Infrared Remote Decoding
+//
+// Most cheap IR remotes speak the NEC protocol. A 3-pin IR receiver
+// (TSOP38238, VS1838B...) strips the 38 kHz carrier and outputs LOW while
+// it sees a burst and HIGH in between, so a key press looks like this:
+//
+//   9 ms LOW, 4.5 ms HIGH          leader
+//   32 bits, least significant first: address, inverted address (or the
+//   high byte of a 16-bit address), command, inverted command
+//   each bit: 562 µs LOW, then 562 µs HIGH (0) or 1687 µs HIGH (1)
+//
+// Holding a key sends 9 ms LOW, 2.25 ms HIGH every ~110 ms instead: "still
+// held". The firmware times the level changes on the receiver pin and
+// feeds each one to `NecDecoder::level_ended`.
+
+/// A decoded message
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NecEvent {
+    /// A key press: the remote's address and the key's command code
+    Key { address: u16, command: u8 },
+    /// The last key is still held down
+    Repeat,
+}
+
+const LEADER_LOW_US: u32 = 9_000;
+const LEADER_HIGH_US: u32 = 4_500;
+const REPEAT_HIGH_US: u32 = 2_250;
+const BIT_LOW_US: u32 = 562;
+const ZERO_HIGH_US: u32 = 562;
+const ONE_HIGH_US: u32 = 1_687;
+
+/// Where in a message the decoder is
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    /// Waiting for a leader
+    Idle,
+    /// Leader LOW seen - the HIGH after it says key or repeat
+    Leader,
+    /// `count` bits in - waiting for the next bit's LOW
+    BitLow { bits: u32, count: u32 },
+    /// ...and its HIGH, whose length is the bit
+    BitHigh { bits: u32, count: u32 },
+}
+
+/// Turns receiver level timings into key presses
+pub struct NecDecoder {
+    state: State,
+}
+
+impl Default for NecDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NecDecoder {
+    pub fn new() -> Self {
+        Self { state: State::Idle }
+    }
+
+    /// The receiver output has just left a level it held for `us`
+    /// microseconds (`low` = it was LOW, i.e. a burst). Returns a message
+    /// once one is complete; anything that doesn't fit starts again.
+    pub fn level_ended(&mut self, low: bool, us: u32) -> Option<NecEvent> {
+        let (next, event) = match (self.state, low) {
+            (_, true) if near(us, LEADER_LOW_US) => (State::Leader, None),
+            (State::Leader, false) if near(us, LEADER_HIGH_US) => (State::BitLow { bits: 0, count: 0 }, None),
+            (State::Leader, false) if near(us, REPEAT_HIGH_US) => (State::Idle, Some(NecEvent::Repeat)),
+            (State::BitLow { bits, count }, true) if near(us, BIT_LOW_US) => (State::BitHigh { bits, count }, None),
+            (State::BitHigh { bits, count }, false) if near(us, ZERO_HIGH_US) || near(us, ONE_HIGH_US) => {
+                let bits = if near(us, ONE_HIGH_US) { bits | 1 << count } else { bits };
+                match count + 1 {
+                    32 => (State::Idle, decode(bits)),
+                    count => (State::BitLow { bits, count }, None),
+                }
+            }
+            _ => (State::Idle, None),
+        };
+        self.state = next;
+        event
+    }
+}
+
+/// Within 25% of `target`
+fn near(us: u32, target: u32) -> bool {
+    us * 4 >= target * 3 && us * 4 <= target * 5
+}
+
+/// The 32 bits of a message as a key - None if the command check fails
+fn decode(bits: u32) -> Option<NecEvent> {
+    let [address_low, address_high, command, inverted] = bits.to_le_bytes();
+    if command != !inverted {
+        return None;
+    }
+    // The original NEC sends the address inverted too; "extended NEC"
+    // uses both bytes as a 16-bit address
+    let address = if address_high == !address_low {
+        address_low as u16
+    } else {
+        u16::from_le_bytes([address_low, address_high])
+    };
+    Some(NecEvent::Key { address, command })
+}
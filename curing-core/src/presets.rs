@@ -0,0 +1,41 @@
+// Duration Preset Selection
+//
+// Keeps track of which entry of a preset table (DURATION_PRESETS and
+// PRESET_NAMES in config.rs) is selected. Tapping the button moves on to
+// the next one, wrapping round after the last.
+
+/// The currently selected curing preset
+pub struct PresetSelector<'a> {
+    durations: &'a [u64],
+    names: &'a [&'a str],
+    index: usize,
+}
+
+impl<'a> PresetSelector<'a> {
+    /// Start on a previously saved preset, or `default` if it's out of
+    /// range (e.g. the preset table got shorter since it was saved)
+    pub fn with_index(durations: &'a [u64], names: &'a [&'a str], index: usize, default: usize) -> Self {
+        let index = if index < durations.len() { index } else { default };
+        Self { durations, names, index }
+    }
+
+    /// Move to the next preset, wrapping back to the first after the last
+    pub fn next(&mut self) {
+        self.index = (self.index + 1) % self.durations.len();
+    }
+
+    /// Position of the selected preset (0 = first)
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Curing time of the selected preset in seconds
+    pub fn seconds(&self) -> u64 {
+        self.durations[self.index]
+    }
+
+    /// Display name of the selected preset
+    pub fn name(&self) -> &'a str {
+        self.names[self.index]
+    }
+}
@@ -0,0 +1,279 @@
+// Append-Only Record Log
+//
+// How the firmware's flash storage (src/storage.rs) lays out its records,
+// without the flash chip itself - so what happens when the power goes in
+// the middle of a write can be tested on a PC.
+//
+// Flash can only be erased a whole sector at a time and wears out after
+// ~100,000 erases, so instead of rewriting one fixed spot a new fixed-size
+// record is appended each time something changes:
+//
+//   [magic (2)] [sequence number (4)] [payload (24)] [checksum (2)]
+//
+// The magic says what kind of record it is. At start-up every slot is read
+// and the newest valid record of each kind wins (highest sequence number).
+//
+// The sectors are used in turn, each erased just before its first record.
+// Some kinds of record are *kept* (the settings, for the firmware): the
+// newest of each is always in the sector being written, copied forward if
+// need be, so erasing the next sector can never take it.
+//
+// A write cut off by a power loss leaves a slot that's neither blank nor a
+// valid record (the checksum catches it). It's skipped - erasing it would
+// mean erasing its whole sector, and the kept record with it.
+//
+// Erasing a sector takes tens of milliseconds (hundreds, worst case), and
+// the chip can't run from flash meanwhile. Records that must never wait for
+// one (the firmware's cure checkpoints) use `append_without_erase`, with
+// `make_room` beforehand to move on to a fresh sector while nothing's
+// going on.
+
+/// Size of one record in bytes
+pub const RECORD_SIZE: usize = 32;
+
+/// Bytes of each record available for the payload
+pub const PAYLOAD_SIZE: usize = RECORD_SIZE - 2 - 4 - 2;
+
+/// What the log needs from the flash chip, one record slot at a time
+pub trait RecordFlash {
+    type Error;
+
+    /// Read the record slot `slot`
+    fn read(&mut self, slot: u32, record: &mut [u8; RECORD_SIZE]) -> Result<(), Self::Error>;
+
+    /// Write the (blank) record slot `slot`
+    fn write(&mut self, slot: u32, record: &[u8; RECORD_SIZE]) -> Result<(), Self::Error>;
+
+    /// Erase sector `sector` - every slot in it reads as blank (0xFF) after
+    fn erase(&mut self, sector: u32) -> Result<(), Self::Error>;
+}
+
+/// How many sectors the log has, and how many records fit in each
+#[derive(Clone, Copy, Debug)]
+pub struct LogGeometry {
+    pub sectors: u32,
+    pub slots_per_sector: u32,
+}
+
+impl LogGeometry {
+    fn slots(&self) -> u32 {
+        self.sectors * self.slots_per_sector
+    }
+
+    fn sector(&self, slot: u32) -> u32 {
+        slot / self.slots_per_sector
+    }
+}
+
+/// Where the log has got to - `KEPT` is how many kinds of record are kept
+pub struct RecordLog<const KEPT: usize = 1> {
+    geometry: LogGeometry,
+    /// Magics of the kinds of record that must never be erased
+    kept_magics: [[u8; 2]; KEPT],
+    /// Sequence number of the newest record (0 = nothing stored yet)
+    sequence: u32,
+    /// Slot the next record will be written to
+    next_slot: u32,
+    /// The newest kept record of each kind's payload
+    kept: [Option<[u8; PAYLOAD_SIZE]>; KEPT],
+    /// The sector each is in (None = not in flash any more)
+    kept_sectors: [Option<u32>; KEPT],
+}
+
+impl<const KEPT: usize> RecordLog<KEPT> {
+    /// Read every slot to find where the log has got to. `found` is called
+    /// with each valid record (magic, sequence number, payload), in slot
+    /// order - slots that can't be read are skipped.
+    pub fn open<F: RecordFlash>(
+        flash: &mut F,
+        geometry: LogGeometry,
+        kept_magics: [[u8; 2]; KEPT],
+        mut found: impl FnMut([u8; 2], u32, &[u8; PAYLOAD_SIZE]),
+    ) -> Self {
+        let mut log = Self { geometry, kept_magics, sequence: 0, next_slot: 0, kept: [None; KEPT], kept_sectors: [None; KEPT] };
+        let mut kept_sequences = [0; KEPT];
+
+        for slot in 0..geometry.slots() {
+            let mut record = [0u8; RECORD_SIZE];
+            if flash.read(slot, &mut record).is_err() {
+                continue;
+            }
+            let Some((magic, sequence, payload)) = decode(&record) else {
+                continue;
+            };
+            if sequence > log.sequence {
+                log.sequence = sequence;
+                log.next_slot = (slot + 1) % geometry.slots();
+            }
+            if let Some(kind) = log.kind(magic) {
+                if sequence > kept_sequences[kind] {
+                    kept_sequences[kind] = sequence;
+                    log.kept[kind] = Some(payload);
+                    log.kept_sectors[kind] = Some(geometry.sector(slot));
+                }
+            }
+            found(magic, sequence, &payload);
+        }
+        log
+    }
+
+    /// The newest kept record of kind `magic`'s payload, if there is one
+    pub fn kept(&self, magic: [u8; 2]) -> Option<&[u8; PAYLOAD_SIZE]> {
+        self.kind(magic).and_then(|kind| self.kept[kind].as_ref())
+    }
+
+    /// Blank slots left before the next record needs a sector erasing
+    pub fn room(&self) -> u32 {
+        match self.next_slot % self.geometry.slots_per_sector {
+            0 => 0,
+            used => self.geometry.slots_per_sector - used,
+        }
+    }
+
+    /// Make sure at least `slots` records (no more than fit in a sector
+    /// beside the kept records) can go in without erasing - moving on to
+    /// the next sector, erased and with the kept records copied in, if
+    /// need be
+    pub fn make_room<F: RecordFlash>(&mut self, flash: &mut F, slots: u32) -> Result<(), F::Error> {
+        if self.room() >= slots.min(self.geometry.slots_per_sector - KEPT as u32) {
+            return Ok(());
+        }
+        let sector = (self.geometry.sector(self.next_slot) + (self.room() > 0) as u32) % self.geometry.sectors;
+        let first_slot = sector * self.geometry.slots_per_sector;
+        self.next_slot = first_slot;
+        self.erase(flash, sector)?;
+        for kind in 0..KEPT {
+            if let Some(kept) = self.kept[kind] {
+                self.write(flash, self.next_slot, self.kept_magics[kind], &kept)?;
+            }
+        }
+        // Nothing to copy: the sector is ready, just start one slot in so
+        // the next append doesn't erase it again
+        if self.next_slot == first_slot {
+            self.next_slot += 1;
+        }
+        Ok(())
+    }
+
+    /// Append a record, and return the slot it went in
+    ///
+    /// Erases a sector first when the record is the first in it, and copies
+    /// the kept records forward first when they aren't in the sector written.
+    pub fn append<F: RecordFlash>(&mut self, flash: &mut F, magic: [u8; 2], payload: &[u8; PAYLOAD_SIZE]) -> Result<u32, F::Error> {
+        self.append_in(flash, magic, payload, true).map(|slot| slot.unwrap_or_default())
+    }
+
+    /// Append a record only if that doesn't mean erasing a sector - the
+    /// slot it went in, or None when the sector is full (see `make_room`)
+    pub fn append_without_erase<F: RecordFlash>(
+        &mut self,
+        flash: &mut F,
+        magic: [u8; 2],
+        payload: &[u8; PAYLOAD_SIZE],
+    ) -> Result<Option<u32>, F::Error> {
+        self.append_in(flash, magic, payload, false)
+    }
+
+    fn append_in<F: RecordFlash>(&mut self, flash: &mut F, magic: [u8; 2], payload: &[u8; PAYLOAD_SIZE], may_erase: bool) -> Result<Option<u32>, F::Error> {
+        loop {
+            let slot = self.next_slot;
+            let sector = self.geometry.sector(slot);
+            if slot.is_multiple_of(self.geometry.slots_per_sector) {
+                if !may_erase {
+                    return Ok(None);
+                }
+                self.erase(flash, sector)?;
+            } else if !is_blank(flash, slot)? {
+                // Cut off by a power loss - leave it, use the next one
+                self.next_slot = (slot + 1) % self.geometry.slots();
+                continue;
+            }
+
+            // Any other kept record not in this sector goes in first (the
+            // one being written replaces its own)
+            let behind = (0..KEPT).find_map(|kind| match self.kept[kind] {
+                Some(kept) if self.kept_magics[kind] != magic && self.kept_sectors[kind] != Some(sector) => Some((self.kept_magics[kind], kept)),
+                _ => None,
+            });
+            if let Some((kept_magic, kept)) = behind {
+                self.write(flash, slot, kept_magic, &kept)?;
+                continue;
+            }
+            self.write(flash, slot, magic, payload)?;
+            return Ok(Some(slot));
+        }
+    }
+
+    /// Which kept kind `magic` is, if it's one
+    fn kind(&self, magic: [u8; 2]) -> Option<usize> {
+        self.kept_magics.iter().position(|&kept| kept == magic)
+    }
+
+    /// Erase sector `sector` - any kept record in it is only in RAM after
+    fn erase<F: RecordFlash>(&mut self, flash: &mut F, sector: u32) -> Result<(), F::Error> {
+        flash.erase(sector)?;
+        for kept_sector in self.kept_sectors.iter_mut().filter(|kept_sector| **kept_sector == Some(sector)) {
+            *kept_sector = None;
+        }
+        Ok(())
+    }
+
+    /// Write a record to the (blank) slot `slot`
+    fn write<F: RecordFlash>(&mut self, flash: &mut F, slot: u32, magic: [u8; 2], payload: &[u8; PAYLOAD_SIZE]) -> Result<(), F::Error> {
+        flash.write(slot, &encode(magic, self.sequence + 1, payload))?;
+        self.sequence += 1;
+        self.next_slot = (slot + 1) % self.geometry.slots();
+        if let Some(kind) = self.kind(magic) {
+            self.kept[kind] = Some(*payload);
+            self.kept_sectors[kind] = Some(self.geometry.sector(slot));
+        }
+        Ok(())
+    }
+}
+
+fn is_blank<F: RecordFlash>(flash: &mut F, slot: u32) -> Result<bool, F::Error> {
+    let mut record = [0u8; RECORD_SIZE];
+    flash.read(slot, &mut record)?;
+    Ok(record.iter().all(|&b| b == 0xFF))
+}
+
+/// A record as written to flash
+pub fn encode(magic: [u8; 2], sequence: u32, payload: &[u8; PAYLOAD_SIZE]) -> [u8; RECORD_SIZE] {
+    let mut record = [0u8; RECORD_SIZE];
+    record[0..2].copy_from_slice(&magic);
+    record[2..6].copy_from_slice(&sequence.to_le_bytes());
+    record[6..6 + PAYLOAD_SIZE].copy_from_slice(payload);
+    let checksum = crc16(&record[..RECORD_SIZE - 2]);
+    record[RECORD_SIZE - 2..].copy_from_slice(&checksum.to_le_bytes());
+    record
+}
+
+/// A valid record's (magic, sequence number, payload) - None for a blank
+/// or partly written slot
+pub fn decode(record: &[u8; RECORD_SIZE]) -> Option<([u8; 2], u32, [u8; PAYLOAD_SIZE])> {
+    let magic = [record[0], record[1]];
+    if magic == [0xFF, 0xFF] {
+        return None;
+    }
+    let checksum = u16::from_le_bytes([record[RECORD_SIZE - 2], record[RECORD_SIZE - 1]]);
+    if crc16(&record[..RECORD_SIZE - 2]) != checksum {
+        return None;
+    }
+    let sequence = u32::from_le_bytes([record[2], record[3], record[4], record[5]]);
+    let mut payload = [0u8; PAYLOAD_SIZE];
+    payload.copy_from_slice(&record[6..6 + PAYLOAD_SIZE]);
+    Some((magic, sequence, payload))
+}
+
+/// CRC-16/CCITT-FALSE - catches records that were only partly written
+/// (also checks frames in the UART protocol, uart_control.rs)
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
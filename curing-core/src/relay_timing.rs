@@ -0,0 +1,87 @@
+// Relay Switching Time
+//
+// A relay that's wearing out gets slower before it sticks: the contacts
+// pit and the spring tires, so it takes longer to follow the coil. With a
+// feedback contact (relay-feedback builds) the firmware times every switch,
+// from driving the coil to the contact moving, and feeds the time in here -
+// one `SwitchTimes` for closing and one for opening.
+//
+// The first BASELINE_SWITCHES switches are averaged into a baseline: how
+// fast this relay was when it was fitted (or since power-on). After that a
+// running average follows the times, and once it has crept up by
+// `slowdown_percent` (and at least MIN_SLOWDOWN_US, so a 3 ms relay isn't
+// flagged for a 1 ms wobble) `record` says so - once, until it has come
+// back down halfway.
+
+/// How many switches are averaged into the baseline
+pub const BASELINE_SWITCHES: u32 = 8;
+
+/// Slowdowns smaller than this (microseconds) are never reported
+pub const MIN_SLOWDOWN_US: u32 = 2_000;
+
+/// Each new time moves the running average 1/AVERAGE_WEIGHT of the way
+const AVERAGE_WEIGHT: u32 = 8;
+
+/// Switching times of one direction of one relay
+#[derive(Clone, Copy, Debug)]
+pub struct SwitchTimes {
+    /// How much slower than the baseline counts as slowing down (RELAY_SLOWDOWN_PERCENT)
+    slowdown_percent: u32,
+    /// Switches timed so far (stops counting at BASELINE_SWITCHES)
+    count: u32,
+    /// Sum of the first switches' times, while the baseline is built
+    sum_us: u32,
+    /// Average of the first BASELINE_SWITCHES times (0 = not known yet)
+    baseline_us: u32,
+    /// Running average of the times
+    average_us: u32,
+    /// Already reported as slowing down
+    slow: bool,
+}
+
+impl SwitchTimes {
+    pub const fn new(slowdown_percent: u32) -> Self {
+        Self { slowdown_percent, count: 0, sum_us: 0, baseline_us: 0, average_us: 0, slow: false }
+    }
+
+    /// Add one switch's time in microseconds - true the moment the running
+    /// average has become `slowdown_percent` slower than the baseline
+    pub fn record(&mut self, us: u32) -> bool {
+        if self.count < BASELINE_SWITCHES {
+            self.count += 1;
+            self.sum_us = self.sum_us.saturating_add(us);
+            self.average_us = self.sum_us / self.count;
+            if self.count == BASELINE_SWITCHES {
+                self.baseline_us = self.average_us;
+            }
+            return false;
+        }
+
+        // Move the average towards the new time
+        self.average_us = if us > self.average_us {
+            self.average_us + (us - self.average_us) / AVERAGE_WEIGHT
+        } else {
+            self.average_us - (self.average_us - us) / AVERAGE_WEIGHT
+        };
+
+        let margin = (self.baseline_us * self.slowdown_percent / 100).max(MIN_SLOWDOWN_US);
+        if !self.slow && self.average_us >= self.baseline_us + margin {
+            self.slow = true;
+            return true;
+        }
+        if self.slow && self.average_us < self.baseline_us + margin / 2 {
+            self.slow = false;   // Back to normal - report it again if it slows down again
+        }
+        false
+    }
+
+    /// Average of the first BASELINE_SWITCHES times (None until there have been that many)
+    pub fn baseline_us(&self) -> Option<u32> {
+        (self.count >= BASELINE_SWITCHES).then_some(self.baseline_us)
+    }
+
+    /// Running average of the times (0 before the first)
+    pub fn average_us(&self) -> u32 {
+        self.average_us
+    }
+}
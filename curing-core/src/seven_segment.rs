@@ -0,0 +1,78 @@
+// Seven-Segment Digits
+//
+// What a 4-digit 7-segment display (e.g. a TM1637 module) should light up:
+// one byte per digit, one bit per segment. Bit 7 is the "decimal point",
+// which on the common clock-style modules is the colon after the second
+// digit.
+//
+//      --a--
+//     |     |
+//     f     b
+//     |--g--|
+//     e     c
+//     |     |
+//      --d--
+
+/// Segments for 0-9 (bits: 0 = a ... 6 = g)
+const DIGITS: [u8; 10] = [0x3F, 0x06, 0x5B, 0x4F, 0x66, 0x6D, 0x7D, 0x07, 0x7F, 0x6F];
+
+/// The colon (or decimal point) bit
+pub const COLON: u8 = 0x80;
+
+/// All four digits dark
+pub const BLANK: [u8; 4] = [0; 4];
+
+/// The segments for one decimal digit (`n` 0-9; anything else is blank)
+pub fn digit(n: u64) -> u8 {
+    DIGITS.get(n as usize).copied().unwrap_or(0)
+}
+
+/// `seconds` as MM:SS - 99:59 at most
+pub fn minutes_seconds(seconds: u64, colon: bool) -> [u8; 4] {
+    let seconds = seconds.min(99 * 60 + 59);
+    let (minutes, seconds) = (seconds / 60, seconds % 60);
+    let colon = if colon { COLON } else { 0 };
+    [digit(minutes / 10), digit(minutes % 10) | colon, digit(seconds / 10), digit(seconds % 10)]
+}
+
+/// A time of day as H:MM, like a clock: no leading zero on the hour
+pub fn hours_minutes(hour: u8, minute: u8, colon: bool) -> [u8; 4] {
+    let (hour, minute) = (hour as u64 % 24, minute as u64 % 60);
+    let colon = if colon { COLON } else { 0 };
+    let tens = if hour >= 10 { digit(hour / 10) } else { 0 };
+    [tens, digit(hour % 10) | colon, digit(minute / 10), digit(minute % 10)]
+}
+
+/// Up to four letters, digits or dashes - anything that can't be shown is
+/// left blank
+pub fn text(text: &str) -> [u8; 4] {
+    let mut segments = BLANK;
+    for (segment, c) in segments.iter_mut().zip(text.chars()) {
+        *segment = match c {
+            '0'..='9' => digit(c as u64 - '0' as u64),
+            'A' | 'a' => 0x77,
+            'b' => 0x7C,
+            'C' => 0x39,
+            'c' => 0x58,
+            'd' => 0x5E,
+            'E' | 'e' => 0x79,
+            'F' | 'f' => 0x71,
+            'H' => 0x76,
+            'h' => 0x74,
+            'I' => 0x30,
+            'L' => 0x38,
+            'n' => 0x54,
+            'O' => 0x3F,
+            'o' => 0x5C,
+            'P' | 'p' => 0x73,
+            'r' => 0x50,
+            'S' | 's' => 0x6D,
+            't' => 0x78,
+            'U' => 0x3E,
+            'u' => 0x1C,
+            '-' => 0x40,
+            _ => 0,
+        };
+    }
+    segments
+}
@@ -0,0 +1,170 @@
+// Buzzer Signals
+//
+// Every sound the curer makes is a list of steps: a pitch (or silence) held
+// for some milliseconds. Working out the list is kept apart from playing
+// it, so the rhythm of each signal - how many beeps, how long, the gaps
+// between them - doesn't depend on the buzzer that's fitted.
+//
+// Which signal means what is an `AudioEvent`. A table (`AudioPatterns`, set
+// up in the firmware's config.rs) gives each event its own beeps, so "done",
+// "no" and "fault" never sound alike.
+//
+// `QuietHours` is a stretch of the night in which the sounds that only report
+// progress or remind (`AudioEvent::is_quiet_hours_muted`) are left out -
+// alarms, and the answers to a button press, always sound.
+
+/// One step of a signal: a pitch held for a while
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Note {
+    /// Pitch in Hz (0 = silent rest)
+    pub hz: u32,
+    /// Length in milliseconds
+    pub ms: u64,
+}
+
+/// A run of identical beeps, each followed by a gap
+#[derive(Clone, Copy, Debug)]
+pub struct Beeps {
+    pub count: u32,
+    /// Pitch in Hz (an active buzzer ignores it)
+    pub hz: u32,
+    /// Length of each beep in milliseconds
+    pub on_ms: u64,
+    /// Silence after each beep in milliseconds
+    pub off_ms: u64,
+}
+
+impl Beeps {
+    /// Beep, gap, beep, gap...
+    pub fn steps(self) -> impl Iterator<Item = Note> {
+        (0..self.count).flat_map(move |_| [Note { hz: self.hz, ms: self.on_ms }, Note { hz: 0, ms: self.off_ms }])
+    }
+}
+
+/// A melody with `gap_ms` of silence after every note, so repeated notes
+/// don't blur into one
+pub fn melody(notes: &[Note], gap_ms: u64) -> impl Iterator<Item = Note> + '_ {
+    notes.iter().flat_map(move |note| [*note, Note { hz: 0, ms: gap_ms }])
+}
+
+/// Something the buzzer tells you about - each one has its own pattern in
+/// an `AudioPatterns` table, so they can be told apart by ear
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioEvent {
+    /// A cure is starting
+    Start,
+    /// A cure finished
+    Complete,
+    /// A cure was aborted
+    Abort,
+    /// Something is wrong - sounded once per step of the fault's number
+    Fault,
+    /// The chamber got too hot (one burst of the alarm)
+    Overheated,
+    /// A different duration preset was picked
+    PresetChanged,
+    /// The battery is too low for a cure
+    LowBattery,
+    /// "Got it" - a key, a remote button, more time added, waking up
+    Chirp,
+    /// "No" - that can't be done
+    Refused,
+    /// "Done" - settings saved, lamp hours reset, going to sleep
+    Confirm,
+    /// One of the last seconds of a cure (or of an auto-start)
+    Countdown,
+    /// Another full minute of a cure has gone by
+    MinuteMark,
+    /// A cure can't start with the lid open
+    LidOpen,
+    /// The lid was opened mid-cure (repeats until it's closed)
+    LidWarning,
+    /// The short beeps of "service the lamp" (a `Confirm` follows them)
+    LampService,
+    /// Which settings menu item this is - sounded once per item number
+    MenuItem,
+}
+
+impl AudioEvent {
+    /// Is this a sound quiet hours leave out? The progress chirps, the
+    /// "done" beeps and the lamp reminder - nothing that warns of danger,
+    /// and nothing that answers someone standing at the curer
+    pub const fn is_quiet_hours_muted(&self) -> bool {
+        matches!(self, AudioEvent::Complete | AudioEvent::Countdown | AudioEvent::MinuteMark | AudioEvent::LampService)
+    }
+}
+
+/// Quiet hours, from `start` to `end` (hour, minute) - over midnight if
+/// `end` is earlier in the day than `start`
+#[derive(Clone, Copy, Debug)]
+pub struct QuietHours {
+    pub start: (u8, u8),
+    pub end: (u8, u8),
+}
+
+impl QuietHours {
+    /// Is this time of day within the quiet hours? (Start included, end not;
+    /// the same start and end is never)
+    pub const fn contains(&self, hour: u8, minute: u8) -> bool {
+        let start = self.start.0 as u16 * 60 + self.start.1 as u16;
+        let end = self.end.0 as u16 * 60 + self.end.1 as u16;
+        let now = hour as u16 * 60 + minute as u16;
+        if start <= end {
+            start <= now && now < end
+        } else {
+            now >= start || now < end
+        }
+    }
+}
+
+/// The sound of every `AudioEvent`: how many beeps, how long, what pitch
+#[derive(Clone, Copy, Debug)]
+pub struct AudioPatterns {
+    pub start: Beeps,
+    pub complete: Beeps,
+    pub abort: Beeps,
+    pub fault: Beeps,
+    pub overheated: Beeps,
+    pub preset_changed: Beeps,
+    pub low_battery: Beeps,
+    pub chirp: Beeps,
+    pub refused: Beeps,
+    pub confirm: Beeps,
+    pub countdown: Beeps,
+    pub minute_mark: Beeps,
+    pub lid_open: Beeps,
+    pub lid_warning: Beeps,
+    pub lamp_service: Beeps,
+    pub menu_item: Beeps,
+}
+
+impl AudioPatterns {
+    /// The pattern for `event`
+    pub const fn pattern(&self, event: AudioEvent) -> Beeps {
+        match event {
+            AudioEvent::Start => self.start,
+            AudioEvent::Complete => self.complete,
+            AudioEvent::Abort => self.abort,
+            AudioEvent::Fault => self.fault,
+            AudioEvent::Overheated => self.overheated,
+            AudioEvent::PresetChanged => self.preset_changed,
+            AudioEvent::LowBattery => self.low_battery,
+            AudioEvent::Chirp => self.chirp,
+            AudioEvent::Refused => self.refused,
+            AudioEvent::Confirm => self.confirm,
+            AudioEvent::Countdown => self.countdown,
+            AudioEvent::MinuteMark => self.minute_mark,
+            AudioEvent::LidOpen => self.lid_open,
+            AudioEvent::LidWarning => self.lid_warning,
+            AudioEvent::LampService => self.lamp_service,
+            AudioEvent::MenuItem => self.menu_item,
+        }
+    }
+
+    /// `event`'s pattern with `count` beeps instead - for numbers (a fault
+    /// code, a menu item) and the completion beeps picked in the menu
+    pub const fn counted(&self, event: AudioEvent, count: u32) -> Beeps {
+        let pattern = self.pattern(event);
+        Beeps { count, ..pattern }
+    }
+}
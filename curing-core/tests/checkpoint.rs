@@ -0,0 +1,100 @@
+// Cure checkpoints: taking one mid-cure, saving it as bytes, and resuming
+// from it through the state machine
+
+use curing_core::checkpoint::{Checkpoint, SavedPlan};
+use curing_core::cure::{CurePhase, PhaseKind};
+use curing_core::cycle::{CureEvent, CureState, Plan, ResumePoint};
+
+const PROFILE: [CurePhase; 3] = [
+    CurePhase { kind: PhaseKind::UvOn, seconds: 30 },
+    CurePhase { kind: PhaseKind::Rest, seconds: 60 },
+    CurePhase { kind: PhaseKind::UvOn, seconds: 30 },
+];
+
+/// `plan` started at 0 ms, then ticked along until `now_ms`
+fn curing(plan: Plan<'_>, now_ms: u64) -> CureState<'_> {
+    let mut state = CureState::Idle.transition(CureEvent::Start(plan), 0).transition(CureEvent::Armed, 0);
+    let mut at = 0;
+    while let CureState::Curing { remaining, .. } = state {
+        if remaining.end_ms() > now_ms {
+            break;
+        }
+        at = remaining.end_ms();
+        state = state.transition(CureEvent::Tick, at);
+    }
+    assert!(at <= now_ms);
+    state
+}
+
+#[test]
+fn a_checkpoint_rounds_the_time_left_up() {
+    let state = curing(Plan::Single(300), 100_400);
+    let checkpoint = Checkpoint::of(&state, 100_400, 7).unwrap();
+    assert_eq!(checkpoint.plan, SavedPlan::Single(300));
+    assert_eq!(checkpoint.from, ResumePoint { phase: 0, remaining_seconds: 200 });
+    assert_eq!(checkpoint.uv_on_seconds, 100);
+    assert_eq!(checkpoint.cure_number, 7);
+}
+
+#[test]
+fn only_a_running_cure_has_a_checkpoint() {
+    assert_eq!(Checkpoint::of(&CureState::Idle, 0, 0), None);
+    let arming = CureState::Idle.transition(CureEvent::Start(Plan::Single(60)), 0);
+    assert_eq!(Checkpoint::of(&arming, 0, 0), None);
+}
+
+#[test]
+fn uv_time_leaves_out_rest_phases() {
+    // 10 s into the second UV phase: 30 + 10 s of UV, the 60 s rest not counted
+    let state = curing(Plan::Profile(&PROFILE), 100_000);
+    let checkpoint = Checkpoint::of(&state, 100_000, 0).unwrap();
+    assert_eq!(checkpoint.from, ResumePoint { phase: 2, remaining_seconds: 20 });
+    assert_eq!(checkpoint.uv_on_seconds, 40);
+}
+
+#[test]
+fn resuming_picks_up_in_the_same_phase() {
+    let state = curing(Plan::Profile(&PROFILE), 45_000);
+    let checkpoint = Checkpoint::of(&state, 45_000, 0).unwrap();
+    let plan = checkpoint.plan(&PROFILE).unwrap();
+    assert_eq!(checkpoint.seconds_to_go(&plan), 75);
+
+    // After the power cut, at 2 s since power-on
+    let resumed = CureState::Idle
+        .transition(CureEvent::Resume { plan, from: checkpoint.from }, 2_000)
+        .transition(CureEvent::Armed, 2_000);
+    match resumed {
+        CureState::Curing { phase, remaining, paused: None, .. } => {
+            assert_eq!(phase, 1);
+            assert_eq!(remaining.end_ms(), 47_000);
+        }
+        other => panic!("not curing: {:?}", other),
+    }
+    assert!(!resumed.uv_on());
+    assert_eq!(resumed.progress_ms(2_000), Some((45_000, 120_000)));
+}
+
+#[test]
+fn a_phase_that_no_longer_exists_cant_be_resumed() {
+    let state = curing(Plan::Profile(&PROFILE), 100_000);
+    let checkpoint = Checkpoint::of(&state, 100_000, 0).unwrap();
+    assert_eq!(checkpoint.plan(&PROFILE[..2]), None);
+}
+
+#[test]
+fn checkpoints_survive_being_saved() {
+    let duty = Plan::DutyCycle { seconds: 300, on_seconds: 20, off_seconds: 10 };
+    for plan in [Plan::Single(300), Plan::Profile(&PROFILE), duty] {
+        let state = curing(plan, 25_500);
+        let checkpoint = Checkpoint::of(&state, 25_500, 1234).unwrap();
+        assert_eq!(Checkpoint::from_bytes(&checkpoint.to_bytes()), Some(checkpoint));
+        assert_eq!(checkpoint.plan(&PROFILE), Some(plan));
+    }
+}
+
+#[test]
+fn blank_flash_is_not_a_checkpoint() {
+    assert_eq!(Checkpoint::from_bytes(&[0x00; Checkpoint::SIZE]), None);
+    assert_eq!(Checkpoint::from_bytes(&[0xFF; Checkpoint::SIZE]), None);
+    assert_eq!(Checkpoint::from_bytes(&[1; 4]), None);
+}
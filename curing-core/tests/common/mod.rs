@@ -0,0 +1,106 @@
+// Test Fakes
+//
+// Stand-ins for the hardware the firmware reads: a button whose level is
+// scripted against a virtual clock, and a loop that feeds it to a
+// GestureDetector the same way src/button.rs does - except that instead of
+// sleeping it jumps straight to the next moment anything can happen.
+
+#![allow(dead_code)]
+
+use curing_core::gesture::{Gesture, GestureDetector, GestureTiming};
+
+/// The timing the firmware uses at idle (config.rs defaults)
+pub const IDLE_TIMING: GestureTiming = GestureTiming { debounce_ms: 50, hold_ms: 1500, double_tap_ms: Some(300), triple_tap: true, five_taps: false };
+
+/// The timing for picking presets with one button (multi-duration builds):
+/// every tap is reported straight away, hold = start
+pub const PRESET_TIMING: GestureTiming = GestureTiming { debounce_ms: 50, hold_ms: 1000, double_tap_ms: None, triple_tap: false, five_taps: false };
+
+/// The timing the firmware uses mid-cure: no double taps, hold = abort
+pub const CURE_TIMING: GestureTiming = GestureTiming { debounce_ms: 50, hold_ms: 2000, double_tap_ms: None, triple_tap: false, five_taps: false };
+
+/// A button pin that goes down and up at scripted times
+#[derive(Default)]
+pub struct FakeButton {
+    /// (time, pressed) level changes, in time order
+    changes: Vec<(u64, bool)>,
+}
+
+impl FakeButton {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Press at `at_ms` and release `for_ms` later
+    pub fn press(self, at_ms: u64, for_ms: u64) -> Self {
+        self.level(at_ms, true).level(at_ms + for_ms, false)
+    }
+
+    /// Press at `at_ms` and never let go
+    pub fn press_and_hold(self, at_ms: u64) -> Self {
+        self.level(at_ms, true)
+    }
+
+    /// Contact bounce: `count` flips 2 ms apart starting at `at_ms`,
+    /// ending on `settles_pressed`
+    pub fn bounce(mut self, at_ms: u64, count: u64, settles_pressed: bool) -> Self {
+        for i in 0..count {
+            let pressed = if (count - i) % 2 == 1 { settles_pressed } else { !settles_pressed };
+            self = self.level(at_ms + i * 2, pressed);
+        }
+        self
+    }
+
+    fn level(mut self, at_ms: u64, pressed: bool) -> Self {
+        self.changes.push((at_ms, pressed));
+        self.changes.sort_by_key(|&(at, _)| at);
+        self
+    }
+
+    /// The level at `now_ms`: true = pressed (pin LOW)
+    pub fn is_pressed(&self, now_ms: u64) -> bool {
+        self.changes.iter().take_while(|&&(at, _)| at <= now_ms).last().is_some_and(|&(_, pressed)| pressed)
+    }
+
+    /// The first level change after `now_ms`
+    pub fn next_change_after(&self, now_ms: u64) -> Option<u64> {
+        self.changes.iter().map(|&(at, _)| at).find(|&at| at > now_ms)
+    }
+}
+
+/// Feed `button` to `detector` from `*now` until it reports a gesture or
+/// `until_ms` is reached - returns the gesture and when it was reported.
+/// `*now` is left at that moment.
+pub fn next_gesture(detector: &mut GestureDetector, button: &FakeButton, now: &mut u64, until_ms: u64) -> Option<(Gesture, u64)> {
+    loop {
+        if let Some(gesture) = detector.update(*now, button.is_pressed(*now)) {
+            return Some((gesture, *now));
+        }
+
+        // Like button.rs: while bouncing only the settle time matters,
+        // otherwise the next level change or the detector's deadline
+        let next = if *now < detector.settled_at() {
+            Some(detector.settled_at())
+        } else {
+            [button.next_change_after(*now), detector.wake_at()].into_iter().flatten().min()
+        };
+        match next {
+            Some(at) if at <= until_ms => *now = at,
+            _ => {
+                *now = until_ms;
+                return None;
+            }
+        }
+    }
+}
+
+/// Every gesture from power-on to `until_ms`
+pub fn gestures(timing: GestureTiming, button: &FakeButton, until_ms: u64) -> Vec<(Gesture, u64)> {
+    let mut now = 0;
+    let mut detector = GestureDetector::new(timing, now, button.is_pressed(now));
+    let mut seen = Vec::new();
+    while let Some(gesture) = next_gesture(&mut detector, button, &mut now, until_ms) {
+        seen.push(gesture);
+    }
+    seen
+}
@@ -0,0 +1,72 @@
+// Cure phases: phase timing, pause/resume, the heat estimate
+
+use curing_core::cure::{self, CurePhase, PhaseEnd, PhaseKind, PhaseTimer};
+use curing_core::fault::FaultCode;
+use curing_core::heat::{HeatEstimate, HeatModel};
+
+/// config.rs defaults
+const HEAT_MODEL: HeatModel = HeatModel { budget_seconds: 600, cooling_ratio: 1, max_extra_cooldown_seconds: 600 };
+
+#[test]
+fn paused_timer_keeps_its_remaining_time() {
+    let mut timer = PhaseTimer::start(1_000, 10);
+    assert_eq!(timer.remaining_seconds(4_500), 6);
+    assert_eq!(timer.pause(4_500), 6_500);
+    assert!(timer.is_paused());
+    assert_eq!(timer.remaining_ms(100_000), 6_500);
+    assert!(!timer.is_finished(100_000));
+
+    timer.resume(100_000);
+    assert_eq!(timer.end_ms(), 106_500);
+    assert!(timer.is_finished(106_500));
+}
+
+#[test]
+fn resume_without_pause_changes_nothing() {
+    let mut timer = PhaseTimer::start(0, 10);
+    timer.resume(5_000);
+    assert_eq!(timer.end_ms(), 10_000);
+}
+
+#[test]
+fn phase_end_names_and_completion() {
+    assert!(PhaseEnd::Finished.completed());
+    assert!(PhaseEnd::SwitchedOff.completed());
+    assert!(!PhaseEnd::Aborted.completed());
+    assert!(!PhaseEnd::Fault(FaultCode::OverTemp).completed());
+    assert_eq!(PhaseEnd::SwitchedOff.name(), "switched_off");
+    assert_eq!(PhaseEnd::Fault(FaultCode::OverTemp).name(), "overheated");
+}
+
+#[test]
+fn profile_length_includes_rest_phases() {
+    let profile = [
+        CurePhase { kind: PhaseKind::UvOn, seconds: 30 },
+        CurePhase { kind: PhaseKind::Rest, seconds: 60 },
+        CurePhase { kind: PhaseKind::UvOn, seconds: 30 },
+    ];
+    assert_eq!(cure::planned_seconds(&profile), 120);
+    assert!(!profile[1].is_uv());
+}
+
+#[test]
+fn dial_stays_between_one_step_and_the_maximum() {
+    assert_eq!(cure::dial(300, true, 10, 600), 310);
+    assert_eq!(cure::dial(595, true, 10, 600), 600);
+    assert_eq!(cure::dial(10, false, 10, 600), 10);
+    assert_eq!(cure::dial(15, false, 10, 600), 10);
+}
+
+#[test]
+fn back_to_back_cures_stretch_the_cooldown() {
+    let mut heat = HeatEstimate::new(HEAT_MODEL, 0);
+    heat.uv_on(0);
+    heat.uv_off(500_000);
+    assert_eq!(heat.extra_cooldown_seconds(), 0);    // One cure stays within the budget
+
+    heat.uv_on(510_000);                             // 10 s later: sheds 10 s of heat
+    heat.uv_off(810_000);
+    assert_eq!(heat.heat_seconds(), 790);
+    assert_eq!(heat.extra_cooldown_seconds(), 190);
+    assert_eq!(heat.total_on_ms(), 800_000);
+}
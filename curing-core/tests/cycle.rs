@@ -0,0 +1,327 @@
+// The cure cycle state machine: whole cures with a scripted button, and
+// single transitions
+
+mod common;
+
+use common::{next_gesture, FakeButton, CURE_TIMING};
+use curing_core::cure::{CurePhase, PhaseEnd, PhaseKind};
+use curing_core::cycle::{CureEvent, CureState, Pause, Plan};
+use curing_core::fault::FaultCode;
+use curing_core::gesture::{Gesture, GestureDetector};
+
+/// How a cure went
+#[derive(Debug, PartialEq)]
+struct Run {
+    end: PhaseEnd,
+    /// When it ended
+    at_ms: u64,
+    /// Every time the UV switched: (time, on)
+    uv: Vec<(u64, bool)>,
+}
+
+/// A cure started at 0 ms, driven the way the main loop does it with a
+/// momentary button (no heater, lid or thermistor): a tap pauses, a tap
+/// while paused resumes, a hold aborts
+fn run(plan: Plan<'_>, button: &FakeButton) -> Run {
+    let mut now = 0;
+    let mut state = CureState::Idle.transition(CureEvent::Start(plan), now).transition(CureEvent::Armed, now);
+    let mut uv = vec![];
+
+    loop {
+        if uv.last().map(|&(_, on)| on) != Some(state.uv_on()) {
+            uv.push((now, state.uv_on()));
+        }
+        let event = match state {
+            CureState::Curing { remaining, paused: None, .. } => {
+                let mut detector = GestureDetector::new(CURE_TIMING, now, button.is_pressed(now));
+                match next_gesture(&mut detector, button, &mut now, remaining.end_ms()) {
+                    None => CureEvent::Tick,
+                    Some((Gesture::Hold, _)) => CureEvent::Abort,
+                    Some(_) => CureEvent::Tap,
+                }
+            }
+            CureState::Curing { paused: Some(Pause::Button), .. } => {
+                let mut detector = GestureDetector::new(CURE_TIMING, now, button.is_pressed(now));
+                match next_gesture(&mut detector, button, &mut now, u64::MAX) {
+                    Some((Gesture::Hold, _)) => CureEvent::Abort,
+                    Some(_) => CureEvent::Tap,
+                    None => panic!("paused for ever"),
+                }
+            }
+            CureState::Finishing { end } => return Run { end, at_ms: now, uv },
+            other => panic!("unexpected state {:?}", other),
+        };
+        state = state.transition(event, now);
+    }
+}
+
+#[test]
+fn untouched_cure_runs_its_full_time() {
+    let run = run(Plan::Single(60), &FakeButton::new());
+    assert_eq!(run, Run { end: PhaseEnd::Finished, at_ms: 60_000, uv: vec![(0, true), (60_000, false)] });
+}
+
+#[test]
+fn hold_mid_cure_aborts_at_the_hold_time() {
+    let button = FakeButton::new().press_and_hold(30_000);
+    let run = run(Plan::Single(60), &button);
+    assert_eq!(run, Run { end: PhaseEnd::Aborted, at_ms: 32_000, uv: vec![(0, true), (32_000, false)] });
+}
+
+#[test]
+fn tap_pauses_and_the_pause_is_added_on() {
+    // Paused at 10.15 s with 49.85 s left, resumed at 20.15 s
+    let button = FakeButton::new().press(10_000, 100).press(20_000, 100);
+    let run = run(Plan::Single(60), &button);
+    assert_eq!(run.end, PhaseEnd::Finished);
+    assert_eq!(run.at_ms, 70_000);
+    assert_eq!(run.uv, [(0, true), (10_150, false), (20_150, true), (70_000, false)]);
+}
+
+#[test]
+fn hold_while_paused_aborts() {
+    let button = FakeButton::new().press(10_000, 100).press_and_hold(15_000);
+    let run = run(Plan::Single(60), &button);
+    assert_eq!(run, Run { end: PhaseEnd::Aborted, at_ms: 17_000, uv: vec![(0, true), (10_150, false)] });
+}
+
+#[test]
+fn press_held_from_before_the_cure_does_nothing() {
+    // The hold that started the cure (multi-duration builds) is still down
+    let button = FakeButton::new().press(0, 5_000);
+    let run = run(Plan::Single(60), &button);
+    assert_eq!(run.end, PhaseEnd::Finished);
+    assert_eq!(run.at_ms, 60_000);
+}
+
+#[test]
+fn profile_runs_each_phase_in_turn() {
+    let profile = [
+        CurePhase { kind: PhaseKind::UvOn, seconds: 30 },
+        CurePhase { kind: PhaseKind::Rest, seconds: 60 },
+        CurePhase { kind: PhaseKind::UvOn, seconds: 30 },
+    ];
+    let run = run(Plan::Profile(&profile), &FakeButton::new());
+    assert_eq!(run.end, PhaseEnd::Finished);
+    assert_eq!(run.uv, [(0, true), (30_000, false), (90_000, true), (120_000, false)]);
+}
+
+#[test]
+fn abort_during_a_rest_phase_skips_the_rest() {
+    let profile = [
+        CurePhase { kind: PhaseKind::UvOn, seconds: 30 },
+        CurePhase { kind: PhaseKind::Rest, seconds: 60 },
+        CurePhase { kind: PhaseKind::UvOn, seconds: 30 },
+    ];
+    let button = FakeButton::new().press_and_hold(40_000);
+    let run = run(Plan::Profile(&profile), &button);
+    assert_eq!(run, Run { end: PhaseEnd::Aborted, at_ms: 42_000, uv: vec![(0, true), (30_000, false)] });
+}
+
+#[test]
+fn lid_pauses_and_closing_it_resumes() {
+    let curing = CureState::Idle.transition(CureEvent::Start(Plan::Single(60)), 0).transition(CureEvent::Armed, 0);
+    let open = curing.transition(CureEvent::LidOpened, 20_000);
+    assert!(!open.uv_on());
+
+    // A tap doesn't resume a lid pause
+    assert_eq!(open.transition(CureEvent::Tap, 25_000), open);
+
+    let closed = open.transition(CureEvent::LidClosed, 50_000);
+    assert!(closed.uv_on());
+    match closed {
+        CureState::Curing { remaining, .. } => assert_eq!(remaining.end_ms(), 90_000),
+        other => panic!("not curing: {:?}", other),
+    }
+}
+
+#[test]
+fn heat_pauses_until_the_chamber_cools() {
+    let curing = CureState::Idle.transition(CureEvent::Start(Plan::Single(60)), 0).transition(CureEvent::Armed, 0);
+    let warm = curing.transition(CureEvent::TooWarm, 10_000);
+    assert!(!warm.uv_on());
+
+    // Neither a tap nor the lid closing resumes a heat pause
+    assert_eq!(warm.transition(CureEvent::Tap, 15_000), warm);
+    assert_eq!(warm.transition(CureEvent::LidClosed, 15_000), warm);
+
+    let cooled = warm.transition(CureEvent::CooledDown, 40_000);
+    assert!(cooled.uv_on());
+    match cooled {
+        CureState::Curing { remaining, .. } => assert_eq!(remaining.end_ms(), 90_000),
+        other => panic!("not curing: {:?}", other),
+    }
+}
+
+#[test]
+fn cooling_down_does_not_end_a_button_pause() {
+    let curing = CureState::Idle.transition(CureEvent::Start(Plan::Single(60)), 0).transition(CureEvent::Armed, 0);
+    let paused = curing.transition(CureEvent::Tap, 10_000);
+    assert_eq!(paused.transition(CureEvent::TooWarm, 12_000), paused);
+    assert_eq!(paused.transition(CureEvent::CooledDown, 20_000), paused);
+}
+
+#[test]
+fn lid_open_at_start_goes_back_to_idle() {
+    let arming = CureState::Idle.transition(CureEvent::Start(Plan::Single(60)), 0);
+    assert_eq!(arming, CureState::Arming { plan: Plan::Single(60), from: None });
+    assert_eq!(arming.transition(CureEvent::Refused, 0), CureState::Idle);
+}
+
+#[test]
+fn abort_while_arming_finishes_without_any_uv() {
+    let arming = CureState::Idle.transition(CureEvent::Start(Plan::Single(60)), 0);
+    let stopped = arming.transition(CureEvent::Abort, 5_000);
+    assert_eq!(stopped, CureState::Finishing { end: PhaseEnd::Aborted });
+}
+
+#[test]
+fn overheating_leads_to_a_fault_until_cleared() {
+    let curing = CureState::Idle.transition(CureEvent::Start(Plan::Single(60)), 0).transition(CureEvent::Armed, 0);
+    let finishing = curing.transition(CureEvent::Fault(FaultCode::OverTemp), 10_000);
+    assert_eq!(finishing, CureState::Finishing { end: PhaseEnd::Fault(FaultCode::OverTemp) });
+    assert!(!finishing.uv_on());
+
+    let fault = finishing.transition(CureEvent::Finished, 20_000);
+    assert_eq!(fault, CureState::Fault(FaultCode::OverTemp));
+    // No new cure while in the fault state
+    assert_eq!(fault.transition(CureEvent::Start(Plan::Single(60)), 30_000), fault);
+    assert_eq!(fault.transition(CureEvent::Cleared, 40_000), CureState::Idle);
+}
+
+#[test]
+fn a_fault_while_arming_never_turns_the_uv_on() {
+    let arming = CureState::Idle.transition(CureEvent::Start(Plan::Single(60)), 0);
+    let finishing = arming.transition(CureEvent::Fault(FaultCode::RelayStuck), 1_000);
+    assert_eq!(finishing, CureState::Finishing { end: PhaseEnd::Fault(FaultCode::RelayStuck) });
+    assert_eq!(finishing.transition(CureEvent::Finished, 2_000), CureState::Fault(FaultCode::RelayStuck));
+}
+
+#[test]
+fn switching_off_ends_the_cure_normally() {
+    let curing = CureState::Idle.transition(CureEvent::Start(Plan::Single(600)), 0).transition(CureEvent::Armed, 0);
+    let finishing = curing.transition(CureEvent::SwitchedOff, 90_000);
+    assert_eq!(finishing, CureState::Finishing { end: PhaseEnd::SwitchedOff });
+    assert_eq!(finishing.transition(CureEvent::Finished, 95_000), CureState::Idle);
+}
+
+#[test]
+fn idle_ignores_everything_but_a_start() {
+    for event in [CureEvent::Tap, CureEvent::Abort, CureEvent::Tick, CureEvent::LidOpened, CureEvent::Finished] {
+        assert_eq!(CureState::Idle.transition(event, 1_000), CureState::Idle);
+    }
+}
+
+#[test]
+fn early_tick_changes_nothing() {
+    let curing = CureState::Idle.transition(CureEvent::Start(Plan::Single(60)), 0).transition(CureEvent::Armed, 0);
+    assert_eq!(curing.transition(CureEvent::Tick, 59_999), curing);
+}
+
+#[test]
+fn progress_counts_every_phase_and_stops_while_paused() {
+    let profile = [
+        CurePhase { kind: PhaseKind::UvOn, seconds: 30 },
+        CurePhase { kind: PhaseKind::Rest, seconds: 60 },
+        CurePhase { kind: PhaseKind::UvOn, seconds: 30 },
+    ];
+    let plan = Plan::Profile(&profile);
+    let state = CureState::Idle.transition(CureEvent::Start(plan), 0).transition(CureEvent::Armed, 0);
+    assert_eq!(state.progress_ms(10_000), Some((10_000, 120_000)));
+
+    // Into the rest phase
+    let resting = state.transition(CureEvent::Tick, 30_000);
+    assert_eq!(resting.progress_ms(45_000), Some((45_000, 120_000)));
+
+    // Paused: frozen where it was
+    let paused = resting.transition(CureEvent::Tap, 50_000);
+    assert_eq!(paused.progress_ms(80_000), Some((50_000, 120_000)));
+
+    assert_eq!(CureState::Idle.progress_ms(0), None);
+}
+
+#[test]
+fn duty_cycle_alternates_and_cuts_the_last_uv_phase_short() {
+    // 75 s of UV as 20 s on, 10 s off: UV 0-20, 30-50, 60-80, 90-105
+    let plan = Plan::DutyCycle { seconds: 75, on_seconds: 20, off_seconds: 10 };
+    let run = run(plan, &FakeButton::new());
+    assert_eq!(run.end, PhaseEnd::Finished);
+    assert_eq!(run.at_ms, 105_000);
+    assert_eq!(
+        run.uv,
+        [(0, true), (20_000, false), (30_000, true), (50_000, false), (60_000, true), (80_000, false), (90_000, true), (105_000, false)]
+    );
+    assert_eq!(plan.phase_count(), 7);
+}
+
+#[test]
+fn duty_cycle_uv_adds_up_to_the_curing_time() {
+    // However the curing time divides up - including a last bit no longer
+    // than a rest (25 s: 20 s, rest, 5 s)
+    for seconds in [1, 5, 20, 25, 30, 40, 41, 75, 300] {
+        let plan = Plan::DutyCycle { seconds, on_seconds: 20, off_seconds: 10 };
+        let run = run(plan, &FakeButton::new());
+        let uv_ms: u64 = run.uv.chunks(2).map(|on_off| on_off[1].0 - on_off[0].0).sum();
+        assert_eq!(uv_ms, seconds * 1000, "{} s", seconds);
+    }
+}
+
+#[test]
+fn duty_cycle_never_ends_on_a_rest() {
+    // 40 s of UV as 20 s on, 10 s off: no rest after the second UV phase
+    let plan = Plan::DutyCycle { seconds: 40, on_seconds: 20, off_seconds: 10 };
+    assert_eq!(plan.phase_count(), 3);
+    assert_eq!(plan.planned_seconds(), 50);
+    assert_eq!(run(plan, &FakeButton::new()).at_ms, 50_000);
+}
+
+#[test]
+fn duty_cycle_counts_down_the_whole_cure() {
+    // 75 s of UV plus three 10 s rests
+    let plan = Plan::DutyCycle { seconds: 75, on_seconds: 20, off_seconds: 10 };
+    let state = CureState::Idle.transition(CureEvent::Start(plan), 0).transition(CureEvent::Armed, 0);
+    assert_eq!(state.countdown_ms(5_000), Some(100_000));
+    let resting = state.transition(CureEvent::Tick, 20_000);
+    assert_eq!(resting.countdown_ms(25_000), Some(80_000));
+
+    // Anything else counts down the running phase
+    let single = CureState::Idle.transition(CureEvent::Start(Plan::Single(60)), 0).transition(CureEvent::Armed, 0);
+    assert_eq!(single.countdown_ms(5_000), Some(55_000));
+}
+
+#[test]
+fn added_time_stretches_the_running_phase() {
+    let state = CureState::Idle.transition(CureEvent::Start(Plan::Single(60)), 0).transition(CureEvent::Armed, 0);
+    let longer = state.transition(CureEvent::AddTime { seconds: 30, max_seconds: 600 }, 10_000);
+    assert_eq!(longer.countdown_ms(10_000), Some(80_000));
+    assert_eq!(longer.progress_ms(10_000), Some((10_000, 90_000)));
+    assert_eq!(longer.transition(CureEvent::Tick, 60_000), longer);      // Not over yet
+    assert_eq!(longer.transition(CureEvent::Tick, 90_000), CureState::Finishing { end: PhaseEnd::Finished });
+}
+
+#[test]
+fn added_time_stops_at_the_maximum() {
+    let state = CureState::Idle.transition(CureEvent::Start(Plan::Single(60)), 0).transition(CureEvent::Armed, 0);
+    assert_eq!(state.transition(CureEvent::AddTime { seconds: 30, max_seconds: 80 }, 10_000), state);
+
+    // A profile's phases are fixed
+    const PROFILE: &[CurePhase] = &[CurePhase { kind: PhaseKind::UvOn, seconds: 30 }];
+    let profile = CureState::Idle.transition(CureEvent::Start(Plan::Profile(PROFILE)), 0).transition(CureEvent::Armed, 0);
+    assert_eq!(profile.transition(CureEvent::AddTime { seconds: 30, max_seconds: 600 }, 10_000), profile);
+}
+
+#[test]
+fn added_time_goes_on_the_end_of_a_duty_cycle() {
+    // 75 s of UV as 20 s on, 10 s off, in the cut-short last UV phase (90-105 s)
+    let plan = Plan::DutyCycle { seconds: 75, on_seconds: 20, off_seconds: 10 };
+    let mut state = CureState::Idle.transition(CureEvent::Start(plan), 0).transition(CureEvent::Armed, 0);
+    for at in [20_000, 30_000, 50_000, 60_000, 80_000, 90_000] {
+        state = state.transition(CureEvent::Tick, at);
+    }
+    // +30 s of UV: this phase runs its full 20 s (to 110 s), then a rest,
+    // 20 s UV, a rest and the last 5 s UV (to 155 s)
+    let longer = state.transition(CureEvent::AddTime { seconds: 30, max_seconds: 600 }, 95_000);
+    assert_eq!(longer.countdown_ms(95_000), Some(60_000));
+    let resting = longer.transition(CureEvent::Tick, 110_000);
+    assert!(!resting.uv_on());
+}
@@ -0,0 +1,170 @@
+// Pin driver tests: each driver against a fake pin, checking which level
+// it ends up at
+
+use std::cell::Cell;
+use std::convert::Infallible;
+
+use curing_core::drivers::{ActiveBuzzer, Button, DimmableLed, Led, PinRelay, PinState, Tone, UvSwitch};
+use embedded_hal::digital::{ErrorType, InputPin, OutputPin};
+use embedded_hal::pwm::{self, SetDutyCycle};
+use embedded_hal_async::digital::Wait;
+
+/// A pin that just remembers its level in a shared cell (starting HIGH, so
+/// the drivers have to set their own idle level)
+struct FakePin<'a> {
+    high: &'a Cell<bool>,
+}
+
+impl<'a> FakePin<'a> {
+    fn new(high: &'a Cell<bool>) -> Self {
+        high.set(true);
+        Self { high }
+    }
+}
+
+impl ErrorType for FakePin<'_> {
+    type Error = Infallible;
+}
+
+impl OutputPin for FakePin<'_> {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        self.high.set(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        self.high.set(true);
+        Ok(())
+    }
+}
+
+impl InputPin for FakePin<'_> {
+    fn is_high(&mut self) -> Result<bool, Infallible> {
+        Ok(self.high.get())
+    }
+
+    fn is_low(&mut self) -> Result<bool, Infallible> {
+        Ok(!self.high.get())
+    }
+}
+
+// Never waited on here - only the sync side of `Button` is tested
+impl Wait for FakePin<'_> {
+    async fn wait_for_high(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+/// A PWM output that remembers its duty cycle (starting fully on)
+struct FakePwm<'a> {
+    duty: &'a Cell<u16>,
+}
+
+impl pwm::ErrorType for FakePwm<'_> {
+    type Error = Infallible;
+}
+
+impl SetDutyCycle for FakePwm<'_> {
+    fn max_duty_cycle(&self) -> u16 {
+        1000
+    }
+
+    fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Infallible> {
+        self.duty.set(duty);
+        Ok(())
+    }
+}
+
+#[test]
+fn active_high_relay_starts_off() {
+    let level = Cell::default();
+    let mut relay = PinRelay::new(FakePin::new(&level), PinState::High, 50);
+    assert!(!level.get());
+    relay.on();
+    assert!(level.get());
+    relay.off();
+    assert!(!level.get());
+    assert_eq!(relay.settle_time_ms(), 50);
+}
+
+#[test]
+fn active_low_relay_starts_off() {
+    let level = Cell::default();
+    let mut relay = PinRelay::new(FakePin::new(&level), PinState::Low, 0);
+    assert!(level.get());
+    relay.on();
+    assert!(!level.get());
+    relay.off();
+    assert!(level.get());
+}
+
+#[test]
+fn active_buzzer_sounds_for_any_pitch() {
+    let level = Cell::default();
+    let mut buzzer = ActiveBuzzer::new(FakePin::new(&level));
+    assert!(!level.get());
+    buzzer.tone(2_700);
+    assert!(level.get());
+    buzzer.tone(0);
+    assert!(!level.get());
+}
+
+#[test]
+fn led_starts_dark() {
+    let level = Cell::default();
+    let mut led = Led::new(FakePin::new(&level));
+    assert!(!level.get());
+    led.on();
+    assert!(level.get());
+    led.set(false);
+    assert!(!level.get());
+}
+
+#[test]
+fn dimmable_led_starts_dark_and_dims() {
+    let duty = Cell::new(1000);
+    let mut led = DimmableLed::new(FakePwm { duty: &duty });
+    assert_eq!(duty.get(), 0);
+    led.on();
+    assert_eq!(duty.get(), 1000);
+    led.set_percent(25);
+    assert_eq!(duty.get(), 250);
+    led.set_percent(200);
+    assert_eq!(duty.get(), 1000);
+    led.off();
+    assert_eq!(duty.get(), 0);
+}
+
+#[test]
+fn pull_up_button_is_pressed_when_low() {
+    let level = Cell::default();
+    let mut button = Button::new(FakePin::new(&level), PinState::Low);
+    assert!(!button.is_pressed());
+    level.set(false);
+    assert!(button.is_pressed());
+}
+
+#[test]
+fn pull_down_button_is_pressed_when_high() {
+    let level = Cell::default();
+    let mut button = Button::new(FakePin::new(&level), PinState::High);
+    assert!(button.is_pressed());
+    level.set(false);
+    assert!(!button.is_pressed());
+}
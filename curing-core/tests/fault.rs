@@ -0,0 +1,35 @@
+// Fault codes: one number per fault, none of them easy to misread
+
+use curing_core::fault::FaultCode;
+
+const ALL: [FaultCode; 6] = [
+    FaultCode::LampOpen,
+    FaultCode::LampShorted,
+    FaultCode::RelayStuck,
+    FaultCode::LampDark,
+    FaultCode::OverTemp,
+    FaultCode::SensorMissing,
+];
+
+#[test]
+fn every_fault_has_its_own_code() {
+    for (i, a) in ALL.iter().enumerate() {
+        for b in &ALL[i + 1..] {
+            assert_ne!(a.code(), b.code(), "{:?} and {:?} blink the same", a, b);
+        }
+    }
+}
+
+#[test]
+fn codes_are_easy_to_count() {
+    for fault in ALL {
+        assert!((2..=9).contains(&fault.code()), "{:?} blinks {} times", fault, fault.code());
+    }
+}
+
+#[test]
+fn only_sensor_faults_wait_for_the_sensor() {
+    assert!(FaultCode::OverTemp.waits_for_sensor());
+    assert!(FaultCode::SensorMissing.waits_for_sensor());
+    assert!(!FaultCode::RelayStuck.waits_for_sensor());
+}
@@ -0,0 +1,156 @@
+// Button gestures: debouncing, taps, holds, double, triple and five taps
+
+mod common;
+
+use common::{gestures, FakeButton, CURE_TIMING, IDLE_TIMING, PRESET_TIMING};
+use curing_core::gesture::{Gesture, GestureDetector, GestureTiming};
+
+#[test]
+fn tap_is_reported_once_the_release_has_settled() {
+    let button = FakeButton::new().press(1000, 200);
+    assert_eq!(gestures(CURE_TIMING, &button, 10_000), [(Gesture::Tap, 1250)]);
+}
+
+#[test]
+fn contact_bounce_is_one_press() {
+    // Bouncy press and bouncy release around a 200 ms tap
+    let button = FakeButton::new().bounce(1000, 7, true).bounce(1200, 5, false);
+    assert_eq!(gestures(CURE_TIMING, &button, 10_000), [(Gesture::Tap, 1250)]);
+}
+
+#[test]
+fn glitch_shorter_than_a_bounce_is_still_a_tap() {
+    // The debounce doesn't filter short presses - it just ignores changes
+    // for a while after each one. A 20 ms press reads as a tap, with the
+    // release seen once the press has settled at 1050.
+    let button = FakeButton::new().press(1000, 20);
+    assert_eq!(gestures(CURE_TIMING, &button, 10_000), [(Gesture::Tap, 1100)]);
+}
+
+#[test]
+fn hold_is_reported_while_still_held() {
+    let button = FakeButton::new().press_and_hold(1000);
+    assert_eq!(gestures(CURE_TIMING, &button, 10_000), [(Gesture::Hold, 3000)]);
+}
+
+#[test]
+fn release_after_a_hold_is_not_a_tap() {
+    let button = FakeButton::new().press(1000, 2500).bounce(3500, 5, false);
+    assert_eq!(gestures(CURE_TIMING, &button, 10_000), [(Gesture::Hold, 3000)]);
+}
+
+#[test]
+fn release_just_before_the_hold_time_is_a_tap() {
+    let button = FakeButton::new().press(1000, 1999);
+    assert_eq!(gestures(CURE_TIMING, &button, 10_000), [(Gesture::Tap, 3049)]);
+}
+
+#[test]
+fn double_tap_inside_the_window() {
+    let timing = GestureTiming { triple_tap: false, ..IDLE_TIMING };
+    let button = FakeButton::new().press(1000, 100).press(1300, 100);
+    assert_eq!(gestures(timing, &button, 10_000), [(Gesture::DoubleTap, 1450)]);
+}
+
+#[test]
+fn double_tap_waits_for_the_triple_tap_window() {
+    let button = FakeButton::new().press(1000, 100).press(1300, 100);
+    assert_eq!(gestures(IDLE_TIMING, &button, 10_000), [(Gesture::DoubleTap, 1750)]);
+}
+
+#[test]
+fn triple_tap_inside_the_window() {
+    let button = FakeButton::new().press(1000, 100).press(1300, 100).press(1600, 100);
+    assert_eq!(gestures(IDLE_TIMING, &button, 10_000), [(Gesture::TripleTap, 1750)]);
+}
+
+#[test]
+fn five_taps_inside_the_window() {
+    let timing = GestureTiming { five_taps: true, ..IDLE_TIMING };
+    let button = FakeButton::new().press(1000, 100).press(1300, 100).press(1600, 100).press(1900, 100).press(2200, 100);
+    assert_eq!(gestures(timing, &button, 10_000), [(Gesture::FiveTaps, 2350)]);
+}
+
+#[test]
+fn triple_tap_waits_for_the_five_tap_window() {
+    let timing = GestureTiming { five_taps: true, ..IDLE_TIMING };
+    let button = FakeButton::new().press(1000, 100).press(1300, 100).press(1600, 100);
+    assert_eq!(gestures(timing, &button, 10_000), [(Gesture::TripleTap, 2050)]);
+}
+
+#[test]
+fn four_taps_are_nothing() {
+    // ...and don't stop the next tap being seen
+    let timing = GestureTiming { five_taps: true, ..IDLE_TIMING };
+    let button = FakeButton::new().press(1000, 100).press(1300, 100).press(1600, 100).press(1900, 100).press(3000, 100);
+    assert_eq!(gestures(timing, &button, 10_000), [(Gesture::Tap, 3450)]);
+}
+
+#[test]
+fn taps_further_apart_are_two_taps() {
+    // The second press comes after the 300 ms window following the settled release
+    let button = FakeButton::new().press(1000, 100).press(1500, 100);
+    assert_eq!(gestures(IDLE_TIMING, &button, 10_000), [(Gesture::Tap, 1450), (Gesture::Tap, 1950)]);
+}
+
+#[test]
+fn single_tap_waits_for_the_double_tap_window() {
+    let button = FakeButton::new().press(1000, 100);
+    assert_eq!(gestures(IDLE_TIMING, &button, 10_000), [(Gesture::Tap, 1450)]);
+}
+
+#[test]
+fn idle_hold_uses_the_idle_hold_time() {
+    let button = FakeButton::new().press_and_hold(1000);
+    assert_eq!(gestures(IDLE_TIMING, &button, 10_000), [(Gesture::Hold, 2500)]);
+}
+
+#[test]
+fn button_already_down_at_start_is_ignored() {
+    // Still held from the last gesture: only a fresh press counts
+    let button = FakeButton::new().press(0, 5000).press(6000, 100);
+    assert_eq!(gestures(CURE_TIMING, &button, 10_000), [(Gesture::Tap, 6150)]);
+}
+
+#[test]
+fn no_gesture_without_a_press() {
+    let button = FakeButton::new();
+    assert!(gestures(IDLE_TIMING, &button, 60_000).is_empty());
+}
+
+#[test]
+fn debounced_level_follows_the_button() {
+    let mut detector = GestureDetector::new(CURE_TIMING, 0, false);
+    assert_eq!(detector.update(100, true), None);
+    assert!(detector.is_pressed());
+    assert_eq!(detector.settled_at(), 150);
+
+    // A bounce back up during the settle time is ignored
+    assert_eq!(detector.update(110, false), None);
+    assert!(detector.is_pressed());
+    assert_eq!(detector.wake_at(), Some(2100));
+}
+
+#[test]
+fn quick_preset_taps_are_never_a_double_tap() {
+    // Stepping through the presets as fast as possible: two taps, not a
+    // double tap (which would run the last cure again)
+    let button = FakeButton::new().press(1000, 100).press(1200, 100);
+    assert_eq!(gestures(PRESET_TIMING, &button, 10_000), [(Gesture::Tap, 1150), (Gesture::Tap, 1350)]);
+}
+
+#[test]
+fn quick_preset_taps_are_never_a_triple_tap() {
+    // Three quick steps through the presets, not the settings menu
+    let button = FakeButton::new().press(1000, 100).press(1200, 100).press(1400, 100);
+    let taps = gestures(PRESET_TIMING, &button, 10_000);
+    assert_eq!(taps, [(Gesture::Tap, 1150), (Gesture::Tap, 1350), (Gesture::Tap, 1550)]);
+}
+
+#[test]
+fn five_quick_preset_taps_are_five_steps() {
+    // Not a BOOTSEL restart, and no triple or double tap in among them
+    let button = FakeButton::new().press(1000, 100).press(1200, 100).press(1400, 100).press(1600, 100).press(1800, 100);
+    let taps = gestures(PRESET_TIMING, &button, 10_000);
+    assert_eq!(taps, [(Gesture::Tap, 1150), (Gesture::Tap, 1350), (Gesture::Tap, 1550), (Gesture::Tap, 1750), (Gesture::Tap, 1950)]);
+}
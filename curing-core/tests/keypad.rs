@@ -0,0 +1,50 @@
+// Keypad duration entry: typing, rubbing out and starting
+
+use curing_core::keypad::{DurationEntry, EntryUpdate};
+
+fn type_keys(entry: &mut DurationEntry, keys: &str) -> Vec<Option<EntryUpdate>> {
+    keys.chars().map(|key| entry.key(key)).collect()
+}
+
+#[test]
+fn digits_then_hash_start_that_many_seconds() {
+    let mut entry = DurationEntry::new(300, 600);
+    assert_eq!(
+        type_keys(&mut entry, "180#"),
+        [Some(EntryUpdate::Typed(1)), Some(EntryUpdate::Typed(18)), Some(EntryUpdate::Typed(180)), Some(EntryUpdate::Start(180))]
+    );
+    // Typed time becomes the one an empty # repeats
+    assert_eq!(entry.last_seconds(), 180);
+    assert_eq!(entry.key('#'), Some(EntryUpdate::Start(180)));
+}
+
+#[test]
+fn empty_hash_starts_the_default() {
+    let mut entry = DurationEntry::new(300, 600);
+    assert_eq!(entry.key('#'), Some(EntryUpdate::Start(300)));
+}
+
+#[test]
+fn star_rubs_out_the_last_digit() {
+    let mut entry = DurationEntry::new(300, 600);
+    type_keys(&mut entry, "125");
+    assert_eq!(entry.key('*'), Some(EntryUpdate::Typed(12)));
+    assert_eq!(type_keys(&mut entry, "0#").last(), Some(&Some(EntryUpdate::Start(120))));
+}
+
+#[test]
+fn too_long_or_zero_is_rejected() {
+    let mut entry = DurationEntry::new(300, 600);
+    assert_eq!(type_keys(&mut entry, "7000#")[3..], [Some(EntryUpdate::Typed(7000)), Some(EntryUpdate::Rejected)]);
+    assert_eq!(type_keys(&mut entry, "0#")[1], Some(EntryUpdate::Rejected));
+    assert_eq!(type_keys(&mut entry, "12345")[4], Some(EntryUpdate::Rejected));
+    // A rejected # leaves the last good time alone
+    entry.clear();
+    assert_eq!(entry.key('#'), Some(EntryUpdate::Start(300)));
+}
+
+#[test]
+fn letters_do_nothing() {
+    let mut entry = DurationEntry::new(300, 600);
+    assert_eq!(type_keys(&mut entry, "ABCD"), [None, None, None, None]);
+}
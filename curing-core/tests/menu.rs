@@ -0,0 +1,62 @@
+// Settings menu: stepping through the choices and the settings
+
+use curing_core::menu::{MenuChoices, MenuItem, MenuSettings, MenuUpdate, SettingsMenu};
+
+const CHOICES: MenuChoices<'static> = MenuChoices {
+    cure_seconds: &[60, 120, 300, 600],
+    completion_beeps: &[1, 3, 5],
+    cooldown_ms: &[0, 1000, 5000],
+};
+
+const DEFAULTS: MenuSettings = MenuSettings { cure_seconds: 300, completion_beeps: 3, cooldown_ms: 1000, muted: false };
+
+#[test]
+fn tap_steps_through_the_choices_and_wraps() {
+    let mut menu = SettingsMenu::new(DEFAULTS, CHOICES);
+    assert_eq!(menu.choice(), 3);
+    assert_eq!(menu.tap(), MenuUpdate::Changed { item: MenuItem::CureTime, choice: 4 });
+    assert_eq!(menu.tap(), MenuUpdate::Changed { item: MenuItem::CureTime, choice: 1 });
+    assert_eq!(menu.settings().cure_seconds, 60);
+}
+
+#[test]
+fn hold_moves_on_and_finishes_after_the_last_setting() {
+    let mut menu = SettingsMenu::new(DEFAULTS, CHOICES);
+    assert_eq!(menu.hold(), MenuUpdate::NextItem(MenuItem::CompletionBeeps));
+    menu.tap();
+    assert_eq!(menu.hold(), MenuUpdate::NextItem(MenuItem::Cooldown));
+    menu.tap();
+    menu.tap();
+    assert_eq!(menu.hold(), MenuUpdate::NextItem(MenuItem::Sound));
+    assert_eq!(menu.hold(), MenuUpdate::Done(MenuSettings { cure_seconds: 300, completion_beeps: 5, cooldown_ms: 0, muted: false }));
+}
+
+#[test]
+fn tap_toggles_the_sound() {
+    let mut menu = SettingsMenu::new(DEFAULTS, CHOICES);
+    for _ in 0..3 {
+        menu.hold();
+    }
+    assert_eq!(menu.item(), MenuItem::Sound);
+    assert_eq!(menu.choice(), 1);             // 1 = on
+    assert_eq!(menu.tap(), MenuUpdate::Changed { item: MenuItem::Sound, choice: 2 });
+    assert!(menu.settings().muted);
+    assert_eq!(menu.tap(), MenuUpdate::Changed { item: MenuItem::Sound, choice: 1 });
+    assert!(!menu.settings().muted);
+}
+
+#[test]
+fn a_value_off_the_list_moves_to_the_next_choice_above() {
+    let settings = MenuSettings { cure_seconds: 200, ..DEFAULTS };
+    let mut menu = SettingsMenu::new(settings, CHOICES);
+    assert_eq!(menu.choice(), 3);             // Shown as the nearest choice above
+    menu.tap();
+    assert_eq!(menu.settings().cure_seconds, 300);
+
+    // Beyond the last choice: wraps to the first
+    let settings = MenuSettings { cure_seconds: 900, ..DEFAULTS };
+    let mut menu = SettingsMenu::new(settings, CHOICES);
+    assert_eq!(menu.choice(), 4);
+    menu.tap();
+    assert_eq!(menu.settings().cure_seconds, 60);
+}
@@ -0,0 +1,57 @@
+// NEC IR decoding: whole messages fed in as level timings
+
+use curing_core::nec::{NecDecoder, NecEvent};
+
+/// Feed a leader and 32 bits, returning whatever the decoder reported
+fn send(decoder: &mut NecDecoder, bits: u32) -> Vec<NecEvent> {
+    let mut timings = vec![(true, 9_000), (false, 4_500)];
+    for i in 0..32 {
+        timings.push((true, 562));
+        timings.push((false, if bits & 1 << i != 0 { 1_687 } else { 562 }));
+    }
+    timings.push((true, 562));      // Stop burst
+    timings.into_iter().filter_map(|(low, us)| decoder.level_ended(low, us)).collect()
+}
+
+/// address, !address, command, !command - least significant byte first
+fn message(address: u8, command: u8) -> u32 {
+    u32::from_le_bytes([address, !address, command, !command])
+}
+
+#[test]
+fn decodes_a_key() {
+    let mut decoder = NecDecoder::new();
+    assert_eq!(send(&mut decoder, message(0x00, 0x1C)), [NecEvent::Key { address: 0x00, command: 0x1C }]);
+    // And the next one, straight after
+    assert_eq!(send(&mut decoder, message(0x00, 0x45)), [NecEvent::Key { address: 0x00, command: 0x45 }]);
+}
+
+#[test]
+fn extended_address_is_16_bits() {
+    let mut decoder = NecDecoder::new();
+    let bits = u32::from_le_bytes([0x34, 0x12, 0x07, !0x07]);
+    assert_eq!(send(&mut decoder, bits), [NecEvent::Key { address: 0x1234, command: 0x07 }]);
+}
+
+#[test]
+fn bad_command_check_is_dropped() {
+    let mut decoder = NecDecoder::new();
+    assert!(send(&mut decoder, u32::from_le_bytes([0x00, 0xFF, 0x1C, 0x1C])).is_empty());
+}
+
+#[test]
+fn held_key_repeats() {
+    let mut decoder = NecDecoder::new();
+    assert_eq!(decoder.level_ended(true, 9_100), None);
+    assert_eq!(decoder.level_ended(false, 2_200), Some(NecEvent::Repeat));
+}
+
+#[test]
+fn noise_midway_starts_again() {
+    let mut decoder = NecDecoder::new();
+    decoder.level_ended(true, 9_000);
+    decoder.level_ended(false, 4_500);
+    decoder.level_ended(true, 562);
+    decoder.level_ended(false, 3_000);      // Not a bit
+    assert_eq!(send(&mut decoder, message(0x00, 0x16)), [NecEvent::Key { address: 0x00, command: 0x16 }]);
+}
@@ -0,0 +1,217 @@
+// The flash record log: finding the newest records again, wrapping round
+// the sectors, and power cuts in the middle of a write
+
+use curing_core::record_log::{encode, LogGeometry, RecordFlash, RecordLog, PAYLOAD_SIZE, RECORD_SIZE};
+
+const SETTINGS: [u8; 2] = [0xC7, 0x55];
+const CHECKPOINT: [u8; 2] = [0xC7, 0x5C];
+const TOKEN: [u8; 2] = [0xC7, 0x54];
+
+/// Small sectors, so a few records fill one
+const GEOMETRY: LogGeometry = LogGeometry { sectors: 2, slots_per_sector: 4 };
+
+/// Flash in RAM: erased = 0xFF, like the real thing
+struct FakeFlash {
+    slots: Vec<[u8; RECORD_SIZE]>,
+    erases: u32,
+}
+
+impl FakeFlash {
+    fn new() -> Self {
+        Self { slots: vec![[0xFF; RECORD_SIZE]; 8], erases: 0 }
+    }
+
+    /// A write cut off by a power loss: only the first bytes made it
+    fn tear(&mut self, slot: u32) {
+        let record = encode(CHECKPOINT, 99, &[7; PAYLOAD_SIZE]);
+        self.slots[slot as usize][..10].copy_from_slice(&record[..10]);
+    }
+}
+
+impl RecordFlash for FakeFlash {
+    type Error = ();
+
+    fn read(&mut self, slot: u32, record: &mut [u8; RECORD_SIZE]) -> Result<(), ()> {
+        *record = self.slots[slot as usize];
+        Ok(())
+    }
+
+    fn write(&mut self, slot: u32, record: &[u8; RECORD_SIZE]) -> Result<(), ()> {
+        assert_eq!(self.slots[slot as usize], [0xFF; RECORD_SIZE], "slot {} written twice", slot);
+        self.slots[slot as usize] = *record;
+        Ok(())
+    }
+
+    fn erase(&mut self, sector: u32) -> Result<(), ()> {
+        let first = (sector * GEOMETRY.slots_per_sector) as usize;
+        self.slots[first..first + GEOMETRY.slots_per_sector as usize].fill([0xFF; RECORD_SIZE]);
+        self.erases += 1;
+        Ok(())
+    }
+}
+
+/// Open the log, as at power-on: (the log, the newest checkpoint payload)
+fn open(flash: &mut FakeFlash) -> (RecordLog, Option<[u8; PAYLOAD_SIZE]>) {
+    let mut newest: Option<(u32, [u8; PAYLOAD_SIZE])> = None;
+    let log = RecordLog::open(flash, GEOMETRY, [SETTINGS], |magic, sequence, payload| {
+        if magic == CHECKPOINT && newest.is_none_or(|(newest, _)| sequence > newest) {
+            newest = Some((sequence, *payload));
+        }
+    });
+    (log, newest.map(|(_, payload)| payload))
+}
+
+#[test]
+fn blank_flash_has_nothing() {
+    let (log, checkpoint) = open(&mut FakeFlash::new());
+    assert_eq!(log.kept(SETTINGS), None);
+    assert_eq!(checkpoint, None);
+}
+
+#[test]
+fn the_newest_records_are_found_again() {
+    let mut flash = FakeFlash::new();
+    let (mut log, _) = open(&mut flash);
+    log.append(&mut flash, SETTINGS, &[1; PAYLOAD_SIZE]).unwrap();
+    log.append(&mut flash, SETTINGS, &[2; PAYLOAD_SIZE]).unwrap();
+    log.append(&mut flash, CHECKPOINT, &[3; PAYLOAD_SIZE]).unwrap();
+
+    let (log, checkpoint) = open(&mut flash);
+    assert_eq!(log.kept(SETTINGS), Some(&[2; PAYLOAD_SIZE]));
+    assert_eq!(checkpoint, Some([3; PAYLOAD_SIZE]));
+}
+
+#[test]
+fn settings_survive_a_long_run_of_checkpoints() {
+    let mut flash = FakeFlash::new();
+    let (mut log, _) = open(&mut flash);
+    log.append(&mut flash, SETTINGS, &[1; PAYLOAD_SIZE]).unwrap();
+    // Round both sectors several times
+    for i in 0..20 {
+        log.append(&mut flash, CHECKPOINT, &[100 + i; PAYLOAD_SIZE]).unwrap();
+    }
+
+    let (log, checkpoint) = open(&mut flash);
+    assert_eq!(log.kept(SETTINGS), Some(&[1; PAYLOAD_SIZE]));
+    assert_eq!(checkpoint, Some([119; PAYLOAD_SIZE]));
+}
+
+#[test]
+fn a_torn_record_is_skipped_not_erased() {
+    let mut flash = FakeFlash::new();
+    let (mut log, _) = open(&mut flash);
+    log.append(&mut flash, SETTINGS, &[1; PAYLOAD_SIZE]).unwrap();      // slot 0
+    log.append(&mut flash, CHECKPOINT, &[2; PAYLOAD_SIZE]).unwrap();    // slot 1
+    flash.tear(2);                                                      // Power cut
+
+    // After power-on the next record goes past the torn slot - the
+    // settings in the same sector are left alone
+    let (mut log, checkpoint) = open(&mut flash);
+    assert_eq!(checkpoint, Some([2; PAYLOAD_SIZE]));
+    let erases = flash.erases;
+    assert_eq!(log.append(&mut flash, CHECKPOINT, &[3; PAYLOAD_SIZE]), Ok(3));
+    assert_eq!(flash.erases, erases);
+
+    let (log, checkpoint) = open(&mut flash);
+    assert_eq!(log.kept(SETTINGS), Some(&[1; PAYLOAD_SIZE]));
+    assert_eq!(checkpoint, Some([3; PAYLOAD_SIZE]));
+}
+
+#[test]
+fn a_torn_last_slot_still_keeps_the_settings() {
+    let mut flash = FakeFlash::new();
+    let (mut log, _) = open(&mut flash);
+    log.append(&mut flash, SETTINGS, &[1; PAYLOAD_SIZE]).unwrap();      // slot 0
+    log.append(&mut flash, CHECKPOINT, &[2; PAYLOAD_SIZE]).unwrap();    // slot 1
+    log.append(&mut flash, CHECKPOINT, &[3; PAYLOAD_SIZE]).unwrap();    // slot 2
+    flash.tear(3);
+
+    // The next sector is erased and the settings copied into it before the
+    // checkpoint - then the first sector can go too
+    let (mut log, _) = open(&mut flash);
+    for i in 0..5 {
+        log.append(&mut flash, CHECKPOINT, &[10 + i; PAYLOAD_SIZE]).unwrap();
+    }
+    let (log, checkpoint) = open(&mut flash);
+    assert_eq!(log.kept(SETTINGS), Some(&[1; PAYLOAD_SIZE]));
+    assert_eq!(checkpoint, Some([14; PAYLOAD_SIZE]));
+}
+
+#[test]
+fn new_settings_replace_the_kept_ones() {
+    let mut flash = FakeFlash::new();
+    let (mut log, _) = open(&mut flash);
+    log.append(&mut flash, SETTINGS, &[1; PAYLOAD_SIZE]).unwrap();
+    for i in 0..6 {
+        log.append(&mut flash, CHECKPOINT, &[10 + i; PAYLOAD_SIZE]).unwrap();
+    }
+    log.append(&mut flash, SETTINGS, &[2; PAYLOAD_SIZE]).unwrap();
+    log.append(&mut flash, CHECKPOINT, &[20; PAYLOAD_SIZE]).unwrap();
+
+    let (log, _) = open(&mut flash);
+    assert_eq!(log.kept(SETTINGS), Some(&[2; PAYLOAD_SIZE]));
+}
+
+#[test]
+fn checkpoints_without_erase_stop_at_the_sector_end() {
+    let mut flash = FakeFlash::new();
+    let (mut log, _) = open(&mut flash);
+    log.append(&mut flash, SETTINGS, &[1; PAYLOAD_SIZE]).unwrap();      // slot 0
+    let erases = flash.erases;
+    assert_eq!(log.append_without_erase(&mut flash, CHECKPOINT, &[2; PAYLOAD_SIZE]), Ok(Some(1)));
+    assert_eq!(log.append_without_erase(&mut flash, CHECKPOINT, &[3; PAYLOAD_SIZE]), Ok(Some(2)));
+    assert_eq!(log.append_without_erase(&mut flash, CHECKPOINT, &[4; PAYLOAD_SIZE]), Ok(Some(3)));
+    // The sector's full - skipped rather than erasing the next one
+    assert_eq!(log.append_without_erase(&mut flash, CHECKPOINT, &[5; PAYLOAD_SIZE]), Ok(None));
+    assert_eq!(flash.erases, erases);
+
+    let (log, checkpoint) = open(&mut flash);
+    assert_eq!(log.kept(SETTINGS), Some(&[1; PAYLOAD_SIZE]));
+    assert_eq!(checkpoint, Some([4; PAYLOAD_SIZE]));
+}
+
+#[test]
+fn making_room_moves_on_with_the_settings() {
+    let mut flash = FakeFlash::new();
+    let (mut log, _) = open(&mut flash);
+    log.append(&mut flash, SETTINGS, &[1; PAYLOAD_SIZE]).unwrap();      // slot 0
+    log.append(&mut flash, CHECKPOINT, &[2; PAYLOAD_SIZE]).unwrap();    // slot 1
+    assert_eq!(log.room(), 2);
+
+    // Already enough room: nothing erased
+    let erases = flash.erases;
+    log.make_room(&mut flash, 2).unwrap();
+    assert_eq!(flash.erases, erases);
+
+    // Asking for more than a sector holds gets as much as there can be
+    log.make_room(&mut flash, 10).unwrap();
+    assert_eq!(flash.erases, erases + 1);
+    assert_eq!(log.room(), 3);
+    for i in 0..3 {
+        assert!(log.append_without_erase(&mut flash, CHECKPOINT, &[10 + i; PAYLOAD_SIZE]).unwrap().is_some());
+    }
+    assert_eq!(flash.erases, erases + 1);
+
+    let (log, checkpoint) = open(&mut flash);
+    assert_eq!(log.kept(SETTINGS), Some(&[1; PAYLOAD_SIZE]));
+    assert_eq!(checkpoint, Some([12; PAYLOAD_SIZE]));
+}
+
+#[test]
+fn every_kept_kind_survives_a_long_run_of_checkpoints() {
+    let mut flash = FakeFlash::new();
+    let mut log = RecordLog::open(&mut flash, GEOMETRY, [SETTINGS, TOKEN], |_, _, _| {});
+    log.append(&mut flash, SETTINGS, &[1; PAYLOAD_SIZE]).unwrap();
+    log.append(&mut flash, TOKEN, &[2; PAYLOAD_SIZE]).unwrap();
+    for i in 0..20 {
+        log.append(&mut flash, CHECKPOINT, &[100 + i; PAYLOAD_SIZE]).unwrap();
+    }
+    // A new one of either kind still takes the other with it
+    log.append(&mut flash, TOKEN, &[3; PAYLOAD_SIZE]).unwrap();
+    log.make_room(&mut flash, 10).unwrap();
+    assert_eq!(log.room(), 2);
+
+    let log = RecordLog::open(&mut flash, GEOMETRY, [SETTINGS, TOKEN], |_, _, _| {});
+    assert_eq!(log.kept(SETTINGS), Some(&[1; PAYLOAD_SIZE]));
+    assert_eq!(log.kept(TOKEN), Some(&[3; PAYLOAD_SIZE]));
+}
@@ -0,0 +1,65 @@
+// Relay switching time: the baseline, and spotting a relay slowing down
+
+use curing_core::relay_timing::{SwitchTimes, BASELINE_SWITCHES, MIN_SLOWDOWN_US};
+
+/// config.rs default
+const SLOWDOWN_PERCENT: u32 = 50;
+
+/// A relay timed `count` times at `us` each - true if any of them was reported
+fn switch(times: &mut SwitchTimes, us: u32, count: u32) -> bool {
+    (0..count).fold(false, |reported, _| times.record(us) || reported)
+}
+
+#[test]
+fn the_first_switches_make_the_baseline() {
+    let mut times = SwitchTimes::new(SLOWDOWN_PERCENT);
+    for i in 0..BASELINE_SWITCHES {
+        assert_eq!(times.baseline_us(), None);
+        assert!(!times.record(9_000 + 250 * (i % 2)));
+    }
+    assert_eq!(times.baseline_us(), Some(9_125));
+    assert_eq!(times.average_us(), 9_125);
+}
+
+#[test]
+fn a_steady_relay_is_never_reported() {
+    let mut times = SwitchTimes::new(SLOWDOWN_PERCENT);
+    assert!(!switch(&mut times, 10_000, 1_000));
+}
+
+#[test]
+fn one_slow_switch_is_not_a_trend() {
+    let mut times = SwitchTimes::new(SLOWDOWN_PERCENT);
+    switch(&mut times, 10_000, BASELINE_SWITCHES);
+    assert!(!times.record(40_000));
+    assert!(!switch(&mut times, 10_000, 20));
+}
+
+#[test]
+fn a_relay_slowing_down_is_reported_once() {
+    let mut times = SwitchTimes::new(SLOWDOWN_PERCENT);
+    switch(&mut times, 10_000, BASELINE_SWITCHES);
+
+    // Creeping up a little with every switch
+    let reported: Vec<u32> = (1..200).filter(|i| times.record(10_000 + i * 100)).collect();
+    assert_eq!(reported.len(), 1, "reported at switches {:?}", reported);
+    assert!(times.average_us() >= 15_000);
+}
+
+#[test]
+fn reported_again_after_recovering() {
+    let mut times = SwitchTimes::new(SLOWDOWN_PERCENT);
+    switch(&mut times, 10_000, BASELINE_SWITCHES);
+    assert!(switch(&mut times, 20_000, 50));
+    switch(&mut times, 10_000, 50);
+    assert!(switch(&mut times, 20_000, 50));
+}
+
+#[test]
+fn a_fast_relay_needs_a_real_slowdown() {
+    // 50% of 2 ms is only 1 ms - not worth a warning
+    let mut times = SwitchTimes::new(SLOWDOWN_PERCENT);
+    switch(&mut times, 2_000, BASELINE_SWITCHES);
+    assert!(!switch(&mut times, 3_500, 100));
+    assert!(switch(&mut times, 2_000 + MIN_SLOWDOWN_US + 500, 100));
+}
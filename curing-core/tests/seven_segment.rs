@@ -0,0 +1,31 @@
+// 7-segment digits: MM:SS and clock layout, and text
+
+use curing_core::seven_segment::{self, BLANK, COLON};
+
+#[test]
+fn countdown_is_minutes_and_seconds() {
+    // 5:07 = 0, 5 with the colon, 0, 7
+    assert_eq!(seven_segment::minutes_seconds(307, true), [0x3F, 0x6D | COLON, 0x3F, 0x07]);
+    assert_eq!(seven_segment::minutes_seconds(307, false)[1], 0x6D);
+}
+
+#[test]
+fn countdown_stops_at_99_59() {
+    assert_eq!(seven_segment::minutes_seconds(100 * 60, false), seven_segment::minutes_seconds(99 * 60 + 59, false));
+}
+
+#[test]
+fn text_blanks_what_it_cant_show() {
+    assert_eq!(seven_segment::text("HEAt"), [0x76, 0x79, 0x77, 0x78]);
+    assert_eq!(seven_segment::text("IdLE"), [0x30, 0x5E, 0x38, 0x79]);
+    assert_eq!(seven_segment::text("--"), [0x40, 0x40, 0, 0]);
+    assert_eq!(seven_segment::text("Wxyz!"), BLANK);
+}
+
+#[test]
+fn clock_has_no_leading_zero_on_the_hour() {
+    // 9:05 = blank, 9 with the colon, 0, 5
+    assert_eq!(seven_segment::hours_minutes(9, 5, true), [0, 0x6F | COLON, 0x3F, 0x6D]);
+    assert_eq!(seven_segment::hours_minutes(14, 30, false), [0x06, 0x66, 0x4F, 0x3F]);
+    assert_eq!(seven_segment::hours_minutes(0, 0, false), [0, 0x3F, 0x3F, 0x3F]);
+}
@@ -0,0 +1,110 @@
+// Buzzer signals: the order and length of beeps and gaps
+
+use curing_core::signals::{self, AudioEvent, AudioPatterns, Beeps, Note, QuietHours};
+
+#[test]
+fn beeps_alternate_with_gaps() {
+    let beeps = Beeps { count: 3, hz: 2700, on_ms: 200, off_ms: 300 };
+    let steps: Vec<Note> = beeps.steps().collect();
+    assert_eq!(steps.len(), 6);
+    for pair in steps.chunks(2) {
+        assert_eq!(pair, [Note { hz: 2700, ms: 200 }, Note { hz: 0, ms: 300 }]);
+    }
+}
+
+#[test]
+fn signal_length_is_beeps_plus_gaps() {
+    let abort = Beeps { count: 2, hz: 2700, on_ms: 800, off_ms: 300 };
+    assert_eq!(abort.steps().map(|note| note.ms).sum::<u64>(), 2200);
+}
+
+#[test]
+fn no_beeps_is_silence() {
+    let beeps = Beeps { count: 0, hz: 2700, on_ms: 200, off_ms: 300 };
+    assert_eq!(beeps.steps().count(), 0);
+}
+
+#[test]
+fn melody_notes_get_a_gap_each() {
+    let tune = [Note { hz: 784, ms: 400 }, Note { hz: 523, ms: 600 }];
+    let steps: Vec<Note> = signals::melody(&tune, 20).collect();
+    assert_eq!(
+        steps,
+        [Note { hz: 784, ms: 400 }, Note { hz: 0, ms: 20 }, Note { hz: 523, ms: 600 }, Note { hz: 0, ms: 20 }]
+    );
+}
+
+const PATTERNS: AudioPatterns = AudioPatterns {
+    start: Beeps { count: 2, hz: 3200, on_ms: 60, off_ms: 60 },
+    complete: Beeps { count: 3, hz: 2700, on_ms: 200, off_ms: 300 },
+    abort: Beeps { count: 2, hz: 1800, on_ms: 800, off_ms: 300 },
+    fault: Beeps { count: 1, hz: 2000, on_ms: 300, off_ms: 200 },
+    overheated: Beeps { count: 5, hz: 2500, on_ms: 100, off_ms: 100 },
+    preset_changed: Beeps { count: 1, hz: 2700, on_ms: 100, off_ms: 0 },
+    low_battery: Beeps { count: 6, hz: 2000, on_ms: 50, off_ms: 50 },
+    chirp: Beeps { count: 1, hz: 3200, on_ms: 30, off_ms: 0 },
+    refused: Beeps { count: 1, hz: 1500, on_ms: 400, off_ms: 0 },
+    confirm: Beeps { count: 1, hz: 2700, on_ms: 700, off_ms: 0 },
+    countdown: Beeps { count: 1, hz: 3200, on_ms: 40, off_ms: 0 },
+    minute_mark: Beeps { count: 1, hz: 3200, on_ms: 15, off_ms: 0 },
+    lid_open: Beeps { count: 3, hz: 2000, on_ms: 100, off_ms: 100 },
+    lid_warning: Beeps { count: 1, hz: 2000, on_ms: 100, off_ms: 900 },
+    lamp_service: Beeps { count: 3, hz: 2700, on_ms: 120, off_ms: 120 },
+    menu_item: Beeps { count: 1, hz: 2700, on_ms: 60, off_ms: 120 },
+};
+
+#[test]
+fn each_event_gets_its_own_pattern() {
+    let refused = PATTERNS.pattern(AudioEvent::Refused);
+    assert_eq!((refused.count, refused.hz, refused.on_ms), (1, 1500, 400));
+    let abort = PATTERNS.pattern(AudioEvent::Abort);
+    assert_eq!((abort.count, abort.hz, abort.on_ms), (2, 1800, 800));
+}
+
+#[test]
+fn counted_pattern_keeps_length_and_pitch() {
+    let code = PATTERNS.counted(AudioEvent::Fault, 4);
+    let steps: Vec<Note> = code.steps().collect();
+    assert_eq!(steps.len(), 8);
+    assert_eq!(steps[0], Note { hz: 2000, ms: 300 });
+    assert_eq!(steps[1], Note { hz: 0, ms: 200 });
+}
+
+#[test]
+fn quiet_hours_over_midnight() {
+    let night = QuietHours { start: (22, 0), end: (7, 0) };
+    assert!(!night.contains(21, 59));
+    assert!(night.contains(22, 0));
+    assert!(night.contains(0, 0));
+    assert!(night.contains(6, 59));
+    assert!(!night.contains(7, 0));
+    assert!(!night.contains(12, 0));
+}
+
+#[test]
+fn quiet_hours_within_a_day() {
+    let afternoon = QuietHours { start: (13, 30), end: (15, 0) };
+    assert!(!afternoon.contains(13, 29));
+    assert!(afternoon.contains(13, 30));
+    assert!(!afternoon.contains(15, 0));
+    assert!(!afternoon.contains(23, 0));
+}
+
+#[test]
+fn quiet_hours_with_no_length_are_never() {
+    let none = QuietHours { start: (22, 0), end: (22, 0) };
+    assert!((0..24).all(|hour| !none.contains(hour, 0)));
+}
+
+#[test]
+fn alarms_sound_in_quiet_hours() {
+    for alarm in [AudioEvent::Fault, AudioEvent::Overheated, AudioEvent::LowBattery, AudioEvent::LidOpen, AudioEvent::LidWarning] {
+        assert!(!alarm.is_quiet_hours_muted());
+    }
+    for answer in [AudioEvent::Start, AudioEvent::Abort, AudioEvent::Chirp, AudioEvent::Refused, AudioEvent::Confirm] {
+        assert!(!answer.is_quiet_hours_muted());
+    }
+    for progress in [AudioEvent::Complete, AudioEvent::Countdown, AudioEvent::MinuteMark, AudioEvent::LampService] {
+        assert!(progress.is_quiet_hours_muted());
+    }
+}
@@ -0,0 +1,27 @@
+/* Flash layout for ota builds (the application, started by bootloader/)
+ *
+ *   BOOT2             0x10000000   256 bytes   second-stage boot (flash setup)
+ *   BOOTLOADER        0x10000100   ~24K        bootloader/ - picks the slot to run
+ *   BOOTLOADER_STATE  0x10006000   4K          "update ready" / "booted OK" flags
+ *   FLASH (ACTIVE)    0x10007000   896K        this firmware
+ *   DFU               0x100E7000   900K        new firmware is uploaded here
+ *   (unused)          0x101C8000   216K
+ *   settings          0x101FE000   8K          saved settings (src/storage.rs)
+ *
+ * Must match bootloader/memory.x. DFU is one sector bigger than ACTIVE - the
+ * bootloader needs the spare sector to swap the two slots page by page.
+ */
+MEMORY {
+    BOOT2 : ORIGIN = 0x10000000, LENGTH = 0x100
+    BOOTLOADER_STATE : ORIGIN = 0x10006000, LENGTH = 4K
+    FLASH : ORIGIN = 0x10007000, LENGTH = 896K
+    DFU : ORIGIN = 0x100E7000, LENGTH = 900K
+    RAM   : ORIGIN = 0x20000000, LENGTH = 264K
+}
+
+/* Flash offsets the firmware updater (src/ota.rs) writes to */
+__bootloader_state_start = ORIGIN(BOOTLOADER_STATE) - ORIGIN(BOOT2);
+__bootloader_state_end = ORIGIN(BOOTLOADER_STATE) + LENGTH(BOOTLOADER_STATE) - ORIGIN(BOOT2);
+
+__bootloader_dfu_start = ORIGIN(DFU) - ORIGIN(BOOT2);
+__bootloader_dfu_end = ORIGIN(DFU) + LENGTH(DFU) - ORIGIN(BOOT2);
@@ -0,0 +1,36 @@
+// Shared ADC (thermistor, battery and uv-sensor builds)
+//
+// The RP2040 has a single ADC that switches between its inputs (GPIO 26-29).
+// Several tasks want readings from it - the thermistor on GPIO 26, the UV
+// sensor on GPIO 27, the battery voltage on GPIO 29 - so nobody owns it: it sits behind an async
+// mutex, and each task keeps its own `Channel` (pin) and borrows the ADC
+// for one reading at a time. A reading takes a couple of microseconds, so
+// no one waits long.
+
+use embassy_rp::adc::{self, Adc, Async, Channel};
+use embassy_rp::bind_interrupts;
+use embassy_rp::peripherals::ADC;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::mutex::Mutex;
+
+bind_interrupts!(struct Irqs {
+    ADC_IRQ_FIFO => adc::InterruptHandler;
+});
+
+/// The ADC, once `init` has set it up
+static SHARED: Mutex<CriticalSectionRawMutex, Option<Adc<'static, Async>>> = Mutex::new(None);
+
+/// Set up the ADC - call once, before any task reads from it
+pub fn init(adc: ADC) {
+    let adc = Adc::new(adc, Irqs, adc::Config::default());
+    // Nothing else can hold the lock yet
+    *defmt::unwrap!(SHARED.try_lock()) = Some(adc);
+}
+
+/// Take one reading (0-4095) from `channel`
+pub async fn read(channel: &mut Channel<'_>) -> Result<u16, adc::Error> {
+    match SHARED.lock().await.as_mut() {
+        Some(adc) => adc.read(channel).await,
+        None => Err(adc::Error::ConversionFailed),   // init() wasn't called
+    }
+}
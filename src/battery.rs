@@ -0,0 +1,89 @@
+// Battery Voltage Monitoring (battery builds)
+//
+// For curers running from a battery pack. On a Raspberry Pi Pico, GPIO 29
+// is wired on the board to VSYS (the supply coming in) through a divide-
+// by-3 resistor divider, so the ADC can measure the supply with no extra
+// parts. On another board, wire the battery to GPIO 29 through your own
+// divider and set BATTERY_DIVIDER_RATIO.
+//
+// A background task samples it and smooths the readings (the voltage dips
+// each time the relay or the UV LEDs switch on). Below BATTERY_LOW_MV the
+// battery counts as low until it recovers past BATTERY_LOW_MV +
+// BATTERY_HYSTERESIS_MV, so it doesn't flicker around the threshold. While
+// it's low, no cure can start - better to refuse than to have the pack
+// die halfway through and leave the resin half cured.
+
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use defmt::*;
+use embassy_rp::adc::Channel;
+use embassy_rp::gpio::Pull;
+use embassy_rp::peripherals::PIN_29;
+use embassy_time::Timer;
+
+use crate::analog;
+use crate::buzzer::{self, AudioEvent};
+use crate::config::*;
+use crate::events::{self, Event};
+
+/// ADC reference voltage in millivolts
+const ADC_REFERENCE_MV: u32 = 3300;
+
+/// Full-scale reading of the 12-bit ADC
+const ADC_MAX: u32 = 4095;
+
+/// Whether the battery is currently too low to start a cure
+static LOW: AtomicBool = AtomicBool::new(false);
+
+/// Latest smoothed battery voltage in millivolts (0 = no reading yet)
+static LATEST_MV: AtomicU32 = AtomicU32::new(0);
+
+/// Is the battery too low to start a cure?
+pub fn is_low() -> bool {
+    LOW.load(Ordering::Relaxed)
+}
+
+/// Latest battery voltage in millivolts, once it has been measured
+pub fn millivolts() -> Option<u32> {
+    match LATEST_MV.load(Ordering::Relaxed) {
+        0 => None,
+        mv => Some(mv),
+    }
+}
+
+/// Play the low-battery warning (also used when a start is refused)
+pub fn warn_low() {
+    buzzer::play(AudioEvent::LowBattery);
+}
+
+/// Background task: sample the battery voltage and raise the low flag
+#[embassy_executor::task]
+pub async fn battery_monitor_task(pin: PIN_29) {
+    let mut channel = Channel::new_pin(pin, Pull::None);
+    let mut smoothed_mv: Option<u32> = None;
+
+    loop {
+        if let Ok(raw) = analog::read(&mut channel).await {
+            let mv = raw as u32 * ADC_REFERENCE_MV * BATTERY_DIVIDER_RATIO / ADC_MAX;
+            // Each new reading moves the average 1/8th of the way
+            let average = smoothed_mv.map_or(mv, |average| (average * 7 + mv) / 8);
+            smoothed_mv = Some(average);
+            LATEST_MV.store(average, Ordering::Relaxed);
+
+            let low = match is_low() {
+                true => average < BATTERY_LOW_MV + BATTERY_HYSTERESIS_MV,  // Stay low until properly recovered
+                false => average < BATTERY_LOW_MV,
+            };
+            if low != is_low() {
+                LOW.store(low, Ordering::Relaxed);
+                if low {
+                    events::record(Event::BatteryLow { millivolts: average });
+                    warn_low();
+                } else {
+                    info!("Battery back up to {} mV - cures allowed again", average);
+                }
+            }
+        }
+        Timer::after_millis(BATTERY_SAMPLE_INTERVAL_MS).await;
+    }
+}
@@ -0,0 +1,287 @@
+// Button Input Task
+//
+// Turns the button pin into "gestures" the rest of the firmware can act on.
+// The rules - debouncing, hold timing, telling single, double, triple and five taps apart -
+// are in curing-core (curing-core/src/gesture.rs). The button task just reads
+// the pin and the clock and feeds them in, sleeping until the level changes
+// or the detector has a deadline. Finished gestures go into a queue for the
+// cure supervisor, so none are missed while it's busy with something else.
+//
+// The button is wired between the GPIO and GND with the internal pull-up on,
+// so pressed = LOW and released = HIGH. main.rs says so when it hands over
+// the pin, wrapped in curing-core's generic `Button` driver (drivers.rs).
+// A TTP223 touch module (ttp223 builds) is the other way round: HIGH while
+// touched. A bare touch pad (touch-pad builds) isn't a pin at all, but
+// touch.rs makes it look like one.
+//
+// Multi-duration builds can have a second button just for picking the preset
+// (select-button builds, GPIO 2). It only needs presses, so it gets a
+// simpler task of its own. `wait_for_idle_control` then maps whichever
+// buttons are fitted to what the user wants at idle - with one button a tap
+// picks the preset and a hold starts; with two, each has one job. With one
+// button every tap is a preset step, reported straight away: a quick run of
+// taps through the presets must never look like a double tap (repeat, UV on)
+// or a triple tap (settings).
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use curing_core::drivers::Button;
+use curing_core::gesture::{GestureDetector, GestureTiming};
+use embassy_executor::Spawner;
+use embassy_futures::select::{select, Either};
+#[cfg(feature = "dormant")]
+use embassy_futures::select::{select3, Either3};
+use embassy_rp::gpio::{AnyPin, Input};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::Channel;
+use embassy_sync::signal::Signal;
+use embassy_time::{Instant, Timer};
+
+use crate::config::*;
+use crate::events::{self, Event};
+use crate::settings;
+
+pub use curing_core::gesture::Gesture;
+
+/// Gestures not yet collected (more than this and new ones are dropped)
+static GESTURES: Channel<CriticalSectionRawMutex, Gesture, 4> = Channel::new();
+
+/// New timing for the detector - it starts again from scratch
+static LISTEN: Signal<CriticalSectionRawMutex, GestureTiming> = Signal::new();
+
+/// The debounced button level: true = pressed
+static PRESSED: AtomicBool = AtomicBool::new(false);
+
+/// Signalled whenever PRESSED changes
+static LEVEL_CHANGED: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Asks the button task to put the chip to sleep until the next press
+#[cfg(feature = "dormant")]
+static SLEEP: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Select button presses not yet collected
+#[cfg(feature = "select-button")]
+static SELECTS: Channel<CriticalSectionRawMutex, (), 4> = Channel::new();
+
+/// The start button pin
+#[cfg(not(feature = "touch-pad"))]
+pub type StartButton = Button<Input<'static, AnyPin>>;
+/// The start button: a touch pad
+#[cfg(feature = "touch-pad")]
+pub type StartButton = Button<crate::touch::TouchPad<'static>>;
+
+/// Start the button task on the start button pin
+pub fn init(spawner: &Spawner, mut button: StartButton) {
+    // Known straight away, before the task has had a look
+    PRESSED.store(button.is_pressed(), Ordering::Relaxed);
+    defmt::unwrap!(spawner.spawn(button_task(button)));
+}
+
+/// Wait for the next button gesture
+///
+/// Returns `Gesture::Hold` as soon as the button has been down for `hold_ms`,
+/// without waiting for it to be released - so the caller can react while the
+/// user is still holding. A press that's already down when this is called
+/// doesn't count, so neither does the release of a hold.
+pub async fn wait_for_gesture(hold_ms: u64) -> Gesture {
+    listen(hold_ms, None, false, false);
+    GESTURES.receive().await
+}
+
+/// Wait for the next gesture during a cure - a hold is ABORT_HOLD_MS
+///
+/// When a tap adds time (ADD_TIME_SECONDS), a double tap pauses, so a tap
+/// is only reported once DOUBLE_TAP_WINDOW_MS has passed without a second
+/// one. Otherwise a tap pauses, straight away.
+pub async fn wait_for_cure_gesture() -> Gesture {
+    let double_tap_ms = (ADD_TIME_SECONDS > 0).then_some(DOUBLE_TAP_WINDOW_MS);
+    listen(ABORT_HOLD_MS, double_tap_ms, false, false);
+    GESTURES.receive().await
+}
+
+/// Wait for the next gesture while idle, telling single, double and triple
+/// taps apart - and five taps, with BOOTSEL_FIVE_TAPS
+///
+/// A tap is only reported once DOUBLE_TAP_WINDOW_MS has passed without a
+/// second press (and a double tap once it has passed again without a third),
+/// so taps are that much slower to react - fine at idle, but not for pausing
+/// a running cure (use `wait_for_gesture` there).
+pub async fn wait_for_idle_gesture(hold_ms: u64) -> Gesture {
+    listen(hold_ms, Some(DOUBLE_TAP_WINDOW_MS), true, BOOTSEL_FIVE_TAPS);
+    GESTURES.receive().await
+}
+
+/// What a button gesture at idle asks for (multi-duration builds)
+#[cfg(feature = "multi-duration")]
+pub enum Control {
+    /// Start a cure with the selected preset
+    Start,
+    /// Move on to the next preset
+    Select,
+    /// Turn the UV on while held, to check the LEDs
+    #[cfg(feature = "select-button")]
+    Peek,
+    /// Run the last cure again
+    #[cfg(feature = "select-button")]
+    Repeat,
+    /// Open the settings menu
+    #[cfg(feature = "select-button")]
+    Settings,
+    /// Restart into BOOTSEL mode for a firmware update
+    #[cfg(feature = "select-button")]
+    Bootsel,
+}
+
+/// Wait for the next thing the user asks for at idle, whichever button it
+/// came from (multi-duration builds)
+///
+/// One button: tap = select (each one straight away - no double or triple
+/// taps), hold PRESET_START_HOLD_MS = start (returned while still held - the
+/// cure starts on release). With a select button: select press = select,
+/// start tap = start, start hold PEEK_HOLD_MS = peek, double tap = repeat,
+/// triple tap = settings, five taps = BOOTSEL.
+#[cfg(all(feature = "multi-duration", not(feature = "select-button")))]
+pub async fn wait_for_idle_control() -> Control {
+    match wait_for_gesture(PRESET_START_HOLD_MS).await {
+        Gesture::Hold => Control::Start,
+        _ => Control::Select,      // Only taps, with no double-tap window
+    }
+}
+
+/// Wait for the next thing the user asks for at idle, from either button
+/// (see above - this is the two-button layout)
+#[cfg(feature = "select-button")]
+pub async fn wait_for_idle_control() -> Control {
+    // Presses from before now (e.g. during the cure) don't count
+    while SELECTS.try_receive().is_ok() {}
+    match select(wait_for_idle_gesture(PEEK_HOLD_MS), SELECTS.receive()).await {
+        Either::First(Gesture::Tap) => Control::Start,
+        Either::First(Gesture::Hold) => Control::Peek,
+        Either::First(Gesture::DoubleTap) => Control::Repeat,
+        Either::First(Gesture::TripleTap) => Control::Settings,
+        Either::First(Gesture::FiveTaps) => Control::Bootsel,
+        Either::Second(()) => Control::Select,
+    }
+}
+
+/// Start the select button task (select-button builds)
+#[cfg(feature = "select-button")]
+pub fn init_select(spawner: &Spawner, button: Button<Input<'static, AnyPin>>) {
+    defmt::unwrap!(spawner.spawn(select_button_task(button)));
+}
+
+/// Background task: queue a select for each press of the select button
+#[cfg(feature = "select-button")]
+#[embassy_executor::task]
+async fn select_button_task(mut button: Button<Input<'static, AnyPin>>) {
+    loop {
+        button.wait_for_change(false).await;
+        // Contacts bounce - only count it if it's still down afterwards
+        Timer::after_millis(settings::button_debounce_ms()).await;
+        if button.is_pressed() {
+            let _ = SELECTS.try_send(());    // Full = nobody is listening
+            button.wait_for_change(true).await;
+            Timer::after_millis(settings::button_debounce_ms()).await;
+        }
+    }
+}
+
+/// Is the button down right now? (debounced)
+pub fn is_pressed() -> bool {
+    PRESSED.load(Ordering::Relaxed)
+}
+
+/// Wait until the button is pressed (or a latching switch turned ON),
+/// and has stopped bouncing
+pub async fn wait_for_press() {
+    wait_for_level(true).await;
+}
+
+/// Put the whole chip into dormant sleep until the button is pressed, and
+/// return once that press has settled (dormant builds)
+///
+/// The button task does the sleeping, since it has the pin. Make sure the
+/// UV is off first - `dormant::sleep` refuses otherwise.
+#[cfg(feature = "dormant")]
+pub async fn sleep_until_pressed() {
+    SLEEP.signal(());
+    wait_for_press().await;
+}
+
+/// Wait until the button is released (or a latching switch turned OFF),
+/// and has stopped bouncing
+pub async fn wait_for_release() {
+    wait_for_level(false).await;
+}
+
+async fn wait_for_level(pressed: bool) {
+    while is_pressed() != pressed {
+        LEVEL_CHANGED.wait().await;
+    }
+    Timer::after_millis(settings::button_debounce_ms()).await;
+}
+
+/// Forget any gestures not yet collected and start detecting afresh
+fn listen(hold_ms: u64, double_tap_ms: Option<u64>, triple_tap: bool, five_taps: bool) {
+    while GESTURES.try_receive().is_ok() {}
+    LISTEN.signal(GestureTiming { debounce_ms: settings::button_debounce_ms(), hold_ms, double_tap_ms, triple_tap, five_taps });
+}
+
+/// Background task: feed the button level into a gesture detector, and
+/// queue each gesture it reports
+#[embassy_executor::task]
+async fn button_task(mut button: StartButton) {
+    // Until someone listens: the cure timing, and nobody collects the gestures
+    let timing = GestureTiming { debounce_ms: settings::button_debounce_ms(), hold_ms: ABORT_HOLD_MS, double_tap_ms: None, triple_tap: false, five_taps: false };
+    let mut detector = GestureDetector::new(timing, Instant::now().as_millis(), button.is_pressed());
+
+    loop {
+        let now = Instant::now().as_millis();
+        if let Some(gesture) = detector.update(now, button.is_pressed()) {
+            let _ = GESTURES.try_send(gesture);     // Full = nobody is listening
+        }
+        if PRESSED.swap(detector.is_pressed(), Ordering::Relaxed) != detector.is_pressed() {
+            LEVEL_CHANGED.signal(());
+            if detector.is_pressed() {
+                events::record(Event::ButtonPressed);
+            }
+        }
+
+        // Sleep until the level changes or the detector needs another look -
+        // or until new timing comes in
+        let settled_at = detector.settled_at();
+        let wake_at = detector.wake_at();
+        let pressed = detector.is_pressed();
+        let sleep = async {
+            if now < settled_at {
+                // Still bouncing - the level means nothing until it has settled
+                Timer::at(Instant::from_millis(settled_at)).await;
+                return;
+            }
+            let level_change = button.wait_for_change(pressed);
+            match wake_at {
+                Some(at) => {
+                    select(level_change, Timer::at(Instant::from_millis(at))).await;
+                }
+                None => level_change.await,
+            }
+        };
+        #[cfg(not(feature = "dormant"))]
+        let woke = select(LISTEN.wait(), sleep).await;
+        #[cfg(feature = "dormant")]
+        let woke = match select3(LISTEN.wait(), sleep, SLEEP.wait()).await {
+            Either3::First(timing) => Either::First(timing),
+            Either3::Second(()) => Either::Second(()),
+            Either3::Third(()) => {
+                // Asked to sleep - back here once the button wakes the chip
+                crate::dormant::sleep(button.pin_mut());
+                Either::Second(())
+            }
+        };
+        if let Either::First(timing) = woke {
+            // A press that's already down doesn't count
+            detector = GestureDetector::new(timing, Instant::now().as_millis(), button.is_pressed());
+            while GESTURES.try_receive().is_ok() {}
+        }
+    }
+}
@@ -0,0 +1,297 @@
+// Buzzer Driver
+//
+// Two kinds of buzzer can be fitted on GPIO 7:
+//
+// - Active buzzer (default): has its own oscillator, so it just needs the
+//   pin HIGH to sound. One fixed pitch - signals differ only in rhythm.
+// - Passive piezo (`--features passive-buzzer`): needs a square wave at the
+//   pitch you want to hear. An RP2040 PWM slice generates it, so it can play
+//   any frequency - and short melodies from the note tables in config.rs.
+//
+// The buzzer has a task of its own, so a signal never holds up the cure
+// timing: the rest of the firmware calls `play()` with an `AudioEvent` ("cure
+// started", "no", "fault"...), which is queued and played in turn while
+// everything else carries on. How each event sounds comes from the
+// AUDIO_PATTERNS table in config.rs - nothing here picks its own beeps. `stop()` cuts
+// off whatever is playing. The `Buzzer` driver below works the same with
+// either kind of buzzer - anything with a `Tone` impl (curing-core's
+// drivers.rs): the generic `ActiveBuzzer` on any output pin, or the
+// RP2040-only `PwmPiezo` below. Each signal is worked out as a list of steps
+// (pitch and length) by curing-core/src/signals.rs, and played by it.
+//
+// In QUIET_HOURS (config.rs) `play()` leaves out the sounds that only report
+// progress or remind - alarms still sound. It goes by the wall clock, so
+// until that's been set every sound plays.
+//
+// Volume (BUZZER_VOLUME_PERCENT) works by shrinking the high part of each
+// tone cycle: 50% duty is the loudest a piezo gets, narrower pulses are
+// quieter. An active buzzer can only be on or off, so for it any volume
+// above 0 is full volume and 0 is silent.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(not(feature = "passive-buzzer"))]
+use curing_core::drivers::ActiveBuzzer;
+use curing_core::drivers::Tone;
+#[cfg(feature = "passive-buzzer")]
+use curing_core::signals;
+use curing_core::signals::Note;
+use defmt::*;
+use embassy_futures::select::select;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::Channel;
+use embassy_sync::signal::Signal;
+use embassy_time::Timer;
+#[cfg(not(feature = "passive-buzzer"))]
+use embassy_rp::gpio::{AnyPin, Output};
+#[cfg(feature = "passive-buzzer")]
+use embassy_rp::{peripherals::{PIN_7, PWM_CH3}, pwm::{self, Pwm}};
+
+use crate::clock;
+use crate::config::*;
+#[cfg(not(feature = "passive-buzzer"))]
+use crate::events::{self, Event};
+use crate::settings;
+
+/// PWM clock divider for tones: 125 MHz / 64 = ~1.95 MHz counter clock,
+/// which covers ~30 Hz to well beyond hearing with a 16-bit counter
+#[cfg(feature = "passive-buzzer")]
+const TONE_DIVIDER: u8 = 64;
+
+/// PWM counter clock after the divider, in Hz
+#[cfg(feature = "passive-buzzer")]
+const TONE_CLOCK_HZ: u32 = 125_000_000 / TONE_DIVIDER as u32;
+
+/// The buzzer hardware picked by the Cargo features
+#[cfg(not(feature = "passive-buzzer"))]
+pub type BuzzerOutput = ActiveBuzzer<Output<'static, AnyPin>>;
+#[cfg(feature = "passive-buzzer")]
+pub type BuzzerOutput = PwmPiezo<'static>;
+
+/// What a sound is for - defined in curing-core/src/signals.rs
+pub use curing_core::signals::AudioEvent;
+
+/// Something for the buzzer task to play: an event's pattern, with this
+/// many beeps
+#[derive(Clone, Copy)]
+struct Sound {
+    event: AudioEvent,
+    count: u32,
+}
+
+/// Sounds waiting to be played
+static SOUNDS: Channel<CriticalSectionRawMutex, Sound, 4> = Channel::new();
+
+/// Signalled by `stop()` to cut off the sound that's playing
+static STOP: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Silent mode - no sound at all
+static MUTED: AtomicBool = AtomicBool::new(false);
+
+/// Queue the sound for `event` - never waits
+pub fn play(event: AudioEvent) {
+    let count = match event {
+        AudioEvent::Complete => settings::completion_beeps(),   // Set in the menu
+        _ => AUDIO_PATTERNS.pattern(event).count,
+    };
+    play_count(event, count);
+}
+
+/// Queue the sound for `event`, with `count` beeps - a number to count
+/// out (fault code, menu item), or a preview of the completion beeps
+pub fn play_count(event: AudioEvent, count: u32) {
+    if event.is_quiet_hours_muted() && is_quiet_hours() {
+        debug!("Buzzer: quiet hours - {} not sounded", Debug2Format(&event));
+        return;
+    }
+    if SOUNDS.try_send(Sound { event, count }).is_err() {
+        warn!("Buzzer: too many sounds queued - one dropped");
+    }
+}
+
+/// Silence the buzzer now, and forget any sounds still queued
+pub fn stop() {
+    while SOUNDS.try_receive().is_ok() {}
+    STOP.signal(());
+}
+
+/// Switch silent mode on or off
+pub fn set_muted(muted: bool) {
+    MUTED.store(muted, Ordering::Relaxed);
+    if muted {
+        stop();
+    }
+}
+
+/// Is silent mode on?
+pub fn is_muted() -> bool {
+    MUTED.load(Ordering::Relaxed)
+}
+
+/// Is it quiet hours now? (Never with none set, or before the clock is)
+pub fn is_quiet_hours() -> bool {
+    match (QUIET_HOURS, clock::now()) {
+        (Some(quiet), Some(now)) => quiet.contains(now.hour, now.minute),
+        _ => false,
+    }
+}
+
+/// Background task: play queued sounds one after another
+#[embassy_executor::task]
+pub async fn buzzer_task(mut buzzer: Buzzer<BuzzerOutput>) {
+    loop {
+        let sound = SOUNDS.receive().await;
+        STOP.reset();                  // A stop() from before this sound doesn't count
+        select(buzzer.sound(sound), STOP.wait()).await;
+        buzzer.off();                  // Don't leave it on mid-beep
+    }
+}
+
+/// Passive piezo on GPIO 7 = PWM slice 3, channel B
+#[cfg(feature = "passive-buzzer")]
+pub struct PwmPiezo<'d> {
+    pwm: Pwm<'d, PWM_CH3>,
+    config: pwm::Config,
+}
+
+#[cfg(feature = "passive-buzzer")]
+impl<'d> PwmPiezo<'d> {
+    /// Take the PWM slice and pin (starts silent)
+    pub fn new(slice: PWM_CH3, pin: PIN_7) -> Self {
+        let mut config = pwm::Config::default();
+        config.divider = TONE_DIVIDER.into();
+        config.compare_b = 0;          // 0% duty = silent
+        let pwm = Pwm::new_output_b(slice, pin, config.clone());
+        Self { pwm, config }
+    }
+}
+
+#[cfg(feature = "passive-buzzer")]
+impl Tone for PwmPiezo<'_> {
+    fn tone(&mut self, hz: u32) {
+        if hz == 0 {
+            self.config.compare_b = 0;
+        } else {
+            // One PWM period = one cycle of the tone. High for half of it
+            // at full volume, less than that for quieter
+            let period = (TONE_CLOCK_HZ / hz).clamp(2, u16::MAX as u32);
+            let high = (period / 2 * BUZZER_VOLUME_PERCENT / 100).max(1);
+            self.config.top = (period - 1) as u16;
+            self.config.compare_b = high as u16;
+        }
+        self.pwm.set_config(&self.config);
+    }
+}
+
+/// The buzzer on GPIO 7
+pub struct Buzzer<T> {
+    /// Loudness, 0-100 %
+    volume_percent: u32,
+    output: T,
+}
+
+impl<T: Tone> Buzzer<T> {
+    /// Wrap the buzzer hardware (which should start silent)
+    pub fn new(output: T) -> Self {
+        Self { volume_percent: BUZZER_VOLUME_PERCENT, output }
+    }
+
+    /// Start sounding at `hz` (0 = silence)
+    ///
+    /// An active buzzer can't change pitch - any non-zero frequency just
+    /// switches it on.
+    pub fn tone(&mut self, hz: u32) {
+        let silent = hz == 0 || self.volume_percent == 0 || is_muted();
+        self.output.tone(if silent { 0 } else { hz });
+    }
+
+    /// Stop sounding
+    pub fn off(&mut self) {
+        self.tone(0);
+    }
+
+    /// Play one step of a signal (`hz: 0` is silence)
+    pub async fn step(&mut self, note: Note) {
+        self.tone(note.hz);
+        Timer::after_millis(note.ms).await;
+    }
+
+    /// Play a whole signal, step by step - silent afterwards
+    pub async fn play(&mut self, steps: impl Iterator<Item = Note>) {
+        for note in steps {
+            self.step(note).await;
+        }
+        self.off();
+    }
+
+    /// Play one queued sound
+    async fn sound(&mut self, sound: Sound) {
+        let Sound { event, count } = sound;
+        match event {
+            AudioEvent::Complete => self.completion(count).await,
+            AudioEvent::Abort => self.abort().await,
+            #[cfg(feature = "thermistor")]
+            AudioEvent::Overheated => self.alarm().await,
+            AudioEvent::LampService => self.lamp_service().await,
+            AudioEvent::LidWarning => loop {
+                self.play(AUDIO_PATTERNS.pattern(event).steps()).await;
+            },
+            _ => self.play(AUDIO_PATTERNS.counted(event, count).steps()).await,
+        }
+    }
+
+    /// "Cure finished" signal
+    #[cfg_attr(feature = "passive-buzzer", allow(unused_variables))]
+    async fn completion(&mut self, count: u32) {
+        #[cfg(feature = "passive-buzzer")]
+        self.play(signals::melody(COMPLETION_MELODY, NOTE_GAP_MS)).await;
+
+        // Configurable number, length and spacing of the beeps
+        #[cfg(not(feature = "passive-buzzer"))]
+        {
+            let beeps = AUDIO_PATTERNS.counted(AudioEvent::Complete, count);
+            let mut number = 0;
+            for note in beeps.steps() {
+                if note.hz != 0 {
+                    number += 1;
+                    events::record(Event::Beep { number, of: beeps.count });
+                }
+                self.step(note).await;
+            }
+            self.off();
+        }
+    }
+
+    /// "Cure aborted" signal - long beeps, clearly different from "done"
+    async fn abort(&mut self) {
+        #[cfg(feature = "passive-buzzer")]
+        self.play(signals::melody(ABORT_MELODY, NOTE_GAP_MS)).await;
+
+        #[cfg(not(feature = "passive-buzzer"))]
+        self.play(AUDIO_PATTERNS.abort.steps()).await;
+    }
+
+    /// Over-temperature alarm - repeated bursts of rapid beeps,
+    /// nothing like the other signals
+    #[cfg(feature = "thermistor")]
+    async fn alarm(&mut self) {
+        for _ in 0..OVERTEMP_ALARM_REPEATS {
+            #[cfg(feature = "passive-buzzer")]
+            self.play(signals::melody(ERROR_MELODY, NOTE_GAP_MS)).await;
+
+            #[cfg(not(feature = "passive-buzzer"))]
+            self.play(AUDIO_PATTERNS.overheated.steps()).await;
+
+            Timer::after_millis(BEEP_PAUSE_MS * 2).await;
+        }
+    }
+
+    /// "Service lamp" signal - three short beeps and a long one, twice
+    async fn lamp_service(&mut self) {
+        for _ in 0..LAMP_SERVICE_REPEATS {
+            self.play(AUDIO_PATTERNS.lamp_service.steps()).await;
+            self.play(AUDIO_PATTERNS.confirm.steps()).await;
+            Timer::after_millis(BEEP_PAUSE_MS * 2).await;
+        }
+    }
+}
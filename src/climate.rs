@@ -0,0 +1,233 @@
+// Chamber Climate Monitor (bme280 builds)
+//
+// Reads temperature and humidity from a Bosch BME280 sensor over I2C:
+//
+//   GPIO 8 (I2C0 SDA) ── SDA
+//   GPIO 9 (I2C0 SCL) ── SCL
+//   3.3V ── VIN, GND ── GND   (most breakout boards have pull-ups fitted)
+//
+// A background task takes a reading every CLIMATE_SAMPLE_INTERVAL_SECONDS
+// and stores it, so the rest of the firmware can use it without touching
+// the I2C bus:
+// - `latest()` returns the most recent reading at any time
+// - `NEW_READING` is signalled with every fresh reading, for anything that
+//   wants to react to changes (future interlocks)
+//
+// While no cure is running it also logs the climate every
+// CLIMATE_IDLE_LOG_INTERVAL_SECONDS - the latest reading plus the lowest and
+// highest since the last log - so a night's log shows how the enclosure
+// (and any resin stored in it) fared. These go straight to the log rather
+// than the event log (events.rs), so a night of them doesn't push the last
+// cure out of its ring buffer. The readings are also in the status outputs
+// (MQTT, the web page and JSON status).
+//
+// The BME280 needs per-chip calibration values (read once at startup) to
+// turn its raw readings into real units - the maths below is the integer
+// version straight from the Bosch datasheet (section 4.2.3).
+
+use core::cell::Cell;
+
+use defmt::*;
+use embassy_rp::bind_interrupts;
+use embassy_rp::i2c::{self, Async, I2c};
+use embassy_rp::peripherals::{I2C0, PIN_8, PIN_9};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Instant, Timer};
+
+use crate::config::*;
+use crate::remote::{self, CureStatus};
+
+bind_interrupts!(struct Irqs {
+    I2C0_IRQ => i2c::InterruptHandler<I2C0>;
+});
+
+/* BME280 registers */
+const REG_CHIP_ID: u8 = 0xD0;
+const REG_CALIB_T_P: u8 = 0x88;   // 26 bytes: temperature/pressure calibration + H1
+const REG_CALIB_H: u8 = 0xE1;     // 7 bytes: humidity calibration
+const REG_CTRL_HUM: u8 = 0xF2;
+const REG_CTRL_MEAS: u8 = 0xF4;
+const REG_TEMP_MSB: u8 = 0xFA;    // 5 bytes: temperature (3) + humidity (2)
+
+const CHIP_ID: u8 = 0x60;
+
+/// Humidity oversampling x1
+const CTRL_HUM_X1: u8 = 0x01;
+/// Temperature oversampling x1, pressure skipped, forced (one-shot) mode
+const CTRL_MEAS_FORCED: u8 = 0b001_000_01;
+
+/// One temperature/humidity reading
+#[derive(Clone, Copy, Format)]
+pub struct ClimateReading {
+    /// Temperature in hundredths of a degree C (2345 = 23.45 °C)
+    pub centi_celsius: i32,
+    /// Relative humidity in hundredths of a percent (4567 = 45.67 %RH)
+    pub centi_percent_rh: u32,
+}
+
+/// Most recent reading (None until the first one arrives)
+static LATEST: Mutex<CriticalSectionRawMutex, Cell<Option<ClimateReading>>> = Mutex::new(Cell::new(None));
+
+/// Signalled with every new reading
+pub static NEW_READING: Signal<CriticalSectionRawMutex, ClimateReading> = Signal::new();
+
+/// The most recent reading, if the sensor has produced one
+pub fn latest() -> Option<ClimateReading> {
+    LATEST.lock(|latest| latest.get())
+}
+
+/// Log the most recent reading alongside a cure event
+pub fn log_latest(event: &str) {
+    match latest() {
+        Some(r) => info!("{} - chamber {}.{:02} °C, {}.{:02} %RH", event,
+                         r.centi_celsius / 100, (r.centi_celsius % 100).abs(),
+                         r.centi_percent_rh / 100, r.centi_percent_rh % 100),
+        None => info!("{} - no chamber climate reading yet", event),
+    }
+}
+
+/// Log the climate while idle: the latest reading and the range since the last log
+fn log_idle(latest: ClimateReading, low: ClimateReading, high: ClimateReading) {
+    info!(
+        "Idle chamber climate: {}.{:02} °C, {}.{:02} %RH (last {} min: {}.{:02} to {}.{:02} °C, {}.{:02} to {}.{:02} %RH)",
+        latest.centi_celsius / 100, (latest.centi_celsius % 100).abs(),
+        latest.centi_percent_rh / 100, latest.centi_percent_rh % 100,
+        CLIMATE_IDLE_LOG_INTERVAL_SECONDS / 60,
+        low.centi_celsius / 100, (low.centi_celsius % 100).abs(),
+        high.centi_celsius / 100, (high.centi_celsius % 100).abs(),
+        low.centi_percent_rh / 100, low.centi_percent_rh % 100,
+        high.centi_percent_rh / 100, high.centi_percent_rh % 100
+    );
+}
+
+/// Per-chip calibration values from the sensor's NVM
+struct Calibration {
+    t1: i32, t2: i32, t3: i32,
+    h1: i32, h2: i32, h3: i32, h4: i32, h5: i32, h6: i32,
+}
+
+impl Calibration {
+    fn from_registers(tp: &[u8; 26], h: &[u8; 7]) -> Self {
+        let u16_at = |b: &[u8], i: usize| u16::from_le_bytes([b[i], b[i + 1]]) as i32;
+        let i16_at = |b: &[u8], i: usize| i16::from_le_bytes([b[i], b[i + 1]]) as i32;
+        Self {
+            t1: u16_at(tp, 0),
+            t2: i16_at(tp, 2),
+            t3: i16_at(tp, 4),
+            h1: tp[25] as i32,
+            h2: i16_at(h, 0),
+            h3: h[2] as i32,
+            h4: ((h[3] as i8 as i32) << 4) | (h[4] & 0x0F) as i32,
+            h5: ((h[5] as i8 as i32) << 4) | (h[4] >> 4) as i32,
+            h6: h[6] as i8 as i32,
+        }
+    }
+
+    /// Turn raw ADC values into a reading (datasheet compensation formulas)
+    fn compensate(&self, adc_t: i32, adc_h: i32) -> ClimateReading {
+        // Temperature
+        let var1 = (((adc_t >> 3) - (self.t1 << 1)) * self.t2) >> 11;
+        let var2 = (((((adc_t >> 4) - self.t1) * ((adc_t >> 4) - self.t1)) >> 12) * self.t3) >> 14;
+        let t_fine = var1 + var2;
+        let centi_celsius = (t_fine * 5 + 128) >> 8;
+
+        // Humidity (uses t_fine from the temperature calculation)
+        let mut v = t_fine - 76_800;
+        v = ((((adc_h << 14) - (self.h4 << 20) - (self.h5 * v)) + 16_384) >> 15)
+            * (((((((v * self.h6) >> 10) * (((v * self.h3) >> 11) + 32_768)) >> 10) + 2_097_152) * self.h2 + 8_192) >> 14);
+        v -= ((((v >> 15) * (v >> 15)) >> 7) * self.h1) >> 4;
+        let v = v.clamp(0, 419_430_400);
+        let q22_10 = (v >> 12) as u32;  // %RH * 1024
+        let centi_percent_rh = q22_10 * 100 / 1024;
+
+        ClimateReading { centi_celsius, centi_percent_rh }
+    }
+}
+
+/// Minimal BME280 driver: just temperature and humidity, one-shot readings
+struct Bme280<'d> {
+    i2c: I2c<'d, I2C0, Async>,
+    calibration: Calibration,
+}
+
+impl<'d> Bme280<'d> {
+    /// Check the chip is there and read its calibration
+    async fn new(mut i2c: I2c<'d, I2C0, Async>) -> Result<Self, i2c::Error> {
+        let mut id = [0u8; 1];
+        i2c.write_read_async(BME280_ADDRESS, [REG_CHIP_ID], &mut id).await?;
+        if id[0] != CHIP_ID {
+            warn!("Device at 0x{:02x} is not a BME280 (chip id 0x{:02x})", BME280_ADDRESS, id[0]);
+        }
+
+        let mut tp = [0u8; 26];
+        let mut h = [0u8; 7];
+        i2c.write_read_async(BME280_ADDRESS, [REG_CALIB_T_P], &mut tp).await?;
+        i2c.write_read_async(BME280_ADDRESS, [REG_CALIB_H], &mut h).await?;
+
+        Ok(Self { i2c, calibration: Calibration::from_registers(&tp, &h) })
+    }
+
+    /// Take one forced-mode measurement
+    async fn read(&mut self) -> Result<ClimateReading, i2c::Error> {
+        // ctrl_hum only takes effect after a write to ctrl_meas, so write it first
+        self.i2c.write_async(BME280_ADDRESS, [REG_CTRL_HUM, CTRL_HUM_X1]).await?;
+        self.i2c.write_async(BME280_ADDRESS, [REG_CTRL_MEAS, CTRL_MEAS_FORCED]).await?;
+        Timer::after_millis(10).await;  // x1 temperature + humidity takes ~5 ms
+
+        let mut data = [0u8; 5];
+        self.i2c.write_read_async(BME280_ADDRESS, [REG_TEMP_MSB], &mut data).await?;
+        let adc_t = ((data[0] as i32) << 12) | ((data[1] as i32) << 4) | ((data[2] as i32) >> 4);
+        let adc_h = ((data[3] as i32) << 8) | data[4] as i32;
+        Ok(self.calibration.compensate(adc_t, adc_h))
+    }
+}
+
+/// Background task: sample the BME280 and publish the readings
+#[embassy_executor::task]
+pub async fn climate_monitor_task(i2c: I2C0, sda: PIN_8, scl: PIN_9) {
+    let bus = I2c::new_async(i2c, scl, sda, Irqs, i2c::Config::default());
+
+    let mut sensor = match Bme280::new(bus).await {
+        Ok(sensor) => sensor,
+        Err(_) => {
+            // Not fatal - the curer works fine without climate readings
+            warn!("BME280 not found at 0x{:02x} - climate monitoring disabled", BME280_ADDRESS);
+            return;
+        }
+    };
+    info!("BME280 climate monitor running");
+
+    // Lowest and highest readings since the last idle log
+    let mut range: Option<(ClimateReading, ClimateReading)> = None;
+    let mut last_log = Instant::now();
+
+    loop {
+        match sensor.read().await {
+            Ok(reading) => {
+                LATEST.lock(|latest| latest.set(Some(reading)));
+                NEW_READING.signal(reading);
+                debug!("Chamber climate: {}", reading);
+
+                let (low, high) = range.get_or_insert((reading, reading));
+                low.centi_celsius = low.centi_celsius.min(reading.centi_celsius);
+                low.centi_percent_rh = low.centi_percent_rh.min(reading.centi_percent_rh);
+                high.centi_celsius = high.centi_celsius.max(reading.centi_celsius);
+                high.centi_percent_rh = high.centi_percent_rh.max(reading.centi_percent_rh);
+            }
+            Err(_) => warn!("BME280 read failed"),
+        }
+
+        if last_log.elapsed() >= Duration::from_secs(CLIMATE_IDLE_LOG_INTERVAL_SECONDS) {
+            // Cures log the climate as they start and end, so only idle time is logged here
+            let idle = matches!(remote::status(), CureStatus::Idle | CureStatus::Standby);
+            if let (true, Some(current), Some((low, high))) = (idle, latest(), range) {
+                log_idle(current, low, high);
+            }
+            range = None;
+            last_log = Instant::now();
+        }
+        Timer::after_secs(CLIMATE_SAMPLE_INTERVAL_SECONDS).await;
+    }
+}
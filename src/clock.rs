@@ -0,0 +1,187 @@
+// Wall-Clock Time
+//
+// Cures are timed from power-on (embassy_time::Instant) and don't need the
+// date. Logs do, and so does anything that should happen at a time of day,
+// so this module keeps track of the wall-clock time for the whole firmware:
+// - `set()` tells it the time - read from the DS3231 at startup (rtc-ds3231
+//   builds, see ds3231.rs)
+// - `adjust()` is the user setting the clock (`time 2026-10-16 14:30:00`
+//   over USB serial); the DS3231 is updated to match
+// - `now()` is the current time, `at()` the time of an earlier moment -
+//   both None until the clock has been set
+//
+// There are no time zones or daylight saving - set the clock to local time.
+
+use core::cell::Cell;
+use core::fmt;
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::signal::Signal;
+use embassy_time::Instant;
+
+/// A date and time of day (2000-2099, to the second)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl DateTime {
+    /// A checked date and time - None if any part is out of range
+    pub fn new(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> Option<Self> {
+        let valid = (2000..=2099).contains(&year)
+            && (1..=12).contains(&month)
+            && day >= 1
+            && day <= days_in_month(year, month)
+            && hour < 24
+            && minute < 60
+            && second < 60;
+        valid.then_some(Self { year, month, day, hour, minute, second })
+    }
+
+    /// Parse "2026-10-16" plus "14:30" or "14:30:00"
+    #[cfg_attr(not(any(feature = "usb-serial", feature = "pico-w", feature = "uart-control")), allow(dead_code))]
+    pub fn parse(date: &str, time: &str) -> Option<Self> {
+        let mut date = date.split('-');
+        let year = date.next()?.parse().ok()?;
+        let month = date.next()?.parse().ok()?;
+        let day = date.next()?.parse().ok()?;
+        let mut time = time.split(':');
+        let hour = time.next()?.parse().ok()?;
+        let minute = time.next()?.parse().ok()?;
+        let second = time.next().map_or(Some(0), |s| s.parse().ok())?;
+        if date.next().is_some() || time.next().is_some() {
+            return None;
+        }
+        Self::new(year, month, day, hour, minute, second)
+    }
+
+    /// Parse ISO 8601 "2026-10-16T14:30:00" (or with a space instead of the T)
+    #[cfg_attr(not(any(feature = "usb-serial", feature = "pico-w", feature = "uart-control")), allow(dead_code))]
+    pub fn parse_iso(text: &str) -> Option<Self> {
+        let (date, time) = text.split_once(['T', ' '])?;
+        Self::parse(date, time)
+    }
+
+    /// Days since 1970-01-01
+    pub fn days_since_epoch(&self) -> u32 {
+        // Days from 1970 to the start of the year, then to the start of the month
+        let mut days = 0;
+        for year in 1970..self.year {
+            days += if is_leap_year(year) { 366 } else { 365 };
+        }
+        for month in 1..self.month {
+            days += days_in_month(self.year, month) as u32;
+        }
+        days + self.day as u32 - 1
+    }
+
+    /// Seconds since 1970-01-01 00:00:00
+    pub fn to_seconds(&self) -> u64 {
+        self.days_since_epoch() as u64 * 86_400 + self.hour as u64 * 3600 + self.minute as u64 * 60 + self.second as u64
+    }
+
+    /// The date and time `seconds` after 1970-01-01 00:00:00
+    pub fn from_seconds(seconds: u64) -> Self {
+        let mut days = (seconds / 86_400) as u32;
+        let mut year = 1970;
+        loop {
+            let length = if is_leap_year(year) { 366 } else { 365 };
+            if days < length {
+                break;
+            }
+            days -= length;
+            year += 1;
+        }
+        let mut month = 1;
+        while days >= days_in_month(year, month) as u32 {
+            days -= days_in_month(year, month) as u32;
+            month += 1;
+        }
+        let time = seconds % 86_400;
+        Self {
+            year,
+            month,
+            day: days as u8 + 1,
+            hour: (time / 3600) as u8,
+            minute: (time % 3600 / 60) as u8,
+            second: (time % 60) as u8,
+        }
+    }
+}
+
+/// ISO 8601, e.g. "2026-10-16T14:30:00"
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+impl defmt::Format for DateTime {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "{=u16}-{=u8:02}-{=u8:02} {=u8:02}:{=u8:02}:{=u8:02}",
+            self.year,
+            self.month,
+            self.day,
+            self.hour,
+            self.minute,
+            self.second
+        )
+    }
+}
+
+fn is_leap_year(year: u16) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// The wall-clock time (seconds since 1970) at a moment since power-on (ms)
+static BASE: Mutex<CriticalSectionRawMutex, Cell<Option<(u64, u64)>>> = Mutex::new(Cell::new(None));
+
+/// Signalled when the user sets the clock, so the DS3231 can be updated
+pub static ADJUSTED: Signal<CriticalSectionRawMutex, DateTime> = Signal::new();
+
+/// Tell the clock what time it is now
+pub fn set(time: DateTime) {
+    BASE.lock(|base| base.set(Some((time.to_seconds(), Instant::now().as_millis()))));
+}
+
+/// The user set the clock - like `set()`, and saved to the DS3231 if there is one
+#[cfg_attr(not(any(feature = "usb-serial", feature = "pico-w", feature = "uart-control")), allow(dead_code))]
+pub fn adjust(time: DateTime) {
+    set(time);
+    ADJUSTED.signal(time);
+}
+
+/// The current time, if the clock has been set
+pub fn now() -> Option<DateTime> {
+    at(Instant::now().as_millis())
+}
+
+/// The wall-clock time of a moment `ms` milliseconds after power-on, if
+/// the clock has been set
+pub fn at(ms: u64) -> Option<DateTime> {
+    let (base_seconds, base_ms) = BASE.lock(|base| base.get())?;
+    // The moment may be before the clock was set
+    let seconds = (base_seconds as i64 + (ms as i64 - base_ms as i64).div_euclid(1000)).max(0);
+    Some(DateTime::from_seconds(seconds as u64))
+}
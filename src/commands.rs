@@ -0,0 +1,760 @@
+// Remote Commands
+//
+// Turns one line from a remote interface (e.g. the USB serial port) into a
+// `Command`, and runs it. The web interface builds its commands directly and
+// only uses `execute()`; the UART protocol wraps the plain text commands in
+// checksummed frames (uart_control.rs). Two formats are understood:
+//
+// Plain text for typing in a terminal (case-insensitive, extra spaces ignored):
+//   start            start a cure of the normal length
+//   start 120        start a 120-second cure
+//   stop             abort the running cure
+//   status           report what the curer is doing
+//   config           show the settings
+//   stats            show the lifetime cure counters
+//   mem              show the most stack ever used (see stack.rs)
+//   time             show the date and time (see clock.rs)
+//   time 2026-10-16 14:30:00   set the date and time
+//   set debounce 80  change the button debounce time (ms)
+//   set verbosity 3  change how much the event log prints (0-3, see events.rs)
+//   set cure 120     change the curing time (s)
+//   set cooldown 5000  change the pause after each cure (ms)
+//   set beeps 3      change the number of "cure finished" beeps (1-10)
+//   set silent 1     silent mode on (1) or off (0)
+//   set poweron resume  what to do at power-on: idle, resume or autostart
+//   token s3cret     set the API token network commands need (see below)
+//   token off        no API token needed any more
+//   wifi             show the Wi-Fi network and whether it's connected (pico-w)
+//   wifi set MyNetwork s3cret-password   join this network, and save it
+//   wifi clear       back to the built-in network
+//   gpio read 6      read a pin (gpio-diagnostics builds, see gpio_diag.rs)
+//   gpio set 10 low  drive a pin high or low, at idle
+//   gpio pulse 10 100  flip a pin for 100 ms, at idle
+//   gpio release 10  give a pin back to the firmware
+//   help             list the commands
+//
+// JSON, one object per line, for scripts and GUIs:
+//   {"cmd":"start","seconds":120}
+//   {"cmd":"stop"}
+//   {"cmd":"status"}
+//   {"cmd":"get_config"}
+//   {"cmd":"get_stats"}
+//   {"cmd":"get_mem"}
+//   {"cmd":"get_time"}
+//   {"cmd":"set_time","time":"2026-10-16T14:30:00"}
+//   {"cmd":"set_config","debounce_ms":80}
+//   {"cmd":"set_config","verbosity":3}
+//   {"cmd":"set_config","cure_s":120}    (also "cooldown_ms", "beeps", "silent":true)
+//   {"cmd":"set_config","power_on":"autostart"}
+//   {"cmd":"set_token","token":"s3cret"}   ("off" for none)
+//   {"cmd":"get_wifi"}
+//   {"cmd":"set_wifi","ssid":"My Network","password":"s3cret-password"}   ("ssid":"" for the built-in one)
+// The debounce time and verbosity last until power off. The cure time,
+// cooldown, beeps and silent mode are the settings menu's: they're saved
+// to flash just as if they'd been picked there (see settings.rs), and so
+// is the power-on behavior.
+// An optional "id" number is echoed back in the response, so a script can
+// match responses to requests.
+//
+// Once an API token is set (saved to flash like the settings), the network
+// interfaces - the web API and MQTT - must give it with any command that
+// changes something: `authorize()` checks. The serial ports don't need it:
+// they're plugged in, and where the token is set from.
+//
+// Parsing doesn't touch any hardware. `execute()` hands start/stop to the
+// main loop through remote.rs - it never drives outputs itself.
+
+use core::fmt;
+
+use serde::Deserialize;
+
+use crate::clock::{self, DateTime};
+use crate::config::*;
+use crate::remote::{self, CureStatus, RemoteCommand};
+#[cfg(feature = "pico-w")]
+use crate::settings::WifiCredentials;
+use crate::settings::{self, ApiToken};
+use crate::stack;
+use crate::stats::{self, CureStats};
+
+/// A parsed command
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Start a cure, optionally with a curing time in seconds
+    Start(Option<u64>),
+    /// Abort the running cure
+    Stop,
+    /// Report the current state
+    Status,
+    /// Report the settings
+    Config,
+    /// Report the lifetime cure counters
+    Stats,
+    /// Report the stack high-water mark
+    Mem,
+    /// Report the date and time
+    Time,
+    /// Set the date and time
+    SetTime(DateTime),
+    /// Change a runtime setting
+    Set(Setting, u64),
+    /// Change the API token (empty = none)
+    SetToken(ApiToken),
+    /// Report the Wi-Fi network and connection
+    #[cfg(feature = "pico-w")]
+    Wifi,
+    /// Join a new Wi-Fi network (empty = the built-in one)
+    #[cfg(feature = "pico-w")]
+    SetWifi(WifiCredentials),
+    /// Read or drive a GPIO pin by number
+    #[cfg(feature = "gpio-diagnostics")]
+    Gpio(u8, GpioAction),
+    /// List the commands
+    Help,
+}
+
+/// What `gpio` does to its pin
+#[cfg(feature = "gpio-diagnostics")]
+#[derive(Debug, PartialEq, Eq)]
+pub enum GpioAction {
+    /// Report the level
+    Read,
+    /// Drive it high (true) or low
+    Set(bool),
+    /// Flip it for this many milliseconds
+    Pulse(u64),
+    /// Hand it back to the firmware
+    Release,
+}
+
+/// Settings that can be changed with `set`
+#[derive(Debug, PartialEq, Eq)]
+pub enum Setting {
+    /// Button debounce time in milliseconds
+    Debounce,
+    /// Event log verbosity, 0 (errors only) to 3 (everything)
+    Verbosity,
+    /// Curing time in seconds (saved, like the rest below)
+    CureTime,
+    /// Pause after each cure in milliseconds
+    Cooldown,
+    /// Number of "cure finished" beeps
+    Beeps,
+    /// Silent mode, 1 = on and 0 = off
+    Silent,
+    /// What to do at power-on, a PowerOnBehavior as a number (saved)
+    PowerOn,
+}
+
+/// Why a line couldn't be parsed
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// Blank line
+    Empty,
+    /// First word isn't a known command
+    UnknownCommand,
+    /// `set` with a setting name we don't know
+    UnknownSetting,
+    /// A number was expected but missing
+    MissingValue,
+    /// Something that should be a number isn't
+    BadNumber,
+    /// A setting that takes a name was given one it doesn't know
+    UnknownChoice,
+    /// Extra words after a complete command
+    TooManyArguments,
+    /// Not a valid JSON request object
+    BadJson,
+    /// Not a date and time, or not a real one
+    BadTime,
+    /// An API token that's too long or has characters it can't have
+    BadToken,
+    /// A Wi-Fi network name or password that's too long or too short
+    #[cfg_attr(not(feature = "pico-w"), allow(dead_code))]
+    BadWifi,
+}
+
+#[cfg_attr(not(feature = "usb-serial"), allow(dead_code))]  // Only the serial port parses lines
+impl ParseError {
+    /// Short explanation to send back to the user
+    pub fn message(&self) -> &'static str {
+        match self {
+            ParseError::Empty => "empty command",
+            ParseError::UnknownCommand => "unknown command (try 'help')",
+            ParseError::UnknownSetting => "unknown setting (try 'help')",
+            ParseError::MissingValue => "missing value",
+            ParseError::BadNumber => "not a number",
+            ParseError::UnknownChoice => "not one of the choices (try 'help')",
+            ParseError::TooManyArguments => "too many arguments",
+            ParseError::BadJson => "invalid JSON request",
+            ParseError::BadTime => "not a valid date and time (YYYY-MM-DD HH:MM:SS)",
+            ParseError::BadToken => "API tokens are up to 23 letters, digits and -._~+/=",
+            ParseError::BadWifi => "Wi-Fi names are 1-32 characters and passwords 8-63",
+        }
+    }
+
+    /// Stable machine-readable name, used in JSON responses
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseError::Empty => "empty",
+            ParseError::UnknownCommand => "unknown_command",
+            ParseError::UnknownSetting => "unknown_setting",
+            ParseError::MissingValue => "missing_value",
+            ParseError::BadNumber => "bad_number",
+            ParseError::UnknownChoice => "unknown_choice",
+            ParseError::TooManyArguments => "too_many_arguments",
+            ParseError::BadJson => "bad_json",
+            ParseError::BadTime => "bad_time",
+            ParseError::BadToken => "bad_token",
+            ParseError::BadWifi => "bad_wifi",
+        }
+    }
+}
+
+/// Why a command was refused
+#[derive(Debug, PartialEq, Eq)]
+pub enum CommandError {
+    /// A cure (or its cooldown) is already running
+    Busy,
+    /// Nothing to stop
+    NotRunning,
+    /// A latching start switch decides when cures run
+    StartSwitch,
+    /// Curing time of 0 or beyond MAX_CURING_DURATION_SECONDS
+    BadDuration,
+    /// Setting value outside its allowed range
+    OutOfRange,
+    /// The main loop hasn't picked up earlier commands yet
+    QueueFull,
+    /// A network command without the right API token
+    Unauthorized,
+}
+
+impl CommandError {
+    /// Short explanation to send back to the user
+    pub fn message(&self) -> &'static str {
+        match self {
+            CommandError::Busy => "busy - send 'stop' first",
+            CommandError::NotRunning => "no cure running",
+            CommandError::StartSwitch => "the start switch controls cures",
+            CommandError::BadDuration => "time must be 1 second up to the safety maximum",
+            CommandError::OutOfRange => "value out of range",
+            CommandError::QueueFull => "busy, try again",
+            CommandError::Unauthorized => "API token missing or wrong",
+        }
+    }
+
+    /// Stable machine-readable name, used in JSON responses
+    pub fn code(&self) -> &'static str {
+        match self {
+            CommandError::Busy => "busy",
+            CommandError::NotRunning => "not_running",
+            CommandError::StartSwitch => "start_switch",
+            CommandError::BadDuration => "bad_duration",
+            CommandError::OutOfRange => "out_of_range",
+            CommandError::QueueFull => "queue_full",
+            CommandError::Unauthorized => "unauthorized",
+        }
+    }
+}
+
+/// What a successfully run command has to report
+pub enum Reply {
+    /// Done, nothing more to say
+    Done,
+    /// Current state, for `status`
+    Status(CureStatus),
+    /// Settings, for `config`
+    Config,
+    /// Cure counters, for `stats`
+    Stats(CureStats),
+    /// Deepest stack use since boot and the stack's size in bytes, for `mem`
+    Mem { used: usize, size: usize },
+    /// Date and time, for `time` (None = the clock hasn't been set)
+    Time(Option<DateTime>),
+    /// Wi-Fi network and connection, for `wifi`
+    #[cfg(feature = "pico-w")]
+    Wifi,
+    /// A pin's level, for `gpio read`
+    #[cfg(feature = "gpio-diagnostics")]
+    Gpio { pin: u8, high: bool },
+    /// Command list, for `help`
+    Help,
+}
+
+/// Parse one line of plain text into a command
+#[cfg_attr(not(any(feature = "usb-serial", feature = "uart-control")), allow(dead_code))]
+pub fn parse(line: &str) -> Result<Command, ParseError> {
+    let mut words = line.split_whitespace();
+    let name = words.next().ok_or(ParseError::Empty)?;
+
+    let command = if name.eq_ignore_ascii_case("start") {
+        match words.next() {
+            Some(seconds) => Command::Start(Some(number(seconds)?)),
+            None => Command::Start(None),
+        }
+    } else if name.eq_ignore_ascii_case("stop") {
+        Command::Stop
+    } else if name.eq_ignore_ascii_case("status") {
+        Command::Status
+    } else if name.eq_ignore_ascii_case("config") {
+        Command::Config
+    } else if name.eq_ignore_ascii_case("stats") {
+        Command::Stats
+    } else if name.eq_ignore_ascii_case("mem") {
+        Command::Mem
+    } else if name.eq_ignore_ascii_case("time") {
+        match words.next() {
+            // "2026-10-16 14:30:00", or "2026-10-16T14:30:00" in one word
+            Some(date) => {
+                let time = match date.split_once(['T', 't']) {
+                    Some((date, time)) => DateTime::parse(date, time),
+                    None => DateTime::parse(date, words.next().ok_or(ParseError::MissingValue)?),
+                };
+                Command::SetTime(time.ok_or(ParseError::BadTime)?)
+            }
+            None => Command::Time,
+        }
+    } else if name.eq_ignore_ascii_case("token") {
+        match words.next().ok_or(ParseError::MissingValue)? {
+            off if off.eq_ignore_ascii_case("off") => Command::SetToken(ApiToken::default()),
+            token => Command::SetToken(ApiToken::new(token).ok_or(ParseError::BadToken)?),
+        }
+    } else if name.eq_ignore_ascii_case("wifi") {
+        wifi(&mut words)?
+    } else if name.eq_ignore_ascii_case("gpio") {
+        gpio(&mut words)?
+    } else if name.eq_ignore_ascii_case("help") {
+        Command::Help
+    } else if name.eq_ignore_ascii_case("set") {
+        let setting = words.next().ok_or(ParseError::MissingValue)?;
+        let setting = if setting.eq_ignore_ascii_case("debounce") {
+            Setting::Debounce
+        } else if setting.eq_ignore_ascii_case("verbosity") {
+            Setting::Verbosity
+        } else if setting.eq_ignore_ascii_case("cure") {
+            Setting::CureTime
+        } else if setting.eq_ignore_ascii_case("cooldown") {
+            Setting::Cooldown
+        } else if setting.eq_ignore_ascii_case("beeps") {
+            Setting::Beeps
+        } else if setting.eq_ignore_ascii_case("silent") {
+            Setting::Silent
+        } else if setting.eq_ignore_ascii_case("poweron") {
+            Setting::PowerOn
+        } else {
+            return Err(ParseError::UnknownSetting);
+        };
+        let value = words.next().ok_or(ParseError::MissingValue)?;
+        let value = match setting {
+            Setting::PowerOn => power_on(value)?,
+            _ => number(value)?,
+        };
+        Command::Set(setting, value)
+    } else {
+        return Err(ParseError::UnknownCommand);
+    };
+
+    if words.next().is_some() {
+        return Err(ParseError::TooManyArguments);
+    }
+    Ok(command)
+}
+
+fn number(word: &str) -> Result<u64, ParseError> {
+    word.parse().map_err(|_| ParseError::BadNumber)
+}
+
+/// The rest of a `wifi` command
+#[cfg(feature = "pico-w")]
+fn wifi<'a>(words: &mut impl Iterator<Item = &'a str>) -> Result<Command, ParseError> {
+    match words.next() {
+        None => Ok(Command::Wifi),
+        Some(status) if status.eq_ignore_ascii_case("status") => Ok(Command::Wifi),
+        Some(clear) if clear.eq_ignore_ascii_case("clear") => Ok(Command::SetWifi(WifiCredentials::NONE)),
+        Some(set) if set.eq_ignore_ascii_case("set") => {
+            let ssid = words.next().ok_or(ParseError::MissingValue)?;
+            let password = words.next().ok_or(ParseError::MissingValue)?;
+            WifiCredentials::new(ssid, password).map(Command::SetWifi).ok_or(ParseError::BadWifi)
+        }
+        Some(_) => Err(ParseError::UnknownSetting),
+    }
+}
+
+/// (No Wi-Fi in this build)
+#[cfg(not(feature = "pico-w"))]
+fn wifi<'a>(_words: &mut impl Iterator<Item = &'a str>) -> Result<Command, ParseError> {
+    Err(ParseError::UnknownCommand)
+}
+
+/// The rest of a `gpio` command: an action, a pin and maybe a value
+#[cfg(feature = "gpio-diagnostics")]
+fn gpio<'a>(words: &mut impl Iterator<Item = &'a str>) -> Result<Command, ParseError> {
+    let action = words.next().ok_or(ParseError::MissingValue)?;
+    let pin = words.next().ok_or(ParseError::MissingValue)?;
+    let pin = pin.parse().map_err(|_| ParseError::BadNumber)?;
+    let action = if action.eq_ignore_ascii_case("read") {
+        GpioAction::Read
+    } else if action.eq_ignore_ascii_case("set") {
+        match words.next().ok_or(ParseError::MissingValue)? {
+            high if high.eq_ignore_ascii_case("high") || high == "1" => GpioAction::Set(true),
+            low if low.eq_ignore_ascii_case("low") || low == "0" => GpioAction::Set(false),
+            _ => return Err(ParseError::UnknownChoice),
+        }
+    } else if action.eq_ignore_ascii_case("pulse") {
+        // "100" or "100ms"
+        let ms = words.next().ok_or(ParseError::MissingValue)?;
+        GpioAction::Pulse(number(ms.strip_suffix("ms").unwrap_or(ms))?)
+    } else if action.eq_ignore_ascii_case("release") {
+        GpioAction::Release
+    } else {
+        return Err(ParseError::UnknownSetting);
+    };
+    Ok(Command::Gpio(pin, action))
+}
+
+/// (No GPIO diagnostics in this build)
+#[cfg(not(feature = "gpio-diagnostics"))]
+fn gpio<'a>(_words: &mut impl Iterator<Item = &'a str>) -> Result<Command, ParseError> {
+    Err(ParseError::UnknownCommand)
+}
+
+/// A power-on behavior's name, as its number for `Command::Set`
+fn power_on(name: &str) -> Result<u64, ParseError> {
+    settings::power_on_named(name).map(|behavior| behavior as u64).ok_or(ParseError::UnknownChoice)
+}
+
+/// The fields a JSON request may have - anything else is ignored
+#[derive(Deserialize)]
+struct JsonRequest<'a> {
+    cmd: &'a str,
+    id: Option<u32>,
+    seconds: Option<u64>,
+    debounce_ms: Option<u64>,
+    verbosity: Option<u64>,
+    time: Option<&'a str>,
+    cure_s: Option<u64>,
+    cooldown_ms: Option<u64>,
+    beeps: Option<u64>,
+    silent: Option<bool>,
+    power_on: Option<&'a str>,
+    token: Option<&'a str>,
+    #[cfg(feature = "pico-w")]
+    ssid: Option<&'a str>,
+    #[cfg(feature = "pico-w")]
+    password: Option<&'a str>,
+}
+
+/// Parse one JSON request line into a command, plus the request's "id" if any
+///
+/// The id comes back even when the command itself is bad, so the error
+/// response can still be matched to its request.
+#[cfg_attr(not(feature = "usb-serial"), allow(dead_code))]
+pub fn parse_json(line: &str) -> (Option<u32>, Result<Command, ParseError>) {
+    let request: JsonRequest = match serde_json_core::from_str(line) {
+        Ok((request, _)) => request,
+        Err(_) => return (None, Err(ParseError::BadJson)),
+    };
+
+    let command = match request.cmd {
+        "start" => Ok(Command::Start(request.seconds)),
+        "stop" => Ok(Command::Stop),
+        "status" => Ok(Command::Status),
+        "get_config" => Ok(Command::Config),
+        "get_stats" => Ok(Command::Stats),
+        "get_mem" => Ok(Command::Mem),
+        "get_time" => Ok(Command::Time),
+        #[cfg(feature = "pico-w")]
+        "get_wifi" => Ok(Command::Wifi),
+        #[cfg(feature = "pico-w")]
+        "set_wifi" => match (request.ssid, request.password) {
+            (Some(""), _) => Ok(Command::SetWifi(WifiCredentials::NONE)),
+            (Some(ssid), Some(password)) => WifiCredentials::new(ssid, password).map(Command::SetWifi).ok_or(ParseError::BadWifi),
+            _ => Err(ParseError::MissingValue),
+        },
+        "set_token" => match request.token {
+            Some(off) if off.is_empty() || off.eq_ignore_ascii_case("off") => Ok(Command::SetToken(ApiToken::default())),
+            Some(token) => ApiToken::new(token).map(Command::SetToken).ok_or(ParseError::BadToken),
+            None => Err(ParseError::MissingValue),
+        },
+        "set_time" => match request.time {
+            Some(time) => DateTime::parse_iso(time).map(Command::SetTime).ok_or(ParseError::BadTime),
+            None => Err(ParseError::MissingValue),
+        },
+        // One setting per request
+        "set_config" => {
+            let power_on = match request.power_on {
+                Some(name) => match power_on(name) {
+                    Ok(value) => Some(value),
+                    Err(e) => return (request.id, Err(e)),
+                },
+                None => None,
+            };
+            let mut given = [
+                request.debounce_ms.map(|ms| (Setting::Debounce, ms)),
+                request.verbosity.map(|level| (Setting::Verbosity, level)),
+                request.cure_s.map(|seconds| (Setting::CureTime, seconds)),
+                request.cooldown_ms.map(|ms| (Setting::Cooldown, ms)),
+                request.beeps.map(|beeps| (Setting::Beeps, beeps)),
+                request.silent.map(|silent| (Setting::Silent, silent as u64)),
+                power_on.map(|value| (Setting::PowerOn, value)),
+            ]
+            .into_iter()
+            .flatten();
+            match (given.next(), given.next()) {
+                (Some((setting, value)), None) => Ok(Command::Set(setting, value)),
+                (None, _) => Err(ParseError::MissingValue),
+                (Some(_), Some(_)) => Err(ParseError::TooManyArguments),
+            }
+        }
+        _ => Err(ParseError::UnknownCommand),
+    };
+    (request.id, command)
+}
+
+impl Command {
+    /// Does it change anything (rather than just report)?
+    pub fn changes_something(&self) -> bool {
+        match self {
+            Command::Status | Command::Config | Command::Stats | Command::Mem | Command::Time | Command::Help => false,
+            #[cfg(feature = "pico-w")]
+            Command::Wifi => false,
+            #[cfg(feature = "gpio-diagnostics")]
+            Command::Gpio(_, GpioAction::Read) => false,
+            _ => true,
+        }
+    }
+
+    /// Does it carry a secret (the API token, a Wi-Fi password) that
+    /// mustn't be logged?
+    #[cfg_attr(not(feature = "uart-control"), allow(dead_code))]
+    pub fn has_secret(&self) -> bool {
+        match self {
+            Command::SetToken(_) => true,
+            #[cfg(feature = "pico-w")]
+            Command::SetWifi(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// May a network interface run `command`, given the API token that came
+/// with it (None = none did)? Reports always; changes need the token, once
+/// one is set.
+#[cfg_attr(not(feature = "pico-w"), allow(dead_code))]
+pub fn authorize(command: &Command, token: Option<&[u8]>) -> Result<(), CommandError> {
+    if command.changes_something() && !settings::api_token_matches(token) {
+        return Err(CommandError::Unauthorized);
+    }
+    Ok(())
+}
+
+/// Run a command
+pub fn execute(command: Command) -> Result<Reply, CommandError> {
+    match command {
+        Command::Start(seconds) => {
+            if matches!(START_INPUT, StartInput::Latching) {
+                return Err(CommandError::StartSwitch);
+            }
+            if !matches!(remote::status(), CureStatus::Idle) {
+                return Err(CommandError::Busy);
+            }
+            if seconds.map_or(false, |s| s == 0 || s > MAX_CURING_DURATION_SECONDS) {
+                return Err(CommandError::BadDuration);
+            }
+            if !remote::send(RemoteCommand::Start(seconds)) {
+                return Err(CommandError::QueueFull);
+            }
+            Ok(Reply::Done)
+        }
+        Command::Stop => {
+            if matches!(remote::status(), CureStatus::Idle | CureStatus::Cooldown | CureStatus::Standby) {
+                return Err(CommandError::NotRunning);
+            }
+            if !remote::send(RemoteCommand::Stop) {
+                return Err(CommandError::QueueFull);
+            }
+            Ok(Reply::Done)
+        }
+        Command::Status => Ok(Reply::Status(remote::status())),
+        Command::Config => Ok(Reply::Config),
+        Command::Stats => Ok(Reply::Stats(stats::get())),
+        Command::Mem => Ok(Reply::Mem { used: stack::high_water_mark(), size: stack::stack_size() }),
+        Command::Time => Ok(Reply::Time(clock::now())),
+        Command::SetTime(time) => {
+            clock::adjust(time);
+            Ok(Reply::Done)
+        }
+        Command::Set(Setting::Debounce, ms) => {
+            if settings::set_button_debounce_ms(ms) {
+                Ok(Reply::Done)
+            } else {
+                Err(CommandError::OutOfRange)
+            }
+        }
+        Command::Set(Setting::Verbosity, level) => {
+            if settings::set_log_level(level) {
+                Ok(Reply::Done)
+            } else {
+                Err(CommandError::OutOfRange)
+            }
+        }
+        Command::SetToken(token) => {
+            settings::set_api_token(token);
+            settings::request_save();
+            Ok(Reply::Done)
+        }
+        #[cfg(feature = "pico-w")]
+        Command::Wifi => Ok(Reply::Wifi),
+        #[cfg(feature = "pico-w")]
+        Command::SetWifi(credentials) => {
+            settings::set_wifi_credentials(credentials);
+            settings::request_save();
+            crate::wifi::rejoin();
+            Ok(Reply::Done)
+        }
+        #[cfg(feature = "gpio-diagnostics")]
+        Command::Gpio(pin, action) => gpio_command(pin, action),
+        Command::Set(Setting::PowerOn, value) => {
+            let behavior = match value {
+                0 => PowerOnBehavior::Idle,
+                1 => PowerOnBehavior::Resume,
+                2 => PowerOnBehavior::AutoStart,
+                _ => return Err(CommandError::OutOfRange),
+            };
+            if !settings::set_power_on_behavior(behavior) {
+                return Err(CommandError::StartSwitch);
+            }
+            settings::request_save();
+            Ok(Reply::Done)
+        }
+        // The settings menu's own settings - changed the same way, and
+        // saved to flash by the main loop once it's idle
+        Command::Set(setting, value) => {
+            let mut changed = settings::menu_settings();
+            match setting {
+                Setting::CureTime => changed.cure_seconds = value,
+                Setting::Cooldown => changed.cooldown_ms = value,
+                Setting::Beeps => changed.completion_beeps = u32::try_from(value).map_err(|_| CommandError::OutOfRange)?,
+                Setting::Silent if value <= 1 => changed.muted = value == 1,
+                Setting::Silent => return Err(CommandError::OutOfRange),
+                Setting::Debounce | Setting::Verbosity | Setting::PowerOn => {}   // Handled above
+            }
+            if !settings::set_menu_settings(changed) {
+                return Err(CommandError::OutOfRange);
+            }
+            settings::request_save();
+            Ok(Reply::Done)
+        }
+        Command::Help => Ok(Reply::Help),
+    }
+}
+
+/// Run a `gpio` command - driving a pin takes it from the firmware, so only
+/// while no cure is running
+#[cfg(feature = "gpio-diagnostics")]
+fn gpio_command(pin: u8, action: GpioAction) -> Result<Reply, CommandError> {
+    use crate::gpio_diag;
+
+    let idle = matches!(remote::status(), CureStatus::Idle | CureStatus::Standby);
+    match action {
+        _ if !gpio_diag::exists(pin) => return Err(CommandError::OutOfRange),
+        GpioAction::Read => return Ok(Reply::Gpio { pin, high: gpio_diag::read(pin) }),
+        _ if !gpio_diag::may_drive(pin) => return Err(CommandError::OutOfRange),
+        // Always allowed - it puts things back
+        GpioAction::Release => {
+            gpio_diag::release(pin);
+        }
+        _ if !idle => return Err(CommandError::Busy),
+        GpioAction::Set(high) => gpio_diag::set(pin, high),
+        GpioAction::Pulse(ms) if ms == 0 || ms > GPIO_PULSE_MAX_MS => return Err(CommandError::OutOfRange),
+        GpioAction::Pulse(ms) => gpio_diag::pulse(pin, ms),
+    }
+    Ok(Reply::Done)
+}
+
+/// JSON response: {"ok":true,...} or {"ok":false,"error":"<code>"}, with
+/// the request's "id" echoed back if it had one
+///
+/// Every value is a number or one of our own fixed names, so nothing
+/// needs escaping - apart from a Wi-Fi network name (`write_json_text`).
+pub fn write_json_reply(out: &mut impl fmt::Write, id: Option<u32>, result: Result<Reply, &str>) -> fmt::Result {
+    write!(out, "{{")?;
+    if let Some(id) = id {
+        write!(out, "\"id\":{},", id)?;
+    }
+    match result {
+        Err(code) => write!(out, "\"ok\":false,\"error\":\"{}\"", code)?,
+        Ok(Reply::Done) | Ok(Reply::Help) => write!(out, "\"ok\":true")?,
+        Ok(Reply::Status(status)) => {
+            write!(out, "\"ok\":true,\"state\":\"{}\"", status.name())?;
+            if let Some(seconds) = status.remaining_seconds() {
+                write!(out, ",\"remaining_s\":{}", seconds)?;
+            }
+            if let Some(time) = status.ends_at() {
+                write!(out, ",\"ends_at\":\"{}\"", time)?;
+            }
+            #[cfg(any(feature = "thermistor", feature = "bme280"))]
+            if let Some(centi) = crate::sensors::chamber_centi_celsius() {
+                let sign = if centi < 0 { "-" } else { "" };
+                write!(out, ",\"temperature_c\":{}{}.{:02}", sign, centi.abs() / 100, centi.abs() % 100)?;
+            }
+            #[cfg(feature = "bme280")]
+            if let Some(reading) = crate::climate::latest() {
+                write!(out, ",\"humidity_pct\":{}.{:02}", reading.centi_percent_rh / 100, reading.centi_percent_rh % 100)?;
+            }
+        }
+        Ok(Reply::Config) => write!(
+            out,
+            "\"ok\":true,\"config\":{{\"cure_s\":{},\"max_cure_s\":{},\"cooldown_ms\":{},\"beeps\":{},\"silent\":{},\"power_on\":\"{}\",\"api_token\":{},\"debounce_ms\":{},\"verbosity\":{}}}",
+            settings::cure_seconds(),
+            MAX_CURING_DURATION_SECONDS,
+            settings::cooldown_ms(),
+            settings::completion_beeps(),
+            crate::buzzer::is_muted(),
+            settings::power_on_name(settings::power_on_behavior()),
+            !settings::api_token().is_empty(),
+            settings::button_debounce_ms(),
+            settings::log_level() as u8
+        )?,
+        Ok(Reply::Stats(stats)) => write!(
+            out,
+            "\"ok\":true,\"stats\":{{\"completed\":{},\"aborted\":{},\"uv_on_s\":{},\"lamp_s\":{},\"lamp_service_due\":{}}}",
+            stats.completed,
+            stats.aborted,
+            stats.uv_on_seconds,
+            stats.lamp_seconds,
+            stats.lamp_service_due()
+        )?,
+        Ok(Reply::Mem { used, size }) => write!(out, "\"ok\":true,\"mem\":{{\"stack_used\":{},\"stack_size\":{}}}", used, size)?,
+        Ok(Reply::Time(Some(time))) => write!(out, "\"ok\":true,\"time\":\"{}\"", time)?,
+        Ok(Reply::Time(None)) => write!(out, "\"ok\":true,\"time\":null")?,
+        #[cfg(feature = "gpio-diagnostics")]
+        Ok(Reply::Gpio { pin, high }) => write!(out, "\"ok\":true,\"gpio\":{},\"high\":{}", pin, high)?,
+        #[cfg(feature = "pico-w")]
+        Ok(Reply::Wifi) => {
+            write!(out, "\"ok\":true,\"wifi\":{{\"ssid\":")?;
+            write_json_text(out, &crate::wifi::network_name())?;
+            write!(out, ",\"saved\":{},\"connected\":", !settings::wifi_credentials().is_empty())?;
+            match crate::wifi::address() {
+                Some([a, b, c, d]) => write!(out, "true,\"address\":\"{}.{}.{}.{}\"}}", a, b, c, d)?,
+                None => write!(out, "false}}")?,
+            }
+        }
+    }
+    write!(out, "}}")
+}
+
+/// A JSON string - for the one value that isn't our own (a Wi-Fi network name)
+#[cfg(feature = "pico-w")]
+fn write_json_text(out: &mut impl fmt::Write, text: &str) -> fmt::Result {
+    write!(out, "\"")?;
+    for c in text.chars() {
+        match c {
+            '"' | '\\' => write!(out, "\\{}", c)?,
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32)?,
+            c => write!(out, "{}", c)?,
+        }
+    }
+    write!(out, "\"")
+}
@@ -3,6 +3,28 @@
 // This file contains all user-configurable settings in one place.
 // To change curing time or other settings, just modify the values here
 // and rebuild the project - no need to edit the main program logic!
+//
+// The most common settings can also be set from a curer.toml file (see
+// curer.example.toml) or environment variables like CURER_DURATION_SECS,
+// without touching this file: build.rs passes them in, and `setting()`
+// below uses them in place of the defaults written here.
+
+/// A setting from curer.toml or the environment (handed over by build.rs
+/// as a `CURER_CONFIG_...` variable), or `default` when neither sets it.
+/// build.rs has already checked it's a whole number.
+const fn setting(value: Option<&str>, default: u64) -> u64 {
+    let Some(value) = value else {
+        return default;
+    };
+    let digits = value.as_bytes();
+    let mut number = 0;
+    let mut i = 0;
+    while i < digits.len() {
+        number = number * 10 + (digits[i] - b'0') as u64;
+        i += 1;
+    }
+    number
+}
 
 /* ===========================================
    🔧 USER CONFIGURABLE SETTINGS 
@@ -17,35 +39,1209 @@
 /// - Full cure: 60 seconds
 /// - Extended cure: 120 seconds (2 minutes)
 /// - Long cure: 300 seconds (5 minutes)
-pub const CURING_DURATION_SECONDS: u64 = 300;
+pub const CURING_DURATION_SECONDS: u64 = setting(option_env!("CURER_CONFIG_CURING_DURATION_SECONDS"), 300);
+
+/// Safety limit on any single cure, in seconds
+/// 
+/// No cure may run longer than this, whatever started it
+pub const MAX_CURING_DURATION_SECONDS: u64 = setting(option_env!("CURER_CONFIG_MAX_CURING_DURATION_SECONDS"), 600);
+
+/// What kind of start control is wired to GPIO 6
+pub enum StartInput {
+    /// Momentary push button: a press starts a cure of CURING_DURATION_SECONDS
+    Momentary,
+    /// Latching/toggle (or keyed) switch: the cure runs while the switch is ON,
+    /// up to MAX_CURING_DURATION_SECONDS
+    Latching,
+}
+
+/// Start control type - change to StartInput::Latching for a toggle switch
+pub const START_INPUT: StartInput = StartInput::Momentary;
+
+/// What the curer does when it powers on
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PowerOnBehavior {
+    /// Wait for a button press (default) - a cure cut off by a power cut
+    /// is only finished if the button is pressed while it's offered
+    Idle,
+    /// Finish a cure cut off by a power cut by itself, unless the button
+    /// is pressed while it's offered - otherwise wait, as for Idle
+    Resume,
+    /// Count down AUTO_START_COUNTDOWN_SECONDS with a chirp each second,
+    /// then start a cure - for curers switched on by an external smart plug.
+    /// Pressing the button during the countdown cancels it.
+    AutoStart,
+}
+
+/// Power-on behavior - change to PowerOnBehavior::AutoStart for smart-plug setups
+///
+/// This is the default: it can also be changed while running (`set poweron
+/// autostart` over USB serial, see commands.rs), which is saved to flash.
+pub const POWER_ON_BEHAVIOR: PowerOnBehavior = PowerOnBehavior::Idle;
+
+/// Countdown before an automatic cure starts at power-on, in seconds
+pub const AUTO_START_COUNTDOWN_SECONDS: u64 = setting(option_env!("CURER_CONFIG_AUTO_START_COUNTDOWN_SECONDS"), 10);
+
+/// How often a running cure saves how far it has got, in seconds (0 = never)
+/// 
+/// If the power goes mid-cure, the curer offers to finish the cure at the
+/// next power-on. Up to this much is lost, and run again when it's resumed.
+/// Each save is a small flash write - flash wears out, so don't go much lower.
+/// Momentary button only (a latching switch times the cure itself).
+pub const CHECKPOINT_INTERVAL_SECONDS: u64 = 30;
+
+/// How long the offer to finish a cut-off cure waits for a press at power-on, in seconds
+pub const RESUME_OFFER_SECONDS: u64 = 10;
+
+// The phase types live in the curing-core library (curing-core/src/cure.rs)
+pub use curing_core::cure::{CurePhase, PhaseKind};
+
+/// Multi-stage cure profile
+/// 
+/// Leave empty for a normal single cure of CURING_DURATION_SECONDS.
+/// When filled in, a button press runs these phases in order instead -
+/// e.g. alternating UV and rest so thick prints don't overheat.
+/// Only used with a momentary button in the standard (single-duration) build.
+pub const CURE_PROFILE: &[CurePhase] = &[];
+
+/* THICK PRINT PROFILE - uncomment (and delete the line above) to use:
+pub const CURE_PROFILE: &[CurePhase] = &[
+    CurePhase { kind: PhaseKind::UvOn, seconds: 30 },
+    CurePhase { kind: PhaseKind::Rest, seconds: 60 },
+    CurePhase { kind: PhaseKind::UvOn, seconds: 30 },
+];
+*/
+
+/// Duty-cycled curing
+/// 
+/// When true, the UV switches on for DUTY_CYCLE_ON_SECONDS and off for
+/// DUTY_CYCLE_OFF_SECONDS, over and over, for the whole curing time - so
+/// thick prints get a breather without writing out a CURE_PROFILE. The
+/// curing time (however it was picked) is the UV time in all - the rests
+/// come on top - and the countdown shows the time left for all of it.
+pub const DUTY_CYCLE_ENABLED: bool = false;
+
+/// Duty cycle: seconds of UV in each cycle
+pub const DUTY_CYCLE_ON_SECONDS: u64 = setting(option_env!("CURER_CONFIG_DUTY_CYCLE_ON_SECONDS"), 20);
+
+/// Duty cycle: seconds of rest in each cycle
+pub const DUTY_CYCLE_OFF_SECONDS: u64 = setting(option_env!("CURER_CONFIG_DUTY_CYCLE_OFF_SECONDS"), 10);
 
 /// Button debounce delay in milliseconds
 /// 
 /// Prevents multiple triggers from a single button press
 /// Increase if you experience double-triggering
-pub const BUTTON_DEBOUNCE_MS: u64 = 50;
+pub const BUTTON_DEBOUNCE_MS: u64 = setting(option_env!("CURER_CONFIG_BUTTON_DEBOUNCE_MS"), 50);
+
+/// Relay module input polarity (default relay driver)
+/// 
+/// true: the relay closes when its input is LOW (the blue SRD-05VDC modules).
+/// false: it closes when the input is HIGH. Either way OFF is the "pin kill"
+/// (see uv_output.rs), and the pin never drives the ON level by accident.
+/// (The relay-active-high feature is the plain push-pull alternative.)
+pub const RELAY_ACTIVE_LOW: bool = true;
 
 /// Relay settling time in milliseconds
 /// 
 /// Time to wait after turning off relay to ensure it fully opens
 /// Increase if UV LEDs don't turn off reliably
-pub const RELAY_SETTLE_TIME_MS: u64 = 500;
+pub const RELAY_SETTLE_TIME_MS: u64 = setting(option_env!("CURER_CONFIG_RELAY_SETTLE_TIME_MS"), 500);
 
 /// Completion buzzer beep settings
 /// 
 /// How many beeps to sound when curing is complete
-pub const COMPLETION_BEEPS: u32 = 3;
+pub const COMPLETION_BEEPS: u32 = setting(option_env!("CURER_CONFIG_COMPLETION_BEEPS"), 3) as u32;
 
 /// Duration of each beep in milliseconds
-pub const BEEP_DURATION_MS: u64 = 200;
+pub const BEEP_DURATION_MS: u64 = setting(option_env!("CURER_CONFIG_BEEP_DURATION_MS"), 200);
 
 /// Pause between beeps in milliseconds  
-pub const BEEP_PAUSE_MS: u64 = 300;
+pub const BEEP_PAUSE_MS: u64 = setting(option_env!("CURER_CONFIG_BEEP_PAUSE_MS"), 300);
+
+/// Warning beeps before the end of a cure (0 = none)
+/// 
+/// One short beep a second for the last few seconds - 5 means at 5, 4, 3,
+/// 2 and 1 seconds to go - so you're ready to swap parts when the UV goes off
+pub const COUNTDOWN_BEEPS: u64 = setting(option_env!("CURER_CONFIG_COUNTDOWN_BEEPS"), 5);
+
+/// Length of each warning beep in milliseconds (short, to tell them
+/// apart from the completion beeps)
+pub const COUNTDOWN_BEEP_MS: u64 = 40;
+
+/// Chirp length in milliseconds for each full minute of a cure (0 = none)
+/// 
+/// Cures longer than a minute chirp once at 1:00, 2:00, ... in, so you can
+/// follow along from across the room. Silent mode mutes them too.
+pub const MINUTE_CHIRP_MS: u64 = setting(option_env!("CURER_CONFIG_MINUTE_CHIRP_MS"), 15);
+
+/// Pitch of plain beeps and chirps in Hz
+/// 
+/// Only matters for a passive piezo (passive-buzzer feature) - an active
+/// buzzer always sounds at its own built-in pitch. Most piezo discs are
+/// loudest around 2-4 kHz.
+pub const BUZZER_TONE_HZ: u32 = setting(option_env!("CURER_CONFIG_BUZZER_TONE_HZ"), 2700) as u32;
+
+/// Buzzer loudness in percent (0 = silent, 100 = full volume)
+/// 
+/// With a passive piezo (passive-buzzer feature) this sets the loudness;
+/// low values like 5-10 are good for a shared workshop at night. An active
+/// buzzer can't be dimmed - any value above 0 is full volume.
+pub const BUZZER_VOLUME_PERCENT: u32 = setting(option_env!("CURER_CONFIG_BUZZER_VOLUME_PERCENT"), 100) as u32;
+
+/// Double-tap window in milliseconds
+/// 
+/// Double-tapping the button at idle runs the last cure again, and a triple
+/// tap opens the settings menu. A single tap only takes effect once this
+/// long has passed without a second tap (a double tap, without a third).
+pub const DOUBLE_TAP_WINDOW_MS: u64 = setting(option_env!("CURER_CONFIG_DOUBLE_TAP_WINDOW_MS"), 300);
+
+/// Five quick taps at idle restart the Pico into BOOTSEL mode
+///
+/// It shows up as the RPI-RP2 drive, ready for a new .uf2 - no need to open
+/// the enclosure to reach the BOOTSEL button. Unplug it to go back to the
+/// old firmware. With this on, a triple tap takes one double-tap window
+/// longer to open the settings menu (it waits for a fourth tap).
+///
+/// Never with one button in multi-duration builds: there every tap steps
+/// through the presets, so five taps are five preset steps.
+pub const BOOTSEL_FIVE_TAPS: bool = true;
+
+/// LED flashes that replace the completion beeps in silent mode
+pub const MUTED_COMPLETION_FLASHES: u32 = 10;
 
 /// Delay before accepting next button press
 /// 
 /// Prevents accidental immediate re-triggering after completion
-pub const CYCLE_COOLDOWN_MS: u64 = 1000;
+pub const CYCLE_COOLDOWN_MS: u64 = setting(option_env!("CURER_CONFIG_CYCLE_COOLDOWN_MS"), 1000);
+
+/// Adaptive cooldown: UV-on time the panel can take before cooldown is extended
+/// 
+/// The firmware estimates panel heat from recent UV-on time (see curing-core/src/heat.rs).
+/// While the estimate stays under this many seconds, the normal
+/// CYCLE_COOLDOWN_MS applies. Beyond it, the cooldown is stretched.
+pub const HEAT_BUDGET_SECONDS: u64 = 600;
+
+/// Seconds of UV-off time needed to shed one second of UV-on heat
+/// 
+/// Also used to size the extended cooldown. Increase for panels with
+/// poor heatsinking
+pub const PANEL_COOLING_RATIO: u64 = 1;
+
+/// Upper limit on the extra cooldown added for a hot panel, in seconds
+pub const MAX_EXTRA_COOLDOWN_SECONDS: u64 = 600;
+
+/// Status LED blink rate while a cure is paused (milliseconds on / off)
+/// 
+/// Press the button during a cure to pause it (UV off), press again to resume
+/// (with ADD_TIME_SECONDS set, pausing takes a double tap)
+pub const PAUSE_BLINK_MS: u64 = 500;
+
+/// Seconds a tap adds to a running cure (0 = off: a tap pauses)
+/// 
+/// Off unless you set it, so a tap keeps pausing the cure. Set it (e.g. 30)
+/// and each tap mid-cure makes the cure this much longer instead, with a
+/// chirp, up to MAX_CURING_DURATION_SECONDS in all (a long beep says "no
+/// more") - pausing then takes a double tap. Multi-stage profiles can't be
+/// made longer.
+pub const ADD_TIME_SECONDS: u64 = setting(option_env!("CURER_CONFIG_ADD_TIME_SECONDS"), 0);
+
+/// Status LED blink period at the start of a cure (milliseconds)
+/// 
+/// While the UV is on, the LED blinks faster as the cure goes on, from
+/// this down to STATUS_LED_COUNTDOWN_FAST_MS at the end
+pub const STATUS_LED_COUNTDOWN_SLOW_MS: u64 = 2000;
+
+/// Status LED blink period at the very end of a cure (milliseconds)
+pub const STATUS_LED_COUNTDOWN_FAST_MS: u64 = 200;
+
+/// How long one "breath" of the status LED takes while idle (milliseconds)
+/// 
+/// The LED fades slowly up and down while waiting for a start, to show
+/// the curer has power
+pub const STATUS_LED_BREATHE_MS: u64 = 4000;
+
+/// Brightness at the top of each breath (percent, 0 = no breathing)
+pub const STATUS_LED_BREATHE_PERCENT: u32 = 40;
+
+/// Fault code blinks: length of each flash (and the gap after it) in ms
+/// 
+/// After a fault the status LED flashes its number over and over - 4
+/// flashes, a pause, 4 flashes... - until it's cleared (see fault.rs)
+pub const FAULT_BLINK_MS: u64 = 300;
+
+/// Fault code blinks: the code repeats this often (milliseconds) - long
+/// enough for the longest code and a clear pause after it
+pub const FAULT_BLINK_PERIOD_MS: u64 = 6000;
+
+/// Blink the number of completed cures on the status LED at power-on
+/// 
+/// One long flash per hundred cures, a pause, then one short flash per ten
+/// (e.g. 3 long + 4 short = 340-349 cures) - usage at a glance, no debug
+/// probe needed. Fewer than 10 cures shows nothing. A button press skips it.
+pub const CURE_COUNT_AT_POWER_ON: bool = true;
+
+/// Cure count: length of each long flash (and the gap after it) in ms
+pub const CURE_COUNT_LONG_MS: u64 = 600;
+
+/// Cure count: length of each short flash (and the gap after it) in ms
+pub const CURE_COUNT_SHORT_MS: u64 = 200;
+
+/// Hold the button this long during a cure to abort it (milliseconds)
+/// 
+/// The UV switches off immediately and the abort beeps sound
+pub const ABORT_HOLD_MS: u64 = setting(option_env!("CURER_CONFIG_ABORT_HOLD_MS"), 2000);
+
+/// Number of abort beeps
+pub const ABORT_BEEPS: u32 = setting(option_env!("CURER_CONFIG_ABORT_BEEPS"), 2) as u32;
+
+/// Duration of each abort beep in milliseconds (longer than the completion beeps)
+pub const ABORT_BEEP_DURATION_MS: u64 = setting(option_env!("CURER_CONFIG_ABORT_BEEP_DURATION_MS"), 800);
+
+/// How often to log the remaining cure time, in seconds
+/// 
+/// Shows up in the debug output as "Curing... N seconds remaining"
+pub const STATUS_REPORT_INTERVAL_SECONDS: u64 = 10;
+
+/// How much of the event log is printed (see events.rs)
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    /// Only errors (over-temperature, flash failures)
+    Error = 0,
+    /// Errors plus warnings (lid opened)
+    Warn = 1,
+    /// Every step of a cure: start, relay closed/opened, pause, done
+    Info = 2,
+    /// Everything, including each beep and the remaining-time reports
+    Debug = 3,
+}
+
+/// Event log verbosity after power-on
+/// 
+/// Can also be changed while running, e.g. `set verbosity 3` over USB serial
+pub const LOG_VERBOSITY: LogLevel = LogLevel::Info;
+
+/// Number of recent events kept in RAM
+/// 
+/// They're printed all together whenever an error happens, whatever the
+/// verbosity, so the log shows what led up to it
+pub const EVENT_LOG_CAPACITY: usize = 32;
+
+/// How often to log stack usage, in seconds
+/// 
+/// Shows up as a debug! message with the stack high-water mark
+pub const STACK_REPORT_INTERVAL_SECONDS: u64 = 60;
+
+/// Warn when stack usage reaches this percentage of the stack
+/// 
+/// If you see this warning after enabling extra features, you are
+/// close to a stack overflow
+pub const STACK_WARN_PERCENT: usize = 80;
+
+/// Executor load monitor wake-up interval in milliseconds
+/// 
+/// The monitor measures how late it gets woken up. Smaller = more
+/// accurate load figures, but more wake-ups
+pub const LOAD_MONITOR_TICK_MS: u64 = 10;
+
+/// How often to log the executor load estimate, in seconds
+pub const LOAD_REPORT_INTERVAL_SECONDS: u64 = 60;
+
+/// Warn if the executor is blocked for this long in one go (milliseconds)
+/// 
+/// A stall this long means relay switching could be delayed by the same amount
+pub const EXECUTOR_STALL_WARN_MS: u64 = 50;
+
+/// White chamber inspection light (GPIO 11)
+/// 
+/// When true, the white LED strip is switched on whenever the chamber is idle
+/// and switched off for the whole cure. Set to false if nothing is wired to GPIO 11.
+pub const INSPECTION_LIGHT_ENABLED: bool = true;
+
+/// Low-dose "peek" pulse
+/// 
+/// Holding the button for PEEK_HOLD_MS at idle (instead of tapping it)
+/// flashes the UV for PEEK_DURATION_MS so you can check part positioning.
+/// A peek is not a cure - no completion beeps, no cooldown.
+/// (Not available in one-button multi-duration builds - there, holding starts the cure)
+#[cfg(any(not(feature = "multi-duration"), feature = "select-button"))]
+pub const PEEK_HOLD_MS: u64 = 1500;
+
+/// How long the UV stays on for a peek, in milliseconds
+#[cfg(any(not(feature = "multi-duration"), feature = "select-button"))]
+pub const PEEK_DURATION_MS: u64 = 1000;
+
+/// Standby: hold the button this long at idle (past the peek, or past the
+/// hold that starts a preset) to switch everything off - UV, lights, LED,
+/// display - until the next press. For a curer that stays plugged in but
+/// shouldn't react to being bumped.
+pub const STANDBY_HOLD_MS: u64 = 5000;
+
+/// Dormant builds (--features dormant): go into standby by itself - and put
+/// the chip to sleep - after this many seconds at idle with nothing
+/// happening. The start button wakes it. For battery-powered units.
+#[cfg(feature = "dormant")]
+pub const DORMANT_AFTER_IDLE_SECONDS: u64 = 600;
+
+/* ===========================================
+   ⏲️ MULTI-DURATION PRESETS
+   =========================================== */
+
+// Only used when building with: cargo build --release --features multi-duration
+// Tap the button to cycle through the presets, hold it to start curing.
+// With a second button (--features multi-duration,select-button) the
+// select button cycles and a tap on the start button starts - holding the
+// start button is then a peek, as in the single duration build.
+
+/// Duration presets in seconds
+#[cfg(feature = "multi-duration")]
+pub const DURATION_PRESETS: [u64; 5] = [5, 10, 30, 60, 120]; // 5s, 10s, 30s, 1min, 2min
+
+/// Names for each preset (same order as DURATION_PRESETS)
+#[cfg(feature = "multi-duration")]
+pub const PRESET_NAMES: [&str; 5] = ["Quick", "Standard", "Deep", "Full", "Extended"];
+
+/// Preset selected at power-on (0 = first preset)
+#[cfg(feature = "multi-duration")]
+pub const DEFAULT_PRESET_INDEX: usize = 1; // "Standard"
+
+/// How long to hold the button to start curing, in milliseconds
+/// (one-button layout - unused with a select button)
+#[cfg(all(feature = "multi-duration", not(feature = "select-button")))]
+pub const PRESET_START_HOLD_MS: u64 = 1000;
+
+/* ===========================================
+   🎛️ ROTARY ENCODER
+   =========================================== */
+
+// Only used when building with: cargo build --release --features rotary-encoder
+// Turn the knob to change the curing time, push it to start.
+// The dial starts at CURING_DURATION_SECONDS after power-on.
+
+/// Seconds added/removed per encoder detent
+#[cfg(feature = "rotary-encoder")]
+pub const ENCODER_STEP_SECONDS: u64 = 10;
+
+/// Encoder contact debounce in milliseconds
+#[cfg(feature = "rotary-encoder")]
+pub const ENCODER_DEBOUNCE_MS: u64 = 2;
+
+/* ===========================================
+   ⌨️ KEYPAD
+   =========================================== */
+
+// Only used when building with: cargo build --release --features keypad
+// Type the curing time in seconds and press # to start (e.g. 1 8 0 #).
+// * rubs out the last digit; # on its own repeats the last time used,
+// which is CURING_DURATION_SECONDS after power-on. Times over
+// MAX_CURING_DURATION_SECONDS get a long "no" beep.
+
+/// How often the keypad is scanned in milliseconds (a key has to be seen
+/// on two scans in a row, so this is also the debounce time)
+#[cfg(feature = "keypad")]
+pub const KEYPAD_SCAN_MS: u64 = 10;
+
+/* ===========================================
+   🔆 PWM MOSFET UV DRIVER
+   =========================================== */
+
+// Only used when building with: cargo build --release --features pwm-mosfet
+// A logic-level MOSFET on GPIO 10 replaces the relay - silent, and the UV
+// brightness can be turned down for delicate resins.
+
+/// UV LED intensity as a PWM duty cycle (1-100 %)
+#[cfg(feature = "pwm-mosfet")]
+pub const UV_INTENSITY_PERCENT: u32 = 100;
+
+/* ===========================================
+   🔄 STEPPER TURNTABLE
+   =========================================== */
+
+// Only used when building with: cargo build --release --features turntable
+// A stepper driver board (A4988/DRV8825/TMC2208) on GPIO 4 (STEP) and
+// GPIO 5 (DIR) spins the turntable while the UV is on.
+
+/// Turntable speed in revolutions per minute
+#[cfg(feature = "turntable")]
+pub const TURNTABLE_RPM: u64 = 5;
+
+/// Step pulses per turntable revolution
+///
+/// Motor full steps (200 for a 1.8° motor) x driver microstepping x any
+/// gear ratio between motor and turntable
+#[cfg(feature = "turntable")]
+pub const TURNTABLE_STEPS_PER_REV: u64 = 200 * 16;
+
+/// Spin direction - flip this if the turntable goes the wrong way
+#[cfg(feature = "turntable")]
+pub const TURNTABLE_CLOCKWISE: bool = true;
+
+/* ===========================================
+   🔄 DC MOTOR TURNTABLE
+   =========================================== */
+
+// Only used when building with: cargo build --release --features dc-turntable
+// A small geared DC motor, switched by a logic-level MOSFET on GPIO 4 (PWM),
+// spins the platter while the UV is on.
+
+/// Motor speed as a PWM duty cycle (1-100 %)
+#[cfg(feature = "dc-turntable")]
+pub const DC_MOTOR_SPEED_PERCENT: u32 = 60;
+
+/// Soft start time in milliseconds - 0 to start at full speed
+#[cfg(feature = "dc-turntable")]
+pub const DC_MOTOR_RAMP_MS: u64 = 1000;
+
+/// Seconds the platter keeps turning after the UV goes off
+#[cfg(feature = "dc-turntable")]
+pub const DC_MOTOR_RUN_ON_SECONDS: u64 = 5;
+
+/* ===========================================
+   🌡️ OVER-TEMPERATURE PROTECTION
+   =========================================== */
+
+// Only used when building with: cargo build --release --features thermistor
+// An NTC thermistor on GPIO 26 (ADC0) watches the chamber temperature and
+// opens the relay if it gets too hot.
+
+/// Thermistor resistance at 25°C in ohms (the "10K" in "10K NTC")
+#[cfg(feature = "thermistor")]
+pub const THERMISTOR_NOMINAL_OHMS: u32 = 10_000;
+
+/// Thermistor Beta coefficient from its datasheet (3950 is the most common)
+#[cfg(feature = "thermistor")]
+pub const THERMISTOR_BETA: u32 = 3950;
+
+/// Fixed resistor between 3.3V and the thermistor, in ohms
+#[cfg(feature = "thermistor")]
+pub const THERMISTOR_SERIES_OHMS: u32 = 10_000;
+
+/// Open the relay when the chamber reaches this temperature (°C)
+#[cfg(feature = "thermistor")]
+pub const OVERTEMP_LIMIT_C: i32 = 60;
+
+/// Allow curing again once the chamber has cooled below this (°C)
+#[cfg(feature = "thermistor")]
+pub const OVERTEMP_RESUME_C: i32 = 50;
+
+/// Soft limit (°C): a cure pauses (UV off, timer stopped) when the chamber
+/// gets this warm, and carries on by itself once it has cooled below
+/// HEAT_RESUME_C - so a warm day slows a cure down instead of the hard limit
+/// above aborting it. The fan (fan builds) keeps running meanwhile.
+/// None = no soft limit, only the hard one
+#[cfg(feature = "thermistor")]
+pub const HEAT_PAUSE_C: Option<i32> = Some(50);
+
+/// A cure paused by HEAT_PAUSE_C resumes below this (°C)
+#[cfg(feature = "thermistor")]
+pub const HEAT_RESUME_C: i32 = 42;
+
+/// How often to read the thermistor in milliseconds
+#[cfg(feature = "thermistor")]
+pub const TEMP_SAMPLE_INTERVAL_MS: u64 = 500;
+
+/// How often to log the chamber temperature in seconds
+#[cfg(feature = "thermistor")]
+pub const TEMP_LOG_INTERVAL_SECONDS: u64 = 10;
+
+/// Over-temperature alarm: number of bursts (each burst is
+/// `AUDIO_PATTERNS.overheated`)
+#[cfg(feature = "thermistor")]
+pub const OVERTEMP_ALARM_REPEATS: u32 = 3;
+
+/* ===========================================
+   🔋 BATTERY MONITORING
+   =========================================== */
+
+// Only used when building with: cargo build --release --features battery
+// The supply voltage is read on GPIO 29 (ADC3). A cure won't start while
+// it's below BATTERY_LOW_MV - it would likely die halfway through.
+
+/// Divider between the battery and GPIO 29: 3 for the Pico's own VSYS/3
+/// divider. With your own divider: (top + bottom resistor) / bottom resistor
+#[cfg(feature = "battery")]
+pub const BATTERY_DIVIDER_RATIO: u32 = 3;
+
+/// Refuse to start a cure below this voltage, in millivolts. The default
+/// suits 3 AA/NiMH cells or a single Li-ion cell on VSYS - set it to what
+/// your pack reads with about a cure's worth of charge left
+#[cfg(feature = "battery")]
+pub const BATTERY_LOW_MV: u32 = 3400;
+
+/// Once low, the battery has to come back up this far above BATTERY_LOW_MV
+/// before cures are allowed again (millivolts)
+#[cfg(feature = "battery")]
+pub const BATTERY_HYSTERESIS_MV: u32 = 200;
+
+/// How often to read the battery voltage in milliseconds
+#[cfg(feature = "battery")]
+pub const BATTERY_SAMPLE_INTERVAL_MS: u64 = 1000;
+
+// The low-battery warning beeps are `AUDIO_PATTERNS.low_battery` (the LED
+// flashes along with them)
+
+/* ===========================================
+   🔦 UV INTENSITY SENSOR
+   =========================================== */
+
+// Only used when building with: cargo build --release --features uv-sensor
+// A GUVA-S12SD on GPIO 27 (ADC1) checks that the UV LEDs really light up.
+
+/// Below this sensor output (millivolts) the UV counts as dark. The sensor
+/// reads a few mV in a dark chamber and hundreds under the lamp - check
+/// the logged intensity for your lamp and set this well below it
+#[cfg(feature = "uv-sensor")]
+pub const UV_SENSOR_MIN_MV: u32 = 50;
+
+/// Abort the cure if the UV stays dark this long after it should have come
+/// on (milliseconds) - long enough for the relay to close and the LEDs to start
+#[cfg(feature = "uv-sensor")]
+pub const UV_SENSOR_FAULT_MS: u64 = 3000;
+
+/// How often to read the UV sensor in milliseconds
+#[cfg(feature = "uv-sensor")]
+pub const UV_SENSOR_SAMPLE_MS: u64 = 250;
+
+/// How often to log the UV intensity during a cure, in seconds
+#[cfg(feature = "uv-sensor")]
+pub const UV_SENSOR_LOG_INTERVAL_SECONDS: u64 = 10;
+
+/* ===========================================
+   ⚡ LAMP CURRENT (INA219)
+   =========================================== */
+
+// Only used when building with: cargo build --release --features ina219
+// An INA219 on I2C1 (GPIO 22 SDA, GPIO 27 SCL) measures the UV LED current.
+
+/// I2C address of the INA219 (0x40 with A0/A1 both to GND)
+#[cfg(feature = "ina219")]
+pub const INA219_ADDRESS: u8 = 0x40;
+
+/// Shunt resistor on the INA219 board in milliohms (the usual R100 = 100)
+#[cfg(feature = "ina219")]
+pub const INA219_SHUNT_MILLIOHMS: u32 = 100;
+
+/// How often to read the current in milliseconds
+#[cfg(feature = "ina219")]
+pub const INA219_SAMPLE_MS: u64 = 250;
+
+/// Less current than this with the UV on means the LED string is open
+/// (a dead LED or a loose wire). Check the logged current for your lamp
+#[cfg(feature = "ina219")]
+pub const LAMP_CURRENT_MIN_MA: u32 = 200;
+
+/// More current than this means part of the LED string is shorted
+#[cfg(feature = "ina219")]
+pub const LAMP_CURRENT_MAX_MA: u32 = 2000;
+
+/// Don't judge the current until the UV has been on this long
+/// (milliseconds) - time for the relay to close and the driver to settle
+#[cfg(feature = "ina219")]
+pub const LAMP_CURRENT_SETTLE_MS: u64 = 500;
+
+/* ===========================================
+   🔁 RELAY FEEDBACK
+   =========================================== */
+
+// Only used when building with: cargo build --release --features relay-feedback
+// The relay's spare contact on GPIO 3 (COM to GND) shows whether it switched.
+
+/// Which spare contact is wired to GPIO 3
+#[cfg(feature = "relay-feedback")]
+pub enum FeedbackContact {
+    /// NO: made (LOW) while the relay is closed
+    NormallyOpen,
+    /// NC: made (LOW) while the relay is open
+    NormallyClosed,
+}
+
+/// The spare contact wired to GPIO 3
+#[cfg(feature = "relay-feedback")]
+pub const RELAY_FEEDBACK_CONTACT: FeedbackContact = FeedbackContact::NormallyOpen;
+
+/// The relay must follow a command within this many milliseconds, or it
+/// counts as stuck. An SRD relay takes about 10 ms
+#[cfg(feature = "relay-feedback")]
+pub const RELAY_FEEDBACK_TIMEOUT_MS: u64 = 200;
+
+/// Contact bounce filter for the feedback contact in milliseconds
+#[cfg(feature = "relay-feedback")]
+pub const RELAY_FEEDBACK_DEBOUNCE_MS: u64 = 10;
+
+/// Warn when the relay's switching time creeps up this many percent over
+/// what it was for the first few switches - a worn relay slows down before
+/// it sticks (see curing-core/src/relay_timing.rs)
+#[cfg(feature = "relay-feedback")]
+pub const RELAY_SLOWDOWN_PERCENT: u32 = 50;
+
+/* ===========================================
+   💧 BME280 CLIMATE SENSOR
+   =========================================== */
+
+// Only used when building with: cargo build --release --features bme280
+// A BME280 on I2C0 (GPIO 8 SDA, GPIO 9 SCL) logs chamber temperature and
+// humidity.
+
+/// I2C address of the BME280 - 0x76 with SDO to GND, 0x77 with SDO to 3.3V
+#[cfg(feature = "bme280")]
+pub const BME280_ADDRESS: u16 = 0x76;
+
+/// How often to read the BME280 in seconds
+#[cfg(feature = "bme280")]
+pub const CLIMATE_SAMPLE_INTERVAL_SECONDS: u64 = 5;
+
+/// How often to log the chamber climate while no cure is running, in
+/// seconds (with the lowest and highest readings since the last log)
+#[cfg(feature = "bme280")]
+pub const CLIMATE_IDLE_LOG_INTERVAL_SECONDS: u64 = 600;
+
+/* ===========================================
+   🌀 COOLING FAN
+   =========================================== */
+
+// Only used when building with: cargo build --release --features fan
+// (plus thermistor or bme280 for the temperature reading)
+// A fan on GPIO 12 runs during every cure, and while idle whenever the
+// chamber is warm.
+
+/// Turn the fan on when the chamber reaches this temperature (°C)
+#[cfg(feature = "fan")]
+pub const FAN_ON_C: i32 = 35;
+
+/// Turn the fan off again once the chamber is below this temperature (°C)
+#[cfg(feature = "fan")]
+pub const FAN_OFF_C: i32 = 30;
+
+/// How often the thermostat checks the temperature in seconds
+#[cfg(feature = "fan")]
+pub const FAN_CHECK_INTERVAL_SECONDS: u64 = 2;
+
+/* ===========================================
+   🚪 LID INTERLOCK
+   =========================================== */
+
+// Only used when building with: cargo build --release --features lid-interlock
+// A lid switch on GPIO 13 stops the UV whenever the lid is open.
+// (Not feature-gated: without the switch the lid simply counts as closed.)
+
+/// Lid switch debounce in milliseconds (kept short - the UV must go off fast)
+pub const LID_DEBOUNCE_MS: u64 = 20;
+
+/// Length of each "lid open" warning beep in milliseconds
+pub const LID_BEEP_MS: u64 = 100;
+
+/// Time between "lid open" warning beeps while a cure waits, in milliseconds
+pub const LID_BEEP_INTERVAL_MS: u64 = 1000;
+
+/// Quick beeps when a cure is refused because the lid is open
+pub const LID_OPEN_START_BEEPS: u32 = 3;
+
+/// Maintenance bypass: power on with the lid open and the button held, and
+/// keep holding this long (ms), to have the interlock ignored for a while -
+/// for aligning the UV panel with the lid off (see lid.rs)
+#[cfg(feature = "lid-interlock")]
+pub const LID_BYPASS_HOLD_MS: u64 = 10_000;
+
+/// How long the bypass lasts in seconds - beeping and flashing throughout.
+/// A power cycle ends it sooner
+#[cfg(feature = "lid-interlock")]
+pub const LID_BYPASS_SECONDS: u64 = 600;
+
+/* ===========================================
+   🔥 CHAMBER HEATER / PREHEAT
+   =========================================== */
+
+// Only used when building with: cargo build --release --features heater
+// A heater relay on GPIO 14 warms the chamber before each cure.
+
+/// Longest preheat before the UV comes on, in seconds (0 = no preheat)
+///
+/// Without a temperature sensor the heater always runs for this long
+#[cfg(feature = "heater")]
+pub const PREHEAT_SECONDS: u64 = 120;
+
+/// Stop preheating once the chamber reaches this temperature (°C)
+/// - needs the thermistor or bme280 feature
+#[cfg(all(feature = "heater", any(feature = "thermistor", feature = "bme280")))]
+pub const PREHEAT_TARGET_C: i32 = 30;
+
+/* ===========================================
+   🎵 PASSIVE BUZZER MELODIES
+   =========================================== */
+
+// Only used when building with: cargo build --release --features passive-buzzer
+// A passive piezo can play tunes, so each event gets its own melody.
+// Each note is a pitch in Hz (0 = rest) and a length in milliseconds.
+// Handy pitches: C5 523, E5 659, G5 784, C6 1047, E6 1319, G6 1568, C7 2093
+
+/// One note of a buzzer melody: `hz` (0 = silent rest) held for `ms`
+/// (defined in curing-core/src/signals.rs)
+#[cfg(feature = "passive-buzzer")]
+pub use curing_core::signals::Note;
+
+/// Silence between consecutive notes in milliseconds
+#[cfg(feature = "passive-buzzer")]
+pub const NOTE_GAP_MS: u64 = 20;
+
+/// Cure finished: cheerful rising arpeggio
+#[cfg(feature = "passive-buzzer")]
+pub const COMPLETION_MELODY: &[Note] = &[
+    Note { hz: 1047, ms: 120 },   // C6
+    Note { hz: 1319, ms: 120 },   // E6
+    Note { hz: 1568, ms: 120 },   // G6
+    Note { hz: 2093, ms: 300 },   // C7
+];
+
+/// Cure aborted: slow falling tones
+#[cfg(feature = "passive-buzzer")]
+pub const ABORT_MELODY: &[Note] = &[
+    Note { hz: 784, ms: 400 },    // G5
+    Note { hz: 523, ms: 600 },    // C5
+];
+
+/// Error alarm (one burst): fast high-low warble
+#[cfg(feature = "passive-buzzer")]
+pub const ERROR_MELODY: &[Note] = &[
+    Note { hz: 2500, ms: 100 },
+    Note { hz: 1800, ms: 100 },
+    Note { hz: 2500, ms: 100 },
+    Note { hz: 1800, ms: 100 },
+    Note { hz: 2500, ms: 100 },
+    Note { hz: 1800, ms: 100 },
+];
+
+/* ===========================================
+   🔔 SOUNDS
+   =========================================== */
+
+// Every sound the buzzer makes is for one AudioEvent (curing-core/src/signals.rs),
+// and this table says how each one sounds: how many beeps, how long each
+// beep and the gap after it are (ms), and the pitch. Give each event its own
+// rhythm so they can be told apart without looking.
+// Pitch only matters for a passive piezo (passive-buzzer feature) - an active
+// buzzer has a pitch of its own, so there the rhythm is all that differs. On
+// a passive piezo, completion, abort and over-temperature play the melodies
+// above instead.
+
+/// The sound of each event, as `Beeps` (defined in curing-core/src/signals.rs)
+pub use curing_core::signals::{AudioPatterns, Beeps};
+
+/// How each AudioEvent sounds
+pub const AUDIO_PATTERNS: AudioPatterns = AudioPatterns {
+    // Two quick high beeps: "off we go"
+    start: Beeps { count: 2, hz: 3200, on_ms: 60, off_ms: 60 },
+    // The count is the menu's completion beeps setting (COMPLETION_BEEPS until changed)
+    complete: Beeps { count: COMPLETION_BEEPS, hz: BUZZER_TONE_HZ, on_ms: BEEP_DURATION_MS, off_ms: BEEP_PAUSE_MS },
+    // Long low beeps - clearly different from "done"
+    abort: Beeps { count: ABORT_BEEPS, hz: 1800, on_ms: ABORT_BEEP_DURATION_MS, off_ms: BEEP_PAUSE_MS },
+    // One beep per step of the fault number (the count here isn't used)
+    fault: Beeps { count: 1, hz: 2000, on_ms: 300, off_ms: 200 },
+    // One burst of the over-temperature alarm (OVERTEMP_ALARM_REPEATS bursts)
+    overheated: Beeps { count: 5, hz: 2500, on_ms: 100, off_ms: 100 },
+    preset_changed: Beeps { count: 1, hz: BUZZER_TONE_HZ, on_ms: 100, off_ms: 0 },
+    low_battery: Beeps { count: 6, hz: 2000, on_ms: 50, off_ms: 50 },
+    chirp: Beeps { count: 1, hz: 3200, on_ms: 30, off_ms: 0 },
+    // One long low beep: "no"
+    refused: Beeps { count: 1, hz: 1500, on_ms: 400, off_ms: 0 },
+    // One long beep: "done"
+    confirm: Beeps { count: 1, hz: BUZZER_TONE_HZ, on_ms: LAMP_SERVICE_LONG_MS, off_ms: 0 },
+    countdown: Beeps { count: 1, hz: 3200, on_ms: COUNTDOWN_BEEP_MS, off_ms: 0 },
+    minute_mark: Beeps { count: 1, hz: 3200, on_ms: MINUTE_CHIRP_MS, off_ms: 0 },
+    lid_open: Beeps { count: LID_OPEN_START_BEEPS, hz: 2000, on_ms: LID_BEEP_MS, off_ms: LID_BEEP_MS },
+    // Played over and over until the lid closes
+    lid_warning: Beeps { count: 1, hz: 2000, on_ms: LID_BEEP_MS, off_ms: LID_BEEP_INTERVAL_MS - LID_BEEP_MS },
+    // The short beeps of "service lamp" - a `confirm` beep follows them
+    lamp_service: Beeps { count: LAMP_SERVICE_SHORT_BEEPS, hz: BUZZER_TONE_HZ, on_ms: LAMP_SERVICE_SHORT_MS, off_ms: LAMP_SERVICE_SHORT_MS },
+    // One beep per menu item number (the count here isn't used)
+    menu_item: Beeps { count: 1, hz: BUZZER_TONE_HZ, on_ms: 60, off_ms: 120 },
+};
+
+/// Quiet hours (defined in curing-core/src/signals.rs)
+pub use curing_core::signals::QuietHours;
+
+/// Quiet hours for living with the curer at night: from `start` to `end`
+/// (hour, minute on a 24-hour clock - over midnight is fine) the progress
+/// chirps, the "done" beeps and the lamp reminder are left out. Alarms (a
+/// fault, over-temperature, the lid, a low battery) and the answers to a
+/// button press still sound.
+///
+/// It needs the time of day: a DS3231 (rtc-ds3231 feature), or the clock set
+/// with `time` over USB serial. Until the clock is set, every sound plays.
+///
+/// None = no quiet hours; for example
+/// `Some(QuietHours { start: (22, 0), end: (7, 0) })`
+pub const QUIET_HOURS: Option<QuietHours> = None;
+
+/* ===========================================
+   🔌 USB SERIAL COMMANDS
+   =========================================== */
+
+// Only used when building with: cargo build --release --features usb-serial
+// The Pico's USB port becomes a serial port that accepts text commands
+// (start, stop, status, set debounce) - see usb_serial.rs.
+
+/// USB vendor ID the curer enumerates with
+///
+/// 0xc0de/0xcafe is a test ID - fine for your own bench, but pick a proper
+/// one (e.g. from pid.codes) before handing devices out
+#[cfg(feature = "usb-serial")]
+pub const USB_VENDOR_ID: u16 = 0xc0de;
+
+/// USB product ID the curer enumerates with
+#[cfg(feature = "usb-serial")]
+pub const USB_PRODUCT_ID: u16 = 0xcafe;
+
+/// Bytes of log kept for the USB log port (usb-log builds, see usb_log.rs)
+///
+/// Holds what's logged before a computer opens the port - the start-up log
+/// is around 1-2 KB. When it's full, new lines are dropped until there's
+/// room again.
+#[cfg(feature = "usb-log")]
+pub const USB_LOG_BUFFER_BYTES: usize = 4096;
+
+/// Longest `gpio pulse` in milliseconds (gpio-diagnostics builds, see gpio_diag.rs)
+#[cfg(feature = "gpio-diagnostics")]
+pub const GPIO_PULSE_MAX_MS: u64 = 10_000;
+
+/* ===========================================
+   🔗 UART CONTROL LINK
+   =========================================== */
+
+// Only used when building with: cargo build --release --features uart-control
+// A Raspberry Pi or printer controller sends framed commands on GPIO 1 (RX)
+// and gets ACK/NAK replies on GPIO 0 (TX) - see uart_control.rs.
+
+/// Serial speed in baud (8 data bits, no parity, 1 stop bit)
+///
+/// Lower it (e.g. 9600) for long or noisy wires
+#[cfg(feature = "uart-control")]
+pub const UART_BAUD_RATE: u32 = 115_200;
+
+/* ===========================================
+   📶 PICO W WI-FI / WEB INTERFACE
+   =========================================== */
+
+// Only used when building with: cargo build --release --features pico-w
+// The network name and password are read from environment variables at
+// build time, so they never end up in the source code:
+//   WIFI_SSID="MyNetwork" WIFI_PASSWORD="secret" cargo build --release --features pico-w
+// Leave them out to set the network over USB serial instead
+// (`wifi set <name> <password>`, usb-serial builds) - a network set that
+// way is saved in flash and used in place of these.
+
+/// Wi-Fi network to join (WPA2) - "" = none built in
+#[cfg(feature = "pico-w")]
+pub const WIFI_SSID: &str = match option_env!("WIFI_SSID") {
+    Some(ssid) => ssid,
+    None => "",
+};
+
+/// Wi-Fi password
+#[cfg(feature = "pico-w")]
+pub const WIFI_PASSWORD: &str = match option_env!("WIFI_PASSWORD") {
+    Some(password) => password,
+    None => "",
+};
+
+/// TCP port for the web interface
+#[cfg(feature = "pico-w")]
+pub const WEB_PORT: u16 = 80;
+
+/// Network name: the web page is at http://<MDNS_HOSTNAME>.local/
+/// (letters, digits and dashes only - change it if you have more than one curer)
+#[cfg(feature = "pico-w")]
+pub const MDNS_HOSTNAME: &str = "octo-curer";
+
+/* ===========================================
+   🏠 MQTT / HOME ASSISTANT
+   =========================================== */
+
+// Only used when building with: cargo build --release --features mqtt
+// (mqtt includes pico-w). Broker login, if it needs one, comes from
+// environment variables at build time like the Wi-Fi password:
+//   MQTT_USERNAME="curer" MQTT_PASSWORD="secret" WIFI_SSID=... cargo build ...
+
+/// IP address of the MQTT broker (e.g. the Home Assistant machine)
+#[cfg(feature = "mqtt")]
+pub const MQTT_BROKER_IP: [u8; 4] = [192, 168, 1, 10];
+
+/// MQTT broker port (1883 = plain MQTT)
+#[cfg(feature = "mqtt")]
+pub const MQTT_BROKER_PORT: u16 = 1883;
+
+/// Broker username (None = anonymous)
+#[cfg(feature = "mqtt")]
+pub const MQTT_USERNAME: Option<&str> = option_env!("MQTT_USERNAME");
+
+/// Broker password (None = anonymous)
+#[cfg(feature = "mqtt")]
+pub const MQTT_PASSWORD: Option<&str> = option_env!("MQTT_PASSWORD");
+
+/// Start of every topic the curer uses, e.g. "octo-curer/state"
+#[cfg(feature = "mqtt")]
+pub const MQTT_BASE_TOPIC: &str = "octo-curer";
+
+/// MQTT client ID and Home Assistant device ID - change it if you have more than one curer
+#[cfg(feature = "mqtt")]
+pub const MQTT_NODE_ID: &str = "octo_curer";
+
+/// How often the state is published, in seconds
+#[cfg(feature = "mqtt")]
+pub const MQTT_PUBLISH_INTERVAL_SECONDS: u64 = 5;
+
+/// Wait before reconnecting to the broker after a failure, in seconds
+#[cfg(feature = "mqtt")]
+pub const MQTT_RETRY_SECONDS: u64 = 10;
+
+/* ===========================================
+   🔦 UV LAMP MAINTENANCE
+   =========================================== */
+
+// UV LEDs get dimmer as they age, so cures slowly get weaker. The curer
+// counts the hours the UV has been on (saved in flash, see stats.rs) and
+// asks for the lamp to be checked once LAMP_SERVICE_HOURS have built up:
+// three short beeps and a long one (with the status LED flashing along),
+// at power-on and after every cure.
+// After fitting new LEDs, hold the button while powering on to reset it.
+
+/// UV-on hours before the "service lamp" alert starts (0 = never)
+pub const LAMP_SERVICE_HOURS: u32 = 1000;
+
+/// Hold the button this long at power-on to reset the lamp hours (milliseconds)
+pub const LAMP_RESET_HOLD_MS: u64 = 5000;
+
+/// Short beeps at the start of the "service lamp" pattern
+pub const LAMP_SERVICE_SHORT_BEEPS: u32 = 3;
+
+/// Length of each short beep (and of the gaps) in milliseconds
+pub const LAMP_SERVICE_SHORT_MS: u64 = 120;
+
+/// Length of the closing long beep in milliseconds
+pub const LAMP_SERVICE_LONG_MS: u64 = 700;
+
+/// How many times the whole pattern plays
+pub const LAMP_SERVICE_REPEATS: u32 = 2;
+
+/* ===========================================
+   🛠️ SETTINGS MENU
+   =========================================== */
+
+// Change the cure time, completion beeps, cooldown and silent mode without a computer:
+// triple-tap the button while idle, or hold it while powering on and let go
+// after SETTINGS_MENU_HOLD_MS (but before LAMP_RESET_HOLD_MS, which resets
+// the lamp hours instead).
+// In the menu, tap for the next choice and hold for the next setting.
+// The new settings are saved in flash and replace the values above
+// (CURING_DURATION_SECONDS, COMPLETION_BEEPS, CYCLE_COOLDOWN_MS).
+// See menu.rs for the LED flashes and beeps that say what's picked.
+
+/// Hold the button this long at power-on to open the menu (milliseconds)
+pub const SETTINGS_MENU_HOLD_MS: u64 = 2000;
+
+/// In the menu, hold this long to move on to the next setting (milliseconds)
+pub const SETTINGS_NEXT_HOLD_MS: u64 = 1000;
+
+/// Leave the menu (saving what's been picked) after this long untouched
+pub const SETTINGS_MENU_TIMEOUT_SECONDS: u64 = 60;
+
+/// Curing times the menu offers, in seconds (ascending)
+pub const SETTINGS_CURE_CHOICES: &[u64] = &[30, 60, 120, 180, 300, 450, 600];
+
+/// Numbers of completion beeps the menu offers (ascending)
+pub const SETTINGS_BEEP_CHOICES: &[u64] = &[1, 2, 3, 5, 8];
+
+/// Cooldowns the menu offers, in milliseconds (ascending)
+pub const SETTINGS_COOLDOWN_CHOICES_MS: &[u64] = &[0, 1000, 3000, 10_000, 30_000];
+
+/* ===========================================
+   🕰️ DS3231 REAL-TIME CLOCK
+   =========================================== */
+
+// Only used when building with: cargo build --release --features rtc-ds3231
+// A DS3231 module on I2C1 (GPIO 22 SDA, GPIO 27 SCL) keeps the date and
+// time through power cuts, for log timestamps (see clock.rs / ds3231.rs).
+// Set it once with `time 2026-10-16 14:30:00` over USB serial.
+
+/// I2C address of the DS3231 (fixed on every module)
+#[cfg(feature = "rtc-ds3231")]
+pub const DS3231_ADDRESS: u8 = 0x68;
+
+/// How often the firmware re-reads the DS3231, in seconds
+/// 
+/// The Pico's own crystal drifts a few seconds a day - the DS3231 far less
+#[cfg(feature = "rtc-ds3231")]
+pub const RTC_SYNC_INTERVAL_SECONDS: u64 = 3600;
+
+/* ===========================================
+   💾 SD CARD CURE LOG
+   =========================================== */
+
+// Only used when building with: cargo build --release --features sd-log
+// A microSD card on SPI0 (GPIO 16 MISO, 17 CS, 18 SCK, 19 MOSI) gets one
+// CSV line per cure - see sd_log.rs for the columns.
+
+/// Log file name on the card (8.3 format: up to 8 letters, a dot, 3 letters)
+#[cfg(feature = "sd-log")]
+pub const SD_LOG_FILE_NAME: &str = "CURES.CSV";
+
+/// SPI clock speed for the card in Hz
+/// 
+/// Cards must start at 400 kHz or less, and one line per cure needs nothing faster
+#[cfg(feature = "sd-log")]
+pub const SD_SPI_FREQUENCY_HZ: u32 = 400_000;
+
+/* ===========================================
+   👆 TOUCH START BUTTON
+   =========================================== */
+
+// Only used when building with: cargo build --release --features touch-pad
+// A bare pad on GPIO 6 with a 1 MΩ resistor to 3.3V, sensed by timing how
+// long it takes to charge (see touch.rs). A TTP223 module (ttp223 feature)
+// needs no settings here - its own sensitivity is fixed.
+// Both follow START_INPUT: Momentary = touch like a button, Latching = each
+// touch toggles ON/OFF (set the TTP223's TOG jumper to match).
+
+/// How much longer the pad takes to charge when touched, in percent of the
+/// untouched time
+/// 
+/// Lower = more sensitive. Raise it if the curer starts by itself, lower it
+/// if touches through thick acrylic are missed
+#[cfg(feature = "touch-pad")]
+pub const TOUCH_PAD_THRESHOLD_PERCENT: u32 = 30;
+
+/// How often the pad is checked, in milliseconds
+#[cfg(feature = "touch-pad")]
+pub const TOUCH_PAD_POLL_MS: u64 = 10;
+
+/* ===========================================
+   📡 IR REMOTE CONTROL
+   =========================================== */
+
+// Only used when building with: cargo build --release --features ir-remote
+// An IR receiver on GPIO 15 lets a cheap NEC remote start and stop cures
+// (see ir.rs). Keys not listed below are logged with their codes - press
+// each key you want once, then add its code here.
+
+/// What an IR remote key does
+#[cfg(feature = "ir-remote")]
+#[derive(Clone, Copy)]
+pub enum IrAction {
+    /// Start a cure of the normal (or selected) length
+    Start,
+    /// Start a cure of this many seconds
+    Cure(u64),
+    /// Stop (abort) the running cure
+    Stop,
+}
+
+/// Only listen to the remote with this address (logged with each key), or
+/// None for any remote
+#[cfg(feature = "ir-remote")]
+pub const IR_ADDRESS: Option<u16> = None;
+
+/// Key codes and what they do - set up for the common 17-key "car MP3"
+/// remote: OK starts, * stops, 1/2/3 start 1, 2 and 5 minute cures
+#[cfg(feature = "ir-remote")]
+pub const IR_BUTTONS: [(u8, IrAction); 5] = [
+    (0x1C, IrAction::Start),       // OK
+    (0x16, IrAction::Stop),        // *
+    (0x45, IrAction::Cure(60)),    // 1
+    (0x46, IrAction::Cure(120)),   // 2
+    (0x47, IrAction::Cure(300)),   // 3
+];
+
+/* ===========================================
+   🔢 TM1637 COUNTDOWN DISPLAY
+   =========================================== */
+
+// Only used when building with: cargo build --release --features tm1637
+// A TM1637 4-digit display on GPIO 21 (CLK) and GPIO 28 (DIO) counts down
+// MM:SS while curing and shows the selected time while idle (see display.rs).
+
+/// Idle screens show the time of day, once the clock is set (a DS3231, or
+/// `time` over USB serial) - the curer doubles as a workshop clock. Applies
+/// to all three screens (tm1637, hd44780, st7789); the TM1637 shows the
+/// selected cure time instead for a few seconds after it's changed
+#[cfg(any(feature = "tm1637", feature = "hd44780", feature = "st7789"))]
+pub const IDLE_CLOCK: bool = true;
+
+/// Display brightness, 0 (dimmest) to 7 (brightest)
+#[cfg(feature = "tm1637")]
+pub const TM1637_BRIGHTNESS: u8 = 4;
+
+/* ===========================================
+   📟 HD44780 CHARACTER LCD
+   =========================================== */
+
+// Only used when building with: cargo build --release --features hd44780
+// A 16x2 LCD on a PCF8574 I2C backpack, GPIO 28 (SDA) / GPIO 21 (SCL),
+// shows the state, preset name and countdown (see display.rs).
+
+/// I2C address of the backpack - 0x27 on most, 0x3F on some (PCF8574A)
+#[cfg(feature = "hd44780")]
+pub const LCD_I2C_ADDRESS: u8 = 0x27;
+
+/* ===========================================
+   🖥️ ST7789 COLOUR SCREEN
+   =========================================== */
+
+// Only used when building with: cargo build --release --features st7789
+// A 240x240 ST7789 TFT on SPI0 shows a big countdown, a progress bar and
+// a background colour for each state (see tft.rs).
+// Colours are (red, green, blue), 0-255.
+
+/// Background while idle (and while arming)
+#[cfg(feature = "st7789")]
+pub const TFT_IDLE_COLOR: (u8, u8, u8) = (0, 40, 160);
+
+/// Background while a cure runs (paused and rest phases included)
+#[cfg(feature = "st7789")]
+pub const TFT_CURING_COLOR: (u8, u8, u8) = (110, 0, 160);
+
+/// Background after a cure has finished, until the next start
+#[cfg(feature = "st7789")]
+pub const TFT_COMPLETE_COLOR: (u8, u8, u8) = (0, 130, 0);
+
+/// Background on an over-temperature fault
+#[cfg(feature = "st7789")]
+pub const TFT_FAULT_COLOR: (u8, u8, u8) = (180, 0, 0);
+
+/// SPI clock - most modules manage 32 MHz; lower it if the picture is garbled
+#[cfg(feature = "st7789")]
+pub const TFT_SPI_FREQUENCY_HZ: u32 = 32_000_000;
+
+/// Most ST7789 modules show inverted colours unless told to invert them
+/// back - set to false if the colours look like a photo negative
+#[cfg(feature = "st7789")]
+pub const TFT_INVERT_COLORS: bool = true;
+
+/* ===========================================
+   🌈 NEOPIXEL PROGRESS RING
+   =========================================== */
+
+// Only used when building with: cargo build --release --features neopixel
+// A WS2812 ring on GPIO 20 fills as the cure runs (see neopixel.rs).
+// Colours are (red, green, blue), 0-255 - keep them low: a 12-pixel ring
+// at full white draws over 700 mA, and is blinding besides.
+
+/// Number of LEDs in the ring
+#[cfg(feature = "neopixel")]
+pub const NEOPIXEL_COUNT: usize = 12;
+
+/// Colour of the filled part while curing
+#[cfg(feature = "neopixel")]
+pub const NEOPIXEL_PROGRESS_COLOR: (u8, u8, u8) = (40, 0, 60);
+
+/// Colour of the whole ring when a cure has finished
+#[cfg(feature = "neopixel")]
+pub const NEOPIXEL_COMPLETE_COLOR: (u8, u8, u8) = (0, 50, 0);
+
+/// Colour of the whole ring on an over-temperature fault
+#[cfg(feature = "neopixel")]
+pub const NEOPIXEL_FAULT_COLOR: (u8, u8, u8) = (60, 0, 0);
+
+/// How long the ring stays green after a cure, in seconds
+#[cfg(feature = "neopixel")]
+pub const NEOPIXEL_COMPLETE_SECONDS: u64 = 30;
 
 /* ===========================================
    🎯 PRESET CONFIGURATIONS
@@ -78,14 +1274,319 @@ pub const BEEP_DURATION_MS: u64 = 100;
    📋 CONFIGURATION VALIDATION
    =========================================== */
 
+/// True if `list` has at least one entry and each is bigger than the last
+const fn ascending(list: &[u64]) -> bool {
+    let mut i = 1;
+    while i < list.len() {
+        if list[i] <= list[i - 1] {
+            return false;
+        }
+        i += 1;
+    }
+    !list.is_empty()
+}
+
 // Compile-time checks to prevent invalid configurations
 const _: () = {
     assert!(CURING_DURATION_SECONDS > 0, "Curing duration must be greater than 0 seconds");
-    assert!(CURING_DURATION_SECONDS <= 600, "Curing duration should be 10 minutes or less for safety");
+    assert!(CURING_DURATION_SECONDS <= MAX_CURING_DURATION_SECONDS, "Curing duration exceeds the safety maximum");
+    assert!(MAX_CURING_DURATION_SECONDS <= 600, "Curing duration should be 10 minutes or less for safety");
     assert!(COMPLETION_BEEPS > 0, "Must have at least 1 completion beep");
     assert!(COMPLETION_BEEPS <= 10, "Too many beeps could be annoying");
+    assert!(COUNTDOWN_BEEPS <= 10, "More than 10 countdown beeps is just beeping");
+    assert!(COUNTDOWN_BEEP_MS > 0 && COUNTDOWN_BEEP_MS <= 500, "Countdown beeps must fit in their second, and be short");
+    assert!(MINUTE_CHIRP_MS <= 100, "Minute chirps should be very short (100 ms or less)");
+    assert!(AUDIO_PATTERNS.low_battery.count > 0 && AUDIO_PATTERNS.low_battery.count <= 10, "Low-battery warning should be 1-10 beeps");
+    assert!(AUDIO_PATTERNS.overheated.count > 0, "The over-temperature alarm needs at least one beep");
+    assert!(AUDIO_PATTERNS.refused.count > 0 && AUDIO_PATTERNS.confirm.count > 0, "\"No\" and \"done\" need at least one beep each");
+    assert!(BUZZER_VOLUME_PERCENT <= 100, "Buzzer volume can't be more than 100%");
+    assert!(DOUBLE_TAP_WINDOW_MS >= 150 && DOUBLE_TAP_WINDOW_MS <= 600, "Double-tap window should be 150-600 ms");
+    assert!(
+        CURE_PROFILE.is_empty() || !(cfg!(feature = "multi-duration") || cfg!(feature = "rotary-encoder") || cfg!(feature = "keypad")),
+        "Cure profiles can't be combined with the multi-duration, rotary-encoder or keypad features"
+    );
+    let mut profile_uv_seconds = 0;
+    let mut i = 0;
+    while i < CURE_PROFILE.len() {
+        assert!(CURE_PROFILE[i].seconds > 0, "Every profile phase needs a duration greater than 0 seconds");
+        if matches!(CURE_PROFILE[i].kind, PhaseKind::UvOn) {
+            profile_uv_seconds += CURE_PROFILE[i].seconds;
+        }
+        i += 1;
+    }
+    assert!(profile_uv_seconds <= MAX_CURING_DURATION_SECONDS, "Total UV time of the cure profile exceeds the safety maximum");
+    assert!(!(DUTY_CYCLE_ENABLED && !CURE_PROFILE.is_empty()), "Use either a cure profile or the duty cycle, not both");
+    assert!(DUTY_CYCLE_ON_SECONDS > 0 && DUTY_CYCLE_OFF_SECONDS > 0, "Duty cycle on and off times must be greater than 0 seconds");
+    assert!(AUTO_START_COUNTDOWN_SECONDS >= 3, "Auto-start countdown too short to cancel");
+    assert!(CHECKPOINT_INTERVAL_SECONDS == 0 || CHECKPOINT_INTERVAL_SECONDS >= 10, "Checkpoints more often than every 10 seconds wear the flash out");
+    assert!(RESUME_OFFER_SECONDS >= 3, "Resume offer too short to press the button in time");
+    assert!(
+        !(!matches!(POWER_ON_BEHAVIOR, PowerOnBehavior::Idle) && matches!(START_INPUT, StartInput::Latching)),
+        "Auto-start and resume need a momentary button - a latching switch already starts the cure when ON"
+    );
     assert!(BUTTON_DEBOUNCE_MS >= 10, "Debounce time too short, may cause double-triggers");
     assert!(BUTTON_DEBOUNCE_MS <= 500, "Debounce time too long, will feel unresponsive");
+    assert!(ABORT_HOLD_MS >= 1000, "Abort hold too short, a pause tap could trigger it");
+    // (checked_sub rather than <=: with the default 0, clippy calls that always true)
+    assert!(MAX_CURING_DURATION_SECONDS.checked_sub(ADD_TIME_SECONDS).is_some(), "Added time can't be more than the longest cure allowed");
+    assert!(
+        STATUS_LED_COUNTDOWN_FAST_MS >= 50 && STATUS_LED_COUNTDOWN_FAST_MS <= STATUS_LED_COUNTDOWN_SLOW_MS,
+        "Status LED countdown: the fast blink must be at least 50 ms and no slower than the slow one"
+    );
+    assert!(STATUS_LED_BREATHE_PERCENT <= 100, "Status LED breathing brightness is 0-100%");
+    assert!(FAULT_BLINK_PERIOD_MS >= 2 * FAULT_BLINK_MS * 9, "Fault blink period must fit 9 flashes and a pause");
+    assert!(CURE_COUNT_LONG_MS >= 2 * CURE_COUNT_SHORT_MS, "Cure count long flashes must be clearly longer than the short ones");
+    assert!(CURE_COUNT_SHORT_MS >= 100, "Cure count short flashes too quick to count");
+    assert!(STATUS_LED_BREATHE_MS >= 500, "Status LED breathing faster than 500 ms looks like blinking");
+    assert!(PANEL_COOLING_RATIO > 0, "Cooling ratio must be at least 1");
+    assert!(STATUS_REPORT_INTERVAL_SECONDS > 0, "Status report interval must be at least 1 second");
+    assert!(EVENT_LOG_CAPACITY >= 4 && EVENT_LOG_CAPACITY <= 256, "Event log should hold between 4 and 256 events");
+    assert!(LAMP_SERVICE_HOURS <= 100_000, "Lamp service interval too long - no UV LED lasts 100,000 hours");
+    assert!(LAMP_RESET_HOLD_MS >= 2000, "Lamp reset hold too short, a bumped button at power-on could trigger it");
+    assert!(LAMP_SERVICE_REPEATS > 0 && LAMP_SERVICE_LONG_MS > LAMP_SERVICE_SHORT_MS, "Service pattern needs a long beep that's longer than the short ones");
+    assert!(
+        SETTINGS_MENU_HOLD_MS >= 1000 && SETTINGS_MENU_HOLD_MS + 1000 <= LAMP_RESET_HOLD_MS,
+        "Settings menu hold must be at least 1 s, and at least 1 s shorter than the lamp reset hold"
+    );
+    assert!(SETTINGS_NEXT_HOLD_MS >= 500, "Settings menu hold too short, a slow tap could skip a setting");
+    assert!(SETTINGS_MENU_TIMEOUT_SECONDS >= 10, "Settings menu timeout too short to pick anything");
+    assert!(ascending(SETTINGS_CURE_CHOICES) && ascending(SETTINGS_BEEP_CHOICES) && ascending(SETTINGS_COOLDOWN_CHOICES_MS),
+            "Settings menu choices must be non-empty and in ascending order");
+    assert!(
+        SETTINGS_CURE_CHOICES[0] > 0 && SETTINGS_CURE_CHOICES[SETTINGS_CURE_CHOICES.len() - 1] <= MAX_CURING_DURATION_SECONDS,
+        "Settings menu curing times must be between 1 second and MAX_CURING_DURATION_SECONDS"
+    );
+    assert!(
+        SETTINGS_BEEP_CHOICES[0] > 0 && SETTINGS_BEEP_CHOICES[SETTINGS_BEEP_CHOICES.len() - 1] <= 10,
+        "Settings menu beep choices must be between 1 and 10"
+    );
+    // Saved in flash as 16-bit numbers
+    assert!(SETTINGS_COOLDOWN_CHOICES_MS[SETTINGS_COOLDOWN_CHOICES_MS.len() - 1] <= 60_000, "Settings menu cooldowns must be at most 60 seconds");
+};
+
+#[cfg(any(not(feature = "multi-duration"), feature = "select-button"))]
+const _: () = {
+    assert!(PEEK_HOLD_MS >= 500, "Peek hold too short, a normal tap could trigger it");
+    assert!(PEEK_DURATION_MS <= 3000, "Peek should be a short low-dose pulse");
+    assert!(STANDBY_HOLD_MS >= PEEK_HOLD_MS + PEEK_DURATION_MS + 1000, "Standby hold should be at least a second past the end of the peek");
+};
+
+#[cfg(feature = "rotary-encoder")]
+const _: () = {
+    assert!(ENCODER_STEP_SECONDS > 0, "Encoder step must be at least 1 second");
+    assert!(ENCODER_STEP_SECONDS <= MAX_CURING_DURATION_SECONDS, "Encoder step larger than the safety maximum");
+    assert!(!matches!(START_INPUT, StartInput::Latching), "Rotary encoder mode needs a momentary push switch");
+};
+
+#[cfg(feature = "usb-log")]
+const _: () = {
+    assert!(USB_LOG_BUFFER_BYTES >= 512, "The USB log buffer needs room for a few log lines");
+    assert!(USB_LOG_BUFFER_BYTES <= 32 * 1024, "The USB log buffer is taking too much of the RP2040's RAM");
+};
+
+#[cfg(feature = "battery")]
+const _: () = {
+    assert!(BATTERY_DIVIDER_RATIO >= 1, "The divider ratio is at least 1 (no divider)");
+    assert!(BATTERY_LOW_MV > 0 && BATTERY_LOW_MV + BATTERY_HYSTERESIS_MV < 3300 * BATTERY_DIVIDER_RATIO, "Battery thresholds must be within what the ADC can measure");
+    assert!(BATTERY_SAMPLE_INTERVAL_MS >= 100 && BATTERY_SAMPLE_INTERVAL_MS <= 10_000, "Sample the battery every 0.1-10 seconds");
+};
+
+#[cfg(feature = "uv-sensor")]
+const _: () = {
+    assert!(UV_SENSOR_MIN_MV > 0 && UV_SENSOR_MIN_MV < 3300, "UV sensor threshold must be within the ADC range");
+    assert!(UV_SENSOR_FAULT_MS >= 4 * UV_SENSOR_SAMPLE_MS, "Take a few UV readings before calling the lamp dead");
+    assert!(UV_SENSOR_FAULT_MS <= 10_000, "Don't run a dead lamp for more than 10 seconds");
+};
+
+#[cfg(feature = "ina219")]
+const _: () = {
+    assert!(INA219_ADDRESS >= 0x40 && INA219_ADDRESS <= 0x4F, "INA219 address must be 0x40-0x4F");
+    assert!(INA219_SHUNT_MILLIOHMS > 0, "Shunt resistance can't be zero");
+    assert!(INA219_SAMPLE_MS >= 10 && INA219_SAMPLE_MS <= 1000, "Read the lamp current every 10-1000 ms");
+    assert!(LAMP_CURRENT_MIN_MA < LAMP_CURRENT_MAX_MA, "Lamp current window is empty");
+    assert!(LAMP_CURRENT_MAX_MA * INA219_SHUNT_MILLIOHMS <= 320_000, "LAMP_CURRENT_MAX_MA is beyond the INA219's 320 mV shunt range");
+    assert!(LAMP_CURRENT_SETTLE_MS <= 5000, "Don't run a faulty lamp for more than 5 seconds unchecked");
+};
+
+#[cfg(feature = "relay-feedback")]
+const _: () = {
+    assert!(RELAY_FEEDBACK_TIMEOUT_MS >= 50 && RELAY_FEEDBACK_TIMEOUT_MS <= 2000, "Relay feedback timeout should be 50-2000 ms");
+    assert!(RELAY_FEEDBACK_DEBOUNCE_MS < RELAY_FEEDBACK_TIMEOUT_MS, "Feedback debounce must be shorter than the timeout");
+    assert!(RELAY_SLOWDOWN_PERCENT >= 10 && RELAY_SLOWDOWN_PERCENT <= 500, "Relay slowdown warning should be 10-500%");
+};
+
+#[cfg(feature = "dormant")]
+const _: () = {
+    assert!(DORMANT_AFTER_IDLE_SECONDS >= 30, "Sleeping sooner than 30 seconds after the last press would be annoying");
+    assert!(!matches!(START_INPUT, StartInput::Latching), "Dormant mode needs a momentary start button to wake it");
+};
+
+#[cfg(feature = "keypad")]
+const _: () = {
+    assert!(KEYPAD_SCAN_MS >= 2 && KEYPAD_SCAN_MS <= 50, "Scan the keypad every 2-50 ms (slower misses quick presses)");
+    assert!(!matches!(START_INPUT, StartInput::Latching), "Keypad mode needs a momentary start button");
+};
+
+#[cfg(feature = "pwm-mosfet")]
+const _: () = {
+    assert!(UV_INTENSITY_PERCENT > 0, "UV intensity must be at least 1%");
+    assert!(UV_INTENSITY_PERCENT <= 100, "UV intensity can't be more than 100%");
+};
+
+#[cfg(feature = "turntable")]
+const _: () = {
+    assert!(TURNTABLE_RPM > 0, "Turntable speed must be at least 1 RPM");
+    assert!(TURNTABLE_STEPS_PER_REV > 0, "Turntable needs at least 1 step per revolution");
+    // Each half step pulse must be at least a few microseconds for the driver to see it
+    assert!(60_000_000 / (TURNTABLE_RPM * TURNTABLE_STEPS_PER_REV) / 2 >= 10, "Turntable too fast - lower the RPM or microstepping");
+};
+
+#[cfg(feature = "dc-turntable")]
+const _: () = {
+    assert!(DC_MOTOR_SPEED_PERCENT > 0, "DC motor speed must be at least 1%");
+    assert!(DC_MOTOR_SPEED_PERCENT <= 100, "DC motor speed can't be more than 100%");
+    assert!(DC_MOTOR_RAMP_MS <= 10_000, "DC motor ramp should be 10 seconds or less");
+};
+
+#[cfg(feature = "thermistor")]
+const _: () = {
+    assert!(OVERTEMP_RESUME_C < OVERTEMP_LIMIT_C, "Resume temperature must be below the limit, or the alarm will flicker");
+    assert!(OVERTEMP_LIMIT_C <= 80, "Over-temperature limit is too high for a resin curing chamber");
+    assert!(TEMP_SAMPLE_INTERVAL_MS <= 2000, "Temperature must be sampled at least every 2 seconds");
+    if let Some(pause) = HEAT_PAUSE_C {
+        assert!(pause < OVERTEMP_LIMIT_C, "The soft limit (HEAT_PAUSE_C) must be below the over-temperature limit");
+        assert!(HEAT_RESUME_C < pause, "HEAT_RESUME_C must be below HEAT_PAUSE_C, or the cure will stutter");
+    }
+};
+
+#[cfg(feature = "bme280")]
+const _: () = {
+    assert!(BME280_ADDRESS == 0x76 || BME280_ADDRESS == 0x77, "BME280 address must be 0x76 or 0x77");
+    assert!(CLIMATE_SAMPLE_INTERVAL_SECONDS > 0, "Climate sample interval must be at least 1 second");
+    assert!(
+        CLIMATE_IDLE_LOG_INTERVAL_SECONDS >= 60 && CLIMATE_IDLE_LOG_INTERVAL_SECONDS >= CLIMATE_SAMPLE_INTERVAL_SECONDS,
+        "Idle climate log interval must be at least a minute, and no shorter than the sample interval"
+    );
+};
+
+#[cfg(feature = "fan")]
+const _: () = {
+    assert!(FAN_OFF_C < FAN_ON_C, "Fan off temperature must be below the on temperature");
+    assert!(FAN_CHECK_INTERVAL_SECONDS > 0, "Fan check interval must be at least 1 second");
+};
+
+const _: () = {
+    assert!(LID_DEBOUNCE_MS <= 50, "Lid debounce too long - the UV must switch off quickly");
+    assert!(LID_BEEP_MS < LID_BEEP_INTERVAL_MS, "Lid beep must be shorter than the beep interval");
+    #[cfg(feature = "lid-interlock")]
+    assert!(LID_BYPASS_HOLD_MS >= 5000, "Lid bypass hold too short - it has to be deliberate");
+    #[cfg(feature = "lid-interlock")]
+    assert!(LID_BYPASS_SECONDS > 0 && LID_BYPASS_SECONDS <= 1800, "Lid bypass should last at most 30 minutes");
+};
+
+#[cfg(feature = "heater")]
+const _: () = {
+    assert!(PREHEAT_SECONDS <= 900, "Preheat should be 15 minutes or less");
+};
+
+#[cfg(all(feature = "heater", feature = "thermistor"))]
+const _: () = {
+    assert!(PREHEAT_TARGET_C < OVERTEMP_RESUME_C, "Preheat target must be below the over-temperature limits");
+    assert!(HEAT_PAUSE_C.is_none() || PREHEAT_TARGET_C < HEAT_RESUME_C, "Preheat target must be below HEAT_RESUME_C, or the cure pauses as soon as it starts");
+};
+
+#[cfg(feature = "passive-buzzer")]
+const _: () = {
+    assert!(BUZZER_TONE_HZ >= 100 && BUZZER_TONE_HZ <= 10_000, "Beep pitch should be between 100 Hz and 10 kHz");
+    assert!(!COMPLETION_MELODY.is_empty() && !ABORT_MELODY.is_empty() && !ERROR_MELODY.is_empty(), "Melodies need at least one note");
+};
+
+#[cfg(feature = "uart-control")]
+const _: () = {
+    assert!(UART_BAUD_RATE >= 1200 && UART_BAUD_RATE <= 921_600, "UART baud rate should be between 1200 and 921600");
+};
+
+#[cfg(feature = "pico-w")]
+const _: () = {
+    let name = MDNS_HOSTNAME.as_bytes();
+    assert!(!name.is_empty() && name.len() <= 63, "MDNS_HOSTNAME must be 1-63 characters");
+    let mut i = 0;
+    while i < name.len() {
+        assert!(name[i].is_ascii_alphanumeric() || name[i] == b'-', "MDNS_HOSTNAME may only use letters, digits and dashes");
+        i += 1;
+    }
+};
+
+#[cfg(feature = "mqtt")]
+const _: () = {
+    assert!(MQTT_PUBLISH_INTERVAL_SECONDS >= 1 && MQTT_PUBLISH_INTERVAL_SECONDS <= 30, "Publish at least every 30 seconds so the broker keep-alive doesn't expire");
+    assert!(MQTT_RETRY_SECONDS >= 1, "Retry delay must be at least 1 second");
+};
+
+#[cfg(feature = "rtc-ds3231")]
+const _: () = {
+    assert!(RTC_SYNC_INTERVAL_SECONDS >= 60, "Re-reading the DS3231 more than once a minute gains nothing");
+};
+
+#[cfg(feature = "sd-log")]
+const _: () = {
+    assert!(SD_LOG_FILE_NAME.len() <= 12, "SD log file name must be 8.3 format, e.g. CURES.CSV");
+    assert!(SD_SPI_FREQUENCY_HZ >= 100_000 && SD_SPI_FREQUENCY_HZ <= 400_000, "SD cards start up at 100-400 kHz");
+};
+
+#[cfg(feature = "touch-pad")]
+const _: () = {
+    assert!(TOUCH_PAD_THRESHOLD_PERCENT >= 5, "Touch threshold under 5% will trigger on noise");
+    assert!(TOUCH_PAD_POLL_MS >= 1 && TOUCH_PAD_POLL_MS <= BUTTON_DEBOUNCE_MS, "Poll the touch pad at least once per debounce time");
+};
+
+#[cfg(feature = "ir-remote")]
+const _: () = {
+    let mut i = 0;
+    while i < IR_BUTTONS.len() {
+        if let IrAction::Cure(seconds) = IR_BUTTONS[i].1 {
+            assert!(seconds > 0 && seconds <= MAX_CURING_DURATION_SECONDS, "IR_BUTTONS cure times must be 1 second to the safety maximum");
+        }
+        i += 1;
+    }
+};
+
+const _: () = {
+    if let Some(quiet) = QUIET_HOURS {
+        assert!(quiet.start.0 < 24 && quiet.end.0 < 24, "Quiet hours are on a 24-hour clock (0-23)");
+        assert!(quiet.start.1 < 60 && quiet.end.1 < 60, "Quiet hours minutes must be 0-59");
+    }
+};
+
+#[cfg(feature = "gpio-diagnostics")]
+const _: () = {
+    assert!(GPIO_PULSE_MAX_MS > 0 && GPIO_PULSE_MAX_MS <= 60_000, "GPIO pulses should be at most a minute");
+};
+
+#[cfg(feature = "tm1637")]
+const _: () = {
+    assert!(TM1637_BRIGHTNESS <= 7, "TM1637 brightness is 0-7");
+};
+
+#[cfg(feature = "neopixel")]
+const _: () = {
+    assert!(NEOPIXEL_COUNT > 0 && NEOPIXEL_COUNT <= 64, "NeoPixel ring should have 1-64 LEDs");
+};
+
+#[cfg(feature = "multi-duration")]
+const _: () = {
+    assert!(DEFAULT_PRESET_INDEX < DURATION_PRESETS.len(), "Default preset must be one of the presets");
+    #[cfg(not(feature = "select-button"))]
+    assert!(PRESET_START_HOLD_MS >= 500, "Start hold too short, a normal tap could trigger it");
+    #[cfg(not(feature = "select-button"))]
+    assert!(STANDBY_HOLD_MS >= PRESET_START_HOLD_MS + 2000, "Standby hold should be well past the hold that starts a preset");
+    assert!(!matches!(START_INPUT, StartInput::Latching), "Multi-duration mode needs a momentary button");
+    let mut i = 0;
+    while i < DURATION_PRESETS.len() {
+        assert!(DURATION_PRESETS[i] > 0, "Preset durations must be greater than 0 seconds");
+        assert!(DURATION_PRESETS[i] <= MAX_CURING_DURATION_SECONDS, "Preset duration exceeds the safety maximum");
+        i += 1;
+    }
 };
 
 /* ===========================================
@@ -106,8 +1607,8 @@ Method 2 - Use Presets:
 3. Build and flash
 
 Method 3 - Multiple Duration Support:
-For advanced users who want button-selectable durations, see the 
-multi_duration_example.rs file for implementation ideas.
+Build with: cargo build --release --features multi-duration
+Then tap the button to cycle through DURATION_PRESETS and hold it to start.
 
 EXAMPLES:
 - Change line 15 to: pub const CURING_DURATION_SECONDS: u64 = 30;  // 30-second cure
@@ -0,0 +1,256 @@
+// Status Display (tm1637 and hd44780 builds)
+//
+// Shows what the curer is doing on whichever screen the Cargo features
+// picked - one at a time:
+//
+// - tm1637:  4-digit 7-segment display (tm1637.rs) - just the time
+// - hd44780: 16x2 character LCD on an I2C backpack (hd44780.rs) - state,
+//            preset name and time
+//
+// Both are drawn from the same `View`, refreshed a few times a second from
+// the status the cure supervisor publishes for remote interfaces
+// (remote.rs), plus the curing time a start would use. Each screen
+// implements `Screen` to decide how much of the view it has room for:
+//
+// - idle: the selected time (and preset name, with multi-duration), plus
+//   "No Wi-Fi" on the LCD while a pico-w build is off the network. Once the
+//   clock is set (IDLE_CLOCK), the time of day too - instead of "Ready" on
+//   the LCD, and on the TM1637 unless the cure time has just been changed
+// - preheating: "HEAt" / "Preheating"
+// - curing or resting: the time left in the phase, counting down
+// - paused: the time left, flashing on the TM1637
+// - cooldown: "----" / "Cooling down"
+//
+// Adding another screen means a driver file, a `Screen` impl here and a
+// `Panel` line for its feature.
+
+use core::cell::Cell;
+#[cfg(feature = "hd44780")]
+use core::fmt::Write;
+
+use defmt::*;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_time::{Duration, Instant, Ticker};
+
+use crate::clock::{self, DateTime};
+use crate::config::*;
+use crate::remote::{self, CureStatus};
+
+#[cfg(all(feature = "tm1637", feature = "hd44780"))]
+compile_error!("Pick one display feature: tm1637 or hd44780, not both");
+
+/// The screen picked by the Cargo features
+#[cfg(feature = "tm1637")]
+pub type Panel = crate::tm1637::Tm1637<'static>;
+#[cfg(feature = "hd44780")]
+pub type Panel = crate::hd44780::Hd44780<'static>;
+
+/// How often the display is redrawn, in milliseconds (fast enough for a
+/// half-second blink)
+const REFRESH_MS: u64 = 250;
+
+/// How long a newly picked cure time is shown on the TM1637 before the
+/// idle clock comes back, in seconds
+const SELECTED_SHOW_SECONDS: u64 = 5;
+
+/// The curing time a start would use
+#[derive(Clone, Copy)]
+struct Selected {
+    /// Preset name (multi-duration builds only)
+    name: Option<&'static str>,
+    seconds: u64,
+    /// When it last changed
+    changed_at: Instant,
+}
+
+/// The curing time shown while idle
+static SELECTED: Mutex<CriticalSectionRawMutex, Cell<Selected>> =
+    Mutex::new(Cell::new(Selected { name: None, seconds: CURING_DURATION_SECONDS, changed_at: Instant::MIN }));
+
+/// Tell the display which curing time is selected
+pub fn show_selected(name: Option<&'static str>, seconds: u64) {
+    SELECTED.lock(|selected| {
+        let old = selected.get();
+        if (old.name, old.seconds) != (name, seconds) {
+            selected.set(Selected { name, seconds, changed_at: Instant::now() });
+        }
+    });
+}
+
+/// Everything a screen might want to show
+pub struct View {
+    pub status: CureStatus,
+    /// Preset name, if the build has presets
+    pub name: Option<&'static str>,
+    /// Idle: the selected time. Otherwise: the time left in the phase.
+    pub seconds: u64,
+    /// Idle: the time of day, if the clock is set and IDLE_CLOCK is on
+    pub time: Option<DateTime>,
+    /// The selected time changed in the last SELECTED_SHOW_SECONDS
+    #[cfg_attr(not(feature = "tm1637"), allow(dead_code))]  // The LCD always has room for it
+    pub just_selected: bool,
+    /// On for the first half of every second, for blinking
+    pub blink_on: bool,
+    /// A pico-w build that isn't on the Wi-Fi network (yet, or any more)
+    #[cfg_attr(not(feature = "hd44780"), allow(dead_code))]  // The TM1637 has no room for it
+    pub offline: bool,
+}
+
+/// A screen the display task can draw on
+pub trait Screen {
+    /// Which wires to check when it stops answering
+    const WIRING: &'static str;
+
+    /// Draw `view` - false if the screen didn't answer
+    async fn draw(&mut self, view: &View) -> bool;
+}
+
+/// Background task: keep the display up to date
+#[embassy_executor::task]
+pub async fn display_task(mut panel: Panel) {
+    let mut answering = true;
+    let mut ticker = Ticker::every(Duration::from_millis(REFRESH_MS));
+
+    loop {
+        let selected = SELECTED.lock(|selected| selected.get());
+        let status = remote::status();
+        let view = View {
+            status,
+            name: selected.name,
+            seconds: status.remaining_seconds().unwrap_or(selected.seconds),
+            time: if IDLE_CLOCK && matches!(status, CureStatus::Idle) { clock::now() } else { None },
+            just_selected: selected.changed_at.elapsed() < Duration::from_secs(SELECTED_SHOW_SECONDS),
+            blink_on: Instant::now().as_millis() % 1000 < 500,
+            offline: offline(),
+        };
+
+        // Only mention it when it changes, not four times a second
+        if panel.draw(&view).await != answering {
+            answering = !answering;
+            if answering {
+                info!("Display answering again");
+            } else {
+                warn!("Display not answering - check {}", Panel::WIRING);
+            }
+        }
+        ticker.next().await;
+    }
+}
+
+/// Off the Wi-Fi network?
+#[cfg(feature = "pico-w")]
+fn offline() -> bool {
+    !crate::wifi::is_connected()
+}
+
+/// (Never, without Wi-Fi)
+#[cfg(not(feature = "pico-w"))]
+fn offline() -> bool {
+    false
+}
+
+/* ===========================================
+   TM1637: MM:SS OR THE CLOCK
+   =========================================== */
+
+#[cfg(feature = "tm1637")]
+impl Screen for crate::tm1637::Tm1637<'_> {
+    const WIRING: &'static str = "CLK (GPIO 21) and DIO (GPIO 28)";
+
+    async fn draw(&mut self, view: &View) -> bool {
+        use curing_core::seven_segment::{self, BLANK};
+
+        let segments = match view.status {
+            CureStatus::Idle => match view.time {
+                // A clock's colon blinks - the cure time's doesn't
+                Some(time) if !view.just_selected => seven_segment::hours_minutes(time.hour, time.minute, view.blink_on),
+                _ => seven_segment::minutes_seconds(view.seconds, true),
+            },
+            CureStatus::Preheating => seven_segment::text("HEAt"),
+            CureStatus::Curing { .. } | CureStatus::Resting { .. } => {
+                seven_segment::minutes_seconds(view.seconds, view.blink_on)
+            }
+            CureStatus::Paused { .. } if view.blink_on => seven_segment::minutes_seconds(view.seconds, true),
+            CureStatus::Paused { .. } | CureStatus::Standby => BLANK,
+            CureStatus::Cooldown => seven_segment::text("----"),
+        };
+        self.show(segments)
+    }
+}
+
+/* ===========================================
+   HD44780: STATE, NAME AND TIME
+   =========================================== */
+
+#[cfg(feature = "hd44780")]
+impl Screen for crate::hd44780::Hd44780<'_> {
+    const WIRING: &'static str = "SDA (GPIO 28), SCL (GPIO 21) and LCD_I2C_ADDRESS";
+
+    async fn draw(&mut self, view: &View) -> bool {
+        // Top line: what's happening. Bottom line: preset name and time.
+        let state = match view.status {
+            CureStatus::Idle => "Ready",
+            CureStatus::Preheating => "Preheating",
+            CureStatus::Curing { .. } => "Curing",
+            CureStatus::Resting { .. } => "Resting",
+            CureStatus::Paused { .. } => "Paused",
+            CureStatus::Cooldown => "Cooling down",
+            CureStatus::Standby => "Standby",
+        };
+        let mut top = Line::new();
+        let mut bottom = Line::new();
+        let _ = match view.time {
+            Some(time) => write!(top, "{:02}:{:02}", time.hour, time.minute),
+            None => top.write_str(state),
+        };
+        if matches!(view.status, CureStatus::Idle) && view.offline {
+            top.right_align(b"No Wi-Fi");
+        }
+        if !matches!(view.status, CureStatus::Preheating | CureStatus::Cooldown | CureStatus::Standby) {
+            let _ = bottom.write_str(view.name.unwrap_or(match view.status {
+                CureStatus::Idle => "Cure time",
+                _ => "Left",
+            }));
+            let mut time = Line::new();
+            let _ = write!(time, "{}:{:02}", view.seconds / 60, view.seconds % 60);
+            bottom.right_align(time.text());
+        }
+        self.show([top.0, bottom.0]).await
+    }
+}
+
+/// One line of an LCD: text, padded with spaces
+#[cfg(feature = "hd44780")]
+struct Line([u8; crate::hd44780::COLUMNS], usize);
+
+#[cfg(feature = "hd44780")]
+impl Line {
+    fn new() -> Self {
+        Self([b' '; crate::hd44780::COLUMNS], 0)
+    }
+
+    fn text(&self) -> &[u8] {
+        &self.0[..self.1]
+    }
+
+    /// Put `text` at the end of the line, over whatever was there
+    fn right_align(&mut self, text: &[u8]) {
+        let start = self.0.len().saturating_sub(text.len());
+        self.0[start..].copy_from_slice(&text[..self.0.len() - start]);
+    }
+}
+
+/// Write text onto the line, dropping anything past the end
+#[cfg(feature = "hd44780")]
+impl Write for Line {
+    fn write_str(&mut self, text: &str) -> core::fmt::Result {
+        for byte in text.bytes() {
+            if self.1 < self.0.len() {
+                self.0[self.1] = if byte.is_ascii() { byte } else { b'?' };
+                self.1 += 1;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+// Dormant Sleep (dormant builds)
+//
+// For battery-powered curers out in the field. After DORMANT_AFTER_IDLE_SECONDS
+// at idle with nothing happening - or after a standby hold - the RP2040
+// goes "dormant": the crystal oscillator stops, and with it every clock on
+// the chip. It draws well under a milliamp like this, against ~20 mA idling
+// normally. Only the start button can wake it.
+//
+// embassy-rp's `dormant_sleep()` does the clock juggling: it moves the
+// system clock onto the crystal, stops the PLLs and the crystal, and once
+// the button starts the crystal again, restarts the PLLs and puts every
+// clock back the way it was.
+//
+// Everything stops while asleep - every task, and the timer too, so
+// timestamps don't count the time spent asleep. Pins keep their levels,
+// which is why the UV must be off (and the relay settled) before sleeping:
+// `sleep()` refuses otherwise. The USB port and the Pico W's Wi-Fi chip
+// don't survive a sleep, so this can't be combined with usb-serial or
+// pico-w.
+
+use defmt::*;
+use embassy_rp::clocks::dormant_sleep;
+use embassy_rp::gpio::{AnyPin, DormantWakeConfig, Input};
+
+use crate::uv_output;
+
+/// A TTP223 module is HIGH while touched; a button to GND is LOW while pressed
+const WAKE_HIGH: bool = cfg!(feature = "ttp223");
+
+/// Sleep until `button` is pressed (called by the button task, which owns it)
+pub fn sleep(button: &mut Input<'static, AnyPin>) {
+    // Outputs stay as they are while asleep - never with the UV on
+    if !uv_output::is_dark() {
+        warn!("Not going to sleep - the UV isn't off");
+        return;
+    }
+    info!("Going dormant - press the button to wake up");
+
+    // Wake on the pressed level rather than an edge: a press that comes in
+    // while we're on the way down still wakes it straight away
+    let _wake = button.dormant_wake(DormantWakeConfig {
+        edge_high: false,
+        edge_low: false,
+        level_high: WAKE_HIGH,
+        level_low: !WAKE_HIGH,
+    });
+    dormant_sleep();
+    // _wake is dropped here, so the pin stops being a wake source
+
+    info!("Awake - clocks restored");
+}
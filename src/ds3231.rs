@@ -0,0 +1,137 @@
+// DS3231 Real-Time Clock (rtc-ds3231 builds)
+//
+// A DS3231 module keeps the date and time while the curer is unplugged,
+// running from its coin cell. It gets its own I2C bus, so it never waits
+// for the BME280:
+//
+//   GPIO 22 (I2C1 SDA) ── SDA
+//   GPIO 27 (I2C1 SCL) ── SCL
+//   3.3V ── VCC, GND ── GND   (the common ZS-042 boards have pull-ups fitted)
+//
+// At startup the time is read and handed to clock.rs. After that, a
+// background task:
+// - writes the new time to the chip whenever the user sets the clock
+// - re-reads it every RTC_SYNC_INTERVAL_SECONDS, as the DS3231 keeps
+//   better time than the Pico's own crystal
+//
+// The chip stores everything as BCD (binary-coded decimal: 0x59 means 59).
+
+use defmt::*;
+use embassy_futures::select::{select, Either};
+use embassy_rp::bind_interrupts;
+use embassy_rp::i2c::{self, Async, I2c};
+use embassy_rp::peripherals::{I2C1, PIN_22, PIN_27};
+use embassy_time::Timer;
+
+use crate::clock::{self, DateTime};
+use crate::config::*;
+
+bind_interrupts!(struct Irqs {
+    I2C1_IRQ => i2c::InterruptHandler<I2C1>;
+});
+
+/* DS3231 registers */
+const REG_SECONDS: u8 = 0x00;     // 7 bytes: seconds, minutes, hours, weekday, date, month, year
+const REG_STATUS: u8 = 0x0F;
+
+/// Status register: the oscillator stopped (battery flat or removed) - the time is wrong
+const STATUS_OSF: u8 = 0x80;
+/// Month register: set for years 2100 and up (never, here)
+const MONTH_CENTURY: u8 = 0x80;
+/// Hours register: 12-hour mode (we always write 24-hour)
+const HOURS_12H: u8 = 0x40;
+
+type Bus<'d> = I2c<'d, I2C1, Async>;
+
+/// Background task: load the time at startup, then keep the chip and
+/// clock.rs in step (returns if there's no DS3231)
+#[embassy_executor::task]
+pub async fn rtc_task(i2c: I2C1, sda: PIN_22, scl: PIN_27) {
+    let mut bus = I2c::new_async(i2c, scl, sda, Irqs, i2c::Config::default());
+
+    match read_time(&mut bus).await {
+        Ok(Some(time)) => {
+            clock::set(time);
+            info!("DS3231: the time is {}", time);
+        }
+        // Not fatal - the clock can still be set by hand, and is then saved
+        Ok(None) => warn!("DS3231 lost its time (flat battery?) - set the clock with `time YYYY-MM-DD HH:MM:SS`"),
+        Err(_) => {
+            warn!("DS3231 not found at 0x{:02x} - no wall-clock time until it's set by hand", DS3231_ADDRESS);
+            return;
+        }
+    }
+
+    loop {
+        match select(clock::ADJUSTED.wait(), Timer::after_secs(RTC_SYNC_INTERVAL_SECONDS)).await {
+            Either::First(time) => match write_time(&mut bus, &time).await {
+                Ok(()) => info!("DS3231: clock set to {}", time),
+                Err(_) => warn!("DS3231: saving the time failed"),
+            },
+            Either::Second(_) => match read_time(&mut bus).await {
+                Ok(Some(time)) => clock::set(time),
+                Ok(None) => warn!("DS3231 oscillator stopped - set the clock again"),
+                Err(_) => warn!("DS3231 read failed"),
+            },
+        }
+    }
+}
+
+/// Read the time - Ok(None) if the chip has lost it
+async fn read_time(bus: &mut Bus<'_>) -> Result<Option<DateTime>, i2c::Error> {
+    let mut status = [0u8; 1];
+    bus.write_read_async(DS3231_ADDRESS, [REG_STATUS], &mut status).await?;
+    if status[0] & STATUS_OSF != 0 {
+        return Ok(None);
+    }
+
+    let mut r = [0u8; 7];
+    bus.write_read_async(DS3231_ADDRESS, [REG_SECONDS], &mut r).await?;
+    let hour = if r[2] & HOURS_12H != 0 {
+        // Set to 12-hour mode by something else: bit 5 is PM
+        let hour12 = from_bcd(r[2] & 0x1F) % 12;
+        hour12 + if r[2] & 0x20 != 0 { 12 } else { 0 }
+    } else {
+        from_bcd(r[2] & 0x3F)
+    };
+    Ok(DateTime::new(
+        2000 + from_bcd(r[6]) as u16,
+        from_bcd(r[5] & !MONTH_CENTURY),
+        from_bcd(r[4]),
+        hour,
+        from_bcd(r[1]),
+        from_bcd(r[0] & 0x7F),
+    ))
+}
+
+/// Set the time, and clear the "oscillator stopped" flag
+async fn write_time(bus: &mut Bus<'_>, time: &DateTime) -> Result<(), i2c::Error> {
+    // Weekday 1-7 with Monday = 1 (1970-01-01 was a Thursday)
+    let weekday = ((time.days_since_epoch() + 3) % 7 + 1) as u8;
+    bus.write_async(
+        DS3231_ADDRESS,
+        [
+            REG_SECONDS,
+            to_bcd(time.second),
+            to_bcd(time.minute),
+            to_bcd(time.hour),                  // 24-hour mode
+            weekday,
+            to_bcd(time.day),
+            to_bcd(time.month),
+            to_bcd((time.year - 2000) as u8),
+        ],
+    )
+    .await?;
+
+    let mut status = [0u8; 1];
+    bus.write_read_async(DS3231_ADDRESS, [REG_STATUS], &mut status).await?;
+    bus.write_async(DS3231_ADDRESS, [REG_STATUS, status[0] & !STATUS_OSF]).await
+}
+
+fn from_bcd(bcd: u8) -> u8 {
+    (bcd >> 4) * 10 + (bcd & 0x0F)
+}
+
+fn to_bcd(value: u8) -> u8 {
+    ((value / 10) << 4) | (value % 10)
+}
@@ -0,0 +1,51 @@
+// Rotary Encoder Driver (rotary-encoder builds)
+//
+// Reads a standard quadrature rotary encoder (KY-040 style) with two output
+// pins, A and B. Each detent produces one falling edge on A; the level of B
+// at that moment tells us which way the knob turned.
+//
+// The encoder's push switch is wired in place of the start button (GPIO 6),
+// so pushing the knob works exactly like pressing the button.
+//
+// If the knob works "backwards", swap the A and B wires (or pins in main.rs).
+
+use embassy_rp::gpio::{AnyPin, Input};
+use embassy_time::Timer;
+
+use crate::config::*;
+
+/// Which way the knob was turned
+pub enum Direction {
+    Clockwise,
+    CounterClockwise,
+}
+
+/// Quadrature rotary encoder on two GPIO pins
+pub struct RotaryEncoder<'d> {
+    a: Input<'d, AnyPin>,
+    b: Input<'d, AnyPin>,
+}
+
+impl<'d> RotaryEncoder<'d> {
+    /// Create an encoder from its A and B pins (configured with pull-ups)
+    pub fn new(a: Input<'d, AnyPin>, b: Input<'d, AnyPin>) -> Self {
+        Self { a, b }
+    }
+
+    /// Wait for the knob to move one detent
+    pub async fn wait_for_step(&mut self) -> Direction {
+        loop {
+            self.a.wait_for_falling_edge().await;
+
+            // Contacts bounce - only count the edge if A is still low afterwards
+            Timer::after_millis(ENCODER_DEBOUNCE_MS).await;
+            if self.a.is_low() {
+                return if self.b.is_high() {
+                    Direction::Clockwise
+                } else {
+                    Direction::CounterClockwise
+                };
+            }
+        }
+    }
+}
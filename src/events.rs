@@ -0,0 +1,399 @@
+// Event Log
+//
+// The important moments of a cure - starting, the relay closing and
+// opening, pauses, beeps, errors - are recorded here as `Event`s rather
+// than with scattered info! calls. Each recorded event:
+// - goes into a RAM ring buffer with a timestamp (the last
+//   EVENT_LOG_CAPACITY events)
+// - is printed over defmt/RTT if its level is within the current verbosity
+//
+// The verbosity starts at LOG_VERBOSITY (config.rs) and can be changed
+// while running (`set verbosity 3` over USB serial, see settings.rs). When
+// an error is recorded, the whole ring buffer is printed whatever the
+// verbosity, so the log always shows what led up to it - with the date and
+// time of each event once the clock has been set (clock.rs).
+//
+// Every event is also published on an event bus (an embassy_sync
+// `PubSubChannel`). A subsystem that wants to react to cures - a display, a
+// network client, a logger - calls `subscribe()` once and then waits for
+// events, instead of being wired into main.rs by hand. The SD card log
+// works this way. A subscriber that falls behind misses the oldest events
+// (and is told how many), it never holds up the cure.
+
+use core::cell::RefCell;
+
+use defmt::*;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::pubsub::{PubSubChannel, Subscriber};
+use embassy_time::Instant;
+
+use crate::clock;
+use crate::config::*;
+use crate::settings;
+
+/// What started (or stopped) a cure
+#[derive(Clone, Copy, Format)]
+pub enum Source {
+    /// The push button (or the encoder's push switch, or the keypad's #)
+    Button,
+    /// A latching start switch
+    Switch,
+    /// A remote interface (USB serial, UART, web, MQTT)
+    Remote,
+    /// Auto-start after power-on
+    AutoStart,
+    /// A double tap at idle, running the last cure again
+    Repeat,
+    /// Finishing a cure cut off by a power cut
+    Resume,
+}
+
+/// Something worth logging
+#[derive(Clone, Copy, Format)]
+pub enum Event {
+    /// The button went down (whatever it turns out to be - tap, hold...)
+    ButtonPressed,
+    /// A cure is starting
+    CycleStarted { seconds: u64, source: Source },
+    /// A profile phase is starting (multi-stage cures)
+    ProfilePhase { number: usize, of: usize },
+    /// UV on for a phase of `seconds`
+    RelayClosed { seconds: u64 },
+    /// Rest phase of `seconds` with the UV off
+    Resting { seconds: u64 },
+    /// UV off at the end of a cure
+    RelayOpened,
+    /// Remaining time report
+    Progress { remaining_seconds: u64 },
+    /// Tapped mid-cure - UV off until the next tap
+    Paused { remaining_seconds: u64 },
+    /// UV back on after a pause, the lid closing or the chamber cooling
+    Resumed { remaining_seconds: u64 },
+    /// A tap made the running cure `seconds` longer
+    TimeAdded { seconds: u64, remaining_seconds: u64 },
+    /// The lid was opened mid-cure - UV off until it's closed
+    LidOpened { remaining_seconds: u64 },
+    /// A cure or peek was refused because the lid is open
+    LidOpenAtStart,
+    /// The chamber passed the soft limit mid-cure - UV off until it cools
+    #[cfg(feature = "thermistor")]
+    HeatPaused { remaining_seconds: u64 },
+    /// Maintenance bypass: the lid is ignored for `seconds`
+    #[cfg(feature = "lid-interlock")]
+    LidBypassed { seconds: u64 },
+    /// ...and the bypass has run out - the lid counts again
+    #[cfg(feature = "lid-interlock")]
+    LidBypassEnded,
+    /// A cure was aborted
+    Aborted { source: Source },
+    /// A latching start switch was turned OFF, ending the cure
+    SwitchedOff,
+    /// A cure ran for its full time
+    Completed,
+    /// A cure is over, however it ended (after Completed, Aborted, ...)
+    CureFinished {
+        /// completed, switched_off, aborted or overheated
+        result: &'static str,
+        /// Planned length in seconds (all phases of a profile)
+        planned_seconds: u64,
+        /// How long the UV was actually on
+        uv_on_ms: u64,
+        /// Preset name (multi-duration builds), or "-"
+        preset: &'static str,
+        /// How many times the chamber got too warm and the cure paused
+        heat_pauses: u32,
+    },
+    /// How far the cure has got was saved, in case the power goes
+    Checkpoint { remaining_seconds: u64 },
+    /// ...or wasn't - no room left without erasing flash mid-cure
+    CheckpointSkipped,
+    /// At power-on: a cure was cut off with `seconds` still to go - finished
+    /// unless the button is pressed if `automatic` (see PowerOnBehavior)
+    ResumeOffered { seconds: u64, automatic: bool },
+    /// ...and it wasn't finished
+    ResumeDeclined,
+    /// One of the completion beeps
+    Beep { number: u32, of: u32 },
+    /// Back to idle, ready for the next cure
+    Ready,
+    /// The UV was flashed briefly to check part positioning
+    Peek { ms: u64 },
+    /// The chamber got too hot - UV off, no cures until it cools
+    #[cfg(feature = "thermistor")]
+    Overheated,
+    /// The chamber is cool enough again
+    #[cfg(feature = "thermistor")]
+    Cooled,
+    /// The UV lamp has reached LAMP_SERVICE_HOURS
+    LampServiceDue { hours: u32 },
+    /// The lamp hours were reset (new UV LEDs fitted)
+    LampReset,
+    /// The UV sensor saw no light with the UV on - cure aborted
+    #[cfg(feature = "uv-sensor")]
+    UvLampFailed { millivolts: u32 },
+    /// The lamp current left its window with the UV on - cure aborted
+    #[cfg(feature = "ina219")]
+    LampCurrentFault { fault: crate::ina219::LampFault, milliamps: u32 },
+    /// The relay feedback contact says the relay didn't switch
+    /// (`closed` = stuck closed, the UV won't go off)
+    #[cfg(feature = "relay-feedback")]
+    RelayStuck { closed: bool },
+    /// The relay took `us` microseconds to close (or open), by its
+    /// feedback contact
+    #[cfg(feature = "relay-feedback")]
+    RelaySwitched { closed: bool, us: u32 },
+    /// The relay's switching time has crept up RELAY_SLOWDOWN_PERCENT over
+    /// what it was at first - it may be wearing out
+    #[cfg(feature = "relay-feedback")]
+    RelaySlowing { closed: bool, average_us: u32, baseline_us: u32 },
+    /// The battery has dropped below BATTERY_LOW_MV (or a start was refused
+    /// because it's low)
+    #[cfg(feature = "battery")]
+    BatteryLow { millivolts: u32 },
+    /// Gone into standby (a long hold at idle)
+    Standby,
+    /// Woken from standby by a button press
+    Woke,
+    /// Five taps at idle - restarting into BOOTSEL mode for new firmware
+    Bootsel,
+    /// The Wi-Fi network went away - rejoining in the background
+    #[cfg(feature = "pico-w")]
+    WifiLost,
+    /// Back on the Wi-Fi network (after `attempts` tries)
+    #[cfg(feature = "pico-w")]
+    WifiJoined { attempts: u32 },
+    /// Saving a setting to flash failed
+    FlashWriteFailed { what: &'static str },
+    /// A hardware fault ended the cure - its code is blinking until it's
+    /// cleared (FaultCode in curing-core/src/fault.rs)
+    Fault { code: u32, name: &'static str },
+    /// The fault is over and the button was pressed - cures allowed again
+    FaultCleared { code: u32 },
+}
+
+impl Event {
+    /// How important this event is
+    pub fn level(&self) -> LogLevel {
+        match self {
+            #[cfg(feature = "thermistor")]
+            Event::Overheated => LogLevel::Error,
+            #[cfg(feature = "uv-sensor")]
+            Event::UvLampFailed { .. } => LogLevel::Error,
+            #[cfg(feature = "ina219")]
+            Event::LampCurrentFault { .. } => LogLevel::Error,
+            #[cfg(feature = "relay-feedback")]
+            Event::RelayStuck { .. } => LogLevel::Error,
+            Event::FlashWriteFailed { .. } | Event::Fault { .. } => LogLevel::Error,
+            Event::LidOpened { .. } | Event::LidOpenAtStart | Event::LampServiceDue { .. } => LogLevel::Warn,
+            Event::ResumeOffered { .. } | Event::CheckpointSkipped => LogLevel::Warn,
+            #[cfg(feature = "battery")]
+            Event::BatteryLow { .. } => LogLevel::Warn,
+            #[cfg(feature = "lid-interlock")]
+            Event::LidBypassed { .. } => LogLevel::Warn,
+            #[cfg(feature = "thermistor")]
+            Event::HeatPaused { .. } => LogLevel::Warn,
+            #[cfg(feature = "relay-feedback")]
+            Event::RelaySlowing { .. } => LogLevel::Warn,
+            #[cfg(feature = "relay-feedback")]
+            Event::RelaySwitched { .. } => LogLevel::Debug,
+            #[cfg(feature = "pico-w")]
+            Event::WifiLost => LogLevel::Warn,
+            Event::Progress { .. } | Event::Beep { .. } | Event::ProfilePhase { .. } => LogLevel::Debug,
+            Event::Checkpoint { .. } => LogLevel::Debug,
+            Event::ButtonPressed | Event::CureFinished { .. } => LogLevel::Debug,
+            _ => LogLevel::Info,
+        }
+    }
+}
+
+/// An event and when it happened
+#[derive(Clone, Copy)]
+pub struct Record {
+    /// Milliseconds since power-on
+    pub at_ms: u64,
+    pub event: Event,
+}
+
+/// The last EVENT_LOG_CAPACITY events, oldest overwritten first
+struct Ring {
+    records: [Option<Record>; EVENT_LOG_CAPACITY],
+    /// Where the next record goes
+    next: usize,
+}
+
+static LOG: Mutex<CriticalSectionRawMutex, RefCell<Ring>> =
+    Mutex::new(RefCell::new(Ring { records: [None; EVENT_LOG_CAPACITY], next: 0 }));
+
+/// Events a slow subscriber can fall behind by before it misses some
+const BUS_CAPACITY: usize = 8;
+
+/// Most subscribers at once - raise it when adding one
+const BUS_SUBSCRIBERS: usize = 4;
+
+/// The event bus - `record()` is its only publisher
+static BUS: PubSubChannel<CriticalSectionRawMutex, Record, BUS_CAPACITY, BUS_SUBSCRIBERS, 0> = PubSubChannel::new();
+
+/// One subscription to the event bus
+pub type EventSubscriber = Subscriber<'static, CriticalSectionRawMutex, Record, BUS_CAPACITY, BUS_SUBSCRIBERS, 0>;
+
+/// Start receiving every event recorded from now on
+///
+/// Call once per subsystem, at startup. Wait with `next_message()`, which
+/// returns `WaitResult::Lagged(n)` if `n` events went by too fast.
+#[cfg_attr(not(feature = "sd-log"), allow(dead_code))]
+pub fn subscribe() -> EventSubscriber {
+    unwrap!(BUS.subscriber().ok(), "Too many event bus subscribers - raise BUS_SUBSCRIBERS in events.rs")
+}
+
+/// Record an event, and print it if the verbosity allows
+pub fn record(event: Event) {
+    let record = Record { at_ms: Instant::now().as_millis(), event };
+    LOG.lock(|log| {
+        let mut log = log.borrow_mut();
+        let next = log.next;
+        log.records[next] = Some(record);
+        log.next = (next + 1) % EVENT_LOG_CAPACITY;
+    });
+    BUS.immediate_publisher().publish_immediate(record);
+
+    if event.level() <= settings::log_level() {
+        print(&event);
+    }
+    if event.level() == LogLevel::Error {
+        dump();
+    }
+}
+
+/// Print every event in the ring buffer, oldest first
+pub fn dump() {
+    let (records, next) = LOG.lock(|log| {
+        let log = log.borrow();
+        (log.records, log.next)
+    });
+    info!("--- Recent events ---");
+    for i in 0..EVENT_LOG_CAPACITY {
+        if let Some(record) = records[(next + i) % EVENT_LOG_CAPACITY] {
+            match clock::at(record.at_ms) {
+                Some(time) => info!("[{}] {}", time, record.event),
+                None => info!("[{} ms] {}", record.at_ms, record.event),
+            }
+        }
+    }
+    info!("---------------------");
+}
+
+/// The event as a friendly log line
+fn print(event: &Event) {
+    match *event {
+        Event::ButtonPressed => debug!("Button pressed"),
+        Event::CycleStarted { seconds, source } => match source {
+            Source::Button => info!("Button pressed! Starting {}-second curing cycle...", seconds),
+            Source::Switch => info!("Start switch ON! Starting curing cycle..."),
+            Source::Remote => info!("Remote start! Starting {}-second curing cycle...", seconds),
+            Source::AutoStart => info!("Auto-starting {}-second curing cycle...", seconds),
+            Source::Repeat => info!("Double tap! Repeating the last {}-second curing cycle...", seconds),
+            Source::Resume => info!("Finishing the cut-off cure - {} seconds to go...", seconds),
+        },
+        Event::ProfilePhase { number, of } => debug!("Profile phase {}/{}", number, of),
+        Event::RelayClosed { seconds } => match START_INPUT {
+            StartInput::Momentary => info!("Relay CLOSED - UV LEDs ON - Curing for {} seconds", seconds),
+            StartInput::Latching => info!("Relay CLOSED - UV LEDs ON - Curing until switch is OFF (max {} seconds)", seconds),
+        },
+        Event::Resting { seconds } => info!("Relay OPEN - UV LEDs OFF - Resting for {} seconds", seconds),
+        Event::RelayOpened => info!("Relay OPEN - UV LEDs OFF"),
+        Event::Progress { remaining_seconds } => debug!("Curing... {} seconds remaining", remaining_seconds),
+        Event::Paused { remaining_seconds } => {
+            info!("Cure PAUSED - UV LEDs OFF - {} seconds remaining. Tap to resume, hold to abort", remaining_seconds)
+        }
+        Event::Resumed { remaining_seconds } => info!("Cure RESUMED - {} seconds remaining", remaining_seconds),
+        Event::TimeAdded { seconds, remaining_seconds } => {
+            info!("Tap - {} seconds added, {} seconds remaining", seconds, remaining_seconds)
+        }
+        Event::LidOpened { remaining_seconds } => {
+            warn!("Lid OPEN - UV LEDs OFF - {} seconds remaining. Close the lid to resume", remaining_seconds)
+        }
+        Event::LidOpenAtStart => warn!("Lid is OPEN - close it before using the UV"),
+        #[cfg(feature = "thermistor")]
+        Event::HeatPaused { remaining_seconds } => {
+            warn!("Chamber too warm - UV LEDs OFF - {} seconds remaining. Resumes below {} °C", remaining_seconds, HEAT_RESUME_C)
+        }
+        #[cfg(feature = "lid-interlock")]
+        Event::LidBypassed { seconds } => {
+            warn!("LID INTERLOCK BYPASSED for {} s - the UV can come on with the lid open. Power cycle to end it sooner", seconds)
+        }
+        #[cfg(feature = "lid-interlock")]
+        Event::LidBypassEnded => info!("Lid interlock bypass over - the lid counts again"),
+        Event::Aborted { source } => match source {
+            Source::Remote => info!("Remote stop - cure ABORTED"),
+            _ => info!("Button held - cure ABORTED"),
+        },
+        Event::SwitchedOff => info!("Start switch OFF - ending cure"),
+        Event::Completed => info!("Curing complete!"),
+        Event::CureFinished { result, uv_on_ms, heat_pauses: 0, .. } => debug!("Cure over ({}) - UV on for {} ms", result, uv_on_ms),
+        Event::CureFinished { result, uv_on_ms, heat_pauses, .. } => {
+            info!("Cure over ({}) - UV on for {} ms, paused {} time(s) to cool down", result, uv_on_ms, heat_pauses)
+        }
+        Event::Checkpoint { remaining_seconds } => debug!("Checkpoint saved - {} seconds left in this phase", remaining_seconds),
+        Event::CheckpointSkipped => warn!("Checkpoint skipped - the flash is full until this cure ends"),
+        Event::ResumeOffered { seconds, automatic: false } => {
+            warn!("The last cure was cut off by a power cut with {} seconds to go - press the button within {} seconds to finish it", seconds, RESUME_OFFER_SECONDS)
+        }
+        Event::ResumeOffered { seconds, automatic: true } => {
+            warn!("The last cure was cut off by a power cut with {} seconds to go - finishing it in {} seconds, press the button to give it up", seconds, RESUME_OFFER_SECONDS)
+        }
+        Event::ResumeDeclined => info!("Cut-off cure not resumed (counted as aborted)"),
+        Event::Beep { number, of } => debug!("Buzzer beep {}/{}", number, of),
+        Event::Ready => info!("Ready for next cycle."),
+        Event::Peek { ms } => info!("Button held - UV peek for {} ms (not counted as a cure)", ms),
+        #[cfg(feature = "thermistor")]
+        Event::Overheated => {
+            error!("UV LEDs OFF - chamber over-temperature! Waiting for it to cool below {} °C", OVERTEMP_RESUME_C)
+        }
+        #[cfg(feature = "thermistor")]
+        Event::Cooled => info!("Chamber cooled."),
+        Event::LampServiceDue { hours } => {
+            warn!("UV lamp has been on for {} hours - check the LEDs (hold the button at power-on to reset)", hours)
+        }
+        Event::LampReset => info!("Lamp hours reset"),
+        #[cfg(feature = "uv-sensor")]
+        Event::UvLampFailed { millivolts } => {
+            error!("UV LAMP FAULT: the UV sensor reads {} mV with the UV on - check the LEDs, wiring and relay. Cure aborted", millivolts)
+        }
+        #[cfg(feature = "ina219")]
+        Event::LampCurrentFault { fault, milliamps } => {
+            error!("LAMP CURRENT FAULT (code {}): {} mA with the UV on - LED string {}. Cure aborted", fault.fault_code().code(), milliamps, fault)
+        }
+        #[cfg(feature = "relay-feedback")]
+        Event::RelayStuck { closed: true } => {
+            error!("RELAY STUCK CLOSED (code 4): the UV won't switch off - unplug the UV supply! Retrying...")
+        }
+        #[cfg(feature = "relay-feedback")]
+        Event::RelaySwitched { closed, us } => {
+            debug!("Relay {} in {}.{} ms", if closed { "closed" } else { "opened" }, us / 1000, us % 1000 / 100)
+        }
+        #[cfg(feature = "relay-feedback")]
+        Event::RelaySlowing { closed, average_us, baseline_us } => warn!(
+            "Relay getting slower to {}: {} ms now, {} ms at first - it may be wearing out, think about replacing it",
+            if closed { "close" } else { "open" },
+            average_us / 1000,
+            baseline_us / 1000
+        ),
+        #[cfg(feature = "relay-feedback")]
+        Event::RelayStuck { closed: false } => error!("RELAY STUCK OPEN (code 4): the UV didn't come on - check the relay. Cure aborted"),
+        #[cfg(feature = "battery")]
+        Event::BatteryLow { millivolts } => warn!("Battery LOW ({} mV) - no cures until it's charged", millivolts),
+        Event::Standby => info!("Standby - everything off. Press the button to wake up"),
+        Event::Woke => info!("Awake again"),
+        Event::Bootsel => info!("Five taps - restarting into BOOTSEL mode: copy the new .uf2 to the RPI-RP2 drive, or unplug to keep this firmware"),
+        #[cfg(feature = "pico-w")]
+        Event::WifiLost => warn!("Wi-Fi lost - rejoining in the background (the curer works as normal meanwhile)"),
+        #[cfg(feature = "pico-w")]
+        Event::WifiJoined { attempts } => info!("Wi-Fi back after {} attempt(s)", attempts),
+        Event::FlashWriteFailed { what } => error!("Could not save {} to flash", what),
+        Event::Fault { code, name } => {
+            error!("FAULT {} ({}): UV off - the status LED blinks {} times, over and over, until it's fixed and the button is pressed", code, name, code)
+        }
+        Event::FaultCleared { code } => info!("Fault {} cleared - ready again", code),
+    }
+}
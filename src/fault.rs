@@ -0,0 +1,57 @@
+// Fault Handler
+//
+// When the hardware goes wrong during a cure - the chamber is too hot, the
+// lamp is dead or shorted, the relay won't close - the cure stops and the
+// curer goes into its fault state (see cycle.rs in curing-core). `handle()`
+// is what happens there, whatever the fault:
+//
+// 1. Safe state: the UV is switched off (again - the cure is over, but
+//    better twice than never). The heater only runs while preheating, so
+//    it's already off.
+// 2. The fault's number is beeped once, then blinked on the status LED
+//    over and over - 4 flashes, a pause, 4 flashes... - so it can be read
+//    without a debug probe or a computer:
+//      2 = lamp open (ina219)         5 = lamp dark (uv-sensor)
+//      3 = lamp shorted (ina219)      6 = chamber too hot (thermistor)
+//      4 = relay stuck (relay-feedback)  7 = temperature sensor missing (thermistor)
+// 3. It stays that way until the cause has gone (too hot: the chamber has
+//    cooled; sensor missing: it reads again) and the button has been
+//    pressed and let go (a latching switch: turned ON and OFF again), to
+//    say someone has seen it. Only then can a new cure start.
+//
+// The numbers themselves are FaultCode in curing-core/src/fault.rs.
+
+use defmt::*;
+
+use curing_core::fault::FaultCode;
+
+use crate::button;
+use crate::buzzer::{self, AudioEvent};
+use crate::config::*;
+use crate::events::{self, Event};
+use crate::status_led::{self, Led};
+#[cfg(feature = "thermistor")]
+use crate::thermal;
+use crate::uv_output;
+
+/// Make everything safe, then show `fault` until it's cleared and acknowledged
+pub async fn handle(fault: FaultCode) {
+    uv_output::set(false);
+    events::record(Event::Fault { code: fault.code(), name: fault.name() });
+    buzzer::play_count(AudioEvent::Fault, fault.code());
+    status_led::show(Led::Repeat { flashes: fault.code(), flash_ms: FAULT_BLINK_MS, period_ms: FAULT_BLINK_PERIOD_MS });
+
+    // Too hot, or no temperature reading: wait for the thermistor to say it's fine
+    #[cfg(feature = "thermistor")]
+    if fault.waits_for_sensor() {
+        thermal::wait_until_cool().await;
+        events::record(Event::Cooled);
+    }
+
+    // A press and release says "seen it" - the release means it can't
+    // be mistaken for a start
+    info!("Fault {}: press the button to carry on", fault.code());
+    button::wait_for_press().await;
+    button::wait_for_release().await;
+    events::record(Event::FaultCleared { code: fault.code() });
+}
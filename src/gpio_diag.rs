@@ -0,0 +1,157 @@
+// GPIO Diagnostics (gpio-diagnostics builds)
+//
+// Typed over the USB serial port (or the UART link), for checking wiring in
+// the field without flashing a test program like relay_manual_test.rs:
+//
+//   gpio read 6          is GPIO 6 high or low?
+//   gpio set 10 low      drive GPIO 10 low (the relay closes, active-LOW)
+//   gpio pulse 10 100    flip GPIO 10 for 100 ms, then put it back
+//   gpio release 10      give GPIO 10 back to whatever drove it before
+//
+// Reading works any time and changes nothing. Setting and pulsing take the
+// pin over from its driver - the relay task, a PWM channel, anything - so
+// they're only allowed while no cure is running (commands.rs checks), and
+// the pin stays ours until `release` (a pulse gives it back by itself).
+// The Pico W's Wi-Fi chip pins (23, 24, 25, 29) can't be taken.
+//
+// The drivers that own the pins are somewhere else entirely, so - like the
+// panic handler (panic.rs) - this goes straight to the RP2040's registers:
+// SIO for the level, IO_BANK0 for which peripheral the pin belongs to.
+// Taking a pin notes how it was set up; releasing it puts that back.
+//
+// This is a bench tool: whoever types `gpio set 10 low` has switched the
+// UV on, lid or no lid. Leave the feature out of builds that go to users.
+
+use core::cell::Cell;
+
+use defmt::*;
+use embassy_rp::pac;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::signal::Signal;
+use embassy_time::Timer;
+
+/// GPIOs in the RP2040's user bank (0-29)
+const PIN_COUNT: usize = 30;
+
+/// Pins wired to the Pico W's Wi-Fi chip
+#[cfg(feature = "pico-w")]
+const WIFI_PINS: [u8; 4] = [23, 24, 25, 29];
+
+/// IO_BANK0 function number that hands a pin to SIO (plain software GPIO)
+const FUNCSEL_SIO: u8 = 5;
+
+/// How a pin was set up before we took it over
+#[derive(Clone, Copy)]
+struct Taken {
+    /// Peripheral function it was connected to
+    funcsel: u8,
+    /// SIO output enable
+    output: bool,
+    /// SIO output level
+    high: bool,
+}
+
+/// Pins taken over by `set` (None = left to the firmware)
+static TAKEN: Mutex<CriticalSectionRawMutex, Cell<[Option<Taken>; PIN_COUNT]>> = Mutex::new(Cell::new([None; PIN_COUNT]));
+
+/// Pulse waiting for the pulse task: pin and milliseconds
+static PULSE: Signal<CriticalSectionRawMutex, (u8, u64)> = Signal::new();
+
+/// Is there a GPIO with this number that we may read?
+pub fn exists(pin: u8) -> bool {
+    (pin as usize) < PIN_COUNT
+}
+
+/// ...and may we drive it?
+pub fn may_drive(pin: u8) -> bool {
+    #[cfg(feature = "pico-w")]
+    if WIFI_PINS.contains(&pin) {
+        return false;
+    }
+    exists(pin)
+}
+
+/// The level on the pin right now, whoever is driving it
+pub fn read(pin: u8) -> bool {
+    pac::SIO.gpio_in(0).read() & bit(pin) != 0
+}
+
+/// Drive the pin high or low, taking it over first if it isn't yet
+pub fn set(pin: u8, high: bool) {
+    TAKEN.lock(|taken| {
+        let mut pins = taken.get();
+        if pins[pin as usize].is_none() {
+            pins[pin as usize] = Some(Taken {
+                funcsel: pac::IO_BANK0.gpio(pin as usize).ctrl().read().funcsel(),
+                output: pac::SIO.gpio_oe(0).value().read() & bit(pin) != 0,
+                high: pac::SIO.gpio_out(0).value().read() & bit(pin) != 0,
+            });
+            taken.set(pins);
+        }
+    });
+    write_level(pin, high);
+    pac::SIO.gpio_oe(0).value_set().write_value(bit(pin));
+    pac::PADS_BANK0.gpio(pin as usize).modify(|w| {
+        w.set_od(false);
+        w.set_ie(true);
+    });
+    pac::IO_BANK0.gpio(pin as usize).ctrl().write(|w| w.set_funcsel(FUNCSEL_SIO));
+}
+
+/// Give a pin back the way it was before `set` (false if it wasn't taken)
+pub fn release(pin: u8) -> bool {
+    let Some(before) = TAKEN.lock(|taken| {
+        let mut pins = taken.get();
+        let before = pins[pin as usize].take();
+        taken.set(pins);
+        before
+    }) else {
+        return false;
+    };
+    write_level(pin, before.high);
+    if before.output {
+        pac::SIO.gpio_oe(0).value_set().write_value(bit(pin));
+    } else {
+        pac::SIO.gpio_oe(0).value_clr().write_value(bit(pin));
+    }
+    pac::IO_BANK0.gpio(pin as usize).ctrl().write(|w| w.set_funcsel(before.funcsel));
+    true
+}
+
+/// Flip the pin for `ms` milliseconds (done by the pulse task, so the
+/// command doesn't wait)
+pub fn pulse(pin: u8, ms: u64) {
+    PULSE.signal((pin, ms));
+}
+
+/// Background task: run the pulses asked for with `pulse()`
+#[embassy_executor::task]
+pub async fn gpio_pulse_task() {
+    loop {
+        let (pin, ms) = PULSE.wait().await;
+        let was_taken = TAKEN.lock(|taken| taken.get()[pin as usize].is_some());
+        let level = read(pin);
+
+        info!("GPIO {}: pulsing {} for {} ms", pin, if level { "low" } else { "high" }, ms);
+        set(pin, !level);
+        Timer::after_millis(ms).await;
+        set(pin, level);
+        if !was_taken {
+            release(pin);
+        }
+    }
+}
+
+/// The pin's bit in the SIO registers
+fn bit(pin: u8) -> u32 {
+    1 << pin
+}
+
+fn write_level(pin: u8, high: bool) {
+    if high {
+        pac::SIO.gpio_out(0).value_set().write_value(bit(pin));
+    } else {
+        pac::SIO.gpio_out(0).value_clr().write_value(bit(pin));
+    }
+}
@@ -0,0 +1,132 @@
+// HD44780 Character LCD Driver (hd44780 builds)
+//
+// The common blue/green 16x2 LCDs with a PCF8574 "I2C backpack" soldered
+// on the back. The backpack turns two I2C wires into the LCD's parallel
+// pins:
+//
+//   GPIO 28 (I2C0 SDA) ── SDA
+//   GPIO 21 (I2C0 SCL) ── SCL
+//   VBUS (5V) ── VCC, GND ── GND
+//
+// Most backpacks pull SDA/SCL up to their own 5V supply. The RP2040's pins
+// are not 5V tolerant, so either remove the backpack's pull-up resistors
+// or use a level shifter.
+//
+// The backpack's eight outputs drive the LCD in 4-bit mode:
+//   P0 = RS (0 = command, 1 = text)   P1 = RW (always 0 = write)
+//   P2 = E  (the LCD reads on its falling edge)   P3 = backlight
+//   P4-P7 = D4-D7
+// so every byte for the LCD goes out as two nibbles, each written to the
+// backpack twice - once with E high, once with E low.
+//
+// The LCD is set up on the first `show()`, and again after it has failed
+// to answer (unplugged, or powered up after the Pico).
+
+use embassy_rp::bind_interrupts;
+use embassy_rp::i2c::{self, Async, I2c};
+use embassy_rp::peripherals::{I2C0, PIN_21, PIN_28};
+use embassy_time::Timer;
+
+bind_interrupts!(struct Irqs {
+    I2C0_IRQ => i2c::InterruptHandler<I2C0>;
+});
+
+/// Characters per line
+pub const COLUMNS: usize = 16;
+
+/* Backpack pins */
+const RS: u8 = 0x01;
+const E: u8 = 0x04;
+const BACKLIGHT: u8 = 0x08;
+
+/* LCD commands */
+const CMD_CLEAR: u8 = 0x01;
+const CMD_ENTRY_LEFT_TO_RIGHT: u8 = 0x06;
+const CMD_DISPLAY_ON: u8 = 0x0C;     // Cursor and blink off
+const CMD_FUNCTION_4BIT_2LINE: u8 = 0x28;
+const CMD_SET_ADDRESS: u8 = 0x80;
+/// Where each line starts in the LCD's memory
+const LINE_ADDRESS: [u8; 2] = [0x00, 0x40];
+
+/// A 16x2 LCD on a PCF8574 backpack
+pub struct Hd44780<'d> {
+    bus: I2c<'d, I2C0, Async>,
+    address: u8,
+    /// What the LCD is showing, if it's been set up - None means set it up
+    /// on the next `show()`
+    shown: Option<[[u8; COLUMNS]; 2]>,
+}
+
+impl<'d> Hd44780<'d> {
+    pub fn new(i2c: I2C0, scl: PIN_21, sda: PIN_28, address: u8) -> Self {
+        let bus = I2c::new_async(i2c, scl, sda, Irqs, i2c::Config::default());
+        Self { bus, address, shown: None }
+    }
+
+    /// Show two lines of text (ASCII, space padded)
+    ///
+    /// Returns false if the backpack didn't answer. Lines that haven't
+    /// changed aren't sent again.
+    pub async fn show(&mut self, lines: [[u8; COLUMNS]; 2]) -> bool {
+        let result = async {
+            let mut shown = match self.shown {
+                Some(shown) => shown,
+                None => {
+                    self.init().await?;
+                    [[b' '; COLUMNS]; 2]
+                }
+            };
+            for (row, text) in lines.iter().enumerate() {
+                if shown[row] != *text {
+                    self.command(CMD_SET_ADDRESS | LINE_ADDRESS[row]).await?;
+                    for &c in text {
+                        self.write_byte(c, RS).await?;
+                    }
+                    shown[row] = *text;
+                }
+            }
+            Ok::<_, i2c::Error>(shown)
+        }
+        .await;
+
+        // On a failure, start again from scratch when it comes back
+        self.shown = result.ok();
+        self.shown.is_some()
+    }
+
+    /// The HD44780 power-up dance: three "8-bit mode"s to get it into a known
+    /// state whatever it was doing, then switch to 4-bit mode (datasheet
+    /// figure 24)
+    async fn init(&mut self) -> Result<(), i2c::Error> {
+        Timer::after_millis(50).await;
+        for wait_us in [4_500, 150, 150] {
+            self.write_nibble(0x30, 0).await?;
+            Timer::after_micros(wait_us).await;
+        }
+        self.write_nibble(0x20, 0).await?;
+
+        self.command(CMD_FUNCTION_4BIT_2LINE).await?;
+        self.command(CMD_DISPLAY_ON).await?;
+        self.command(CMD_CLEAR).await?;
+        Timer::after_millis(2).await;   // Clearing is the slow one
+        self.command(CMD_ENTRY_LEFT_TO_RIGHT).await
+    }
+
+    async fn command(&mut self, command: u8) -> Result<(), i2c::Error> {
+        self.write_byte(command, 0).await
+    }
+
+    /// One byte to the LCD, top nibble first. `rs` picks command or text.
+    async fn write_byte(&mut self, byte: u8, rs: u8) -> Result<(), i2c::Error> {
+        self.write_nibble(byte & 0xF0, rs).await?;
+        self.write_nibble(byte << 4, rs).await
+    }
+
+    /// Four bits on D4-D7 (the top of `nibble`), clocked in with a pulse on E
+    async fn write_nibble(&mut self, nibble: u8, rs: u8) -> Result<(), i2c::Error> {
+        let pins = nibble | rs | BACKLIGHT;
+        // At 100 kHz each backpack write takes ~200 µs - far longer than the
+        // LCD needs for the E pulse or to take in most commands
+        self.bus.write_async(self.address, [pins | E, pins]).await
+    }
+}
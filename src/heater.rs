@@ -0,0 +1,91 @@
+// Chamber Heater and Preheat (heater builds)
+//
+// Resin cures better (and more evenly) in a warm chamber. A second relay on
+// GPIO 14 switches a small heater, and every cure starts with a preheat
+// phase before the UV comes on:
+// - with a temperature sensor built in (thermistor or bme280), the heater
+//   runs until the chamber reaches PREHEAT_TARGET_C, or PREHEAT_SECONDS at
+//   most, whichever comes first
+// - without one, it simply runs for PREHEAT_SECONDS
+//
+// The heater is always off again before the UV turns on. While preheating,
+// the status LED double-blinks.
+
+use defmt::*;
+use embassy_rp::gpio::{AnyPin, Level, Output};
+use embassy_time::{Duration, Instant, Timer};
+
+use crate::config::*;
+use crate::status_led::{self, Led};
+
+/// Length of each status LED flash while preheating
+const PREHEAT_FLASH_MS: u64 = 100;
+
+/// One double-blink cycle of the status LED (flash, gap, flash, long gap) -
+/// also how often the chamber temperature is checked
+const PREHEAT_BLINK_PERIOD_MS: u64 = 1000;
+
+/// Heater relay (input HIGH = heater on)
+pub struct Heater<'d> {
+    relay: Output<'d, AnyPin>,
+}
+
+impl<'d> Heater<'d> {
+    /// Take the heater relay pin, starting with the heater off
+    pub fn new(relay_pin: AnyPin) -> Self {
+        Self { relay: Output::new(relay_pin, Level::Low) }
+    }
+
+    /// Heater ON
+    pub fn on(&mut self) {
+        self.relay.set_high();
+    }
+
+    /// Heater OFF
+    pub fn off(&mut self) {
+        self.relay.set_low();
+    }
+}
+
+/// Has the chamber reached the preheat target? (never, without a sensor)
+fn target_reached() -> bool {
+    #[cfg(any(feature = "thermistor", feature = "bme280"))]
+    if let Some(centi) = crate::sensors::chamber_centi_celsius() {
+        return centi >= PREHEAT_TARGET_C * 100;
+    }
+    false
+}
+
+/// Run the preheat phase - leaves the heater and status LED off afterwards
+///
+/// If this future is dropped early (the cure was aborted), the caller must
+/// switch the heater off itself.
+pub async fn preheat(heater: &mut Heater<'_>) {
+    #[cfg(any(feature = "thermistor", feature = "bme280"))]
+    info!("Heater ON - preheating to {} °C (max {} seconds)", PREHEAT_TARGET_C, PREHEAT_SECONDS);
+    #[cfg(not(any(feature = "thermistor", feature = "bme280")))]
+    info!("Heater ON - preheating for {} seconds", PREHEAT_SECONDS);
+
+    let deadline = Instant::now() + Duration::from_secs(PREHEAT_SECONDS);
+    heater.on();
+    // Double blink: the status LED pattern for "preheating"
+    status_led::show(Led::Repeat { flashes: 2, flash_ms: PREHEAT_FLASH_MS, period_ms: PREHEAT_BLINK_PERIOD_MS });
+
+    while Instant::now() < deadline {
+        if target_reached() {
+            info!("Preheat target reached");
+            break;
+        }
+        // The over-temperature cutoff beats any preheat target
+        #[cfg(feature = "thermistor")]
+        if crate::thermal::is_too_hot() {
+            break;
+        }
+
+        Timer::after_millis(PREHEAT_BLINK_PERIOD_MS).await;
+    }
+
+    heater.off();
+    status_led::show(Led::Off);
+    info!("Heater OFF - preheat done");
+}
@@ -0,0 +1,161 @@
+// INA219 Lamp Current Monitor (ina219 builds)
+//
+// An INA219 breakout in the UV LED supply measures the current the LEDs
+// actually draw. Its shunt resistor goes in series with the LED string
+// (VIN+ from the supply, VIN- to the LEDs), and it talks I2C:
+//
+//   GPIO 22 (I2C1 SDA) ── SDA
+//   GPIO 27 (I2C1 SCL) ── SCL
+//   3.3V ── VCC, GND ── GND (common with the LED supply's GND)
+//
+// The chip measures the voltage across its shunt (10 µV steps) and the
+// supply voltage (4 mV steps); current = shunt voltage / shunt resistance.
+// We do that sum ourselves rather than programming the chip's calibration
+// register - one less thing to get wrong.
+//
+// A background task samples it every INA219_SAMPLE_MS, keeps the latest
+// current, and adds up the energy used (for the "watt-seconds per cure"
+// log). While a UV phase runs, the main loop watches the current with
+// `wait_for_fault`: once the relay has had LAMP_CURRENT_SETTLE_MS to close,
+// a current below LAMP_CURRENT_MIN_MA means the LED string is open (a
+// broken LED or wire) and above LAMP_CURRENT_MAX_MA means it's shorted.
+// Either way the cure is aborted with its own error code.
+
+use core::cell::Cell;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use curing_core::fault::FaultCode;
+use defmt::*;
+use embassy_rp::bind_interrupts;
+use embassy_rp::i2c::{self, Async, I2c};
+use embassy_rp::peripherals::{I2C1, PIN_22, PIN_27};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_time::{Instant, Timer};
+
+use crate::config::*;
+
+bind_interrupts!(struct Irqs {
+    I2C1_IRQ => i2c::InterruptHandler<I2C1>;
+});
+
+/* INA219 registers */
+const REG_CONFIG: u8 = 0x00;
+const REG_SHUNT_VOLTAGE: u8 = 0x01;
+const REG_BUS_VOLTAGE: u8 = 0x02;
+
+/// 32 V range, ±320 mV shunt range, 12-bit, continuous (the power-on default)
+const CONFIG_DEFAULT: u16 = 0x399F;
+
+/// Shunt voltage register: 10 µV per step
+const SHUNT_UV_PER_STEP: i32 = 10;
+/// Bus voltage register (bits 15-3): 4 mV per step
+const BUS_MV_PER_STEP: u32 = 4;
+
+/// What's wrong with the LED string
+#[derive(Clone, Copy, Format)]
+pub enum LampFault {
+    /// Too little current - an LED or wire is open
+    Open,
+    /// Too much current - something is shorted
+    Shorted,
+}
+
+impl LampFault {
+    /// The fault code it's blinked and beeped as (fault.rs)
+    pub fn fault_code(&self) -> FaultCode {
+        match self {
+            LampFault::Open => FaultCode::LampOpen,
+            LampFault::Shorted => FaultCode::LampShorted,
+        }
+    }
+}
+
+/// Latest lamp current in milliamps (u32::MAX = no reading)
+static LATEST_MA: AtomicU32 = AtomicU32::new(u32::MAX);
+
+/// Energy used since `reset_energy`, in millijoules
+static ENERGY_MJ: Mutex<CriticalSectionRawMutex, Cell<u64>> = Mutex::new(Cell::new(0));
+
+/// Latest lamp current in milliamps, if the INA219 is answering
+pub fn milliamps() -> Option<u32> {
+    match LATEST_MA.load(Ordering::Relaxed) {
+        u32::MAX => None,
+        ma => Some(ma),
+    }
+}
+
+/// Start counting energy from zero (at the start of a cure)
+pub fn reset_energy() {
+    ENERGY_MJ.lock(|energy| energy.set(0));
+}
+
+/// Energy the lamp has used since `reset_energy`, in watt-seconds (joules)
+pub fn energy_watt_seconds() -> u64 {
+    ENERGY_MJ.lock(|energy| energy.get()) / 1000
+}
+
+/// Background task: sample the INA219 (returns if it isn't there)
+#[embassy_executor::task]
+pub async fn lamp_current_task(i2c: I2C1, sda: PIN_22, scl: PIN_27) {
+    let mut bus = I2c::new_async(i2c, scl, sda, Irqs, i2c::Config::default());
+    let [high, low] = CONFIG_DEFAULT.to_be_bytes();
+    if bus.write_async(INA219_ADDRESS, [REG_CONFIG, high, low]).await.is_err() {
+        warn!("INA219 not found at 0x{:02x} - no lamp current checks", INA219_ADDRESS);
+        return;
+    }
+    info!("INA219 ready - watching the UV lamp current");
+
+    let mut last = Instant::now();
+    loop {
+        Timer::after_millis(INA219_SAMPLE_MS).await;
+        let elapsed_ms = last.elapsed().as_millis();
+        last = Instant::now();
+
+        match read(&mut bus).await {
+            Ok((milliamps, millivolts)) => {
+                LATEST_MA.store(milliamps, Ordering::Relaxed);
+                // mA x mV = µW; x ms = nJ - /1_000_000 for mJ
+                let millijoules = milliamps as u64 * millivolts as u64 * elapsed_ms / 1_000_000;
+                ENERGY_MJ.lock(|energy| energy.set(energy.get() + millijoules));
+            }
+            Err(_) => LATEST_MA.store(u32::MAX, Ordering::Relaxed),
+        }
+    }
+}
+
+/// Read the current (mA) and supply voltage (mV)
+async fn read(bus: &mut I2c<'_, I2C1, Async>) -> Result<(u32, u32), i2c::Error> {
+    let mut shunt = [0u8; 2];
+    bus.write_read_async(INA219_ADDRESS, [REG_SHUNT_VOLTAGE], &mut shunt).await?;
+    let mut supply = [0u8; 2];
+    bus.write_read_async(INA219_ADDRESS, [REG_BUS_VOLTAGE], &mut supply).await?;
+
+    // µV / mΩ = mA. Current the wrong way round (wired backwards) reads as 0.
+    let shunt_uv = i16::from_be_bytes(shunt) as i32 * SHUNT_UV_PER_STEP;
+    let milliamps = shunt_uv.max(0) as u32 / INA219_SHUNT_MILLIOHMS;
+    let millivolts = (u16::from_be_bytes(supply) >> 3) as u32 * BUS_MV_PER_STEP;
+    Ok((milliamps, millivolts))
+}
+
+/// Watch the lamp current while the UV is on - returns if it leaves the
+/// LAMP_CURRENT_MIN_MA..=LAMP_CURRENT_MAX_MA window for two readings in a
+/// row, once the relay has had LAMP_CURRENT_SETTLE_MS to close. Call it
+/// when the UV comes on. A missing INA219 is never a fault.
+pub async fn wait_for_fault() -> (LampFault, u32) {
+    Timer::after_millis(LAMP_CURRENT_SETTLE_MS).await;
+    let mut strikes = 0;
+    loop {
+        let fault = match milliamps() {
+            Some(ma) if ma < LAMP_CURRENT_MIN_MA => Some((LampFault::Open, ma)),
+            Some(ma) if ma > LAMP_CURRENT_MAX_MA => Some((LampFault::Shorted, ma)),
+            _ => None,
+        };
+        match fault {
+            Some(fault) if strikes >= 1 => return fault,
+            Some(_) => strikes += 1,
+            None => strikes = 0,
+        }
+        Timer::after_millis(INA219_SAMPLE_MS).await;
+    }
+}
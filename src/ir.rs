@@ -0,0 +1,80 @@
+// IR Remote Control (ir-remote builds)
+//
+// Start and stop cures with a cheap IR remote, without opening the UV
+// enclosure. Wire a 3-pin 38 kHz IR receiver (TSOP38238, VS1838B...):
+//
+//   GPIO 15 ── OUT
+//   3.3V ── VCC, GND ── GND
+//
+// The receiver pin is watched with edge interrupts: each time it changes,
+// the time since the last change goes to curing-core's NEC decoder
+// (curing-core/src/nec.rs). Decoded keys are looked up in IR_BUTTONS
+// (config.rs) and sent to the main loop as remote commands, just like a
+// start or stop from the USB serial port. Keys that aren't mapped are
+// logged with their codes, so a new remote is easy to set up: press each
+// key, note the code, add it to IR_BUTTONS.
+
+use curing_core::nec::{NecDecoder, NecEvent};
+use defmt::*;
+use embassy_rp::gpio::{Input, Pull};
+use embassy_rp::peripherals::PIN_15;
+use embassy_time::Instant;
+
+use crate::buzzer::{self, AudioEvent};
+use crate::config::*;
+use crate::remote::{self, CureStatus, RemoteCommand};
+
+/// Background task: decode the receiver and act on the keys
+#[embassy_executor::task]
+pub async fn ir_task(pin: PIN_15) {
+    // The receiver idles HIGH; the pull-up keeps it there if it's unplugged
+    let mut receiver = Input::new(pin, Pull::Up);
+    let mut decoder = NecDecoder::new();
+    let mut last_change = Instant::now();
+    info!("IR receiver ready on GPIO 15");
+
+    loop {
+        receiver.wait_for_any_edge().await;
+        let now = Instant::now();
+        let us = (now - last_change).as_micros().min(u32::MAX as u64) as u32;
+        last_change = now;
+
+        // The pin has just changed, so until now it was at the other level
+        let was_low = receiver.is_high();
+        if let Some(NecEvent::Key { address, command }) = decoder.level_ended(was_low, us) {
+            key_pressed(address, command);
+        }
+        // Repeats (key held down) are ignored - one press, one command
+    }
+}
+
+/// Act on one key press
+fn key_pressed(address: u16, command: u8) {
+    if IR_ADDRESS.is_some_and(|wanted| wanted != address) {
+        return;                       // Another remote (the TV's?)
+    }
+    let Some(&(_, action)) = IR_BUTTONS.iter().find(|(code, _)| *code == command) else {
+        info!("IR key 0x{:02x} (remote address 0x{:04x}) isn't mapped - see IR_BUTTONS in config.rs", command, address);
+        return;
+    };
+
+    // Same rules as a start or stop over USB serial
+    let status = remote::status();
+    let command = match action {
+        IrAction::Start | IrAction::Cure(_) if matches!(START_INPUT, StartInput::Latching) => {
+            warn!("IR start ignored - the latching start switch is in charge");
+            return;
+        }
+        IrAction::Start | IrAction::Cure(_) if !matches!(status, CureStatus::Idle) => {
+            info!("IR start ignored - already {}", status.name());
+            return;
+        }
+        IrAction::Stop if matches!(status, CureStatus::Idle | CureStatus::Cooldown | CureStatus::Standby) => return,
+        IrAction::Start => RemoteCommand::Start(None),
+        IrAction::Cure(seconds) => RemoteCommand::Start(Some(seconds)),
+        IrAction::Stop => RemoteCommand::Stop,
+    };
+    if remote::send(command) {
+        buzzer::play(AudioEvent::Chirp);   // "Got it"
+    }
+}
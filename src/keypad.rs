@@ -0,0 +1,83 @@
+// 4x4 Matrix Keypad Driver (keypad builds)
+//
+// Type the curing time in seconds on a membrane keypad instead of cycling
+// presets: 1, 8, 0, # starts a 3 minute cure. What the keys mean is in
+// curing-core (curing-core/src/keypad.rs); this file just finds out which
+// key is down.
+//
+// The keypad's 8 wires are 4 rows and 4 columns. Pressing a key connects
+// its row to its column:
+//
+//   rows    1-4 ── GPIO 2, 3, 4, 5     (outputs)
+//   columns 1-4 ── GPIO 16, 17, 18, 19 (inputs with pull-ups)
+//
+// Every KEYPAD_SCAN_MS the rows are pulled LOW one at a time; a column that
+// reads LOW while row R is low means the key at (R, column) is pressed.
+// A key has to be seen on two scans in a row before it counts, which takes
+// care of contact bounce. Only one key at a time is read - press two and
+// the first one found wins.
+//
+// If the keys come out wrong (e.g. 1 gives A), the ribbon is plugged in
+// the other way round: flip the connector or swap the pin lists in main.rs.
+
+use curing_core::keypad::KEYS;
+use defmt::*;
+use embassy_rp::gpio::{AnyPin, Input, Output};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::Channel;
+use embassy_time::{block_for, Duration, Timer};
+
+use crate::config::*;
+use crate::remote::{self, CureStatus};
+
+/// Key presses not yet collected (more than this and new ones are dropped)
+static PRESSES: Channel<CriticalSectionRawMutex, char, 8> = Channel::new();
+
+/// Background task: scan the keypad and queue each key press
+#[embassy_executor::task]
+pub async fn keypad_task(mut rows: [Output<'static, AnyPin>; 4], columns: [Input<'static, AnyPin>; 4]) {
+    // All rows idle HIGH - nothing reads as pressed
+    for row in rows.iter_mut() {
+        row.set_high();
+    }
+    info!("Keypad ready - type a time in seconds and press # to start");
+
+    let mut last_seen = None;
+    let mut held = None;
+    loop {
+        Timer::after_millis(KEYPAD_SCAN_MS).await;
+        let seen = scan(&mut rows, &columns);
+
+        // Same key on two scans running = it's really down (or really up)
+        if seen == last_seen && seen != held {
+            held = seen;
+            // Keys are only for setting up a cure - typing during one does nothing
+            if let (Some(key), CureStatus::Idle) = (held, remote::status()) {
+                if PRESSES.try_send(key).is_err() {
+                    warn!("Keypad: too many keys waiting - '{}' dropped", key);
+                }
+            }
+        }
+        last_seen = seen;
+    }
+}
+
+/// Which key is down right now, if any
+fn scan(rows: &mut [Output<'static, AnyPin>; 4], columns: &[Input<'static, AnyPin>; 4]) -> Option<char> {
+    for (r, row) in rows.iter_mut().enumerate() {
+        row.set_low();
+        // Give the column lines a moment to follow (keypad wires have some capacitance)
+        block_for(Duration::from_micros(10));
+        let column = columns.iter().position(|column| column.is_low());
+        row.set_high();
+        if let Some(c) = column {
+            return Some(KEYS[r][c]);
+        }
+    }
+    None
+}
+
+/// Wait for the next key press
+pub async fn wait_for_key() -> char {
+    PRESSES.receive().await
+}
@@ -0,0 +1,148 @@
+// Lid Interlock (lid-interlock builds)
+//
+// A microswitch that is pressed closed by the chamber lid, wired between
+// the lid GPIO and GND with the internal pull-up on:
+// - lid shut   = switch closed = LOW
+// - lid open   = switch open   = HIGH
+// A broken wire also reads HIGH, so it counts as "lid open" - the safe way
+// round.
+//
+// The lid is passed around as an `Option`: `None` in builds without the
+// interlock, where the lid always counts as closed.
+//
+// Maintenance bypass: a technician aligning the UV panel needs it on with
+// the lid off. Rather than jumpering the switch (and forgetting to take the
+// jumper out), they power on with the lid open and the button held, and
+// keep holding for LID_BYPASS_HOLD_MS (main.rs). `start_bypass()` then has
+// the lid count as closed for LID_BYPASS_SECONDS, with a warning beep and
+// LED flashes every second for as long as it lasts. It's in RAM only - a
+// power cycle ends it - and when it runs out mid-cure with the lid still
+// open, the cure pauses as usual.
+
+use core::future::pending;
+
+#[cfg(feature = "lid-interlock")]
+use core::cell::Cell;
+
+use defmt::*;
+#[cfg(feature = "lid-interlock")]
+use embassy_futures::select::{select, Either};
+use embassy_rp::gpio::{AnyPin, Input};
+#[cfg(feature = "lid-interlock")]
+use embassy_sync::blocking_mutex::{raw::CriticalSectionRawMutex, Mutex};
+#[cfg(feature = "lid-interlock")]
+use embassy_sync::signal::Signal;
+#[cfg(feature = "lid-interlock")]
+use embassy_time::{Duration, Instant};
+use embassy_time::Timer;
+
+#[cfg(feature = "lid-interlock")]
+use crate::button;
+use crate::buzzer::{self, AudioEvent};
+use crate::config::*;
+#[cfg(feature = "lid-interlock")]
+use crate::events::{self, Event};
+#[cfg(feature = "lid-interlock")]
+use crate::status_led::{self, Led};
+
+/// When the maintenance bypass runs out (None = not bypassed)
+#[cfg(feature = "lid-interlock")]
+static BYPASS_UNTIL: Mutex<CriticalSectionRawMutex, Cell<Option<Instant>>> = Mutex::new(Cell::new(None));
+
+/// Signalled when the bypass starts, for the warning task
+#[cfg(feature = "lid-interlock")]
+static BYPASS_STARTED: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Lid switch input, or None when there is no interlock
+pub type LidSwitch<'d> = Option<Input<'d, AnyPin>>;
+
+/// Is the lid open right now? (Never while it's bypassed)
+pub fn is_open(lid: &LidSwitch<'_>) -> bool {
+    bypass_until().is_none() && lid.as_ref().map_or(false, |switch| switch.is_high())
+}
+
+/// Wait until the lid is opened (never returns without an interlock)
+pub async fn wait_for_open(lid: &mut LidSwitch<'_>) {
+    let Some(switch) = lid else {
+        return pending().await;
+    };
+    loop {
+        // Bypassed: nothing to watch until it runs out
+        if let Some(until) = bypass_until() {
+            Timer::at(until).await;
+            continue;
+        }
+        switch.wait_for_high().await;
+        // Ignore a bounce, but only briefly - the UV has to go off quickly
+        Timer::after_millis(LID_DEBOUNCE_MS).await;
+        if switch.is_high() {
+            return;
+        }
+    }
+}
+
+/// Sound warning beeps until the lid is closed again
+pub async fn wait_for_close(lid: &mut LidSwitch<'_>) {
+    let Some(switch) = lid else {
+        return;
+    };
+
+    buzzer::play(AudioEvent::LidWarning);
+    loop {
+        switch.wait_for_low().await;
+        Timer::after_millis(LID_DEBOUNCE_MS).await;
+        if switch.is_low() {
+            break;
+        }
+    }
+    buzzer::stop();
+    info!("Lid closed");
+}
+
+/// The button is held at power-on with the lid open: if it's held for
+/// LID_BYPASS_HOLD_MS, bypass the lid
+#[cfg(feature = "lid-interlock")]
+pub async fn bypass_gesture() {
+    info!("Button held with the lid open at power-on - keep holding for {} ms to bypass the lid interlock", LID_BYPASS_HOLD_MS);
+    if let Either::First(_) = select(Timer::after_millis(LID_BYPASS_HOLD_MS), button::wait_for_release()).await {
+        start_bypass();
+    }
+}
+
+/// (No lid switch - nothing to bypass)
+#[cfg(not(feature = "lid-interlock"))]
+pub async fn bypass_gesture() {}
+
+/// Ignore the lid for LID_BYPASS_SECONDS, warning all the while
+#[cfg(feature = "lid-interlock")]
+fn start_bypass() {
+    let until = Instant::now() + Duration::from_secs(LID_BYPASS_SECONDS);
+    BYPASS_UNTIL.lock(|bypass| bypass.set(Some(until)));
+    BYPASS_STARTED.signal(());
+    events::record(Event::LidBypassed { seconds: LID_BYPASS_SECONDS });
+}
+
+/// When the bypass runs out, while it's on
+#[cfg(feature = "lid-interlock")]
+fn bypass_until() -> Option<Instant> {
+    BYPASS_UNTIL.lock(|bypass| bypass.get()).filter(|until| *until > Instant::now())
+}
+
+/// (No lid switch - nothing to bypass)
+#[cfg(not(feature = "lid-interlock"))]
+fn bypass_until() -> Option<embassy_time::Instant> {
+    None
+}
+
+/// Background task: beep and flash every second while the lid is bypassed
+#[cfg(feature = "lid-interlock")]
+#[embassy_executor::task]
+pub async fn bypass_warning_task() {
+    BYPASS_STARTED.wait().await;
+    while bypass_until().is_some() {
+        buzzer::play_count(AudioEvent::LidOpen, 1);
+        status_led::show(Led::Flashes { count: 2, on_ms: 60, off_ms: 60 });
+        Timer::after_secs(1).await;
+    }
+    events::record(Event::LidBypassEnded);
+}
@@ -0,0 +1,52 @@
+// Executor Load Monitor
+//
+// Embassy's executor is cooperative: a task only gives the CPU back when it
+// hits an `.await`. If some task does a lot of work without awaiting (slow
+// display updates, network parsing...), every other task - including the one
+// that switches the relay off - has to wait for it.
+//
+// This monitor measures that directly. It asks to be woken up every few
+// milliseconds and checks how late it actually ran. Being woken late means
+// the executor was busy with someone else; the total lateness over a window
+// gives a rough busy/idle percentage.
+
+use defmt::*;
+use embassy_time::{Duration, Instant, Timer};
+
+use crate::config::*;
+
+/// Background task: measure executor wake-up latency and load
+#[embassy_executor::task]
+pub async fn executor_monitor_task() {
+    let tick = Duration::from_millis(LOAD_MONITOR_TICK_MS);
+    let report_interval = Duration::from_secs(LOAD_REPORT_INTERVAL_SECONDS);
+
+    let mut window_start = Instant::now();
+    let mut busy_us: u64 = 0;      // Total lateness in this report window
+    let mut worst_us: u64 = 0;     // Single worst wake-up delay in this window
+
+    loop {
+        let expected = Instant::now() + tick;
+        Timer::at(expected).await;
+        let late_us = Instant::now().saturating_duration_since(expected).as_micros();
+
+        busy_us += late_us;
+        worst_us = worst_us.max(late_us);
+
+        // One big delay is worth shouting about straight away - it means
+        // relay timing could have been held up by the same amount
+        if late_us >= EXECUTOR_STALL_WARN_MS * 1000 {
+            warn!("Executor stalled for {} ms - a task is running too long without awaiting", late_us / 1000);
+        }
+
+        let window = Instant::now().saturating_duration_since(window_start);
+        if window >= report_interval {
+            let busy_percent = busy_us * 100 / window.as_micros().max(1);
+            debug!("Executor load ~{}% (worst wake-up delay {} us)", busy_percent, worst_us);
+
+            window_start = Instant::now();
+            busy_us = 0;
+            worst_us = 0;
+        }
+    }
+}
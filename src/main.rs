@@ -6,107 +6,1940 @@
 // 'use' statements are like 'import' in Python or '#include' in C++
 use defmt::*;  // Import logging/debugging functions (like println! but for embedded)
 use embassy_executor::Spawner;  // Embassy's async task spawner
-use embassy_rp::gpio::{Flex, Input, Level, Output, Pin, Pull};  // GPIO pin types and functions
-use embassy_time::{Duration, Timer};  // Time-related functions for delays
-use {defmt_rtt as _, panic_probe as _};  // Debugging tools for development
+use embassy_futures::select::{select, select3, select4, Either, Either3, Either4};  // Wait for whichever thing happens first
+use embassy_rp::gpio::{AnyPin, Input, Level, Output, Pin, Pull};  // GPIO pin types and functions
+use embassy_time::{Duration, Instant, Timer};  // Time-related functions for delays
+#[cfg(not(feature = "usb-log"))]
+use defmt_rtt as _;  // Sends the logs to the debug probe (usb-log builds send them over USB instead)
 
 // Import our configuration module - all timing settings are in config.rs
 mod config;
 use config::*;
 
+// Panic handler: switches the UV, buzzer and heater off before halting
+mod panic;
+
+// Stack usage monitor (debug logging of how much stack we've used)
+mod stack;
+
+// Executor load monitor (warns if a task hogs the CPU)
+mod load;
+
+// Cure sequencing logic with no hardware in it: the cure state machine,
+// gestures, phase timing, UV panel heat estimate, presets (the curing-core
+// library, see curing-core/)
+use curing_core::checkpoint::Checkpoint;
+use curing_core::cure::PhaseEnd;
+use curing_core::cycle::{CureEvent, CureState, Pause, Plan};
+use curing_core::fault::FaultCode;
+// ...and the generic relay, button, buzzer and LED drivers: main.rs hands
+// them the RP2040 pins, another board would hand them its own
+#[cfg(not(feature = "passive-buzzer"))]
+use curing_core::drivers::ActiveBuzzer;
+use curing_core::drivers::{self, Button, PinState};
+use curing_core::heat::{HeatEstimate, HeatModel};
+#[cfg(feature = "multi-duration")]
+use curing_core::presets::PresetSelector;
+#[cfg(feature = "keypad")]
+use curing_core::keypad::{DurationEntry, EntryUpdate};
+
+// UV LED output drivers - relay, SSR or PWM MOSFET, picked with a Cargo feature -
+// and the relay task that owns the one built in
+mod uv_output;
+
+// Button task: gestures (tap / hold) with debouncing
+mod button;
+#[cfg(feature = "multi-duration")]
+use button::Control;
+use button::Gesture;
+
+// Buzzer task and driver (active buzzer, or passive piezo tones with --features passive-buzzer)
+mod buzzer;
+use buzzer::{AudioEvent, Buzzer};
+
+// Status LED task: on, off, blinking and flashes
+mod status_led;
+use status_led::Led;
+
+// Lid interlock switch (only wired up with --features lid-interlock)
+mod lid;
+use lid::LidSwitch;
+
+// Settings that can be changed while running (e.g. button debounce over USB)
+mod settings;
+
+// On-device settings menu: cure time, beeps and cooldown with just the button
+mod menu;
+
+// Fault handler: UV off, then the fault code blinking until it's cleared
+mod fault;
+
+// Event log: cure steps and errors, kept in RAM and printed by verbosity
+mod events;
+use events::{Event, Source};
+
+// Start/stop/status link between the main loop and remote interfaces
+mod remote;
+use remote::CureStatus;
+
+// Text and JSON remote commands (only with --features usb-serial, pico-w or uart-control)
+#[cfg(any(feature = "usb-serial", feature = "pico-w", feature = "uart-control"))]
+mod commands;
+
+// USB serial port that accepts text commands (only with --features usb-serial)
+#[cfg(feature = "usb-serial")]
+mod usb_serial;
+
+// The defmt log on a second USB serial port, for builds without a debug probe (only with --features usb-log)
+#[cfg(feature = "usb-log")]
+mod usb_log;
+
+// Pin read/set/pulse commands for checking wiring (only with --features gpio-diagnostics)
+#[cfg(feature = "gpio-diagnostics")]
+mod gpio_diag;
+
+// Framed, checksummed commands from a host on UART0 (only with --features uart-control)
+#[cfg(feature = "uart-control")]
+mod uart_control;
+
+// Pico W Wi-Fi, the web interface it serves and mDNS (only with --features pico-w)
+#[cfg(feature = "pico-w")]
+mod wifi;
+#[cfg(feature = "pico-w")]
+mod web;
+#[cfg(feature = "pico-w")]
+mod mdns;
+
+// MQTT client with Home Assistant discovery (only with --features mqtt, Pico W)
+#[cfg(feature = "mqtt")]
+mod mqtt;
+
+// Firmware updates over Wi-Fi/USB with rollback (only with --features ota, Pico W)
+#[cfg(feature = "ota")]
+mod ota;
+
+// Settings saved in flash so they survive a power cycle
+mod storage;
+
+// Wall-clock time (date and time of day) for the rest of the firmware
+mod clock;
+
+// DS3231 real-time clock that keeps the time through power cuts (only with --features rtc-ds3231)
+#[cfg(feature = "rtc-ds3231")]
+mod ds3231;
+
+// CSV log of every cure on a microSD card (only with --features sd-log)
+#[cfg(feature = "sd-log")]
+mod sd_log;
+
+// Bare touch pad as the start button (only with --features touch-pad)
+#[cfg(feature = "touch-pad")]
+mod touch;
+
+// IR remote receiver (only with --features ir-remote)
+#[cfg(feature = "ir-remote")]
+mod ir;
+
+// Status display - TM1637 4-digit or HD44780 16x2 LCD (only with --features tm1637 or hd44780)
+#[cfg(feature = "tm1637")]
+mod tm1637;
+#[cfg(feature = "hd44780")]
+mod hd44780;
+#[cfg(any(feature = "tm1637", feature = "hd44780"))]
+mod display;
+
+// ST7789 colour screen with countdown and progress bar (only with --features st7789)
+#[cfg(feature = "st7789")]
+mod tft;
+
+// WS2812 ring that fills as the cure runs (only with --features neopixel)
+#[cfg(feature = "neopixel")]
+mod neopixel;
+
+// Lifetime cure counters (completed, aborted, UV time), saved with the settings
+mod stats;
+
+// Rotary encoder for dialling in the curing time (only with --features rotary-encoder)
+#[cfg(feature = "rotary-encoder")]
+mod encoder;
+
+// Dormant sleep after a long idle, woken by the button (only with --features dormant)
+#[cfg(feature = "dormant")]
+mod dormant;
+
+// 4x4 keypad for typing in the curing time (only with --features keypad)
+#[cfg(feature = "keypad")]
+mod keypad;
+
+// Turntable that spins during the cure (only with --features turntable or dc-turntable)
+#[cfg(any(feature = "turntable", feature = "dc-turntable"))]
+mod turntable;
+
+// The one ADC, shared by the analog sensors (thermistor, battery and uv-sensor builds)
+#[cfg(any(feature = "thermistor", feature = "battery", feature = "uv-sensor"))]
+mod analog;
+
+// UV intensity sensor that catches a lamp that isn't lighting (only with --features uv-sensor)
+#[cfg(feature = "uv-sensor")]
+mod uv_sensor;
+
+// UV lamp current and energy from an INA219 (only with --features ina219)
+#[cfg(feature = "ina219")]
+mod ina219;
+
+// Battery voltage monitoring and low-battery lockout (only with --features battery)
+#[cfg(feature = "battery")]
+mod battery;
+
+// Chamber over-temperature protection with an NTC thermistor (only with --features thermistor)
+#[cfg(feature = "thermistor")]
+mod thermal;
+
+// Chamber temperature/humidity from a BME280 sensor (only with --features bme280)
+#[cfg(feature = "bme280")]
+mod climate;
+
+// Chamber heater with a preheat phase before the UV (only with --features heater)
+#[cfg(feature = "heater")]
+mod heater;
+
+// Chamber temperature from whichever sensor is built in
+#[cfg(any(feature = "thermistor", feature = "bme280"))]
+mod sensors;
+
+// Temperature-controlled cooling fan (only with --features fan)
+#[cfg(feature = "fan")]
+mod thermostat;
+
+#[cfg(all(feature = "multi-duration", feature = "rotary-encoder"))]
+compile_error!("Pick one of the multi-duration and rotary-encoder features, not both");
+
+#[cfg(all(feature = "keypad", any(feature = "multi-duration", feature = "rotary-encoder")))]
+compile_error!("The keypad sets the curing time itself - it can't be combined with multi-duration or rotary-encoder");
+
+#[cfg(all(feature = "keypad", any(feature = "turntable", feature = "dc-turntable")))]
+compile_error!("The keypad rows use GPIO 4 and 5, which drive the turntable - pick one");
+
+#[cfg(all(feature = "keypad", any(feature = "sd-log", feature = "st7789")))]
+compile_error!("The keypad columns use GPIO 16-19 (SPI0) - it can't be combined with sd-log or st7789");
+
+#[cfg(all(feature = "select-button", not(feature = "multi-duration")))]
+compile_error!("The select-button feature picks presets - enable multi-duration too");
+
+#[cfg(all(feature = "dormant", any(feature = "usb-serial", feature = "pico-w")))]
+compile_error!("The dormant feature stops all clocks - USB and the Pico W's Wi-Fi don't survive it");
+
+#[cfg(all(feature = "dormant", feature = "touch-pad"))]
+compile_error!("A bare touch pad is sensed by the chip itself, so it can't wake it from dormant - use a button or a ttp223");
+
+#[cfg(all(feature = "uv-sensor", feature = "rtc-ds3231"))]
+compile_error!("The uv-sensor and the rtc-ds3231 clock both use GPIO 27 - pick one");
+
+#[cfg(all(feature = "ina219", any(feature = "rtc-ds3231", feature = "uv-sensor")))]
+compile_error!("The ina219 uses I2C1 on GPIO 22/27 - it can't be combined with rtc-ds3231 or uv-sensor");
+
+#[cfg(all(feature = "relay-feedback", any(feature = "rotary-encoder", feature = "keypad")))]
+compile_error!("The relay feedback contact uses GPIO 3, which the rotary-encoder and keypad need - pick one");
+
+#[cfg(all(feature = "relay-feedback", any(feature = "pwm-mosfet", feature = "relay-ssr")))]
+compile_error!("The relay-feedback feature needs a relay with contacts - not pwm-mosfet or relay-ssr");
+
+#[cfg(all(feature = "battery", feature = "pico-w"))]
+compile_error!("On a Pico W, GPIO 29 (VSYS/3) is shared with the Wi-Fi chip - battery monitoring isn't supported there");
+
+#[cfg(all(feature = "ttp223", feature = "touch-pad"))]
+compile_error!("Pick one touch start button: ttp223 (module) or touch-pad (bare pad), not both");
+
+#[cfg(all(feature = "ir-remote", feature = "pico-w"))]
+compile_error!("The IR receiver uses GPIO 15, which is the status LED on a Pico W");
+
+#[cfg(all(feature = "turntable", feature = "dc-turntable"))]
+compile_error!("Pick one of the turntable (stepper) and dc-turntable features, not both");
+
+#[cfg(all(feature = "hd44780", feature = "bme280"))]
+compile_error!("The hd44780 LCD and the bme280 sensor both need I2C0 - pick one");
+
+#[cfg(all(feature = "st7789", feature = "sd-log"))]
+compile_error!("The st7789 screen and the sd-log card both need SPI0 - pick one");
+
+#[cfg(all(feature = "st7789", any(feature = "tm1637", feature = "hd44780")))]
+compile_error!("The st7789 screen uses GPIO 21 and 28 - it can't be combined with the tm1637 or hd44780 display");
+
+#[cfg(all(feature = "fan", not(any(feature = "thermistor", feature = "bme280"))))]
+compile_error!("The fan feature needs a temperature sensor - enable thermistor or bme280 too");
+
 // This attribute marks our main function for Embassy's async executor
 // Embassy is an async framework for embedded Rust - it handles timing and concurrency
 #[embassy_executor::main]
-async fn main(_spawner: Spawner) {
+async fn main(spawner: Spawner) {
+    // Fill unused stack memory with a marker pattern first thing, so the
+    // stack monitor can later tell how deep the stack has ever grown
+    stack::paint();
+    
     // Initialize the RP2040 hardware with default settings
     // 'let' creates a new variable, 'p' contains all the GPIO pins
     let p = embassy_rp::init(Default::default());
     
     // 'info!' is like println! but optimized for embedded systems
     info!("UV Resin Curing Timer Starting!");
+    
+    // Start background tasks - these run alongside the cure supervisor
+    unwrap!(spawner.spawn(stack::stack_monitor_task()));
+    unwrap!(spawner.spawn(load::executor_monitor_task()));
+
+    // ota builds start from the bootloader, which leaves its watchdog
+    // running - keep feeding it, or the chip resets (and rolls back)
+    #[cfg(feature = "ota")]
+    unwrap!(spawner.spawn(ota::watchdog_task(p.WATCHDOG)));
 
     /* GPIO PIN SETUP SECTION */
     // Create GPIO pins for our hardware connections
     // 'mut' means the variable can be modified (mutable)
     
     // Button input with internal pull-up resistor (pressed = LOW, released = HIGH)
-    let mut button = Input::new(p.PIN_6, Pull::Up);
+    // Can also be a latching/keyed switch wired the same way (ON = LOW) - see START_INPUT
+    // The button task turns it into gestures (button.rs)
+    #[cfg(not(any(feature = "ttp223", feature = "touch-pad")))]
+    button::init(&spawner, Button::new(Input::new(p.PIN_6.degrade(), Pull::Up), PinState::Low));
+    // TTP223 touch module: drives its OUT pin HIGH while touched (or toggles it, TOG jumper set)
+    #[cfg(feature = "ttp223")]
+    button::init(&spawner, Button::new(Input::new(p.PIN_6.degrade(), Pull::Down), PinState::High));
+    // Bare touch pad: HIGH while touched - or toggled by each touch with a latching start input
+    #[cfg(feature = "touch-pad")]
+    button::init(&spawner, Button::new(
+        touch::TouchPad::new(p.PIN_6.degrade(), matches!(START_INPUT, StartInput::Latching)),
+        PinState::High,
+    ));
+    // Second button for picking the preset, wired like the start button (select-button builds only)
+    #[cfg(feature = "select-button")]
+    button::init_select(&spawner, Button::new(Input::new(p.PIN_2.degrade(), Pull::Up), PinState::Low));
     
-    // Buzzer output pin (starts LOW = off)
-    let mut buzzer = Output::new(p.PIN_7, Level::Low);
+    // Buzzer on GPIO 7 (starts silent) - the buzzer task plays queued sounds
+    #[cfg(not(feature = "passive-buzzer"))]
+    let buzzer = Buzzer::new(ActiveBuzzer::new(Output::new(p.PIN_7.degrade(), Level::Low)));
+    #[cfg(feature = "passive-buzzer")]
+    let buzzer = Buzzer::new(buzzer::PwmPiezo::new(p.PWM_CH3, p.PIN_7));
+    unwrap!(spawner.spawn(buzzer::buzzer_task(buzzer)));
     
-    // Onboard LED for status indication (starts LOW = off)
-    let mut status_led = Output::new(p.PIN_25, Level::Low);
+    // Onboard LED for status indication, on PWM so it can breathe (starts off) - driven by its own task
+    #[cfg(not(feature = "pico-w"))]
+    let status_led = drivers::DimmableLed::new(status_led::LedPwm::new(p.PWM_CH4, p.PIN_25));
+    // On a Pico W, GPIO 25 talks to the Wi-Fi chip - fit an LED on GPIO 15 instead
+    #[cfg(feature = "pico-w")]
+    let status_led = drivers::DimmableLed::new(status_led::LedPwm::new(p.PWM_CH7, p.PIN_15));
+    unwrap!(spawner.spawn(status_led::status_led_task(status_led)));
+    
+    // White inspection light relay/MOSFET (starts LOW = off)
+    // Lets you look at parts in the chamber without opening it up
+    let inspection_light = Output::new(p.PIN_11.degrade(), Level::Low);
+    
+    // Lid interlock switch (lid-interlock builds only) - closed by the lid = LOW
+    // To use a different GPIO, change PIN_13 here (and in the README wiring)
+    #[cfg(feature = "lid-interlock")]
+    let lid: LidSwitch = Some(Input::new(p.PIN_13.degrade(), Pull::Up));
+    // Beeps and flashes while the lid is bypassed for maintenance (see lid.rs)
+    #[cfg(feature = "lid-interlock")]
+    unwrap!(spawner.spawn(lid::bypass_warning_task()));
+    #[cfg(not(feature = "lid-interlock"))]
+    let lid: LidSwitch = None;
+    
+    // Rotary encoder A/B pins (rotary-encoder builds only)
+    // Its push switch is wired to GPIO 6 in place of the start button
+    #[cfg(feature = "rotary-encoder")]
+    let encoder = encoder::RotaryEncoder::new(
+        Input::new(p.PIN_2.degrade(), Pull::Up),
+        Input::new(p.PIN_3.degrade(), Pull::Up),
+    );
+
+    // Keypad rows on GPIO 2-5, columns on GPIO 16-19 (keypad builds only)
+    #[cfg(feature = "keypad")]
+    unwrap!(spawner.spawn(keypad::keypad_task(
+        [p.PIN_2.degrade(), p.PIN_3.degrade(), p.PIN_4.degrade(), p.PIN_5.degrade()]
+            .map(|pin| Output::new(pin, Level::High)),
+        [p.PIN_16.degrade(), p.PIN_17.degrade(), p.PIN_18.degrade(), p.PIN_19.degrade()]
+            .map(|pin| Input::new(pin, Pull::Up)),
+    )));
+
+    // Stepper driver STEP/DIR pins (turntable builds only)
+    // The turntable gets its own task so step pulses keep coming during the cure
+    #[cfg(feature = "turntable")]
+    unwrap!(spawner.spawn(turntable::turntable_task(
+        Output::new(p.PIN_4.degrade(), Level::Low),
+        Output::new(p.PIN_5.degrade(), Level::Low),
+    )));
+
+    // DC motor MOSFET on GPIO 4 (PWM slice 2) - dc-turntable builds only
+    #[cfg(feature = "dc-turntable")]
+    unwrap!(spawner.spawn(turntable::turntable_task(p.PWM_CH2, p.PIN_4)));
+
+    // The ADC is shared by the thermistor, battery and UV sensor tasks (analog.rs)
+    #[cfg(any(feature = "thermistor", feature = "battery", feature = "uv-sensor"))]
+    analog::init(p.ADC);
+
+    // NTC thermistor on GPIO 26 (ADC0) - thermistor builds only
+    #[cfg(feature = "thermistor")]
+    unwrap!(spawner.spawn(thermal::thermal_monitor_task(p.PIN_26)));
+
+    // Battery voltage on GPIO 29 (ADC3, VSYS/3 on a Pico) - battery builds only
+    #[cfg(feature = "battery")]
+    unwrap!(spawner.spawn(battery::battery_monitor_task(p.PIN_29)));
+
+    // GUVA-S12SD UV sensor on GPIO 27 (ADC1) - uv-sensor builds only
+    #[cfg(feature = "uv-sensor")]
+    unwrap!(spawner.spawn(uv_sensor::uv_sensor_task(p.PIN_27)));
+
+    // BME280 climate sensor on I2C0: GPIO 8 (SDA) / GPIO 9 (SCL) - bme280 builds only
+    #[cfg(feature = "bme280")]
+    unwrap!(spawner.spawn(climate::climate_monitor_task(p.I2C0, p.PIN_8, p.PIN_9)));
+
+    // DS3231 real-time clock on I2C1: GPIO 22 (SDA) / GPIO 27 (SCL) - rtc-ds3231 builds only
+    #[cfg(feature = "rtc-ds3231")]
+    unwrap!(spawner.spawn(ds3231::rtc_task(p.I2C1, p.PIN_22, p.PIN_27)));
+
+    // INA219 lamp current sensor on I2C1: GPIO 22 (SDA) / GPIO 27 (SCL) - ina219 builds only
+    #[cfg(feature = "ina219")]
+    unwrap!(spawner.spawn(ina219::lamp_current_task(p.I2C1, p.PIN_22, p.PIN_27)));
+
+    // microSD card on SPI0: GPIO 16 (MISO) / 17 (CS) / 18 (SCK) / 19 (MOSI) - sd-log builds only
+    #[cfg(feature = "sd-log")]
+    unwrap!(spawner.spawn(sd_log::sd_log_task(p.SPI0, p.PIN_18, p.PIN_19, p.PIN_16, p.PIN_17)));
+
+    // IR receiver on GPIO 15 - ir-remote builds only
+    #[cfg(feature = "ir-remote")]
+    unwrap!(spawner.spawn(ir::ir_task(p.PIN_15)));
+
+    // TM1637 display on GPIO 21 (CLK) / GPIO 28 (DIO) - tm1637 builds only
+    #[cfg(feature = "tm1637")]
+    unwrap!(spawner.spawn(display::display_task(tm1637::Tm1637::new(
+        p.PIN_21.degrade(),
+        p.PIN_28.degrade(),
+        TM1637_BRIGHTNESS,
+    ))));
+
+    // HD44780 LCD on I2C0: GPIO 28 (SDA) / GPIO 21 (SCL) - hd44780 builds only
+    #[cfg(feature = "hd44780")]
+    unwrap!(spawner.spawn(display::display_task(hd44780::Hd44780::new(p.I2C0, p.PIN_21, p.PIN_28, LCD_I2C_ADDRESS))));
+
+    // ST7789 screen on SPI0: GPIO 18 (SCK) / 19 (MOSI) / 17 (CS) / 16 (DC) / 21 (RST) / 28 (backlight) - st7789 builds only
+    #[cfg(feature = "st7789")]
+    unwrap!(spawner.spawn(tft::tft_task(p.SPI0, p.PIN_18, p.PIN_19, p.PIN_17, p.PIN_16, p.PIN_21, p.PIN_28, p.DMA_CH1)));
+
+    // WS2812 progress ring on GPIO 20, driven by PIO1 - neopixel builds only
+    #[cfg(feature = "neopixel")]
+    unwrap!(spawner.spawn(neopixel::neopixel_task(p.PIO1, p.PIN_20)));
+
+    // Cooling fan MOSFET/relay on GPIO 12 (starts LOW = off) - fan builds only
+    #[cfg(feature = "fan")]
+    unwrap!(spawner.spawn(thermostat::fan_task(Output::new(p.PIN_12.degrade(), Level::Low))));
+
+    // USB serial command port on the Pico's own USB socket - usb-serial builds only
+    #[cfg(feature = "usb-serial")]
+    usb_serial::init(&spawner, p.USB);
+    #[cfg(feature = "gpio-diagnostics")]
+    unwrap!(spawner.spawn(gpio_diag::gpio_pulse_task()));
+
+    // Control link to a host computer on GPIO 0 (TX) / GPIO 1 (RX) - uart-control builds only
+    #[cfg(feature = "uart-control")]
+    unwrap!(spawner.spawn(uart_control::uart_control_task(p.UART0, p.PIN_0, p.PIN_1)));
 
-    info!("System ready - press button to start {}-second curing cycle", CURING_DURATION_SECONDS);
+    // The flash chip holds saved settings, and new firmware in ota builds
+    let flash = storage::share_flash(p.FLASH);
+    #[cfg(feature = "ota")]
+    ota::init(flash);
+    // Read now, so Wi-Fi can use a network saved over USB serial (`wifi set`)
+    let storage = storage::Storage::new(flash);
+    #[cfg(feature = "pico-w")]
+    settings::set_wifi_credentials(storage.load().wifi);
+
+    // Pico W Wi-Fi chip (GPIO 23/24/25/29) and web interface - pico-w builds only
+    // Joining the network happens in the background; the button works meanwhile
+    #[cfg(feature = "pico-w")]
+    unwrap!(spawner.spawn(wifi::wifi_task(spawner, p.PIN_23, p.PIN_24, p.PIN_25, p.PIN_29, p.PIO0, p.DMA_CH0)));
+
+    info!("System ready - press button to start a curing cycle");
+    
+    // Chamber heater relay on GPIO 14 (starts LOW = off) - heater builds only
+    #[cfg(feature = "heater")]
+    let heater = heater::Heater::new(p.PIN_14.degrade());
+    
+    /* UV OUTPUT SETUP - CRITICAL FOR PREVENTING INITIAL ACTIVATION */
+    // Every driver makes sure the UV LEDs are definitely OFF before we go on
+    // (see uv_output.rs for the relay "pin kill" trick)
+    #[cfg(not(any(feature = "pwm-mosfet", feature = "relay-active-high", feature = "relay-ssr")))]
+    let uv = uv_output::FlexPinRelay::new(p.PIN_10.degrade()).await;
+    #[cfg(feature = "relay-active-high")]
+    let uv = uv_output::active_high_relay(Output::new(p.PIN_10.degrade(), Level::Low)).await;
+    #[cfg(feature = "relay-ssr")]
+    let uv = uv_output::solid_state_relay(Output::new(p.PIN_10.degrade(), Level::Low)).await;
+    #[cfg(feature = "pwm-mosfet")]
+    let uv = uv_output::PwmMosfet::new(p.PWM_CH5, p.PIN_10).await;
+    // Relay feedback contact on GPIO 3, closed = LOW (relay-feedback builds only)
+    #[cfg(feature = "relay-feedback")]
+    let feedback: uv_output::RelayFeedback = Some(Input::new(p.PIN_3.degrade(), Pull::Up));
+    #[cfg(not(feature = "relay-feedback"))]
+    let feedback: uv_output::RelayFeedback = None;
+    // From here on only the relay task touches it - everything else asks
+    unwrap!(spawner.spawn(uv_output::relay_task(uv, feedback)));
+    
+    /* CURE SUPERVISOR */
+    // The button, buzzer, status LED and UV output each have a task of their
+    // own now. The cure cycle is one more task: it decides what happens and
+    // tells the others over channels, so a beep or a blink never holds up
+    // the cure timing
+    let io = CureIo {
+        inspection_light,
+        lid,
+        #[cfg(feature = "rotary-encoder")]
+        encoder,
+        #[cfg(feature = "heater")]
+        heater,
+        storage,
+    };
+    unwrap!(spawner.spawn(cure_supervisor_task(io)));
+} // End of main function - the tasks carry on without it
+
+/// Everything the cure supervisor drives itself (the button, buzzer, status
+/// LED and UV output belong to their own tasks)
+struct CureIo {
+    inspection_light: Output<'static, AnyPin>,
+    lid: LidSwitch<'static>,
+    #[cfg(feature = "rotary-encoder")]
+    encoder: encoder::RotaryEncoder<'static>,
+    #[cfg(feature = "heater")]
+    heater: heater::Heater<'static>,
+    storage: storage::Storage<'static>,
+}
+
+/// The cure supervisor: power-on checks, then the cure cycle (never returns)
+#[embassy_executor::task]
+async fn cure_supervisor_task(io: CureIo) {
+    let CureIo {
+        mut inspection_light,
+        mut lid,
+        #[cfg(feature = "rotary-encoder")]
+        mut encoder,
+        #[cfg(feature = "heater")]
+        mut heater,
+        mut storage,
+    } = io;
+    
+    // Panel starts cold - this tracks how much UV time it has had recently
+    let heat_model = HeatModel {
+        budget_seconds: HEAT_BUDGET_SECONDS,
+        cooling_ratio: PANEL_COOLING_RATIO,
+        max_extra_cooldown_seconds: MAX_EXTRA_COOLDOWN_SECONDS,
+    };
+    let mut heat = HeatEstimate::new(heat_model, now_ms());
+    
+    // Settings saved in flash (silent mode, selected preset, menu settings) are restored here
+    let mut persisted = storage.load();
+    if let Some(saved) = persisted.menu {
+        settings::set_menu_settings(saved);
+    }
+    if let Some(saved) = persisted.power_on {
+        settings::set_power_on_behavior(saved);
+    }
+    settings::set_api_token(persisted.api_token);
+    info!("Settings: {} s cure, {} completion beeps, {} ms cooldown",
+          settings::cure_seconds(), settings::completion_beeps(), settings::cooldown_ms());
+    
+    // How much the curer has been used so far
+    stats::publish(persisted.stats);
+    info!("Cure stats: {} completed, {} aborted, {} h {} min of UV time",
+          persisted.stats.completed, persisted.stats.aborted,
+          persisted.stats.uv_on_hours(), persisted.stats.uv_on_seconds % 3600 / 60);
+    
+    // Silent mode - switched on and off in the settings menu (or remotely, see commands.rs)
+    buzzer::set_muted(persisted.muted);
+    if persisted.muted {
+        info!("Silent mode ON - turn the buzzer back on in the settings menu (hold the button at power-on)");
+    }
     
-    /* RELAY CONTROL PIN SETUP */
-    // FlexPin can switch between input/output modes - crucial for relay reset
-    // The SRD-05VDC-SL-C relay module needs this special handling
-    let mut flex_pin = Flex::new(p.PIN_10.degrade());
+    /* SETTINGS MENU / LAMP HOURS RESET / LID BYPASS */
+    // Hold the button while powering on:
+    // - let go after the chirp at SETTINGS_MENU_HOLD_MS to open the settings menu (menu.rs)
+    // - fitted new UV LEDs? Keep holding for LAMP_RESET_HOLD_MS to start the lamp hours from zero
+    // - with the lid open instead (lid-interlock builds), keep holding for
+    //   LID_BYPASS_HOLD_MS to have the lid ignored for a while - for a
+    //   technician aligning the UV panel (see lid.rs). Awkward on purpose
+    // (A latching switch that's ON at power-on is just ON - no menu or reset)
+    let held_at_power_on = matches!(START_INPUT, StartInput::Momentary) && button::is_pressed();
+    if held_at_power_on && lid::is_open(&lid) {
+        lid::bypass_gesture().await;
+        button::wait_for_release().await;
+    } else if held_at_power_on {
+        info!("Button held at power-on - let go after {} ms for the settings menu, or keep holding for {} ms to reset the lamp hours",
+              SETTINGS_MENU_HOLD_MS, LAMP_RESET_HOLD_MS);
+        if let Either::First(_) = select(Timer::after_millis(SETTINGS_MENU_HOLD_MS), button::wait_for_release()).await {
+            buzzer::play(AudioEvent::Chirp);   // "Let go now for the menu"
+            match select(Timer::after_millis(LAMP_RESET_HOLD_MS - SETTINGS_MENU_HOLD_MS), button::wait_for_release()).await {
+                Either::First(_) => {
+                    persisted.stats.lamp_seconds = 0;
+                    stats::publish(persisted.stats);
+                    if storage.save(&persisted).is_err() {
+                        events::record(Event::FlashWriteFailed { what: "lamp hours" });
+                    }
+                    events::record(Event::LampReset);
+                    buzzer::play(AudioEvent::Confirm);   // One long beep = done
+                }
+                Either::Second(_) => menu::run(&mut storage, &mut persisted).await,
+            }
+        }
+        button::wait_for_release().await;   // The release isn't a tap
+    }
+    
+    /* CURE COUNT */
+    // Completed cures on the status LED: long flashes = hundreds, short = tens
+    if CURE_COUNT_AT_POWER_ON {
+        blink_cure_count(persisted.stats.completed).await;
+    }
+    
+    // The display starts on the config.rs curing time - show the one in use
+    #[cfg(all(any(feature = "tm1637", feature = "hd44780"), not(feature = "multi-duration")))]
+    display::show_selected(None, settings::cure_seconds());
+    
+    // Lamp due for a check? Say so now, and again after every cure
+    if persisted.stats.lamp_service_due() {
+        lamp_service_alert(persisted.stats.lamp_hours());
+    }
+    
+    // Selected curing preset (multi-duration builds only)
+    #[cfg(feature = "multi-duration")]
+    let mut selected_preset = PresetSelector::with_index(
+        &DURATION_PRESETS,
+        &PRESET_NAMES,
+        persisted.preset_index.map_or(DEFAULT_PRESET_INDEX, usize::from),
+        DEFAULT_PRESET_INDEX,
+    );
+    #[cfg(feature = "multi-duration")]
+    info!("Multi-duration mode - tap to cycle presets, hold to start. Current: {} ({} seconds)",
+          selected_preset.name(), selected_preset.seconds());
+    #[cfg(all(feature = "multi-duration", any(feature = "tm1637", feature = "hd44780")))]
+    display::show_selected(Some(selected_preset.name()), selected_preset.seconds());
+    
+    // Curing time dialled in on the rotary encoder (rotary-encoder builds only)
+    #[cfg(feature = "rotary-encoder")]
+    let mut dial_seconds = settings::cure_seconds();
+    #[cfg(feature = "rotary-encoder")]
+    info!("Rotary encoder mode - turn to set time, push to start. Current: {} seconds", dial_seconds);
     
-    /* STARTUP RELAY RESET - CRITICAL FOR PREVENTING INITIAL ACTIVATION */
-    // When Pico powers on, GPIO pins can be in undefined states
-    // This ensures the relay is definitely OFF at startup
-    info!("Performing startup relay reset to ensure LEDs are OFF...");
-    flex_pin.set_as_input();       // First set to high-impedance (guaranteed OFF)
-    Timer::after_millis(RELAY_SETTLE_TIME_MS).await;  // Wait for relay to settle
-    flex_pin.set_as_output();      // Then set as output for control
-    flex_pin.set_high();           // HIGH = relay open (UV LEDs off)
-    info!("Relay reset complete - LEDs confirmed OFF");
+    // Curing time typed in on the keypad (keypad builds only) - until
+    // something is typed, # or a tap runs the cure time from settings.rs
+    #[cfg(feature = "keypad")]
+    let mut keypad_entry = DurationEntry::new(settings::cure_seconds(), MAX_CURING_DURATION_SECONDS);
+    
+    // Chamber is idle now, so the white light can come on (if enabled in config.rs)
+    if INSPECTION_LIGHT_ENABLED {
+        inspection_light.set_high();
+        info!("Inspection light ON");
+    }
+    
+    /* RESUME AFTER A POWER CUT */
+    // A cure cut off by a power cut left a checkpoint of how far it had
+    // got (see checkpoint.rs in curing-core) - offer to finish it
+    let mut resume = offer_resume(&mut storage, &mut persisted).await;
+    
+    /* POWER-ON BEHAVIOR */
+    // Normally we just sit idle until the button is pressed. If the curer is
+    // switched by an external smart plug, it can instead start a cure by
+    // itself after a short countdown (see POWER_ON_BEHAVIOR in config.rs -
+    // it can also be changed remotely, and saved)
+    let mut auto_start = false;
+    if resume.is_none() && settings::power_on_behavior() == PowerOnBehavior::AutoStart {
+        info!("Auto-start in {} seconds - press button to cancel", AUTO_START_COUNTDOWN_SECONDS);
+        auto_start = true;
+        
+        for remaining in (1..=AUTO_START_COUNTDOWN_SECONDS).rev() {
+            info!("Auto-start in {}...", remaining);
+            status_led::show(Led::Flashes { count: 1, on_ms: 50, off_ms: 0 });
+            buzzer::play(AudioEvent::Countdown);    // Short chirp each second
+            
+            // Wait out the second, unless the button is pressed
+            if let Either::Second(_) = select(Timer::after_secs(1), button::wait_for_press()).await {
+                info!("Auto-start cancelled - staying idle");
+                auto_start = false;
+                // Don't let this press (or its release) start a cure
+                button::wait_for_release().await;
+                break;
+            }
+        }
+    }
     
     /* MAIN PROGRAM LOOP */
+    // The cure cycle is a state machine - see CureState in curing-core/src/cycle.rs:
+    //   Idle -> Arming (lid check, preheat) -> Curing (each phase, pauses) -> Finishing -> Idle
+    // Each time round the loop we wait for whatever can happen in the current
+    // state and turn it into a CureEvent. `transition()` picks the next state,
+    // and `enter_state()` makes the outputs match it.
+    let mut state = CureState::Idle;
+    status_led::show(Led::Breathe);     // "Powered up, waiting for you"
+    
+    // Bookkeeping for the cure under way (statistics and the CureFinished event)
+    let mut uv_on_ms_before = 0;
+    let mut uv_on_ms_resumed = 0;           // UV time from before a power cut
+    let mut heat_pauses = 0;                // Times it paused for the chamber to cool
+    
+    // When the running cure next saves a checkpoint
+    let mut next_checkpoint = Instant::now();
+    let mut planned_seconds = 0;
+    
+    // CycleStarted for the start being armed - only recorded once the lid
+    // and battery checks have let it through, so a refused start isn't
+    // logged as a cure
+    let mut start_event: Option<Event> = None;
+    
+    // The time picked for the last cure - a double tap at idle runs it again
+    // (RAM only: nothing to repeat after a power cycle)
+    let mut last_cure_seconds: Option<u64> = None;
+    
     // In Rust, 'loop' creates an infinite loop - like 'while True:' in Python
     loop {
-        /* STEP 1: WAIT FOR USER INPUT */
-        // 'await' keyword pauses execution until the button is pressed
-        // This is non-blocking - the CPU can do other things while waiting
-        button.wait_for_falling_edge().await;  // Wait for button press (HIGH to LOW)
-        info!("Button pressed! Starting curing cycle...");
-        
-        /* STEP 2: DEBOUNCE THE BUTTON */
-        // Physical buttons can "bounce" - send multiple signals when pressed once
-        // This delay prevents multiple triggers from a single press (configurable in config.rs)
-        Timer::after_millis(BUTTON_DEBOUNCE_MS).await;
+        let event = match state {
+            // Finishing a cure cut off by a power cut (accepted at power-on)
+            CureState::Idle if resume.is_some() => {
+                let (plan, checkpoint) = unwrap!(resume.take());
+                start_event = Some(Event::CycleStarted { seconds: checkpoint.seconds_to_go(&plan), source: Source::Resume });
+                planned_seconds = plan.planned_seconds();
+                uv_on_ms_resumed = checkpoint.uv_on_seconds * 1000;
+                CureEvent::Resume { plan, from: checkpoint.from }
+            }
+            
+            CureState::Idle => {
+                /* STEP 1: WAIT FOR USER INPUT */
+                // 'await' keyword pauses execution until the button is pressed
+                // This is non-blocking - the CPU can do other things while waiting
+                // Button debouncing and hold timing are handled in button.rs
+                // Each branch decides how long the cure should run for
+                // A remote "start" (e.g. over USB serial) works like a button press
+                remote::set_status(CureStatus::Idle);
+                save_remote_settings(&mut storage, &mut persisted);
+                let cure_seconds = match START_INPUT {
+                    StartInput::Momentary if auto_start => {
+                        auto_start = false;           // Only once, right after power-on
+                        #[cfg(feature = "multi-duration")]
+                        let seconds = selected_preset.seconds();
+                        #[cfg(feature = "rotary-encoder")]
+                        let seconds = dial_seconds;
+                        #[cfg(feature = "keypad")]
+                        let seconds = keypad_entry.last_seconds();
+                        #[cfg(not(any(feature = "multi-duration", feature = "rotary-encoder", feature = "keypad")))]
+                        let seconds = settings::cure_seconds();
+                        start_event = Some(Event::CycleStarted { seconds, source: Source::AutoStart });
+                        seconds
+                    }
+                    
+                    // Single duration: tap to start, hold to "peek"
+                    #[cfg(not(any(feature = "multi-duration", feature = "rotary-encoder", feature = "keypad")))]
+                    StartInput::Momentary => match select(
+                        button::wait_for_idle_gesture(PEEK_HOLD_MS),
+                        wait_for_remote_or_sleep(),
+                    ).await {
+                        Either::First(Gesture::Tap) => {
+                            let seconds = settings::cure_seconds();
+                            start_event = Some(Event::CycleStarted { seconds, source: Source::Button });
+                            seconds
+                        }
+                        Either::First(Gesture::Hold) => {
+                            let pressed_at = Instant::now() - Duration::from_millis(PEEK_HOLD_MS);
+                            peek(&mut inspection_light, &mut heat, &lid).await;
+                            // Still holding? Keep going for standby
+                            if held_until_standby(pressed_at).await {
+                                standby(&mut inspection_light).await;
+                            }
+                            // Don't let the release of this hold start a cure
+                            button::wait_for_release().await;
+                            continue;
+                        }
+                        Either::First(Gesture::DoubleTap) => match repeat_last_cure(last_cure_seconds) {
+                            Some(seconds) => {
+                                start_event = Some(Event::CycleStarted { seconds, source: Source::Repeat });
+                                seconds
+                            }
+                            None => continue,
+                        },
+                        Either::First(Gesture::TripleTap) => {
+                            settings_menu(&mut storage, &mut persisted).await;
+                            continue;
+                        }
+                        Either::First(Gesture::FiveTaps) => bootsel().await,
+                        #[cfg(feature = "dormant")]
+                        Either::Second(IdleEnd::Sleepy) => {
+                            standby(&mut inspection_light).await;
+                            button::wait_for_release().await;
+                            continue;
+                        }
+                        Either::Second(IdleEnd::SettingsChanged) => continue,   // Saved at the top of Idle
+                        Either::Second(IdleEnd::Remote(seconds)) => {
+                            let seconds = seconds.unwrap_or(settings::cure_seconds());
+                            start_event = Some(Event::CycleStarted { seconds, source: Source::Remote });
+                            seconds
+                        }
+                    },
+                    
+                    // Rotary encoder: turn to set the time, push to start, push and hold to "peek"
+                    #[cfg(feature = "rotary-encoder")]
+                    StartInput::Momentary => match select3(
+                        button::wait_for_idle_gesture(PEEK_HOLD_MS),
+                        encoder.wait_for_step(),
+                        wait_for_remote_or_sleep(),
+                    ).await {
+                        Either3::First(Gesture::Tap) => {
+                            start_event = Some(Event::CycleStarted { seconds: dial_seconds, source: Source::Button });
+                            dial_seconds
+                        }
+                        Either3::First(Gesture::Hold) => {
+                            let pressed_at = Instant::now() - Duration::from_millis(PEEK_HOLD_MS);
+                            peek(&mut inspection_light, &mut heat, &lid).await;
+                            if held_until_standby(pressed_at).await {
+                                standby(&mut inspection_light).await;
+                            }
+                            button::wait_for_release().await;
+                            continue;
+                        }
+                        Either3::First(Gesture::DoubleTap) => match repeat_last_cure(last_cure_seconds) {
+                            Some(seconds) => {
+                                start_event = Some(Event::CycleStarted { seconds, source: Source::Repeat });
+                                seconds
+                            }
+                            None => continue,
+                        },
+                        Either3::First(Gesture::TripleTap) => {
+                            settings_menu(&mut storage, &mut persisted).await;
+                            // The menu showed its own cure time - back to the dialled one
+                            #[cfg(any(feature = "tm1637", feature = "hd44780"))]
+                            display::show_selected(None, dial_seconds);
+                            continue;
+                        }
+                        Either3::First(Gesture::FiveTaps) => bootsel().await,
+                        #[cfg(feature = "dormant")]
+                        Either3::Third(IdleEnd::Sleepy) => {
+                            standby(&mut inspection_light).await;
+                            button::wait_for_release().await;
+                            continue;
+                        }
+                        Either3::Third(IdleEnd::SettingsChanged) => continue,   // Saved at the top of Idle
+                        Either3::Third(IdleEnd::Remote(seconds)) => {
+                            // No time given = whatever is dialled in
+                            let seconds = seconds.unwrap_or(dial_seconds);
+                            start_event = Some(Event::CycleStarted { seconds, source: Source::Remote });
+                            seconds
+                        }
+                        Either3::Second(direction) => {
+                            let clockwise = matches!(direction, encoder::Direction::Clockwise);
+                            let new_seconds = curing_core::cure::dial(dial_seconds, clockwise, ENCODER_STEP_SECONDS, MAX_CURING_DURATION_SECONDS);
+                            
+                            if new_seconds == dial_seconds {
+                                // Already at the limit - a short chirp says "can't go further"
+                                buzzer::play(AudioEvent::Chirp);
+                            } else {
+                                dial_seconds = new_seconds;
+                                info!("Curing time set to {} seconds", dial_seconds);
+                                #[cfg(any(feature = "tm1637", feature = "hd44780"))]
+                                display::show_selected(None, dial_seconds);
+                                // Quick LED flick for each step
+                                status_led::show(Led::Flashes { count: 1, on_ms: 20, off_ms: 0 });
+                            }
+                            continue;
+                        }
+                    },
+                    
+                    // Keypad: type the time and press # (or tap the button) to start,
+                    // hold the button to "peek"
+                    #[cfg(feature = "keypad")]
+                    StartInput::Momentary => match select3(
+                        button::wait_for_idle_gesture(PEEK_HOLD_MS),
+                        keypad::wait_for_key(),
+                        wait_for_remote_or_sleep(),
+                    ).await {
+                        Either3::First(Gesture::Tap) => {
+                            // Anything half typed is forgotten - the button runs the last time used
+                            keypad_entry.clear();
+                            let seconds = keypad_entry.last_seconds();
+                            start_event = Some(Event::CycleStarted { seconds, source: Source::Button });
+                            seconds
+                        }
+                        Either3::First(Gesture::Hold) => {
+                            let pressed_at = Instant::now() - Duration::from_millis(PEEK_HOLD_MS);
+                            peek(&mut inspection_light, &mut heat, &lid).await;
+                            if held_until_standby(pressed_at).await {
+                                standby(&mut inspection_light).await;
+                            }
+                            button::wait_for_release().await;
+                            continue;
+                        }
+                        Either3::First(Gesture::DoubleTap) => match repeat_last_cure(last_cure_seconds) {
+                            Some(seconds) => {
+                                start_event = Some(Event::CycleStarted { seconds, source: Source::Repeat });
+                                seconds
+                            }
+                            None => continue,
+                        },
+                        Either3::First(Gesture::TripleTap) => {
+                            settings_menu(&mut storage, &mut persisted).await;
+                            // The menu showed its own cure time - back to the keypad's
+                            #[cfg(any(feature = "tm1637", feature = "hd44780"))]
+                            display::show_selected(None, keypad_entry.last_seconds());
+                            continue;
+                        }
+                        Either3::First(Gesture::FiveTaps) => bootsel().await,
+                        #[cfg(feature = "dormant")]
+                        Either3::Third(IdleEnd::Sleepy) => {
+                            standby(&mut inspection_light).await;
+                            button::wait_for_release().await;
+                            continue;
+                        }
+                        Either3::Third(IdleEnd::SettingsChanged) => continue,   // Saved at the top of Idle
+                        Either3::Third(IdleEnd::Remote(seconds)) => {
+                            // No time given = the last time typed in
+                            keypad_entry.clear();
+                            let seconds = seconds.unwrap_or(keypad_entry.last_seconds());
+                            start_event = Some(Event::CycleStarted { seconds, source: Source::Remote });
+                            seconds
+                        }
+                        Either3::Second(key) => match keypad_entry.key(key) {
+                            Some(EntryUpdate::Typed(typed)) => {
+                                info!("Keypad: {} seconds", typed);
+                                #[cfg(any(feature = "tm1637", feature = "hd44780"))]
+                                display::show_selected(None, typed);
+                                buzzer::play(AudioEvent::Chirp);   // Key click
+                                continue;
+                            }
+                            Some(EntryUpdate::Start(seconds)) => {
+                                info!("Keypad: start {} seconds", seconds);
+                                #[cfg(any(feature = "tm1637", feature = "hd44780"))]
+                                display::show_selected(None, seconds);
+                                start_event = Some(Event::CycleStarted { seconds, source: Source::Button });
+                                seconds
+                            }
+                            Some(EntryUpdate::Rejected) => {
+                                warn!("Keypad: that time isn't allowed (1 to {} seconds)", MAX_CURING_DURATION_SECONDS);
+                                #[cfg(any(feature = "tm1637", feature = "hd44780"))]
+                                display::show_selected(None, keypad_entry.last_seconds());
+                                buzzer::play(AudioEvent::Refused);   // One long beep = "no"
+                                continue;
+                            }
+                            None => continue,         // A-D: not used
+                        },
+                    },
+                    
+                    // Multiple durations: tap to cycle presets, hold to start - or, with
+                    // a select button, select cycles and the start button starts
+                    #[cfg(feature = "multi-duration")]
+                    StartInput::Momentary => match select(
+                        button::wait_for_idle_control(),
+                        wait_for_remote_or_sleep(),
+                    ).await {
+                        Either::First(Control::Select) => {
+                            selected_preset.next();
+                            info!("Duration changed: {} ({} seconds)", selected_preset.name(), selected_preset.seconds());
+                            #[cfg(any(feature = "tm1637", feature = "hd44780"))]
+                            display::show_selected(Some(selected_preset.name()), selected_preset.seconds());
+                            
+                            // Remember the choice across power cycles
+                            persisted.preset_index = Some(selected_preset.index() as u8);
+                            if storage.save(&persisted).is_err() {
+                                events::record(Event::FlashWriteFailed { what: "preset" });
+                            }
+                            
+                            // Audio feedback for duration change
+                            buzzer::play(AudioEvent::PresetChanged);
+                            
+                            // Quick LED blinks to show selected preset number (1 blink = first preset)
+                            let count = selected_preset.index() as u32 + 1;
+                            status_led::show(Led::Flashes { count, on_ms: 150, off_ms: 150 });
+                            continue;
+                        }
+                        Either::First(Control::Start) => {
+                            #[cfg(not(feature = "select-button"))]
+                            if button::is_pressed() {
+                                // Still holding (one-button hold to start): LED on solid = "release to start"
+                                status_led::show(Led::On);
+                                // ...unless it's held on into standby
+                                let pressed_at = Instant::now() - Duration::from_millis(PRESET_START_HOLD_MS);
+                                if held_until_standby(pressed_at).await {
+                                    standby(&mut inspection_light).await;
+                                    button::wait_for_release().await;
+                                    continue;
+                                }
+                            }
+                            info!("START - {} preset", selected_preset.name());
+                            start_event = Some(Event::CycleStarted { seconds: selected_preset.seconds(), source: Source::Button });
+                            selected_preset.seconds()
+                        }
+                        #[cfg(feature = "select-button")]
+                        Either::First(Control::Peek) => {
+                            let pressed_at = Instant::now() - Duration::from_millis(PEEK_HOLD_MS);
+                            peek(&mut inspection_light, &mut heat, &lid).await;
+                            if held_until_standby(pressed_at).await {
+                                standby(&mut inspection_light).await;
+                            }
+                            button::wait_for_release().await;
+                            continue;
+                        }
+                        #[cfg(feature = "select-button")]
+                        Either::First(Control::Repeat) => match repeat_last_cure(last_cure_seconds) {
+                            Some(seconds) => {
+                                start_event = Some(Event::CycleStarted { seconds, source: Source::Repeat });
+                                seconds
+                            }
+                            None => continue,
+                        },
+                        #[cfg(feature = "select-button")]
+                        Either::First(Control::Settings) => {
+                            settings_menu(&mut storage, &mut persisted).await;
+                            // The menu showed its own cure time - back to the preset
+                            #[cfg(any(feature = "tm1637", feature = "hd44780"))]
+                            display::show_selected(Some(selected_preset.name()), selected_preset.seconds());
+                            continue;
+                        }
+                        #[cfg(feature = "select-button")]
+                        Either::First(Control::Bootsel) => bootsel().await,
+                        #[cfg(feature = "dormant")]
+                        Either::Second(IdleEnd::Sleepy) => {
+                            standby(&mut inspection_light).await;
+                            button::wait_for_release().await;
+                            continue;
+                        }
+                        Either::Second(IdleEnd::SettingsChanged) => continue,   // Saved at the top of Idle
+                        Either::Second(IdleEnd::Remote(seconds)) => {
+                            // No time given = the selected preset
+                            let seconds = seconds.unwrap_or(selected_preset.seconds());
+                            start_event = Some(Event::CycleStarted { seconds, source: Source::Remote });
+                            seconds
+                        }
+                    },
+                    
+                    StartInput::Latching => {
+                        // If the switch was left ON after the last cure timed out,
+                        // it has to be turned OFF before it can start another one
+                        if button::is_pressed() {
+                            info!("Start switch still ON - turn it OFF and ON again to start a new cure");
+                            button::wait_for_release().await;
+                        }
+                        button::wait_for_press().await;   // Wait for switch to be turned ON
+                        start_event = Some(Event::CycleStarted { seconds: MAX_CURING_DURATION_SECONDS, source: Source::Switch });
+                        
+                        // A latching switch decides the cure length itself, so it
+                        // only gets the safety maximum as an upper limit
+                        MAX_CURING_DURATION_SECONDS
+                    }
+                };
+                
+                // A multi-stage CURE_PROFILE (config.rs) alternates UV and rest
+                // phases so thick prints don't overheat. Only with a momentary
+                // button - a latching switch times the cure itself. The duty
+                // cycle does the same over whatever time was picked
+                let plan = if !CURE_PROFILE.is_empty() && matches!(START_INPUT, StartInput::Momentary) {
+                    Plan::Profile(CURE_PROFILE)
+                } else if DUTY_CYCLE_ENABLED {
+                    Plan::DutyCycle {
+                        seconds: cure_seconds,
+                        on_seconds: DUTY_CYCLE_ON_SECONDS,
+                        off_seconds: DUTY_CYCLE_OFF_SECONDS,
+                    }
+                } else {
+                    Plan::Single(cure_seconds)
+                };
+                planned_seconds = plan.planned_seconds();
+                last_cure_seconds = Some(cure_seconds);
+                CureEvent::Start(plan)
+            }
+            
+            CureState::Arming { .. } => {
+                /* LID CHECK */
+                // Never start the UV with the lid open
+                if lid::is_open(&lid) {
+                    events::record(Event::LidOpenAtStart);
+                    buzzer::play(AudioEvent::LidOpen);
+                    start_event = None;
+                    CureEvent::Refused            // Back to waiting for a start
+                } else if battery_too_low() {
+                    start_event = None;
+                    CureEvent::Refused            // Better than running flat halfway through
+                } else {
+                    if let Some(started) = start_event.take() {
+                        events::record(started);
+                    }
+                    
+                    /* STEP 2: GET READY FOR THE UV */
+                    // White light always goes off before UV comes on
+                    inspection_light.set_low();
+                    uv_on_ms_before = heat.total_on_ms();     // For the cure statistics
+                    next_checkpoint = Instant::now();          // The first one as soon as the UV is on
+                    if CHECKPOINT_INTERVAL_SECONDS > 0 && matches!(START_INPUT, StartInput::Momentary) {
+                        // Any flash erasing happens now, not mid-cure (see storage.rs)
+                        let checkpoints = planned_seconds / CHECKPOINT_INTERVAL_SECONDS + 2;
+                        if storage.prepare_for_cure(checkpoints as u32).is_err() {
+                            events::record(Event::FlashWriteFailed { what: "checkpoint" });
+                        }
+                    }
+                    #[cfg(feature = "ina219")]
+                    ina219::reset_energy();
+                    #[cfg(feature = "bme280")]
+                    climate::log_latest("Cure starting");
+                    #[cfg(feature = "fan")]
+                    thermostat::set_curing(true);
+                    
+                    // Optional preheat before the UV comes on (heater builds)
+                    #[cfg(feature = "heater")]
+                    let armed = preheat(&mut heater).await;
+                    #[cfg(not(feature = "heater"))]
+                    let armed = CureEvent::Armed;
+                    armed
+                }
+            }
+            
+            // A phase is running: wait for its time to be up, or for
+            // something to interrupt it (the thermistor, lid or button)
+            CureState::Curing { remaining, paused: None, .. } => {
+                let countdown_end = Instant::now() + Duration::from_millis(state.countdown_ms(now_ms()).unwrap_or(0));
+                let (done_ms, total_ms) = state.progress_ms(now_ms()).unwrap_or((0, 0));
+                let cure_start = Instant::now().checked_sub(Duration::from_millis(done_ms)).unwrap_or(Instant::MIN);
+                let cure_end = Instant::now() + Duration::from_millis(total_ms - done_ms);
+                let curing = wait_while_curing(Instant::from_millis(remaining.end_ms()), countdown_end, cure_start, cure_end, &mut lid, state.uv_on());
+                
+                // Meanwhile, save how far it has got every so often
+                match select(curing, save_checkpoints(state, &mut storage, &mut next_checkpoint, persisted.stats.cures())).await {
+                    Either::First(event) => event,
+                    Either::Second(()) => CureEvent::Tick,    // Never - it saves until cancelled
+                }
+            }
+            
+            // Paused with the button (the status LED blinks) until it's pressed again
+            CureState::Curing { paused: Some(Pause::Button), .. } => {
+                match select(button::wait_for_gesture(ABORT_HOLD_MS), remote::wait_for_stop()).await {
+                    Either::First(Gesture::Hold) => {
+                        events::record(Event::Aborted { source: Source::Button });
+                        CureEvent::Abort
+                    }
+                    Either::First(_) => CureEvent::Tap,
+                    Either::Second(_) => {
+                        events::record(Event::Aborted { source: Source::Remote });
+                        CureEvent::Abort
+                    }
+                }
+            }
+            
+            // Paused by the lid - warn until it's closed again, or the cure
+            // is cancelled (a long hold or a remote stop) meanwhile
+            CureState::Curing { paused: Some(Pause::Lid), .. } => {
+                let cancelled = async {
+                    loop {
+                        match select(button::wait_for_gesture(ABORT_HOLD_MS), remote::wait_for_stop()).await {
+                            Either::First(Gesture::Hold) => break Source::Button,
+                            Either::First(_) => {}          // Can't resume with the lid open
+                            Either::Second(_) => break Source::Remote,
+                        }
+                    }
+                };
+                match select(lid::wait_for_close(&mut lid), cancelled).await {
+                    Either::First(()) => CureEvent::LidClosed,
+                    Either::Second(source) => {
+                        buzzer::stop();                     // No more warning beeps
+                        events::record(Event::Aborted { source });
+                        CureEvent::Abort
+                    }
+                }
+            }
+            
+            // Paused because the chamber passed the soft limit (thermistor
+            // builds) - the fan keeps running, and the cure carries on once
+            // it has cooled. It still ends if the hard limit is reached, or
+            // it's cancelled meanwhile
+            CureState::Curing { paused: Some(Pause::Heat), .. } => {
+                let cooled = async {
+                    #[cfg(feature = "thermistor")]
+                    thermal::wait_until_cooled_down().await;
+                    #[cfg(not(feature = "thermistor"))]
+                    core::future::pending::<()>().await;
+                    CureEvent::CooledDown
+                };
+                let cancelled = async {
+                    loop {
+                        match select(button::wait_for_gesture(ABORT_HOLD_MS), remote::wait_for_stop()).await {
+                            Either::First(Gesture::Hold) => break Source::Button,
+                            Either::First(_) => {}          // Can't resume until it has cooled
+                            Either::Second(_) => break Source::Remote,
+                        }
+                    }
+                };
+                match select3(cooled, overheated(), cancelled).await {
+                    Either3::First(event) | Either3::Second(event) => event,
+                    Either3::Third(source) => {
+                        events::record(Event::Aborted { source });
+                        CureEvent::Abort
+                    }
+                }
+            }
+            
+            CureState::Finishing { end } => {
+                /* STEP 3: UV LEDS OFF (CRITICAL SECTION) */
+                // enter_state() switched them off on the way in - wait until
+                // the relay task has opened the relay and it has settled
+                uv_output::wait_until_off().await;
+                events::record(Event::RelayOpened);
+                remote::set_status(CureStatus::Cooldown);
+                #[cfg(feature = "bme280")]
+                climate::log_latest("Cure ended");
+                #[cfg(feature = "fan")]
+                thermostat::set_curing(false);
+                
+                /* CURE STATISTICS */
+                // Saved now that the UV is off - a flash write pauses everything briefly
+                let uv_on_ms = heat.total_on_ms() - uv_on_ms_before + uv_on_ms_resumed;
+                uv_on_ms_resumed = 0;
+                #[cfg(feature = "ina219")]
+                info!("Lamp energy this cure: {} watt-seconds", ina219::energy_watt_seconds());
+                
+                // On the event bus for anything that keeps a record (the SD card log)
+                events::record(Event::CureFinished {
+                    result: end.name(),
+                    planned_seconds,
+                    uv_on_ms,
+                    #[cfg(feature = "multi-duration")]
+                    preset: selected_preset.name(),
+                    #[cfg(not(feature = "multi-duration"))]
+                    preset: "-",
+                    heat_pauses,
+                });
+                heat_pauses = 0;
+                
+                persisted.stats.add_cure(end.completed(), uv_on_ms);
+                stats::publish(persisted.stats);
+                if storage.save(&persisted).is_err() {
+                    events::record(Event::FlashWriteFailed { what: "cure statistics" });
+                }
+                
+                match end {
+                    PhaseEnd::Fault(fault) => {
+                        /* STEP 4 (FAULT): ALARM */
+                        // Too hot: rapid beeps, repeated - nothing like the other patterns.
+                        // The fault state comes next, with its code (fault.rs)
+                        if fault == FaultCode::OverTemp {
+                            buzzer::play(AudioEvent::Overheated);
+                        }
+                    }
+                    PhaseEnd::Aborted => {
+                        /* STEP 4 (ABORTED): ABORT NOTIFICATION */
+                        // Long beeps sound clearly different from the "done" beeps
+                        buzzer::play(AudioEvent::Abort);
+                        
+                        // The button may still be held down - don't let its release start a new cure
+                        // (a latching switch is dealt with when waiting for the next start)
+                        if matches!(START_INPUT, StartInput::Momentary) {
+                            button::wait_for_release().await;
+                        }
+                    }
+                    _ => {
+                        events::record(Event::Completed);
+                        
+                        /* STEP 4: COMPLETION NOTIFICATION */
+                        // Beeps (or a melody on a passive piezo) - configurable in config.rs
+                        // In silent mode or quiet hours, a burst of LED flashes says "done" instead
+                        // Both play while the cooldown below runs
+                        if buzzer::is_muted() || buzzer::is_quiet_hours() {
+                            status_led::show(Led::Flashes {
+                                count: MUTED_COMPLETION_FLASHES,
+                                on_ms: BEEP_DURATION_MS,
+                                off_ms: BEEP_DURATION_MS,
+                            });
+                        } else {
+                            buzzer::play(AudioEvent::Complete);
+                        }
+                    }
+                }
+                
+                // Reminder after every cure until the lamp hours are reset
+                if persisted.stats.lamp_service_due() {
+                    lamp_service_alert(persisted.stats.lamp_hours());
+                }
+                
+                /* STEP 5: PREPARE FOR NEXT CYCLE */
+                // Brief pause before accepting the next button press
+                // Prevents accidental immediate re-triggering (configurable in config.rs)
+                // After back-to-back long cures the panel is hot, so the pause is
+                // stretched to let it cool down before the next cure can start
+                let extra_cooldown_seconds = heat.extra_cooldown_seconds();
+                if extra_cooldown_seconds > 0 {
+                    info!("UV panel is hot ({} s of recent UV time) - extending cooldown by {} seconds",
+                          heat.heat_seconds(), extra_cooldown_seconds);
+                }
+                Timer::after(Duration::from_millis(settings::cooldown_ms()) + Duration::from_secs(extra_cooldown_seconds)).await;
+                
+                CureEvent::Finished
+            }
+            
+            /* FAULT */
+            // UV off and the fault code blinking - no new cure until it's
+            // cleared and the button has been pressed
+            CureState::Fault(fault) => {
+                fault::handle(fault).await;
+                CureEvent::Cleared
+            }
+        };
         
-        /* STEP 3: ACTIVATE UV LEDS */
-        // Set the relay pin to output mode and pull it LOW
-        // SRD-05VDC-SL-C relay: LOW = closed = UV LEDs ON
-        flex_pin.set_as_output();     // Ensure pin is in output mode
-        flex_pin.set_low();           // Close relay (activate UV LEDs)
-        status_led.set_high();        // Turn on internal LED for visual feedback
-        info!("Relay CLOSED - UV LEDs ON - Curing for {} seconds", CURING_DURATION_SECONDS);
-        
-        /* STEP 4: CURING TIMER */
-        // Wait for the configured duration while UV LEDs cure the resin
-        // Duration is configurable in config.rs - change CURING_DURATION_SECONDS
-        Timer::after(Duration::from_secs(CURING_DURATION_SECONDS)).await;
-        
-        /* STEP 5: TURN OFF UV LEDS (CRITICAL SECTION) */
-        // This is the key discovery: setting pin to INPUT mode (high-impedance)
-        // completely "kills" the pin, forcing the relay to open reliably
-        flex_pin.set_as_input();      // High-impedance = no voltage = relay opens
-        status_led.set_low();         // Turn off internal LED
-        Timer::after_millis(RELAY_SETTLE_TIME_MS).await;  // Allow relay time to settle
-        
-        info!("Curing complete! UV LEDs OFF - Sounding completion buzzer...");
-        
-        /* STEP 6: COMPLETION NOTIFICATION */
-        // Loop for configured number of beeps (configurable in config.rs)
-        for i in 1..=COMPLETION_BEEPS {
-            info!("Buzzer beep {}/{}", i, COMPLETION_BEEPS);    // Log which beep we're on
-            buzzer.set_high();            // Turn buzzer ON
-            Timer::after_millis(BEEP_DURATION_MS).await;  // Configurable beep duration
-            buzzer.set_low();             // Turn buzzer OFF
-            Timer::after_millis(BEEP_PAUSE_MS).await;     // Configurable pause between beeps
+        let next = state.transition(event, now_ms());
+        if event == CureEvent::TooWarm && next != state {
+            heat_pauses += 1;
         }
-        
-        info!("Curing cycle complete! Ready for next cycle.");
-        
-        /* STEP 7: PREPARE FOR NEXT CYCLE */
-        // Brief pause before accepting the next button press
-        // Prevents accidental immediate re-triggering (configurable in config.rs)
-        Timer::after_millis(CYCLE_COOLDOWN_MS).await;
-        
-    } // End of loop - jumps back to the beginning to wait for next button press
-} // End of main function
+        if matches!(event, CureEvent::AddTime { .. }) && next == state {
+            // A profile, or already as long as a cure can be
+            info!("Tap - can't make this cure any longer");
+            buzzer::play(AudioEvent::Refused);   // One long beep = "no"
+        }
+        enter_state(&state, &next, &mut heat, &mut inspection_light);
+        state = next;
+    } // End of loop - jumps back to the beginning to wait for the next event
+}
+
+/// Milliseconds since power-on - the time the curing-core logic works in
+fn now_ms() -> u64 {
+    Instant::now().as_millis()
+}
+
+/// Switch the UV LEDs on or off (the status LED mirrors them, until told
+/// to count down)
+fn set_uv(on: bool, heat: &mut HeatEstimate) {
+    uv_output::set(on);
+    if on {
+        heat.uv_on(now_ms());
+        status_led::show(Led::On);    // Turn on internal LED for visual feedback
+        #[cfg(any(feature = "turntable", feature = "dc-turntable"))]
+        turntable::start();
+    } else {
+        heat.uv_off(now_ms());
+        status_led::show(Led::Off);
+        #[cfg(any(feature = "turntable", feature = "dc-turntable"))]
+        turntable::stop();
+    }
+}
+
+/// "Service lamp" signal - three short beeps and a long one, twice, with the
+/// status LED flashing along (so it still shows in silent mode)
+fn lamp_service_alert(lamp_hours: u32) {
+    events::record(Event::LampServiceDue { hours: lamp_hours });
+    buzzer::play(AudioEvent::LampService);
+    status_led::show(Led::Flashes {
+        count: LAMP_SERVICE_REPEATS * (LAMP_SERVICE_SHORT_BEEPS + 1),
+        on_ms: LAMP_SERVICE_SHORT_MS,
+        off_ms: LAMP_SERVICE_SHORT_MS,
+    });
+}
+
+/// Blink `completed` on the status LED: a long flash per hundred, a pause,
+/// then a short flash per ten (1234 = 12 long, 3 short). A press skips it
+/// (not with a latching switch, which may simply have been left ON).
+async fn blink_cure_count(completed: u32) {
+    const PAUSE_MS: u64 = 1500;
+    let (hundreds, tens) = (completed / 100, completed / 10 % 10);
+    if hundreds == 0 && tens == 0 {
+        return;
+    }
+    info!("Blinking the cure count: {} long (hundreds), {} short (tens)", hundreds, tens);
+    
+    // One round of a repeating pattern each, so a press can stop it at once
+    let blinks = async {
+        for (flashes, flash_ms) in [(hundreds, CURE_COUNT_LONG_MS), (tens, CURE_COUNT_SHORT_MS)] {
+            if flashes > 0 {
+                let period_ms = 2 * flashes as u64 * flash_ms + PAUSE_MS;
+                status_led::show(Led::Repeat { flashes, flash_ms, period_ms });
+                Timer::after_millis(period_ms).await;
+            }
+        }
+    };
+    let press = async {
+        if matches!(START_INPUT, StartInput::Latching) {
+            core::future::pending::<()>().await;
+        }
+        button::wait_for_press().await;
+    };
+    let skipped = matches!(select(blinks, press).await, Either::Second(_));
+    status_led::show(Led::Off);
+    if skipped {
+        button::wait_for_release().await;   // The release isn't a tap
+    }
+}
+
+/// At power-on: if a cure was cut off by a power cut, offer to finish it -
+/// RESUME_OFFER_SECONDS to press the button, with a chirp and a double flash
+/// each second. Returns the cure to resume, if the button was pressed.
+/// With the Resume power-on behavior it's the other way round: the cure is
+/// finished unless the button is pressed.
+///
+/// Not resumed: the cure is given up and counted as aborted, which also
+/// makes its checkpoint out of date.
+async fn offer_resume(
+    storage: &mut storage::Storage<'_>,
+    persisted: &mut storage::PersistedState,
+) -> Option<(Plan<'static>, Checkpoint)> {
+    if CHECKPOINT_INTERVAL_SECONDS == 0 || !matches!(START_INPUT, StartInput::Momentary) {
+        return None;
+    }
+    // Only the cure that was running when the power went - not one that finished
+    let checkpoint = storage.checkpoint().filter(|checkpoint| checkpoint.cure_number == persisted.stats.cures())?;
+    let plan = checkpoint.plan(CURE_PROFILE)?;
+    let automatic = settings::power_on_behavior() == PowerOnBehavior::Resume;
+    events::record(Event::ResumeOffered { seconds: checkpoint.seconds_to_go(&plan), automatic });
+    
+    let mut pressed = false;
+    for _ in 0..RESUME_OFFER_SECONDS {
+        status_led::show(Led::Flashes { count: 2, on_ms: 50, off_ms: 100 });
+        buzzer::play(AudioEvent::Countdown);
+        if let Either::Second(_) = select(Timer::after_secs(1), button::wait_for_press()).await {
+            // Don't let the release of this press count as a tap
+            button::wait_for_release().await;
+            pressed = true;
+            break;
+        }
+    }
+    if pressed != automatic {
+        return Some((plan, checkpoint));
+    }
+    
+    events::record(Event::ResumeDeclined);
+    persisted.stats.add_cure(false, checkpoint.uv_on_seconds * 1000);
+    stats::publish(persisted.stats);
+    if storage.save(persisted).is_err() {
+        events::record(Event::FlashWriteFailed { what: "cure statistics" });
+    }
+    None
+}
+
+/// Triple tap at idle: the settings menu (menu.rs), then back to idle
+///
+/// Only reachable from idle, never mid-cure - and while it's open the status
+/// LED repeats the number of the setting being changed instead of breathing.
+#[cfg_attr(all(feature = "multi-duration", not(feature = "select-button")), allow(dead_code))]  // One button: taps only pick presets
+async fn settings_menu(storage: &mut storage::Storage<'_>, persisted: &mut storage::PersistedState) {
+    info!("Triple tap - settings menu");
+    menu::run(storage, persisted).await;
+    info!("Settings menu closed - back to idle");
+    status_led::show(Led::Breathe);
+}
+
+/// Save the menu settings, power-on behavior and API token if they were
+/// changed remotely (set_config, PUT /config, or the serial `token` and
+/// `wifi set`) - only
+/// called at idle, like every other settings save
+fn save_remote_settings(storage: &mut storage::Storage<'_>, persisted: &mut storage::PersistedState) {
+    if !settings::take_unsaved() {
+        return;
+    }
+    let changed = settings::menu_settings();
+    persisted.muted = changed.muted;
+    persisted.menu = Some(changed);
+    persisted.power_on = Some(settings::power_on_behavior());
+    persisted.api_token = settings::api_token();
+    persisted.wifi = settings::wifi_credentials();
+    if storage.save(persisted).is_err() {
+        events::record(Event::FlashWriteFailed { what: "settings" });
+    }
+}
+
+/// The status LED pin, which BOOTSEL mode flickers while the drive is in use
+#[cfg(not(feature = "pico-w"))]
+const BOOTSEL_ACTIVITY_PIN: u32 = 25;
+#[cfg(feature = "pico-w")]
+const BOOTSEL_ACTIVITY_PIN: u32 = 15;
+
+/// Five taps at idle: restart into BOOTSEL mode, as if the BOOTSEL button
+/// had been held at power-on, so new firmware can be copied over USB
+///
+/// Only reachable from idle, so the UV is already off. The restart goes
+/// through the RP2040's boot ROM, which never comes back here - unplugging
+/// (or copying a .uf2) starts the firmware again.
+#[cfg_attr(all(feature = "multi-duration", not(feature = "select-button")), allow(dead_code))]  // One button: taps only pick presets
+async fn bootsel() -> ! {
+    uv_output::set(false);
+    events::record(Event::Bootsel);
+    status_led::show(Led::On);
+    buzzer::play(AudioEvent::Confirm);   // One long beep = on its way
+    Timer::after_millis(AUDIO_PATTERNS.confirm.on_ms).await;
+
+    // Both USB interfaces on (mass storage and picoboot)
+    embassy_rp::rom_data::reset_to_usb_boot(1 << BOOTSEL_ACTIVITY_PIN, 0);
+    loop {
+        cortex_m::asm::wfi();          // Not reached - the ROM has taken over
+    }
+}
+
+/// Double tap at idle: the time to run the last cure again for, or None
+/// (and a long "no" beep) if there hasn't been one since power-on
+#[cfg_attr(all(feature = "multi-duration", not(feature = "select-button")), allow(dead_code))]  // One button: taps only pick presets
+fn repeat_last_cure(last_cure_seconds: Option<u64>) -> Option<u64> {
+    if last_cure_seconds.is_none() {
+        info!("Double tap - no cure to repeat yet since power-on");
+        buzzer::play(AudioEvent::Refused);   // One long beep = "no"
+    }
+    last_cure_seconds
+}
+
+/// Make the outputs (and the event log) follow a change of cure state
+/// 
+/// Called after every transition. The UV is switched first, so an abort,
+/// lid opening or overheat turns it off before anything else happens.
+fn enter_state(
+    old: &CureState<'_>,
+    new: &CureState<'_>,
+    heat: &mut HeatEstimate,
+    inspection_light: &mut Output<'_, AnyPin>,
+) {
+    if old == new {
+        return;                       // Nothing happened (e.g. a tap while preheating)
+    }
+    #[cfg(feature = "neopixel")]
+    neopixel::follow(old, new, now_ms());
+    #[cfg(feature = "st7789")]
+    tft::follow(old, new, now_ms());
+    match *new {
+        CureState::Curing { plan, phase, paused, .. } => {
+            set_uv(new.uv_on(), heat);
+            if paused == Some(Pause::Button) {
+                // Blinking = "paused, press again to carry on"
+                status_led::show(Led::Repeat { flashes: 1, flash_ms: PAUSE_BLINK_MS, period_ms: 2 * PAUSE_BLINK_MS });
+            }
+            // UV on: blink faster as the end of the cure gets closer
+            if let (true, Some((done_ms, total_ms))) = (new.uv_on(), new.progress_ms(now_ms())) {
+                let ends_at = Instant::now() + Duration::from_millis(total_ms - done_ms);
+                status_led::show(Led::Countdown { ends_at, total_ms });
+            }
+            // Time left for the phase - or the whole cure, for a duty cycle
+            let countdown_ms = new.countdown_ms(now_ms()).unwrap_or(0);
+            let remaining_seconds = countdown_ms / 1000;
+            
+            match *old {
+                // Same phase, paused, resumed or made longer
+                CureState::Curing { plan: old_plan, phase: old_phase, paused: was_paused, .. } if old_phase == phase => {
+                    events::record(match (was_paused, paused) {
+                        (None, Some(Pause::Button)) => Event::Paused { remaining_seconds },
+                        (None, Some(Pause::Lid)) => Event::LidOpened { remaining_seconds },
+                        #[cfg(feature = "thermistor")]
+                        (None, Some(Pause::Heat)) => Event::HeatPaused { remaining_seconds },
+                        (None, None) => {
+                            buzzer::play(AudioEvent::Chirp);   // "Time added"
+                            let seconds = plan.planned_seconds() - old_plan.planned_seconds();
+                            Event::TimeAdded { seconds, remaining_seconds }
+                        }
+                        _ => Event::Resumed { remaining_seconds },
+                    });
+                }
+                // A new phase
+                _ => {
+                    if !matches!(old, CureState::Curing { .. }) {
+                        buzzer::play(AudioEvent::Start);   // The first one - the cure is under way
+                    }
+                    if let Plan::Profile(_) = plan {
+                        events::record(Event::ProfilePhase { number: phase + 1, of: plan.phase_count() });
+                    }
+                    let seconds = plan.phase(phase).map_or(0, |p| p.seconds);
+                    events::record(if new.uv_on() {
+                        Event::RelayClosed { seconds }
+                    } else {
+                        Event::Resting { seconds }
+                    });
+                }
+            }
+            
+            // Tell remote interfaces which phase is running and when it (or
+            // the duty cycle) ends
+            let ends_at = Instant::now() + Duration::from_millis(countdown_ms);
+            remote::set_status(match paused {
+                Some(_) => CureStatus::Paused { remaining_seconds },
+                None if new.uv_on() => CureStatus::Curing { ends_at },
+                None => CureStatus::Resting { ends_at },
+            });
+        }
+        CureState::Idle => {
+            status_led::show(Led::Breathe);
+            if matches!(old, CureState::Finishing { .. } | CureState::Fault(_)) {
+                // Back to idle - turn the inspection light back on
+                if INSPECTION_LIGHT_ENABLED {
+                    inspection_light.set_high();
+                }
+                events::record(Event::Ready);
+            }
+        }
+        // Cure over - the UV goes off before the finishing steps
+        CureState::Finishing { .. } => set_uv(false, heat),
+        _ => {}
+    }
+}
+
+/// Wait for whatever ends or interrupts a running phase
+/// 
+/// The end of the phase is fixed as an absolute point in time when it
+/// starts (a PhaseTimer from curing-core). Anything we do meanwhile
+/// (logging, feedback) can't stretch it, because we sleep *until* that
+/// instant rather than adding delays together.
+/// 
+/// While waiting, the remaining time is reported every few seconds, long
+/// cures chirp each full minute, short beeps count down the last
+/// COUNTDOWN_BEEPS seconds of the cure, and the button stays live: a tap adds ADD_TIME_SECONDS (or pauses, if that's 0),
+/// a double tap pauses and a long hold aborts (momentary button),
+/// or turning the switch OFF ends the cure (latching switch). A remote stop
+/// aborts either way. Opening the lid (lid-interlock builds) pauses, and the
+/// thermistor (thermistor builds) pauses a UV phase at the soft limit and
+/// cuts any phase short at the hard one.
+async fn wait_while_curing(
+    phase_end: Instant,
+    countdown_end: Instant,
+    cure_start: Instant,
+    cure_end: Instant,
+    lid: &mut LidSwitch<'_>,
+    #[cfg_attr(not(any(feature = "thermistor", feature = "uv-sensor", feature = "ina219", feature = "relay-feedback")), allow(unused_variables))]
+    uv_on: bool,
+) -> CureEvent<'static> {
+    let time_up = async {
+        select4(Timer::at(phase_end), report_progress(countdown_end), minute_chirps(cure_start, cure_end), final_countdown(cure_end)).await;
+        CureEvent::Tick
+    };
+    let stop = async {
+        match START_INPUT {
+            StartInput::Latching => match select(button::wait_for_release(), remote::wait_for_stop()).await {
+                Either::First(_) => {
+                    events::record(Event::SwitchedOff);
+                    CureEvent::SwitchedOff
+                }
+                Either::Second(_) => {
+                    events::record(Event::Aborted { source: Source::Remote });
+                    CureEvent::Abort
+                }
+            },
+            StartInput::Momentary => match select(button::wait_for_cure_gesture(), remote::wait_for_stop()).await {
+                Either::First(Gesture::Hold) => {
+                    events::record(Event::Aborted { source: Source::Button });
+                    CureEvent::Abort
+                }
+                // A tap adds time if ADD_TIME_SECONDS is set, and a double
+                // tap pauses - otherwise a tap pauses
+                Either::First(Gesture::Tap) if ADD_TIME_SECONDS > 0 => {
+                    CureEvent::AddTime { seconds: ADD_TIME_SECONDS, max_seconds: MAX_CURING_DURATION_SECONDS }
+                }
+                Either::First(_) => CureEvent::Tap,
+                Either::Second(_) => {
+                    events::record(Event::Aborted { source: Source::Remote });
+                    CureEvent::Abort
+                }
+            },
+        }
+    };
+    let lid_opened = async {
+        lid::wait_for_open(lid).await;
+        CureEvent::LidOpened
+    };
+    // Past the soft limit: pause until the chamber cools (thermistor
+    // builds with HEAT_PAUSE_C set, UV phases only - a rest is cooling
+    // down already)
+    let too_warm = async {
+        #[cfg(feature = "thermistor")]
+        if uv_on {
+            thermal::wait_until_too_warm().await;
+            return CureEvent::TooWarm;
+        }
+        core::future::pending::<CureEvent<'static>>().await
+    };
+    let paused = async {
+        match select(lid_opened, too_warm).await {
+            Either::First(event) | Either::Second(event) => event,
+        }
+    };
+    // The UV sensor sees nothing with the UV on: the lamp or relay has failed
+    // (uv-sensor builds, UV phases only)
+    let lamp_failed = async {
+        #[cfg(feature = "uv-sensor")]
+        if uv_on {
+            let millivolts = uv_sensor::wait_for_lamp_failure().await;
+            events::record(Event::UvLampFailed { millivolts });
+            return CureEvent::Fault(FaultCode::LampDark);
+        }
+        core::future::pending::<CureEvent<'static>>().await
+    };
+    // The lamp current is outside its window: the LED string is open or
+    // shorted (ina219 builds, UV phases only)
+    let lamp_current = async {
+        #[cfg(feature = "ina219")]
+        if uv_on {
+            let (fault, milliamps) = ina219::wait_for_fault().await;
+            events::record(Event::LampCurrentFault { fault, milliamps });
+            return CureEvent::Fault(fault.fault_code());
+        }
+        core::future::pending::<CureEvent<'static>>().await
+    };
+    // The relay didn't close when the UV came on (relay-feedback builds,
+    // UV phases only)
+    let relay_stuck = async {
+        #[cfg(feature = "relay-feedback")]
+        if uv_on {
+            uv_output::wait_for_stuck_open().await;
+            return CureEvent::Fault(FaultCode::RelayStuck);
+        }
+        core::future::pending::<CureEvent<'static>>().await
+    };
+    let faults = async {
+        match select4(overheated(), lamp_failed, lamp_current, relay_stuck).await {
+            Either4::First(event) | Either4::Second(event) | Either4::Third(event) | Either4::Fourth(event) => event,
+        }
+    };
+    
+    match select4(time_up, stop, paused, faults).await {
+        Either4::First(event) | Either4::Second(event) | Either4::Third(event) | Either4::Fourth(event) => event,
+    }
+}
+
+/// Wait until the chamber is too hot - or there's no reading from the
+/// thermistor, which is just as unsafe (thermistor builds; never otherwise)
+async fn overheated() -> CureEvent<'static> {
+    #[cfg(feature = "thermistor")]
+    thermal::wait_for_overheat().await;
+    #[cfg(not(feature = "thermistor"))]
+    core::future::pending::<()>().await;
+    #[cfg(feature = "thermistor")]
+    if thermal::latest_centi_celsius().is_none() {
+        return CureEvent::Fault(FaultCode::SensorMissing);
+    }
+    #[cfg(feature = "thermistor")]
+    events::record(Event::Overheated);
+    CureEvent::Fault(FaultCode::OverTemp)
+}
+
+/// Preheat the chamber before the UV comes on (heater builds)
+/// 
+/// Holding the button (or turning the start switch OFF, or a remote stop)
+/// still aborts. The heater is always off again before any UV.
+#[cfg(feature = "heater")]
+async fn preheat(heater: &mut heater::Heater<'_>) -> CureEvent<'static> {
+    if PREHEAT_SECONDS == 0 {
+        return CureEvent::Armed;
+    }
+    remote::set_status(CureStatus::Preheating);
+    let button_stop = async {
+        match START_INPUT {
+            StartInput::Momentary => loop {
+                if let Gesture::Hold = button::wait_for_gesture(ABORT_HOLD_MS).await {
+                    events::record(Event::Aborted { source: Source::Button });
+                    break CureEvent::Abort;
+                }
+            },
+            StartInput::Latching => {
+                button::wait_for_release().await;
+                events::record(Event::SwitchedOff);
+                CureEvent::SwitchedOff
+            }
+        }
+    };
+    let stop_request = async {
+        match select(button_stop, remote::wait_for_stop()).await {
+            Either::First(stopped) => stopped,
+            Either::Second(_) => {
+                events::record(Event::Aborted { source: Source::Remote });
+                CureEvent::Abort
+            }
+        }
+    };
+    let result = select(heater::preheat(heater), stop_request).await;
+    heater.off();                     // Whatever happened, the heater goes off before any UV
+    match result {
+        Either::First(_) => CureEvent::Armed,
+        Either::Second(stopped) => stopped,
+    }
+}
+
+/// Log the remaining cure time every STATUS_REPORT_INTERVAL_SECONDS
+/// 
+/// Never finishes by itself - run it alongside the phase timer with select.
+/// The remaining time is always recalculated from countdown_end (the end of
+/// the phase, or of the whole duty cycle), so it can't drift.
+async fn report_progress(countdown_end: Instant) {
+    loop {
+        Timer::after_secs(STATUS_REPORT_INTERVAL_SECONDS).await;
+        let remaining = countdown_end.saturating_duration_since(Instant::now());
+        if remaining.as_secs() > 0 {
+            events::record(Event::Progress { remaining_seconds: remaining.as_secs() });
+        }
+    }
+}
+
+/// Save a checkpoint of the running cure at `next`, then every
+/// CHECKPOINT_INTERVAL_SECONDS, so it can be finished after a power cut -
+/// runs until cancelled (momentary button only, see config.rs)
+///
+/// `next` is kept from one phase (or pause) to the next, so pausing and
+/// resuming doesn't make it save any more often.
+async fn save_checkpoints(state: CureState<'_>, storage: &mut storage::Storage<'_>, next: &mut Instant, cure_number: u32) {
+    if CHECKPOINT_INTERVAL_SECONDS > 0 && matches!(START_INPUT, StartInput::Momentary) {
+        loop {
+            Timer::at(*next).await;
+            if let Some(checkpoint) = Checkpoint::of(&state, now_ms(), cure_number) {
+                match storage.save_checkpoint(&checkpoint) {
+                    Ok(true) => events::record(Event::Checkpoint { remaining_seconds: checkpoint.from.remaining_seconds }),
+                    Ok(false) => events::record(Event::CheckpointSkipped),
+                    Err(_) => events::record(Event::FlashWriteFailed { what: "checkpoint" }),
+                }
+            }
+            *next = Instant::now() + Duration::from_secs(CHECKPOINT_INTERVAL_SECONDS);
+        }
+    }
+    core::future::pending::<()>().await;
+}
+
+/// A tiny chirp at each full minute into a cure longer than a minute - then
+/// nothing, until cancelled
+///
+/// cure_start leaves out any time spent paused, so the chirps count minutes
+/// of actual curing. None that would land in the final countdown, or at the
+/// very end (the completion beeps are on their way).
+async fn minute_chirps(cure_start: Instant, cure_end: Instant) {
+    if MINUTE_CHIRP_MS > 0 {
+        let quiet_from = cure_end.checked_sub(Duration::from_secs(COUNTDOWN_BEEPS.max(1))).unwrap_or(Instant::MIN);
+        let mut minute = 1;
+        loop {
+            let at = cure_start + Duration::from_secs(60 * minute);
+            if at >= quiet_from {
+                break;
+            }
+            if at > Instant::now() {
+                Timer::at(at).await;
+                buzzer::play(AudioEvent::MinuteMark);
+            }
+            minute += 1;
+        }
+    }
+    core::future::pending::<()>().await;
+}
+
+/// A short beep at COUNTDOWN_BEEPS, ..., 2, 1 seconds before the end of the
+/// whole cure - then nothing, until cancelled
+///
+/// Runs alongside the phase timer, so it's started again after every pause
+/// or added time - beeps whose moment has already gone are skipped.
+async fn final_countdown(cure_end: Instant) {
+    for seconds_left in (1..=COUNTDOWN_BEEPS).rev() {
+        if let Some(at) = cure_end.checked_sub(Duration::from_secs(seconds_left)) {
+            if at > Instant::now() {
+                Timer::at(at).await;
+                buzzer::play(AudioEvent::Countdown);
+            }
+        }
+    }
+    core::future::pending::<()>().await;
+}
+
+/// Is the battery too low to start a cure? Says so if it is (battery
+/// builds - always false otherwise)
+fn battery_too_low() -> bool {
+    #[cfg(feature = "battery")]
+    if battery::is_low() {
+        events::record(Event::BatteryLow { millivolts: battery::millivolts().unwrap_or(0) });
+        battery::warn_low();
+        status_led::show(Led::Flashes { count: AUDIO_PATTERNS.low_battery.count, on_ms: 50, off_ms: 50 });
+        return true;
+    }
+    false
+}
+
+/// What can end a wait at idle, besides the button (and encoder or keypad)
+enum IdleEnd {
+    /// A remote start, with its time if it gave one
+    Remote(Option<u64>),
+    /// Settings changed remotely, to be saved (see settings.rs)
+    SettingsChanged,
+    /// Nothing at all for DORMANT_AFTER_IDLE_SECONDS - time to sleep
+    #[cfg(feature = "dormant")]
+    Sleepy,
+}
+
+/// Wait for a remote start or settings change - or, in dormant builds, for
+/// the idle time to run out (it starts again each time round the main loop,
+/// so any button press, turn or key puts it off)
+async fn wait_for_remote_or_sleep() -> IdleEnd {
+    let remote = async {
+        match select(remote::wait_for_start(), settings::wait_for_unsaved()).await {
+            Either::First(seconds) => IdleEnd::Remote(seconds),
+            Either::Second(()) => IdleEnd::SettingsChanged,
+        }
+    };
+    #[cfg(feature = "dormant")]
+    let remote = async {
+        match select(remote, Timer::after_secs(DORMANT_AFTER_IDLE_SECONDS)).await {
+            Either::First(end) => end,
+            Either::Second(()) => {
+                info!("Idle for {} seconds - going to sleep", DORMANT_AFTER_IDLE_SECONDS);
+                IdleEnd::Sleepy
+            }
+        }
+    };
+    remote.await
+}
+
+/// Wait while a hold at idle goes on: true once the button has been down
+/// for STANDBY_HOLD_MS since `pressed_at`, false if it's let go before then
+async fn held_until_standby(pressed_at: Instant) -> bool {
+    let standby_at = pressed_at + Duration::from_millis(STANDBY_HOLD_MS);
+    matches!(select(Timer::at(standby_at), button::wait_for_release()).await, Either::First(_))
+}
+
+/// Standby: UV, lights, LED and display off, no remote starts, until the
+/// button is pressed again
+///
+/// The temperature monitoring (and the fan, if it needs to run) carry on -
+/// except in dormant builds, where the whole chip sleeps until the press.
+async fn standby(inspection_light: &mut Output<'_, AnyPin>) {
+    // Already off at idle - but make sure
+    uv_output::set(false);
+    inspection_light.set_low();
+    status_led::show(Led::Off);
+    #[cfg(feature = "neopixel")]
+    neopixel::show(neopixel::Ring::Off);
+    #[cfg(feature = "st7789")]
+    tft::show(tft::Tft::Standby);
+    remote::set_status(CureStatus::Standby);
+    buzzer::play(AudioEvent::Confirm);   // One long beep = "going to sleep"
+    events::record(Event::Standby);
+
+    // Let go of the hold that got us here, then wait for a fresh press
+    button::wait_for_release().await;
+    #[cfg(not(feature = "dormant"))]
+    button::wait_for_press().await;
+    #[cfg(feature = "dormant")]
+    {
+        // Everything freezes as it is when the chip sleeps - so the relay
+        // has to have opened, the beep finished and the LED gone dark first
+        uv_output::wait_until_off().await;
+        Timer::after_millis(LAMP_SERVICE_LONG_MS + 100).await;
+        button::sleep_until_pressed().await;
+    }
+    events::record(Event::Woke);
+    buzzer::play(AudioEvent::Chirp);
+    remote::set_status(CureStatus::Idle);
+    if INSPECTION_LIGHT_ENABLED {
+        inspection_light.set_high();
+    }
+    status_led::show(Led::Breathe);
+    #[cfg(feature = "st7789")]
+    tft::show(tft::Tft::Idle);
+    // The caller waits for the release, so the wake press doesn't start a cure
+}
+
+/// Fire the UV briefly so part positioning can be checked ("peek")
+/// 
+/// Not counted as a cure - no beeps, no cooldown. Not available in
+/// one-button multi-duration builds, where holding the button starts the
+/// cure instead.
+#[cfg(any(not(feature = "multi-duration"), feature = "select-button"))]
+async fn peek(inspection_light: &mut Output<'_, AnyPin>, heat: &mut HeatEstimate, lid: &LidSwitch<'_>) {
+    if lid::is_open(lid) {
+        events::record(Event::LidOpenAtStart);
+        return;
+    }
+    events::record(Event::Peek { ms: PEEK_DURATION_MS });
+    inspection_light.set_low();
+    uv_output::set(true);
+    heat.uv_on(now_ms());
+    Timer::after_millis(PEEK_DURATION_MS).await;
+    uv_output::set(false);
+    heat.uv_off(now_ms());
+    uv_output::wait_until_off().await;
+    if INSPECTION_LIGHT_ENABLED {
+        inspection_light.set_high();
+    }
+}
@@ -0,0 +1,294 @@
+// mDNS / DNS-SD Advertisement (pico-w builds)
+//
+// Makes the curer findable on the local network without hunting through the
+// router's DHCP leases:
+// - `octo-curer.local` resolves to the curer's IP address (mDNS)
+// - the web interface is advertised as a service, so network browsers
+//   (e.g. Bonjour/Avahi tools, Home Assistant, apps) list it by themselves:
+//   `_octocurer._tcp` for curer-aware tools and `_http._tcp` for anything
+//   that just wants a web page (DNS-SD)
+//
+// This is a minimal responder: it answers A, PTR, SRV and TXT questions for
+// our own names on 224.0.0.251:5353 and ignores everything else. The names
+// are set with MDNS_HOSTNAME in config.rs.
+
+use defmt::*;
+use embassy_net::udp::{PacketMetadata, UdpSocket};
+use embassy_net::{IpEndpoint, Ipv4Address, Stack};
+use embassy_time::Timer;
+
+use crate::config::*;
+use crate::wifi::NetDriver;
+
+/// mDNS multicast group and port
+pub const MDNS_GROUP: Ipv4Address = Ipv4Address::new(224, 0, 0, 251);
+const MDNS_PORT: u16 = 5353;
+
+/// Multicast MAC address for MDNS_GROUP - the Wi-Fi chip has to be told to
+/// let these frames through
+pub const MDNS_MAC: [u8; 6] = [0x01, 0x00, 0x5e, 0x00, 0x00, 0xfb];
+
+/// How long other devices may cache our answers, in seconds
+const TTL_SECONDS: u32 = 120;
+
+/// Service types we advertise (the web interface, under two names)
+const SERVICES: [[&str; 2]; 2] = [["_octocurer", "_tcp"], ["_http", "_tcp"]];
+
+/// DNS-SD service enumeration name: "which services are there?"
+const SERVICE_ENUMERATION: [&str; 4] = ["_services", "_dns-sd", "_udp", "local"];
+
+// DNS record types and classes
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_SRV: u16 = 33;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+/// IN class with the "cache flush" bit: these records are ours alone
+const CLASS_IN_FLUSH: u16 = 0x8001;
+
+/// Answers mDNS questions about the curer (never returns)
+#[embassy_executor::task]
+pub async fn mdns_task(stack: &'static Stack<NetDriver>) -> ! {
+    let mut rx_meta = [PacketMetadata::EMPTY; 4];
+    let mut tx_meta = [PacketMetadata::EMPTY; 4];
+    let mut rx_buffer = [0u8; 1024];
+    let mut tx_buffer = [0u8; 1024];
+    let mut query = [0u8; 512];
+    let mut reply = [0u8; 512];
+
+    if stack.join_multicast_group(MDNS_GROUP).await.is_err() {
+        warn!("mDNS: can't join the multicast group");
+    }
+    let mut socket = UdpSocket::new(stack, &mut rx_meta, &mut rx_buffer, &mut tx_meta, &mut tx_buffer);
+    unwrap!(socket.bind(MDNS_PORT));
+    let group = IpEndpoint::new(MDNS_GROUP.into(), MDNS_PORT);
+
+    // Announce ourselves twice on startup, as mDNS asks
+    let everything = Wanted { host: true, services: [true; SERVICES.len()], instances: [true; SERVICES.len()], enumerate: false };
+    for _ in 0..2 {
+        if let Some(len) = build_reply(&mut reply, &everything, our_address(stack)) {
+            let _ = socket.send_to(&reply[..len], group).await;
+        }
+        Timer::after_secs(1).await;
+    }
+    info!("mDNS: advertising http://{}.local/", MDNS_HOSTNAME);
+
+    loop {
+        let Ok((len, _)) = socket.recv_from(&mut query).await else {
+            continue;
+        };
+        let Some(wanted) = parse_query(&query[..len]) else {
+            continue;
+        };
+        if let Some(len) = build_reply(&mut reply, &wanted, our_address(stack)) {
+            let _ = socket.send_to(&reply[..len], group).await;
+        }
+    }
+}
+
+/// Our current IPv4 address
+fn our_address(stack: &Stack<NetDriver>) -> [u8; 4] {
+    stack.config_v4().map_or([0; 4], |config| {
+        let mut octets = [0; 4];
+        octets.copy_from_slice(config.address.address().as_bytes());
+        octets
+    })
+}
+
+/// Which of our records a query asked for
+struct Wanted {
+    /// A record for MDNS_HOSTNAME.local
+    host: bool,
+    /// PTR for each service type (plus its SRV/TXT/A)
+    services: [bool; SERVICES.len()],
+    /// SRV/TXT for each service instance (plus A)
+    instances: [bool; SERVICES.len()],
+    /// PTRs listing our service types
+    enumerate: bool,
+}
+
+/// Work out which of our records a packet asks for (None = not a query, or
+/// nothing of ours)
+fn parse_query(msg: &[u8]) -> Option<Wanted> {
+    let flags = u16::from_be_bytes([*msg.get(2)?, *msg.get(3)?]);
+    if flags & 0x8000 != 0 {
+        return None;                   // A response from someone else
+    }
+    let questions = u16::from_be_bytes([*msg.get(4)?, *msg.get(5)?]);
+
+    let mut wanted = Wanted { host: false, services: [false; SERVICES.len()], instances: [false; SERVICES.len()], enumerate: false };
+    let mut any = false;
+    let mut pos = 12;
+    for _ in 0..questions {
+        let name = pos;
+        pos = skip_name(msg, pos)?;
+        let qtype = u16::from_be_bytes([*msg.get(pos)?, *msg.get(pos + 1)?]);
+        pos += 4;                      // Type and class
+
+        let is = |wanted_type: u16| qtype == wanted_type || qtype == TYPE_ANY;
+        if is(TYPE_A) && name_is(msg, name, &[MDNS_HOSTNAME, "local"]) {
+            wanted.host = true;
+            any = true;
+        }
+        if is(TYPE_PTR) && name_is(msg, name, &SERVICE_ENUMERATION) {
+            wanted.enumerate = true;
+            any = true;
+        }
+        for (i, &[service, proto]) in SERVICES.iter().enumerate() {
+            if is(TYPE_PTR) && name_is(msg, name, &[service, proto, "local"]) {
+                wanted.services[i] = true;
+                any = true;
+            }
+            if (is(TYPE_SRV) || is(TYPE_TXT)) && name_is(msg, name, &[MDNS_HOSTNAME, service, proto, "local"]) {
+                wanted.instances[i] = true;
+                any = true;
+            }
+        }
+    }
+    any.then_some(wanted)
+}
+
+/// Offset just past the name starting at `pos`
+fn skip_name(msg: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *msg.get(pos)?;
+        if len == 0 {
+            return Some(pos + 1);
+        }
+        if len & 0xC0 == 0xC0 {
+            return Some(pos + 2);      // Compression pointer ends the name
+        }
+        pos += 1 + len as usize;
+    }
+}
+
+/// Is the (possibly compressed) name at `pos` equal to `labels`? Case-insensitive
+fn name_is(msg: &[u8], mut pos: usize, labels: &[&str]) -> bool {
+    let mut matched = 0;
+    let mut jumps = 0;
+    loop {
+        let Some(&len) = msg.get(pos) else {
+            return false;
+        };
+        if len & 0xC0 == 0xC0 {
+            // Compression pointer - carry on reading somewhere else (with a
+            // limit, so a malicious loop of pointers can't hang us)
+            let Some(&low) = msg.get(pos + 1) else {
+                return false;
+            };
+            jumps += 1;
+            if jumps > 8 {
+                return false;
+            }
+            pos = ((len & 0x3F) as usize) << 8 | low as usize;
+            continue;
+        }
+        if len == 0 {
+            return matched == labels.len();
+        }
+        let Some(label) = msg.get(pos + 1..pos + 1 + len as usize) else {
+            return false;
+        };
+        if matched >= labels.len() || !label.eq_ignore_ascii_case(labels[matched].as_bytes()) {
+            return false;
+        }
+        matched += 1;
+        pos += 1 + len as usize;
+    }
+}
+
+/// Build a response packet with the wanted records - returns its length
+fn build_reply(buf: &mut [u8], wanted: &Wanted, address: [u8; 4]) -> Option<usize> {
+    let mut packet = Packet { buf, len: 0 };
+    // Header: ID 0, "authoritative response", answer count filled in later
+    packet.put(&[0, 0, 0x84, 0, 0, 0, 0, 0, 0, 0, 0, 0])?;
+    let mut answers: u16 = 0;
+    let mut need_host = wanted.host;
+
+    if wanted.enumerate {
+        for [service, proto] in SERVICES {
+            let at = packet.begin_record(&SERVICE_ENUMERATION, TYPE_PTR, CLASS_IN)?;
+            packet.name(&[service, proto, "local"])?;
+            packet.end_record(at);
+            answers += 1;
+        }
+    }
+
+    for (i, &[service, proto]) in SERVICES.iter().enumerate() {
+        let instance = [MDNS_HOSTNAME, service, proto, "local"];
+        if wanted.services[i] {
+            let at = packet.begin_record(&[service, proto, "local"], TYPE_PTR, CLASS_IN)?;
+            packet.name(&instance)?;
+            packet.end_record(at);
+            answers += 1;
+        }
+        if wanted.services[i] || wanted.instances[i] {
+            // SRV: where the service is - our host name and the web port
+            let at = packet.begin_record(&instance, TYPE_SRV, CLASS_IN_FLUSH)?;
+            packet.put(&[0, 0, 0, 0])?;          // Priority, weight
+            packet.put(&WEB_PORT.to_be_bytes())?;
+            packet.name(&[MDNS_HOSTNAME, "local"])?;
+            packet.end_record(at);
+
+            // TXT: the page is at the root
+            let at = packet.begin_record(&instance, TYPE_TXT, CLASS_IN_FLUSH)?;
+            packet.put(b"\x06path=/")?;
+            packet.end_record(at);
+
+            answers += 2;
+            need_host = true;                    // The SRV target needs an address
+        }
+    }
+
+    if need_host {
+        let at = packet.begin_record(&[MDNS_HOSTNAME, "local"], TYPE_A, CLASS_IN_FLUSH)?;
+        packet.put(&address)?;
+        packet.end_record(at);
+        answers += 1;
+    }
+
+    packet.buf[6..8].copy_from_slice(&answers.to_be_bytes());
+    Some(packet.len)
+}
+
+/// A DNS packet being written (None from any method = out of space)
+struct Packet<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl Packet<'_> {
+    fn put(&mut self, bytes: &[u8]) -> Option<()> {
+        self.buf.get_mut(self.len..self.len + bytes.len())?.copy_from_slice(bytes);
+        self.len += bytes.len();
+        Some(())
+    }
+
+    /// A name as length-prefixed labels (no compression)
+    fn name(&mut self, labels: &[&str]) -> Option<()> {
+        for label in labels {
+            self.put(&[label.len() as u8])?;
+            self.put(label.as_bytes())?;
+        }
+        self.put(&[0])
+    }
+
+    /// Write a record's name, type, class and TTL - returns where its data
+    /// length goes, for `end_record`
+    fn begin_record(&mut self, name: &[&str], rtype: u16, class: u16) -> Option<usize> {
+        self.name(name)?;
+        self.put(&rtype.to_be_bytes())?;
+        self.put(&class.to_be_bytes())?;
+        self.put(&TTL_SECONDS.to_be_bytes())?;
+        let at = self.len;
+        self.put(&[0, 0])?;
+        Some(at)
+    }
+
+    /// Fill in the data length of the record started at `at`
+    fn end_record(&mut self, at: usize) {
+        let data_len = (self.len - at - 2) as u16;
+        self.buf[at..at + 2].copy_from_slice(&data_len.to_be_bytes());
+    }
+}
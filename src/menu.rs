@@ -0,0 +1,145 @@
+// Settings Menu
+//
+// Change the cure time, the number of completion beeps, the cooldown and
+// silent mode with just the button - no computer needed. To open it,
+// triple-tap the button while idle (except with one button in multi-duration
+// builds, where a tap picks the preset), or hold it while powering on, wait for
+// the chirp (SETTINGS_MENU_HOLD_MS) and let go. Never during a cure.
+//
+// In the menu:
+//   - tap:  next choice for this setting (wraps round after the last)
+//   - hold: on to the next setting - after the sound, save and leave
+//
+// Which setting is being changed: the status LED keeps repeating 1 to 4
+// flashes, and each new setting starts with that many short beeps.
+//   1 = cure time         choices in SETTINGS_CURE_CHOICES
+//   2 = completion beeps  choices in SETTINGS_BEEP_CHOICES
+//   3 = cooldown          choices in SETTINGS_COOLDOWN_CHOICES_MS
+//   4 = sound             on (a chirp) or silent (two slow flashes)
+//
+// Which choice is picked: after each tap the LED flashes once per step up
+// the list (1 = the first, shortest choice). The beeps setting plays the
+// completion beeps themselves instead, so you hear what you'll get.
+// A TM1637/HD44780 display shows the cure time as it changes.
+//
+// Leaving (after the sound, or SETTINGS_MENU_TIMEOUT_SECONDS with no
+// presses) saves the settings to flash - one long beep says it's done. They
+// replace the config.rs values from then on, until changed here again.
+// The menu logic itself (what a tap or hold does) is in curing-core.
+
+use defmt::*;
+use embassy_futures::select::{select, Either};
+use embassy_time::Timer;
+
+use curing_core::menu::{MenuChoices, MenuItem, MenuUpdate, SettingsMenu};
+
+use crate::button::{self, Gesture};
+use crate::buzzer::{self, AudioEvent};
+use crate::config::*;
+use crate::events::{self, Event};
+use crate::settings;
+use crate::status_led::{self, Led};
+use crate::storage::{PersistedState, Storage};
+
+/// The choices from config.rs
+const CHOICES: MenuChoices<'static> = MenuChoices {
+    cure_seconds: SETTINGS_CURE_CHOICES,
+    completion_beeps: SETTINGS_BEEP_CHOICES,
+    cooldown_ms: SETTINGS_COOLDOWN_CHOICES_MS,
+};
+
+/// Run the settings menu until it's done, then save what was picked
+pub async fn run(storage: &mut Storage<'_>, persisted: &mut PersistedState) {
+    info!("Settings menu - tap for the next choice, hold for the next setting");
+    let mut menu = SettingsMenu::new(settings::menu_settings(), CHOICES);
+    announce(&menu);
+
+    let picked = loop {
+        let gesture = select(
+            button::wait_for_gesture(SETTINGS_NEXT_HOLD_MS),
+            Timer::after_secs(SETTINGS_MENU_TIMEOUT_SECONDS),
+        ).await;
+
+        let update = match gesture {
+            Either::First(Gesture::Hold) => menu.hold(),
+            Either::First(_) => menu.tap(),
+            Either::Second(()) => {
+                info!("Settings menu: nothing pressed for {} seconds - leaving", SETTINGS_MENU_TIMEOUT_SECONDS);
+                break menu.settings();
+            }
+        };
+
+        match update {
+            MenuUpdate::Changed { item, choice } => show_choice(&menu, item, choice),
+            MenuUpdate::NextItem(_) => announce(&menu),
+            MenuUpdate::Done(picked) => break picked,
+        }
+    };
+
+    settings::set_menu_settings(picked);
+    persisted.muted = picked.muted;
+    persisted.menu = Some(picked);
+    if storage.save(persisted).is_err() {
+        events::record(Event::FlashWriteFailed { what: "settings" });
+    }
+    info!("Settings saved: {} s cure, {} completion beeps, {} ms cooldown, silent mode {}",
+          settings::cure_seconds(), settings::completion_beeps(), settings::cooldown_ms(), picked.muted);
+
+    status_led::show(Led::Off);
+    buzzer::play(AudioEvent::Confirm);   // One long beep = saved
+    button::wait_for_release().await;   // Letting go of the last hold isn't a tap
+}
+
+/// Say which setting is being changed, and what it's set to
+fn announce(menu: &SettingsMenu<'_>) {
+    let item = menu.item();
+    let settings = menu.settings();
+    match item {
+        MenuItem::CureTime => info!("Setting 1: cure time = {} seconds", settings.cure_seconds),
+        MenuItem::CompletionBeeps => info!("Setting 2: completion beeps = {}", settings.completion_beeps),
+        MenuItem::Cooldown => info!("Setting 3: cooldown = {} ms", settings.cooldown_ms),
+        MenuItem::Sound => info!("Setting 4: sound {}", if settings.muted { "OFF" } else { "ON" }),
+    }
+    status_led::show(Led::Repeat { flashes: item.number(), flash_ms: 150, period_ms: 2000 });
+    buzzer::play_count(AudioEvent::MenuItem, item.number());
+    show_cure_time(menu);
+}
+
+/// Show the choice a tap just picked
+fn show_choice(menu: &SettingsMenu<'_>, item: MenuItem, choice: u32) {
+    let settings = menu.settings();
+    match item {
+        MenuItem::CureTime => {
+            info!("Cure time: {} seconds (choice {})", settings.cure_seconds, choice);
+            status_led::show(Led::Flashes { count: choice, on_ms: 150, off_ms: 200 });
+            show_cure_time(menu);
+        }
+        MenuItem::CompletionBeeps => {
+            info!("Completion beeps: {} (choice {})", settings.completion_beeps, choice);
+            buzzer::stop();   // Cut the last preview short, if still playing
+            buzzer::play_count(AudioEvent::Complete, settings.completion_beeps);
+            status_led::show(Led::Flashes { count: choice, on_ms: 150, off_ms: 200 });
+        }
+        MenuItem::Cooldown => {
+            info!("Cooldown: {} ms (choice {})", settings.cooldown_ms, choice);
+            status_led::show(Led::Flashes { count: choice, on_ms: 150, off_ms: 200 });
+        }
+        MenuItem::Sound => {
+            // Takes effect now, so the chirp can be heard
+            buzzer::set_muted(settings.muted);
+            if settings.muted {
+                info!("Silent mode ON");
+                status_led::show(Led::Flashes { count: 2, on_ms: 300, off_ms: 300 });
+            } else {
+                info!("Silent mode OFF");
+                buzzer::play(AudioEvent::Chirp);   // "Sound is back"
+            }
+        }
+    }
+}
+
+/// Put the cure time on the display, if there is one
+fn show_cure_time(_menu: &SettingsMenu<'_>) {
+    #[cfg(any(feature = "tm1637", feature = "hd44780"))]
+    crate::display::show_selected(None, _menu.settings().cure_seconds);
+}
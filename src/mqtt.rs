@@ -0,0 +1,267 @@
+// MQTT + Home Assistant (mqtt builds, Pico W only)
+//
+// Connects to an MQTT broker once Wi-Fi is up and:
+// - publishes the cure state, remaining seconds and (with a sensor) the
+//   chamber temperature every MQTT_PUBLISH_INTERVAL_SECONDS
+// - listens for "ON"/"OFF" on <base>/cure/set to start/abort a cure
+//   ("ON <token>"/"OFF <token>" once an API token is set - see commands.rs)
+// - publishes Home Assistant MQTT discovery messages, so the curer shows up
+//   in Home Assistant by itself as a switch plus sensors
+//
+// Topics (MQTT_BASE_TOPIC = "octo-curer"):
+//   octo-curer/availability   online / offline (offline is the broker's last will)
+//   octo-curer/cure           ON while a cure is running, else OFF
+//   octo-curer/cure/set       send ON to start, OFF to abort (followed by a
+//                             space and the API token, if one is set)
+//   octo-curer/state          idle, preheating, curing, resting, paused, cooldown
+//   octo-curer/remaining      seconds left in the current phase (0 when idle)
+//   octo-curer/ends_at        wall-clock time the phase ends, e.g.
+//                             2026-03-14T15:09:26 (empty when idle or the
+//                             clock isn't set)
+//   octo-curer/temperature    chamber °C (thermistor / bme280 builds)
+//   octo-curer/humidity       chamber %RH (bme280 builds)
+//
+// If the broker goes away, the task waits MQTT_RETRY_SECONDS and reconnects.
+
+use core::fmt::Write;
+
+use defmt::*;
+use embassy_futures::select::{select, Either};
+use embassy_net::tcp::TcpSocket;
+use embassy_net::{Ipv4Address, Stack};
+use embassy_time::{Duration, Timer};
+use heapless::String;
+use rust_mqtt::client::client::MqttClient;
+use rust_mqtt::client::client_config::{ClientConfig, MqttVersion};
+use rust_mqtt::packet::v5::publish_packet::QualityOfService;
+use rust_mqtt::packet::v5::reason_codes::ReasonCode;
+use rust_mqtt::utils::rng_generator::CountingRng;
+
+use crate::commands::{self, Command};
+use crate::config::*;
+use crate::remote::{self, CureStatus};
+use crate::wifi::NetDriver;
+
+/// Largest MQTT packet we send or receive (discovery messages are the biggest)
+const MQTT_BUFFER_SIZE: usize = 768;
+
+/// A topic or payload being built
+type Text = String<MQTT_BUFFER_SIZE>;
+
+/// Keeps the curer connected to the broker (never returns)
+#[embassy_executor::task]
+pub async fn mqtt_task(stack: &'static Stack<NetDriver>) -> ! {
+    let mut rx_buffer = [0u8; 1024];
+    let mut tx_buffer = [0u8; 1024];
+    let mut mqtt_rx = [0u8; MQTT_BUFFER_SIZE];
+    let mut mqtt_tx = [0u8; MQTT_BUFFER_SIZE];
+
+    loop {
+        let mut socket = TcpSocket::new(stack, &mut rx_buffer, &mut tx_buffer);
+        let [a, b, c, d] = MQTT_BROKER_IP;
+        if let Err(e) = socket.connect((Ipv4Address::new(a, b, c, d), MQTT_BROKER_PORT)).await {
+            warn!("MQTT: can't reach broker: {}", e);
+        } else {
+            let availability = topic("availability");
+            let mut config = ClientConfig::new(MqttVersion::MQTTv5, CountingRng(20_000));
+            config.add_client_id(MQTT_NODE_ID);
+            config.add_max_subscribe_qos(QualityOfService::QoS1);
+            config.add_will(&availability, b"offline", true);
+            config.max_packet_size = MQTT_BUFFER_SIZE as u32;
+            if let (Some(username), Some(password)) = (MQTT_USERNAME, MQTT_PASSWORD) {
+                config.add_username(username);
+                config.add_password(password);
+            }
+
+            let mut client = MqttClient::<_, 5, _>::new(
+                socket,
+                &mut mqtt_tx,
+                MQTT_BUFFER_SIZE,
+                &mut mqtt_rx,
+                MQTT_BUFFER_SIZE,
+                config,
+            );
+            if let Err(e) = session(&mut client).await {
+                warn!("MQTT: connection lost: {}", Debug2Format(&e));
+            }
+        }
+        Timer::after_secs(MQTT_RETRY_SECONDS).await;
+    }
+}
+
+/// One broker connection - returns when it fails
+async fn session<T>(client: &mut MqttClient<'_, T, 5, CountingRng>) -> Result<(), ReasonCode>
+where
+    T: embedded_io_async::Read + embedded_io_async::Write,
+{
+    client.connect_to_broker().await?;
+    info!("MQTT: connected to broker");
+
+    publish_discovery(client).await?;
+    client.send_message(&topic("availability"), b"online", QualityOfService::QoS1, true).await?;
+    client.subscribe_to_topic(&topic("cure/set")).await?;
+
+    loop {
+        publish_state(client).await?;
+
+        // Wait for a command, or until it's time to publish again
+        // (a message only ever arrives in one piece in practice; if the timer
+        // ever cut one in half, the broken connection is simply reconnected)
+        let wait = Timer::after(Duration::from_secs(MQTT_PUBLISH_INTERVAL_SECONDS));
+        let command = match select(client.receive_message(), wait).await {
+            Either::First(Ok((_, payload))) => {
+                let (word, token) = split_token(payload);
+                match word {
+                    b"ON" => Some((Command::Start(None), token)),
+                    b"OFF" => Some((Command::Stop, token)),
+                    _ => None,
+                }
+            }
+            Either::First(Err(e)) => return Err(e),
+            Either::Second(_) => None,
+        };
+        if let Some((command, token)) = command {
+            match commands::authorize(&command, token).and_then(|_| commands::execute(command)) {
+                Ok(_) => info!("MQTT: command accepted"),
+                Err(e) => warn!("MQTT: command refused: {}", e.message()),
+            }
+            // Give the main loop a moment to act, so the next state is fresh
+            Timer::after_millis(200).await;
+        }
+    }
+}
+
+/// Split "ON <token>" into the command word and the token (None if
+/// there's no token after it)
+fn split_token(payload: &[u8]) -> (&[u8], Option<&[u8]>) {
+    match payload.iter().position(|&b| b == b' ') {
+        Some(space) => (&payload[..space], Some(&payload[space + 1..])),
+        None => (payload, None),
+    }
+}
+
+/// Publish cure state, remaining time, end time and the chamber climate
+async fn publish_state<T>(client: &mut MqttClient<'_, T, 5, CountingRng>) -> Result<(), ReasonCode>
+where
+    T: embedded_io_async::Read + embedded_io_async::Write,
+{
+    let status = remote::status();
+    let running = !matches!(status, CureStatus::Idle | CureStatus::Cooldown | CureStatus::Standby);
+    let on_off: &[u8] = if running { b"ON" } else { b"OFF" };
+    client.send_message(&topic("cure"), on_off, QualityOfService::QoS0, true).await?;
+    client.send_message(&topic("state"), status.name().as_bytes(), QualityOfService::QoS0, true).await?;
+
+    let mut payload = Text::new();
+    let _ = write!(payload, "{}", status.remaining_seconds().unwrap_or(0));
+    client.send_message(&topic("remaining"), payload.as_bytes(), QualityOfService::QoS0, false).await?;
+
+    payload.clear();
+    if let Some(time) = status.ends_at() {
+        let _ = write!(payload, "{}", time);
+    }
+    client.send_message(&topic("ends_at"), payload.as_bytes(), QualityOfService::QoS0, false).await?;
+
+    #[cfg(any(feature = "thermistor", feature = "bme280"))]
+    if let Some(centi) = crate::sensors::chamber_centi_celsius() {
+        payload.clear();
+        let sign = if centi < 0 { "-" } else { "" };
+        let _ = write!(payload, "{}{}.{:02}", sign, centi.abs() / 100, centi.abs() % 100);
+        client.send_message(&topic("temperature"), payload.as_bytes(), QualityOfService::QoS0, false).await?;
+    }
+
+    #[cfg(feature = "bme280")]
+    if let Some(reading) = crate::climate::latest() {
+        payload.clear();
+        let _ = write!(payload, "{}.{:02}", reading.centi_percent_rh / 100, reading.centi_percent_rh % 100);
+        client.send_message(&topic("humidity"), payload.as_bytes(), QualityOfService::QoS0, false).await?;
+    }
+    Ok(())
+}
+
+/// Tell Home Assistant about our switch and sensors (retained, so it finds
+/// them again after a restart)
+async fn publish_discovery<T>(client: &mut MqttClient<'_, T, 5, CountingRng>) -> Result<(), ReasonCode>
+where
+    T: embedded_io_async::Read + embedded_io_async::Write,
+{
+    // The switch: ON starts a cure, OFF aborts it
+    let mut extra = Text::new();
+    let _ = write!(
+        extra,
+        "\"command_topic\":\"{}/cure/set\",\"state_topic\":\"{}/cure\",\"icon\":\"mdi:lightbulb-fluorescent-tube\"",
+        MQTT_BASE_TOPIC, MQTT_BASE_TOPIC
+    );
+    announce(client, "switch", "cure", "UV Cure", &extra).await?;
+
+    extra.clear();
+    let _ = write!(extra, "\"state_topic\":\"{}/state\",\"icon\":\"mdi:state-machine\"", MQTT_BASE_TOPIC);
+    announce(client, "sensor", "state", "Cure State", &extra).await?;
+
+    extra.clear();
+    let _ = write!(
+        extra,
+        "\"state_topic\":\"{}/remaining\",\"unit_of_measurement\":\"s\",\"device_class\":\"duration\"",
+        MQTT_BASE_TOPIC
+    );
+    announce(client, "sensor", "remaining", "Time Remaining", &extra).await?;
+
+    // Plain text, not a timestamp sensor - the clock has no time zone
+    extra.clear();
+    let _ = write!(extra, "\"state_topic\":\"{}/ends_at\",\"icon\":\"mdi:clock-end\"", MQTT_BASE_TOPIC);
+    announce(client, "sensor", "ends_at", "Cure Ends At", &extra).await?;
+
+    #[cfg(any(feature = "thermistor", feature = "bme280"))]
+    {
+        extra.clear();
+        let _ = write!(
+            extra,
+            "\"state_topic\":\"{}/temperature\",\"unit_of_measurement\":\"°C\",\"device_class\":\"temperature\"",
+            MQTT_BASE_TOPIC
+        );
+        announce(client, "sensor", "temperature", "Chamber Temperature", &extra).await?;
+    }
+
+    #[cfg(feature = "bme280")]
+    {
+        extra.clear();
+        let _ = write!(
+            extra,
+            "\"state_topic\":\"{}/humidity\",\"unit_of_measurement\":\"%\",\"device_class\":\"humidity\"",
+            MQTT_BASE_TOPIC
+        );
+        announce(client, "sensor", "humidity", "Chamber Humidity", &extra).await?;
+    }
+    Ok(())
+}
+
+/// Publish one discovery message: homeassistant/<component>/<node>/<object>/config
+async fn announce<T>(
+    client: &mut MqttClient<'_, T, 5, CountingRng>,
+    component: &str,
+    object_id: &str,
+    name: &str,
+    extra: &str,
+) -> Result<(), ReasonCode>
+where
+    T: embedded_io_async::Read + embedded_io_async::Write,
+{
+    let mut discovery_topic: String<128> = String::new();
+    let _ = write!(discovery_topic, "homeassistant/{}/{}/{}/config", component, MQTT_NODE_ID, object_id);
+
+    let mut payload = Text::new();
+    let _ = write!(
+        payload,
+        "{{\"name\":\"{}\",\"unique_id\":\"{}_{}\",{},\
+         \"availability_topic\":\"{}/availability\",\
+         \"device\":{{\"identifiers\":[\"{}\"],\"name\":\"UV Resin Curer\",\"manufacturer\":\"octo-curer\"}}}}",
+        name, MQTT_NODE_ID, object_id, extra, MQTT_BASE_TOPIC, MQTT_NODE_ID
+    );
+    client.send_message(&discovery_topic, payload.as_bytes(), QualityOfService::QoS1, true).await
+}
+
+/// Full topic name under MQTT_BASE_TOPIC, e.g. "octo-curer/state"
+fn topic(name: &str) -> String<64> {
+    let mut topic = String::new();
+    let _ = write!(topic, "{}/{}", MQTT_BASE_TOPIC, name);
+    topic
+}
@@ -0,0 +1,198 @@
+// NeoPixel Progress Ring (neopixel builds)
+//
+// A ring of WS2812 ("NeoPixel") LEDs shows how far the cure has got:
+// pixels fill one by one as it runs, the ring turns green when it has
+// finished and red on an over-temperature fault. Wire the ring to:
+//
+//   GPIO 20 ── DIN (through a ~330 Ω resistor)
+//   VBUS (5V) ── 5V, GND ── GND
+//
+// The data line is 3.3 V, which almost every ring accepts at 5 V supply.
+//
+// WS2812s need a precisely timed 800 kHz bit stream, so one of the PIO
+// state machines (PIO1 - PIO0 belongs to the Pico W Wi-Fi chip) generates
+// it in hardware. The ring has its own task: the cure supervisor passes on
+// each change of state with `follow()`, and the task animates the fill by
+// itself while a cure runs.
+
+use curing_core::cure::PhaseEnd;
+use curing_core::cycle::CureState;
+use defmt::*;
+use embassy_futures::select::{select, Either};
+use embassy_rp::bind_interrupts;
+use embassy_rp::clocks;
+use embassy_rp::peripherals::{PIN_20, PIO1};
+use embassy_rp::pio::{Common, Config, FifoJoin, InterruptHandler, Pio, ShiftConfig, ShiftDirection, StateMachine};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Instant, Timer};
+use fixed::types::U24F8;
+
+use crate::config::*;
+
+bind_interrupts!(struct Irqs {
+    PIO1_IRQ_0 => InterruptHandler<PIO1>;
+});
+
+/// How often the fill is redrawn while a cure runs, in milliseconds
+const FRAME_MS: u64 = 50;
+
+/// What the ring should show
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Ring {
+    Off,
+    /// A cure `done_ms` through its `total_ms` - counting up from now
+    /// while `running`, frozen while paused
+    Progress { done_ms: u64, total_ms: u64, running: bool },
+    /// Finished: green for NEOPIXEL_COMPLETE_SECONDS
+    Complete,
+    /// A fault: red until it has been cleared
+    Fault,
+}
+
+/// What to show next. A signal rather than a queue - only the latest matters.
+static REQUEST: Signal<CriticalSectionRawMutex, Ring> = Signal::new();
+
+/// Change what the ring shows - never waits
+pub fn show(ring: Ring) {
+    REQUEST.signal(ring);
+}
+
+/// Show the cure cycle going from `old` to `new`
+pub fn follow(old: &CureState<'_>, new: &CureState<'_>, now_ms: u64) {
+    match *new {
+        // Fills by itself while running, holds while paused
+        CureState::Curing { paused, .. } => {
+            if let Some((done_ms, total_ms)) = new.progress_ms(now_ms) {
+                show(Ring::Progress { done_ms, total_ms, running: paused.is_none() });
+            }
+        }
+        // Green when done, red on a fault (until cleared), dark if aborted
+        CureState::Finishing { end: PhaseEnd::Fault(_) } => show(Ring::Fault),
+        CureState::Finishing { end } if end.completed() => show(Ring::Complete),
+        CureState::Finishing { .. } => show(Ring::Off),
+        CureState::Idle if matches!(*old, CureState::Fault(_)) => show(Ring::Off),
+        _ => {}
+    }
+}
+
+/// Background task: drive the ring
+#[embassy_executor::task]
+pub async fn neopixel_task(pio: PIO1, pin: PIN_20) {
+    let Pio { mut common, sm0, .. } = Pio::new(pio, Irqs);
+    let mut ring = Ws2812::new(&mut common, sm0, pin);
+    info!("NeoPixel ring ready ({} pixels on GPIO 20)", NEOPIXEL_COUNT);
+
+    let mut showing = Ring::Off;
+    let mut since = Instant::now();
+
+    loop {
+        ring.write(&pixels(showing, since.elapsed().as_millis())).await;
+
+        // Wait for the next request - or, while a cure runs, for the next frame
+        let next = match showing {
+            Ring::Progress { running: true, .. } => select(REQUEST.wait(), Timer::after_millis(FRAME_MS)).await,
+            Ring::Complete => {
+                select(REQUEST.wait(), Timer::at(since + Duration::from_secs(NEOPIXEL_COMPLETE_SECONDS))).await
+            }
+            _ => Either::First(REQUEST.wait().await),
+        };
+        match next {
+            Either::First(request) => {
+                showing = request;
+                since = Instant::now();
+            }
+            Either::Second(()) if showing == Ring::Complete => showing = Ring::Off,
+            Either::Second(()) => {}
+        }
+    }
+}
+
+/// The colour of every pixel for `showing`, `elapsed_ms` after it was asked for
+fn pixels(showing: Ring, elapsed_ms: u64) -> [(u8, u8, u8); NEOPIXEL_COUNT] {
+    match showing {
+        Ring::Off => [(0, 0, 0); NEOPIXEL_COUNT],
+        Ring::Progress { done_ms, total_ms, running } => {
+            let done_ms = if running { done_ms + elapsed_ms } else { done_ms }.min(total_ms);
+            // In 1/256ths of a pixel, so the pixel being filled fades in
+            let filled = done_ms * NEOPIXEL_COUNT as u64 * 256 / total_ms.max(1);
+            let mut pixels = [(0, 0, 0); NEOPIXEL_COUNT];
+            for (i, pixel) in pixels.iter_mut().enumerate() {
+                let level = filled.saturating_sub(i as u64 * 256).min(256) as u32;
+                *pixel = scale(NEOPIXEL_PROGRESS_COLOR, level);
+            }
+            pixels
+        }
+        Ring::Complete => [NEOPIXEL_COMPLETE_COLOR; NEOPIXEL_COUNT],
+        Ring::Fault => [NEOPIXEL_FAULT_COLOR; NEOPIXEL_COUNT],
+    }
+}
+
+/// `color` at `level`/256 brightness
+fn scale((r, g, b): (u8, u8, u8), level: u32) -> (u8, u8, u8) {
+    let dim = |c: u8| (c as u32 * level / 256) as u8;
+    (dim(r), dim(g), dim(b))
+}
+
+/* ===========================================
+   WS2812 PIO DRIVER
+   =========================================== */
+
+/// A WS2812 chain on one PIO state machine
+struct Ws2812<'d> {
+    sm: StateMachine<'d, PIO1, 0>,
+}
+
+impl<'d> Ws2812<'d> {
+    fn new(pio: &mut Common<'d, PIO1>, mut sm: StateMachine<'d, PIO1, 0>, pin: PIN_20) -> Self {
+        // Each bit is 10 PIO cycles: 2 high, then 5 high (a 1) or low (a 0),
+        // then 3 low - so a 1 is a long pulse and a 0 a short one
+        const T1: u8 = 2;
+        const T2: u8 = 5;
+        const T3: u8 = 3;
+        const CYCLES_PER_BIT: u32 = (T1 + T2 + T3) as u32;
+
+        let side_set = pio::SideSet::new(false, 1, false);
+        let mut a: pio::Assembler<32> = pio::Assembler::new_with_side_set(side_set);
+        let mut wrap_target = a.label();
+        let mut wrap_source = a.label();
+        let mut do_zero = a.label();
+        a.set_with_side_set(pio::SetDestination::PINDIRS, 1, 0);
+        a.bind(&mut wrap_target);
+        a.out_with_delay_and_side_set(pio::OutDestination::X, 1, T3 - 1, 0);          // Low: end of the last bit
+        a.jmp_with_delay_and_side_set(pio::JmpCondition::XIsZero, &mut do_zero, T1 - 1, 1); // High: start of this one
+        a.jmp_with_delay_and_side_set(pio::JmpCondition::Always, &mut wrap_target, T2 - 1, 1); // Still high: a 1
+        a.bind(&mut do_zero);
+        a.nop_with_delay_and_side_set(T2 - 1, 0);                                      // Low already: a 0
+        a.bind(&mut wrap_source);
+        let program = a.assemble_with_wrap(wrap_source, wrap_target);
+
+        let mut config = Config::default();
+        let out_pin = pio.make_pio_pin(pin);
+        config.set_out_pins(&[&out_pin]);
+        config.set_set_pins(&[&out_pin]);
+        config.use_program(&pio.load_program(&program), &[&out_pin]);
+
+        // 800 kHz bits, worked out in kHz so the numbers fit
+        let bit_khz = U24F8::from_num(800 * CYCLES_PER_BIT);
+        config.clock_divider = U24F8::from_num(clocks::clk_sys_freq() / 1000) / bit_khz;
+
+        // 24 bits per pixel, most significant first
+        config.fifo_join = FifoJoin::TxOnly;
+        config.shift_out = ShiftConfig { auto_fill: true, threshold: 24, direction: ShiftDirection::Left };
+
+        sm.set_config(&config);
+        sm.set_enable(true);
+        Self { sm }
+    }
+
+    /// Send a colour to every pixel
+    async fn write(&mut self, pixels: &[(u8, u8, u8)]) {
+        for &(r, g, b) in pixels {
+            // WS2812s want green, red, blue - in the top 24 bits
+            let word = (u32::from(g) << 24) | (u32::from(r) << 16) | (u32::from(b) << 8);
+            self.sm.tx().wait_push(word).await;
+        }
+        Timer::after_micros(60).await;   // Low for 50 µs+ = "show it"
+    }
+}
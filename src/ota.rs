@@ -0,0 +1,305 @@
+// Over-the-air Firmware Updates (ota builds, Pico W only)
+//
+// ota builds run under the bootloader in bootloader/, with flash split into
+// two firmware slots (see memory/ota.x):
+// - ACTIVE holds the firmware that's running
+// - DFU receives new firmware, uploaded over Wi-Fi (POST /firmware, see
+//   web.rs) or the USB serial port (`update`, see usb_serial.rs)
+//
+// An update goes:
+// 1. `Upload` writes the new firmware into DFU one 4K sector at a time,
+//    working out its CRC-32 as it goes
+// 2. `Upload::finish` checks the CRC against the one the uploader sent, reads
+//    the whole slot back and checks it again, then tells the bootloader an
+//    update is ready
+// 3. Once the curer is idle it restarts; the bootloader swaps the two slots
+//    and starts the new firmware
+// 4. The new firmware calls `mark_booted` once Wi-Fi is up. If it crashes
+//    or hangs before then (the watchdog catches hangs), the next restart
+//    swaps the old firmware back in - rollback
+//
+// Uploads are refused while a cure runs (erasing flash pauses everything
+// else for ~50 ms at a time), and stop if a cure is started meanwhile.
+
+use core::cell::Cell;
+use core::ptr::addr_of;
+
+use defmt::*;
+use embassy_boot_rp::{AlignedBuffer, BlockingFirmwareUpdater, FirmwareUpdaterConfig};
+use embassy_embedded_hal::flash::partition::BlockingPartition;
+use embassy_rp::flash::{Blocking, Flash, ERASE_SIZE};
+use embassy_rp::peripherals::{FLASH, WATCHDOG};
+use embassy_rp::watchdog::Watchdog;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_time::{Duration, Timer};
+
+use crate::remote::{self, CureStatus};
+use crate::storage::{SharedFlash, FLASH_SIZE};
+
+/// The firmware is reset if the watchdog isn't fed for this long
+/// (about the longest the RP2040 watchdog can count)
+const WATCHDOG_TIMEOUT_SECONDS: u64 = 8;
+
+/// How often the watchdog is fed
+const WATCHDOG_FEED_SECONDS: u64 = 2;
+
+// Flash offsets of the bootloader's state sector and the DFU slot (memory/ota.x)
+extern "C" {
+    static __bootloader_state_start: u32;
+    static __bootloader_state_end: u32;
+    static __bootloader_dfu_start: u32;
+    static __bootloader_dfu_end: u32;
+}
+
+/// Part of the shared flash chip, as the firmware updater sees it
+type Partition = BlockingPartition<'static, CriticalSectionRawMutex, Flash<'static, FLASH, Blocking, FLASH_SIZE>>;
+
+/// The flash chip, shared with storage.rs (set by `init`)
+static SHARED_FLASH: Mutex<CriticalSectionRawMutex, Cell<Option<&'static SharedFlash>>> = Mutex::new(Cell::new(None));
+
+/// Hand over the shared flash chip - call at startup, before Wi-Fi comes up
+pub fn init(flash: &'static SharedFlash) {
+    SHARED_FLASH.lock(|shared| shared.set(Some(flash)));
+}
+
+/// Feeds the watchdog the bootloader left running (never returns)
+///
+/// If the firmware hangs, this stops running and the chip resets.
+#[embassy_executor::task]
+pub async fn watchdog_task(watchdog: WATCHDOG) -> ! {
+    let mut watchdog = Watchdog::new(watchdog);
+    watchdog.start(Duration::from_secs(WATCHDOG_TIMEOUT_SECONDS));
+    loop {
+        watchdog.feed();
+        Timer::after_secs(WATCHDOG_FEED_SECONDS).await;
+    }
+}
+
+/// Tell the bootloader this firmware works, so it's kept
+///
+/// Until this is called after an update, the next restart rolls back to
+/// the previous firmware. Harmless to call on every boot.
+pub fn mark_booted() {
+    match with_updater(|updater| updater.mark_booted()) {
+        Ok(()) => info!("OTA: firmware marked as good"),
+        Err(e) => warn!("OTA: can't mark firmware as good: {}", Debug2Format(&e)),
+    }
+}
+
+/// Why a firmware upload was refused or failed
+#[derive(Debug, PartialEq, Eq)]
+pub enum UploadError {
+    /// A cure is running
+    Busy,
+    /// No CRC-32 was sent with the firmware
+    MissingChecksum,
+    /// Empty, or too big for the firmware slot
+    BadLength,
+    /// The upload stopped before all the bytes arrived
+    Incomplete,
+    /// The firmware doesn't match the CRC-32 it was sent with
+    ChecksumMismatch,
+    /// Writing or reading flash failed
+    FlashError,
+    /// An API token is set and the upload didn't come with it
+    #[cfg_attr(not(feature = "pico-w"), allow(dead_code))]  // Only web uploads check it
+    Unauthorized,
+}
+
+impl UploadError {
+    /// Short explanation to send back to the user
+    #[cfg_attr(not(feature = "usb-serial"), allow(dead_code))]  // Only the serial port shows it
+    pub fn message(&self) -> &'static str {
+        match self {
+            UploadError::Busy => "busy - wait for the cure to finish",
+            UploadError::MissingChecksum => "the firmware's CRC-32 is missing",
+            UploadError::BadLength => "firmware is empty or too big",
+            UploadError::Incomplete => "upload cut short",
+            UploadError::ChecksumMismatch => "CRC-32 doesn't match - firmware damaged",
+            UploadError::FlashError => "flash error",
+            UploadError::Unauthorized => "API token missing or wrong",
+        }
+    }
+
+    /// Stable machine-readable name, used in JSON responses
+    pub fn code(&self) -> &'static str {
+        match self {
+            UploadError::Busy => "busy",
+            UploadError::MissingChecksum => "missing_checksum",
+            UploadError::BadLength => "bad_length",
+            UploadError::Incomplete => "incomplete",
+            UploadError::ChecksumMismatch => "checksum_mismatch",
+            UploadError::FlashError => "flash_error",
+            UploadError::Unauthorized => "unauthorized",
+        }
+    }
+}
+
+/// New firmware being written into the DFU slot
+pub struct Upload {
+    /// Total firmware size in bytes
+    length: usize,
+    /// CRC-32 the uploader says the firmware has
+    expected_crc: u32,
+    /// CRC-32 of what has arrived so far
+    crc: u32,
+    /// Bytes received so far
+    received: usize,
+    /// Bytes already written to flash (whole sectors)
+    written: usize,
+    /// The sector being filled
+    page: [u8; ERASE_SIZE],
+    filled: usize,
+}
+
+impl Upload {
+    /// Get ready for `length` bytes of firmware with the given CRC-32
+    pub fn begin(length: usize, expected_crc: u32) -> Result<Self, UploadError> {
+        if !matches!(remote::status(), CureStatus::Idle) {
+            return Err(UploadError::Busy);
+        }
+        // The bootloader needs one spare sector in DFU to swap the slots
+        let (_, dfu_size) = dfu_region();
+        if length == 0 || length > dfu_size as usize - ERASE_SIZE {
+            return Err(UploadError::BadLength);
+        }
+        info!("OTA: receiving {} bytes of new firmware", length);
+        Ok(Self { length, expected_crc, crc: 0, received: 0, written: 0, page: [0xFF; ERASE_SIZE], filled: 0 })
+    }
+
+    /// Bytes still to come
+    pub fn remaining(&self) -> usize {
+        self.length - self.received
+    }
+
+    /// Add the next piece of firmware (anything past the end is ignored)
+    pub fn write(&mut self, data: &[u8]) -> Result<(), UploadError> {
+        // A cure started meanwhile - flash writes would stall it
+        if !matches!(remote::status(), CureStatus::Idle) {
+            return Err(UploadError::Busy);
+        }
+        let mut data = &data[..data.len().min(self.remaining())];
+        while !data.is_empty() {
+            let n = data.len().min(ERASE_SIZE - self.filled);
+            self.page[self.filled..self.filled + n].copy_from_slice(&data[..n]);
+            self.filled += n;
+            self.received += n;
+            data = &data[n..];
+            if self.filled == ERASE_SIZE || self.remaining() == 0 {
+                self.write_page()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Erase the next sector of DFU and write the page into it
+    fn write_page(&mut self) -> Result<(), UploadError> {
+        self.crc = crc32(self.crc, &self.page[..self.filled]);
+        self.page[self.filled..].fill(0xFF);        // Pad the last page like erased flash
+        let (offset, page) = (self.written, &self.page);
+        with_updater(|updater| updater.write_firmware(offset, page)).map_err(|e| {
+            warn!("OTA: flash write failed: {}", Debug2Format(&e));
+            UploadError::FlashError
+        })?;
+        self.written += ERASE_SIZE;
+        self.filled = 0;
+        Ok(())
+    }
+
+    /// Check the whole upload and tell the bootloader to swap it in at the
+    /// next restart
+    pub fn finish(self) -> Result<(), UploadError> {
+        if self.remaining() > 0 {
+            return Err(UploadError::Incomplete);
+        }
+        if self.crc != self.expected_crc {
+            warn!("OTA: CRC-32 {=u32:#x} doesn't match {=u32:#x}", self.crc, self.expected_crc);
+            return Err(UploadError::ChecksumMismatch);
+        }
+
+        // Read it all back - catches a flash write that didn't take
+        let (dfu_start, _) = dfu_region();
+        let mut buffer = [0u8; 256];
+        let mut crc = 0;
+        let mut offset = 0;
+        while offset < self.length {
+            let n = (self.length - offset).min(buffer.len());
+            flash()
+                .lock(|flash| flash.borrow_mut().blocking_read(dfu_start + offset as u32, &mut buffer[..n]))
+                .map_err(|_| UploadError::FlashError)?;
+            crc = crc32(crc, &buffer[..n]);
+            offset += n;
+        }
+        if crc != self.expected_crc {
+            warn!("OTA: firmware read back from flash is damaged");
+            return Err(UploadError::ChecksumMismatch);
+        }
+
+        with_updater(|updater| updater.mark_updated()).map_err(|_| UploadError::FlashError)?;
+        info!("OTA: new firmware verified - it starts at the next restart");
+        Ok(())
+    }
+}
+
+/// Wait until no cure is running, then restart into the new firmware
+pub async fn restart_when_idle() -> ! {
+    while !matches!(remote::status(), CureStatus::Idle) {
+        Timer::after_secs(1).await;
+    }
+    info!("OTA: restarting into the new firmware");
+    Timer::after_millis(100).await;                 // Let the last log lines out
+    cortex_m::peripheral::SCB::sys_reset()
+}
+
+/// A CRC-32 as sent by the uploader: hex, with or without "0x"
+pub fn parse_crc(text: &str) -> Option<u32> {
+    let text = text.trim();
+    let digits = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")).unwrap_or(text);
+    u32::from_str_radix(digits, 16).ok()
+}
+
+/// Run something with a firmware updater for the DFU slot
+fn with_updater<R>(f: impl FnOnce(&mut BlockingFirmwareUpdater<'_, Partition, Partition>) -> R) -> R {
+    let (dfu_start, dfu_size) = dfu_region();
+    let (state_start, state_size) = state_region();
+    let config = FirmwareUpdaterConfig {
+        dfu: BlockingPartition::new(flash(), dfu_start, dfu_size),
+        state: BlockingPartition::new(flash(), state_start, state_size),
+    };
+    let mut aligned = AlignedBuffer([0; 1]);
+    let mut updater = BlockingFirmwareUpdater::new(config, &mut aligned.0);
+    f(&mut updater)
+}
+
+fn flash() -> &'static SharedFlash {
+    unwrap!(SHARED_FLASH.lock(|shared| shared.get()), "ota::init wasn't called")
+}
+
+/// (flash offset, size) of the DFU slot
+fn dfu_region() -> (u32, u32) {
+    unsafe { region(addr_of!(__bootloader_dfu_start), addr_of!(__bootloader_dfu_end)) }
+}
+
+/// (flash offset, size) of the bootloader's state sector
+fn state_region() -> (u32, u32) {
+    unsafe { region(addr_of!(__bootloader_state_start), addr_of!(__bootloader_state_end)) }
+}
+
+/// The linker script's symbols are the values themselves - their addresses
+fn region(start: *const u32, end: *const u32) -> (u32, u32) {
+    let (start, end) = (start as u32, end as u32);
+    (start, end - start)
+}
+
+/// CRC-32 (the common zlib/Ethernet one), continuing from `crc` (0 to start)
+fn crc32(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
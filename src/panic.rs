@@ -0,0 +1,69 @@
+// Panic Handler
+//
+// A panic means a bug: the firmware has hit something it can't carry on
+// from, and stops. On its own, panic_probe would just print the message and
+// halt, leaving GPIO 10 however it was - so a panic mid-cure could leave the
+// UV LEDs on with nothing left to switch them off.
+//
+// This handler makes the outputs safe *first*, then logs and halts:
+//
+// 1. UV off: GPIO 10's output driver is cut - the "pin kill", high
+//    impedance, whichever driver was built in (relay, SSR or PWM MOSFET).
+//    Boards that switch on HIGH also get the pull-down, so the input can't
+//    float up; the active-LOW relay module is left floating, as it needs.
+// 2. Buzzer off (GPIO 7), and the heater (GPIO 14) in heater builds - the
+//    same way, pulled down. The cooling fan and turntable are left running
+//    on purpose: the fan keeps cooling a chamber that may have been hot, and
+//    a turning turntable hurts nothing.
+// 3. The panic message goes out over defmt, and the core stops on an
+//    undefined instruction, like panic_probe: a debug probe shows where it
+//    happened. Without one it just sits there, UV off, until it's power
+//    cycled (or the ota watchdog restarts it).
+//
+// The drivers that own these pins are stuck wherever the panic happened, so
+// the pins are switched straight through the RP2040's pad registers -
+// nothing to borrow, nothing to await.
+
+use embassy_rp::pac;
+
+use crate::config::*;
+
+/// The UV output pin (GPIO 10, see main.rs)
+const UV_PIN: usize = 10;
+
+/// The buzzer pin (GPIO 7)
+const BUZZER_PIN: usize = 7;
+
+/// The heater relay pin (GPIO 14)
+#[cfg(feature = "heater")]
+const HEATER_PIN: usize = 14;
+
+/// Does the UV output switch on when driven HIGH? (Then a pull-down keeps it off)
+const UV_ON_WHEN_HIGH: bool = cfg!(any(feature = "relay-active-high", feature = "relay-ssr", feature = "pwm-mosfet"))
+    || !RELAY_ACTIVE_LOW;
+
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    cortex_m::interrupt::disable();   // Nothing else runs from here on
+
+    // Safe first...
+    cut_pin(UV_PIN, UV_ON_WHEN_HIGH);
+    cut_pin(BUZZER_PIN, true);
+    #[cfg(feature = "heater")]
+    cut_pin(HEATER_PIN, true);
+
+    // ...then say what went wrong
+    defmt::error!("PANIC (outputs switched off): {}", defmt::Display2Format(info));
+
+    cortex_m::asm::udf()
+}
+
+/// Stop the pin driving anything, whatever peripheral owns it: the pad's
+/// output disable wins over the GPIO and PWM hardware alike
+fn cut_pin(pin: usize, pull_down: bool) {
+    pac::PADS_BANK0.gpio(pin).modify(|w| {
+        w.set_od(true);
+        w.set_pue(false);
+        w.set_pde(pull_down);
+    });
+}
@@ -0,0 +1,129 @@
+// Remote Control
+//
+// The link between the main loop and anything that controls the curer from
+// outside (the USB serial port, the Wi-Fi web page, an IR remote):
+// - commands go in through a channel: `send()` on one side,
+//   `wait_for_start()` / `wait_for_stop()` on the main loop side
+// - the main loop publishes what it's doing with `set_status()`, and
+//   remote interfaces read it back with `status()`
+//
+// In builds without any remote interface nothing ever sends a command, so
+// the main loop's waits simply never finish - no feature checks needed.
+
+use core::cell::Cell;
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::channel::Channel;
+use embassy_time::Instant;
+
+use crate::clock::{self, DateTime};
+
+/// Something a remote interface wants the curer to do
+pub enum RemoteCommand {
+    /// Start a cure - for this many seconds, or the normal time if None
+    Start(Option<u64>),
+    /// Stop (abort) the running cure
+    Stop,
+}
+
+/// What the curer is doing right now
+#[derive(Clone, Copy)]
+#[allow(dead_code)]  // Read only by remote interfaces and the display, and Preheating needs the heater
+pub enum CureStatus {
+    /// Waiting for a start
+    Idle,
+    /// Warming the chamber before the UV comes on
+    Preheating,
+    /// UV on until this instant
+    Curing { ends_at: Instant },
+    /// Rest phase of a cure profile, until this instant
+    Resting { ends_at: Instant },
+    /// Paused (button or lid) with this many seconds of the phase left
+    Paused { remaining_seconds: u64 },
+    /// Cure over, waiting out the cooldown
+    Cooldown,
+    /// Standby: everything off until the button is pressed
+    Standby,
+}
+
+#[cfg_attr(not(any(feature = "usb-serial", feature = "pico-w", feature = "uart-control", feature = "ir-remote")), allow(dead_code))]
+impl CureStatus {
+    /// Short lowercase name for status reports, e.g. "curing"
+    pub fn name(&self) -> &'static str {
+        match self {
+            CureStatus::Idle => "idle",
+            CureStatus::Preheating => "preheating",
+            CureStatus::Curing { .. } => "curing",
+            CureStatus::Resting { .. } => "resting",
+            CureStatus::Paused { .. } => "paused",
+            CureStatus::Cooldown => "cooldown",
+            CureStatus::Standby => "standby",
+        }
+    }
+
+    /// Seconds left in the current phase, if a timed phase is running
+    pub fn remaining_seconds(&self) -> Option<u64> {
+        match *self {
+            CureStatus::Curing { ends_at } | CureStatus::Resting { ends_at } => {
+                Some(ends_at.saturating_duration_since(Instant::now()).as_secs())
+            }
+            CureStatus::Paused { remaining_seconds } => Some(remaining_seconds),
+            _ => None,
+        }
+    }
+
+    /// Wall-clock time the current phase should end, if a timed phase is
+    /// running and the clock is set. Worked out fresh on every call, so it
+    /// moves on while paused and follows added time.
+    pub fn ends_at(&self) -> Option<DateTime> {
+        match *self {
+            CureStatus::Curing { ends_at } | CureStatus::Resting { ends_at } => clock::at(ends_at.as_millis()),
+            CureStatus::Paused { remaining_seconds } => {
+                clock::now().map(|now| DateTime::from_seconds(now.to_seconds() + remaining_seconds))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Commands waiting for the main loop
+static COMMANDS: Channel<CriticalSectionRawMutex, RemoteCommand, 4> = Channel::new();
+
+/// Latest status published by the main loop
+static STATUS: Mutex<CriticalSectionRawMutex, Cell<CureStatus>> = Mutex::new(Cell::new(CureStatus::Idle));
+
+/// Queue a command for the main loop - false if the queue is full
+#[cfg_attr(not(any(feature = "usb-serial", feature = "pico-w", feature = "uart-control", feature = "ir-remote")), allow(dead_code))]
+pub fn send(command: RemoteCommand) -> bool {
+    COMMANDS.try_send(command).is_ok()
+}
+
+/// Wait for a remote start command (stop commands are ignored - nothing to stop)
+pub async fn wait_for_start() -> Option<u64> {
+    loop {
+        if let RemoteCommand::Start(seconds) = COMMANDS.receive().await {
+            return seconds;
+        }
+    }
+}
+
+/// Wait for a remote stop command (start commands are ignored - already running)
+pub async fn wait_for_stop() {
+    loop {
+        if let RemoteCommand::Stop = COMMANDS.receive().await {
+            return;
+        }
+    }
+}
+
+/// Publish what the main loop is doing
+pub fn set_status(status: CureStatus) {
+    STATUS.lock(|current| current.set(status));
+}
+
+/// What the main loop is doing
+#[cfg_attr(not(any(feature = "usb-serial", feature = "pico-w", feature = "uart-control", feature = "ir-remote", feature = "keypad")), allow(dead_code))]
+pub fn status() -> CureStatus {
+    STATUS.lock(|current| current.get())
+}
@@ -0,0 +1,193 @@
+// SD Card Cure Log (sd-log builds)
+//
+// Appends one line per cure to a CSV file on a microSD card, so there's a
+// long-term cure history without a computer connected. Wire an SD card
+// breakout (3.3 V logic) to SPI0:
+//
+//   GPIO 16 (SPI0 RX)  ── MISO / DO
+//   GPIO 17            ── CS
+//   GPIO 18 (SPI0 SCK) ── SCK / CLK
+//   GPIO 19 (SPI0 TX)  ── MOSI / DI
+//   3.3V ── VCC, GND ── GND
+//
+// The card must be FAT16 or FAT32 formatted. Each line of SD_LOG_FILE_NAME is:
+//   start,planned_s,uv_on_s,preset,max_temp_c,result,heat_pauses
+//   2026-10-16T14:30:00,300,300,Standard,31.50,completed,0
+// - start: date and time (clock.rs), or "+<seconds since power-on>" if the
+//   clock isn't set
+// - max_temp_c: hottest chamber reading during the cure (blank without a
+//   thermistor or BME280)
+// - result: completed, switched_off, aborted or overheated
+// - heat_pauses: how often the chamber passed the soft limit and the cure
+//   paused to cool down (always 0 without a thermistor)
+//
+// Nothing has to call this module: it subscribes to the event bus
+// (events.rs), notes when each cure started (CycleStarted) and builds a
+// record when it's over (CureFinished). Records are queued and only
+// written while no cure is running,
+// since a card write holds up everything else for tens of milliseconds.
+// If the card is missing or full, records wait in the queue and the write
+// is retried after the next cure.
+
+use core::fmt::Write as _;
+
+use defmt::*;
+use embassy_futures::select::{select, Either};
+use embassy_rp::gpio::{Level, Output};
+use embassy_rp::peripherals::{PIN_16, PIN_17, PIN_18, PIN_19, SPI0};
+use embassy_rp::spi::{self, Blocking, Spi};
+use embassy_sync::pubsub::WaitResult;
+use embassy_time::{Delay, Timer};
+use embedded_hal_bus::spi::ExclusiveDevice;
+use embedded_sdmmc::{Error, Mode, SdCard, SdCardError, TimeSource, Timestamp, VolumeIdx, VolumeManager};
+use heapless::{Deque, String};
+
+use crate::clock;
+use crate::config::*;
+use crate::events::{self, Event, Record};
+use crate::remote::{self, CureStatus};
+
+/// First line of a new log file
+const CSV_HEADER: &str = "start,planned_s,uv_on_s,preset,max_temp_c,result,heat_pauses\r\n";
+
+/// Records waiting to be written (more than this and the oldest is dropped)
+const QUEUE_LENGTH: usize = 8;
+
+/// One finished cure, put together from the events
+struct CureRecord {
+    /// When the cure started, in milliseconds since power-on
+    started_ms: u64,
+    /// Planned length in seconds (all phases of a profile)
+    planned_seconds: u64,
+    /// How long the UV was actually on, in milliseconds
+    uv_on_ms: u64,
+    /// Preset name (multi-duration builds), or "-"
+    preset: &'static str,
+    /// How it ended: completed, switched_off, aborted or overheated
+    result: &'static str,
+    /// Pauses for the chamber to cool down
+    heat_pauses: u32,
+}
+
+/// One formatted CSV line
+type Line = String<96>;
+
+type Card = SdCard<ExclusiveDevice<Spi<'static, SPI0, Blocking>, Output<'static, PIN_17>, Delay>, Delay>;
+
+/// Timestamps for the files' "modified" dates
+struct WallClock;
+
+impl TimeSource for WallClock {
+    fn get_timestamp(&self) -> Timestamp {
+        // The FAT epoch (1980-01-01) if the clock isn't set
+        let time = clock::now();
+        Timestamp {
+            year_since_1970: time.map_or(10, |t| (t.year - 1970) as u8),
+            zero_indexed_month: time.map_or(0, |t| t.month - 1),
+            zero_indexed_day: time.map_or(0, |t| t.day - 1),
+            hours: time.map_or(0, |t| t.hour),
+            minutes: time.map_or(0, |t| t.minute),
+            seconds: time.map_or(0, |t| t.second),
+        }
+    }
+}
+
+/// Background task: collect finished cures from the event bus and write
+/// them to the card
+#[embassy_executor::task]
+pub async fn sd_log_task(spi: SPI0, sck: PIN_18, mosi: PIN_19, miso: PIN_16, cs: PIN_17) {
+    let mut config = spi::Config::default();
+    config.frequency = SD_SPI_FREQUENCY_HZ;
+    let bus = Spi::new_blocking(spi, sck, mosi, miso, config);
+    let device = unwrap!(ExclusiveDevice::new(bus, Output::new(cs, Level::High), Delay));
+    let mut volumes = VolumeManager::new(SdCard::new(device, Delay), WallClock);
+    info!("SD log: cures will be appended to {}", SD_LOG_FILE_NAME);
+
+    let mut cure_events = events::subscribe();
+    let mut pending: Deque<Line, QUEUE_LENGTH> = Deque::new();
+    let mut write_due = false;
+    let mut started_ms = 0;
+    let mut max_temperature: Option<i32> = None;
+
+    loop {
+        match select(cure_events.next_message(), Timer::after_secs(1)).await {
+            Either::First(WaitResult::Message(Record { at_ms, event: Event::CycleStarted { .. } })) => started_ms = at_ms,
+            Either::First(WaitResult::Message(Record {
+                event: Event::CureFinished { result, planned_seconds, uv_on_ms, preset, heat_pauses },
+                ..
+            })) => {
+                let record = CureRecord { started_ms, planned_seconds, uv_on_ms, preset, result, heat_pauses };
+                if pending.is_full() {
+                    pending.pop_front();
+                    warn!("SD log: card not written for {} cures - oldest record dropped", QUEUE_LENGTH);
+                }
+                let _ = pending.push_back(format(&record, max_temperature.take()));
+                write_due = true;
+            }
+            Either::First(WaitResult::Message(_)) => {}
+            Either::First(WaitResult::Lagged(missed)) => warn!("SD log: {} events went by too fast - a cure may be missing", missed),
+            // Note the hottest reading while a cure runs
+            #[cfg(any(feature = "thermistor", feature = "bme280"))]
+            Either::Second(_) if is_curing() => {
+                if let Some(now) = crate::sensors::chamber_centi_celsius() {
+                    max_temperature = Some(max_temperature.map_or(now, |max| max.max(now)));
+                }
+            }
+            Either::Second(_) => {}
+        }
+
+        // Only touch the card between cures. If it fails, the records stay
+        // queued and the write is tried again after the next cure.
+        if write_due && !is_curing() {
+            write_due = false;
+            match append(&mut volumes, &pending) {
+                Ok(()) => {
+                    debug!("SD log: {} record(s) written", pending.len());
+                    pending.clear();
+                }
+                Err(e) => warn!("SD log: can't write to the card: {}", Debug2Format(&e)),
+            }
+        }
+    }
+}
+
+/// Is a cure (or its preheat) running right now?
+fn is_curing() -> bool {
+    !matches!(remote::status(), CureStatus::Idle | CureStatus::Cooldown | CureStatus::Standby)
+}
+
+/// Write the lines to the end of the log file (creating it if needed)
+fn append(volumes: &mut VolumeManager<Card, WallClock>, lines: &Deque<Line, QUEUE_LENGTH>) -> Result<(), Error<SdCardError>> {
+    let mut volume = volumes.open_volume(VolumeIdx(0))?;
+    let mut root = volume.open_root_dir()?;
+    let mut file = root.open_file_in_dir(SD_LOG_FILE_NAME, Mode::ReadWriteCreateOrAppend)?;
+    if file.length() == 0 {
+        file.write(CSV_HEADER.as_bytes())?;
+    }
+    for line in lines.iter() {
+        file.write(line.as_bytes())?;
+    }
+    file.flush()           // The file, directory and volume close as they go out of scope
+}
+
+/// One CSV line for a cure
+fn format(record: &CureRecord, max_centi_celsius: Option<i32>) -> Line {
+    let mut line = Line::new();
+    let _ = match clock::at(record.started_ms) {
+        Some(time) => write!(line, "{}", time),
+        None => write!(line, "+{}", record.started_ms / 1000),
+    };
+    let _ = write!(
+        line,
+        ",{},{},{},",
+        record.planned_seconds,
+        (record.uv_on_ms + 500) / 1000,
+        record.preset
+    );
+    if let Some(centi) = max_centi_celsius {
+        let sign = if centi < 0 { "-" } else { "" };
+        let _ = write!(line, "{}{}.{:02}", sign, centi.abs() / 100, centi.abs() % 100);
+    }
+    let _ = write!(line, ",{},{}\r\n", record.result, record.heat_pauses);
+    line
+}
@@ -0,0 +1,13 @@
+// Chamber Temperature
+//
+// Several features want "the chamber temperature" without caring which
+// sensor measured it. This picks whichever one is built in: the thermistor
+// (`thermistor` feature) first, otherwise the BME280 (`bme280` feature).
+
+/// Latest chamber temperature in hundredths of a degree C (None = no valid reading)
+pub fn chamber_centi_celsius() -> Option<i32> {
+    #[cfg(feature = "thermistor")]
+    return crate::thermal::latest_centi_celsius();
+    #[cfg(not(feature = "thermistor"))]
+    return crate::climate::latest().map(|reading| reading.centi_celsius);
+}
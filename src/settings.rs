@@ -0,0 +1,334 @@
+// Runtime Settings
+//
+// Most settings are compile-time constants in config.rs. The few that can
+// also be changed while the curer is running (e.g. over the USB serial
+// port, or the on-device settings menu in menu.rs) live here. Each starts
+// at its config.rs value after power-on, until a saved value is restored.
+//
+// The menu saves what it picks to flash itself. A remote interface can't
+// (flash belongs to the main loop, and is only written at idle), so it
+// asks with `request_save()` and the main loop saves them when it can.
+
+use core::cell::Cell;
+use core::sync::atomic::{AtomicU32, AtomicU8, Ordering};
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::signal::Signal;
+
+use curing_core::menu::MenuSettings;
+
+use crate::config::*;
+
+/// Shortest button debounce time that can be set (same limit as config.rs)
+pub const MIN_BUTTON_DEBOUNCE_MS: u64 = 10;
+
+/// Longest button debounce time that can be set (same limit as config.rs)
+pub const MAX_BUTTON_DEBOUNCE_MS: u64 = 500;
+
+/// Current button debounce time in milliseconds
+static BUTTON_DEBOUNCE: AtomicU32 = AtomicU32::new(BUTTON_DEBOUNCE_MS as u32);
+
+/// Button debounce time in milliseconds
+pub fn button_debounce_ms() -> u64 {
+    BUTTON_DEBOUNCE.load(Ordering::Relaxed) as u64
+}
+
+/// Change the button debounce time - returns false if out of range
+pub fn set_button_debounce_ms(ms: u64) -> bool {
+    if !(MIN_BUTTON_DEBOUNCE_MS..=MAX_BUTTON_DEBOUNCE_MS).contains(&ms) {
+        return false;
+    }
+    BUTTON_DEBOUNCE.store(ms as u32, Ordering::Relaxed);
+    true
+}
+
+/// Current event log verbosity (a LogLevel as a number)
+static LOG_LEVEL: AtomicU8 = AtomicU8::new(LOG_VERBOSITY as u8);
+
+/// How much the event log prints (see events.rs)
+pub fn log_level() -> LogLevel {
+    match LOG_LEVEL.load(Ordering::Relaxed) {
+        0 => LogLevel::Error,
+        1 => LogLevel::Warn,
+        2 => LogLevel::Info,
+        _ => LogLevel::Debug,
+    }
+}
+
+/// Change the event log verbosity: 0 = errors only, 1 = + warnings,
+/// 2 = + cure steps, 3 = everything - returns false if out of range
+pub fn set_log_level(level: u64) -> bool {
+    if level > LogLevel::Debug as u64 {
+        return false;
+    }
+    LOG_LEVEL.store(level as u8, Ordering::Relaxed);
+    true
+}
+
+/// Current curing time in seconds (for a plain button press)
+static CURE_SECONDS: AtomicU32 = AtomicU32::new(CURING_DURATION_SECONDS as u32);
+
+/// Current number of "cure finished" beeps
+static COMPLETION_BEEP_COUNT: AtomicU32 = AtomicU32::new(COMPLETION_BEEPS);
+
+/// Current pause after each cure in milliseconds
+static COOLDOWN: AtomicU32 = AtomicU32::new(CYCLE_COOLDOWN_MS as u32);
+
+/// Curing time in seconds for a plain button press
+pub fn cure_seconds() -> u64 {
+    CURE_SECONDS.load(Ordering::Relaxed) as u64
+}
+
+/// How many beeps say "cure finished"
+pub fn completion_beeps() -> u32 {
+    COMPLETION_BEEP_COUNT.load(Ordering::Relaxed)
+}
+
+/// Pause after each cure in milliseconds
+pub fn cooldown_ms() -> u64 {
+    COOLDOWN.load(Ordering::Relaxed) as u64
+}
+
+/// The settings the menu changes, as they are now (silent mode is kept by
+/// buzzer.rs, which needs to know straight away)
+pub fn menu_settings() -> MenuSettings {
+    MenuSettings {
+        cure_seconds: cure_seconds(),
+        completion_beeps: completion_beeps(),
+        cooldown_ms: cooldown_ms(),
+        muted: crate::buzzer::is_muted(),
+    }
+}
+
+/// Use new menu settings (picked in the menu, set remotely, or restored
+/// from flash) - anything out of range keeps the value it had, and then
+/// this returns false
+pub fn set_menu_settings(settings: MenuSettings) -> bool {
+    let mut all_used = true;
+    if (1..=MAX_CURING_DURATION_SECONDS).contains(&settings.cure_seconds) {
+        CURE_SECONDS.store(settings.cure_seconds as u32, Ordering::Relaxed);
+    } else {
+        all_used = false;
+    }
+    if (1..=10).contains(&settings.completion_beeps) {
+        COMPLETION_BEEP_COUNT.store(settings.completion_beeps, Ordering::Relaxed);
+    } else {
+        all_used = false;
+    }
+    if settings.cooldown_ms <= 60_000 {
+        COOLDOWN.store(settings.cooldown_ms as u32, Ordering::Relaxed);
+    } else {
+        all_used = false;
+    }
+    crate::buzzer::set_muted(settings.muted);
+    all_used
+}
+
+/// Current power-on behavior (a PowerOnBehavior as a number)
+static POWER_ON: AtomicU8 = AtomicU8::new(POWER_ON_BEHAVIOR as u8);
+
+/// What to do at the next power-on
+pub fn power_on_behavior() -> PowerOnBehavior {
+    match POWER_ON.load(Ordering::Relaxed) {
+        0 => PowerOnBehavior::Idle,
+        1 => PowerOnBehavior::Resume,
+        _ => PowerOnBehavior::AutoStart,
+    }
+}
+
+/// Change the power-on behavior (set remotely, or restored from flash) -
+/// returns false for anything but Idle with a latching start switch
+pub fn set_power_on_behavior(behavior: PowerOnBehavior) -> bool {
+    if behavior != PowerOnBehavior::Idle && matches!(START_INPUT, StartInput::Latching) {
+        return false;
+    }
+    POWER_ON.store(behavior as u8, Ordering::Relaxed);
+    true
+}
+
+/// Short lowercase name, e.g. for `config` - and what `set poweron` takes
+pub fn power_on_name(behavior: PowerOnBehavior) -> &'static str {
+    match behavior {
+        PowerOnBehavior::Idle => "idle",
+        PowerOnBehavior::Resume => "resume",
+        PowerOnBehavior::AutoStart => "autostart",
+    }
+}
+
+/// The power-on behavior called `name` (any case)
+pub fn power_on_named(name: &str) -> Option<PowerOnBehavior> {
+    [PowerOnBehavior::Idle, PowerOnBehavior::Resume, PowerOnBehavior::AutoStart]
+        .into_iter()
+        .find(|&behavior| name.eq_ignore_ascii_case(power_on_name(behavior)))
+}
+
+/// Longest API token (what fits in a flash record beside its length)
+pub const MAX_API_TOKEN_LENGTH: usize = 23;
+
+/// The token network interfaces must give for anything that changes
+/// something (empty = none needed) - letters, digits and `-._~+/=` only,
+/// so it fits in an HTTP header or after an MQTT command as it is
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct ApiToken {
+    len: u8,
+    bytes: [u8; MAX_API_TOKEN_LENGTH],
+}
+
+impl ApiToken {
+    /// The token `text` - None if it's too long or has other characters
+    pub fn new(text: &str) -> Option<Self> {
+        let allowed = |b: u8| b.is_ascii_alphanumeric() || b"-._~+/=".contains(&b);
+        if text.len() > MAX_API_TOKEN_LENGTH || !text.bytes().all(allowed) {
+            return None;
+        }
+        let mut token = Self { len: text.len() as u8, bytes: [0; MAX_API_TOKEN_LENGTH] };
+        token.bytes[..text.len()].copy_from_slice(text.as_bytes());
+        Some(token)
+    }
+
+    /// No token - nothing needs one
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The token's bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..(self.len as usize).min(MAX_API_TOKEN_LENGTH)]
+    }
+
+    /// Is `given` this token? Looks at every byte whatever it finds, so
+    /// the time taken doesn't give away how much of a guess was right.
+    fn matches(&self, given: &[u8]) -> bool {
+        let token = self.as_bytes();
+        given.len() == token.len() && given.iter().zip(token).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+    }
+}
+
+/// Current API token
+static API_TOKEN: Mutex<CriticalSectionRawMutex, Cell<ApiToken>> = Mutex::new(Cell::new(ApiToken { len: 0, bytes: [0; MAX_API_TOKEN_LENGTH] }));
+
+/// The API token (empty if none is set)
+pub fn api_token() -> ApiToken {
+    API_TOKEN.lock(|token| token.get())
+}
+
+/// Change the API token (set over the serial port, or restored from flash)
+pub fn set_api_token(token: ApiToken) {
+    API_TOKEN.lock(|current| current.set(token));
+}
+
+/// Does `given` (from a network request, None if it had no token) let it
+/// change something? Always, while no token is set.
+#[cfg_attr(not(feature = "pico-w"), allow(dead_code))]
+pub fn api_token_matches(given: Option<&[u8]>) -> bool {
+    let token = api_token();
+    token.is_empty() || given.is_some_and(|given| token.matches(given))
+}
+
+/// Longest Wi-Fi network name (SSID)
+pub const MAX_SSID_LENGTH: usize = 32;
+
+/// Longest WPA2 password
+pub const MAX_WIFI_PASSWORD_LENGTH: usize = 63;
+
+/// Shortest WPA2 password
+const MIN_WIFI_PASSWORD_LENGTH: usize = 8;
+
+/// A Wi-Fi network and its password, set over the serial port (pico-w
+/// builds) - empty = use the built-in WIFI_SSID/WIFI_PASSWORD
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(not(feature = "pico-w"), allow(dead_code))]
+pub struct WifiCredentials {
+    ssid_len: u8,
+    ssid: [u8; MAX_SSID_LENGTH],
+    password_len: u8,
+    password: [u8; MAX_WIFI_PASSWORD_LENGTH],
+}
+
+#[cfg_attr(not(feature = "pico-w"), allow(dead_code))]
+impl WifiCredentials {
+    /// None set - use the built-in network
+    pub const NONE: Self = Self { ssid_len: 0, ssid: [0; MAX_SSID_LENGTH], password_len: 0, password: [0; MAX_WIFI_PASSWORD_LENGTH] };
+
+    /// `ssid` and `password` - None unless the name is 1-32 characters and
+    /// the password 8-63, all printable ASCII
+    pub fn new(ssid: &str, password: &str) -> Option<Self> {
+        let printable = |text: &str| text.bytes().all(|b| (b' '..=b'~').contains(&b));
+        if !(1..=MAX_SSID_LENGTH).contains(&ssid.len())
+            || !(MIN_WIFI_PASSWORD_LENGTH..=MAX_WIFI_PASSWORD_LENGTH).contains(&password.len())
+            || !printable(ssid)
+            || !printable(password)
+        {
+            return None;
+        }
+        let mut credentials = Self::NONE;
+        credentials.ssid_len = ssid.len() as u8;
+        credentials.ssid[..ssid.len()].copy_from_slice(ssid.as_bytes());
+        credentials.password_len = password.len() as u8;
+        credentials.password[..password.len()].copy_from_slice(password.as_bytes());
+        Some(credentials)
+    }
+
+    /// Nothing set?
+    pub fn is_empty(&self) -> bool {
+        self.ssid_len == 0
+    }
+
+    /// The network name
+    pub fn ssid(&self) -> &str {
+        core::str::from_utf8(&self.ssid[..(self.ssid_len as usize).min(MAX_SSID_LENGTH)]).unwrap_or("")
+    }
+
+    /// The password
+    pub fn password(&self) -> &str {
+        core::str::from_utf8(&self.password[..(self.password_len as usize).min(MAX_WIFI_PASSWORD_LENGTH)]).unwrap_or("")
+    }
+}
+
+impl Default for WifiCredentials {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+/// Current Wi-Fi network, if one was set over the serial port
+static WIFI_CREDENTIALS: Mutex<CriticalSectionRawMutex, Cell<WifiCredentials>> = Mutex::new(Cell::new(WifiCredentials::NONE));
+
+/// The Wi-Fi network set over the serial port (empty if none)
+#[cfg_attr(not(feature = "pico-w"), allow(dead_code))]
+pub fn wifi_credentials() -> WifiCredentials {
+    WIFI_CREDENTIALS.lock(|credentials| credentials.get())
+}
+
+/// Change the Wi-Fi network (set over the serial port, or restored from
+/// flash) - wifi.rs rejoins when told to
+#[cfg_attr(not(feature = "pico-w"), allow(dead_code))]
+pub fn set_wifi_credentials(credentials: WifiCredentials) {
+    WIFI_CREDENTIALS.lock(|current| current.set(credentials));
+}
+
+/// Set when the menu settings (or the power-on behavior, API token or
+/// Wi-Fi network) were changed remotely and not saved yet
+static UNSAVED: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Ask the main loop to save the menu settings, power-on behavior and API
+/// token to flash (next time it's idle)
+#[cfg_attr(not(any(feature = "usb-serial", feature = "pico-w", feature = "uart-control")), allow(dead_code))]
+pub fn request_save() {
+    UNSAVED.signal(());
+}
+
+/// Wait until the menu settings need saving (still unsaved after, for
+/// `take_unsaved()`)
+pub async fn wait_for_unsaved() {
+    UNSAVED.wait().await;
+    UNSAVED.signal(());
+}
+
+/// Do the menu settings need saving? Only says yes once per request.
+pub fn take_unsaved() -> bool {
+    let unsaved = UNSAVED.signaled();
+    UNSAVED.reset();
+    unsaved
+}
@@ -0,0 +1,100 @@
+// Stack Usage Monitor
+//
+// Embassy tasks don't get their own stacks: every task's state lives in a
+// static, and all of them are polled on the one main (MSP) stack. So "how
+// close are we to overflowing?" comes down to a single number - the deepest
+// point the main stack has ever reached.
+//
+// We find that with the classic "stack painting" trick:
+// 1. At boot, fill all the unused stack memory with a known pattern
+// 2. Later, scan up from the bottom of the stack until the pattern stops
+// 3. Everything above that point has been used at some time = high-water mark
+
+use defmt::*;
+use embassy_time::Timer;
+
+use crate::config::*;
+
+/// Pattern written into unused stack memory
+const STACK_PAINT: u32 = 0xCCCC_CCCC;
+
+/// Start of RAM (see the memory/ layouts)
+const RAM_START: usize = 0x2000_0000;
+
+/// Bytes below the current stack pointer that we leave unpainted,
+/// so we never scribble over our own stack frame
+const PAINT_SAFETY_MARGIN: usize = 256;
+
+// Symbols provided by the cortex-m-rt linker script
+extern "C" {
+    static _stack_start: u32;  // Top of the stack (it grows downwards)
+    static __sheap: u32;       // End of static data (.data/.bss)
+}
+
+/// Top of the stack (highest address)
+fn stack_top() -> usize {
+    unsafe { core::ptr::addr_of!(_stack_start) as usize }
+}
+
+/// Bottom of the stack (lowest address it may grow down to)
+fn stack_bottom() -> usize {
+    let end_of_statics = unsafe { core::ptr::addr_of!(__sheap) as usize };
+    if end_of_statics < stack_top() {
+        // Normal layout: statics first, stack grows down towards them
+        end_of_statics
+    } else {
+        // flip-link layout: stack sits at the very start of RAM, below the statics
+        RAM_START
+    }
+}
+
+/// Total size of the stack region in bytes
+pub fn stack_size() -> usize {
+    stack_top() - stack_bottom()
+}
+
+/// Fill the unused part of the stack with STACK_PAINT
+///
+/// Call this once, as early as possible in main.
+pub fn paint() {
+    // Interrupts push onto the stack too - keep them out while we paint
+    cortex_m::interrupt::free(|_| {
+        let sp = cortex_m::register::msp::read() as usize;
+        let mut addr = stack_bottom();
+        while addr + PAINT_SAFETY_MARGIN < sp {
+            unsafe { core::ptr::write_volatile(addr as *mut u32, STACK_PAINT) };
+            addr += 4;
+        }
+    });
+}
+
+/// Deepest stack usage seen since boot, in bytes
+pub fn high_water_mark() -> usize {
+    let top = stack_top();
+    let mut addr = stack_bottom();
+    while addr < top && unsafe { core::ptr::read_volatile(addr as *const u32) } == STACK_PAINT {
+        addr += 4;
+    }
+    top - addr
+}
+
+/// Background task: periodically log stack usage
+///
+/// Warns if usage ever crosses STACK_WARN_PERCENT of the stack.
+#[embassy_executor::task]
+pub async fn stack_monitor_task() {
+    let size = stack_size();
+    info!("Stack monitor running - {} bytes of stack available", size);
+
+    loop {
+        Timer::after_secs(STACK_REPORT_INTERVAL_SECONDS).await;
+
+        let used = high_water_mark();
+        let percent = used * 100 / size;
+        if percent >= STACK_WARN_PERCENT {
+            warn!("Stack high-water mark {}/{} bytes ({}%) - close to overflowing!", used, size, percent);
+        } else {
+            debug!("Stack high-water mark {}/{} bytes ({}%)", used, size, percent);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+// Cure Statistics
+//
+// Lifetime counters for how much the curer has been used:
+// - cures that ran to the end
+// - cures that were cut short (aborted, or stopped by the over-temperature cutoff)
+// - total time the UV LEDs have been on
+// - time the UV LEDs have been on since they were last replaced (lamp
+//   hours) - reset with the button at power-on, see LAMP_SERVICE_HOURS
+//
+// They're saved in flash with the other settings (storage.rs), once per cure
+// while the curer is idle. Flash records are appended rather than rewritten,
+// so each flash sector is only erased once every 128 cures (or sooner with
+// the checkpoints long cures save, see storage.rs). The number of cures so
+// far also tells a checkpoint's cure apart from the ones before and after.
+//
+// The main loop is the only place that changes them; it publishes a copy
+// here with `publish()` so remote interfaces can report them with `get()`.
+
+use core::cell::Cell;
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+
+use crate::config::*;
+
+/// Usage counters, kept across power cycles
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub struct CureStats {
+    /// Cures that ran for their full time (or until a latching switch was turned OFF)
+    pub completed: u32,
+    /// Cures that were aborted or cut short by overheating
+    pub aborted: u32,
+    /// Total UV-on time in seconds
+    pub uv_on_seconds: u32,
+    /// UV-on time in seconds since the lamp hours were last reset
+    pub lamp_seconds: u32,
+}
+
+impl CureStats {
+    /// Count one finished cure and the UV time it used
+    pub fn add_cure(&mut self, completed: bool, uv_on_ms: u64) {
+        if completed {
+            self.completed = self.completed.saturating_add(1);
+        } else {
+            self.aborted = self.aborted.saturating_add(1);
+        }
+        // Round to the nearest second - a few ms lost per cure doesn't matter
+        let seconds = ((uv_on_ms + 500) / 1000).min(u32::MAX as u64) as u32;
+        self.uv_on_seconds = self.uv_on_seconds.saturating_add(seconds);
+        self.lamp_seconds = self.lamp_seconds.saturating_add(seconds);
+    }
+
+    /// Cures finished so far, whichever way they ended
+    pub fn cures(&self) -> u32 {
+        self.completed.wrapping_add(self.aborted)
+    }
+
+    /// Total UV-on time in hours, rounded down
+    pub fn uv_on_hours(&self) -> u32 {
+        self.uv_on_seconds / 3600
+    }
+
+    /// UV-on hours of the current lamp, rounded down
+    pub fn lamp_hours(&self) -> u32 {
+        self.lamp_seconds / 3600
+    }
+
+    /// Has the lamp been on long enough to need checking?
+    pub fn lamp_service_due(&self) -> bool {
+        LAMP_SERVICE_HOURS > 0 && self.lamp_hours() >= LAMP_SERVICE_HOURS
+    }
+}
+
+/// Latest counters published by the main loop
+static STATS: Mutex<CriticalSectionRawMutex, Cell<CureStats>> = Mutex::new(Cell::new(CureStats {
+    completed: 0,
+    aborted: 0,
+    uv_on_seconds: 0,
+    lamp_seconds: 0,
+}));
+
+/// Update the counters remote interfaces see (main loop only)
+pub fn publish(stats: CureStats) {
+    STATS.lock(|cell| cell.set(stats));
+}
+
+/// The counters as of the last cure
+#[cfg_attr(not(any(feature = "usb-serial", feature = "pico-w", feature = "uart-control")), allow(dead_code))]
+pub fn get() -> CureStats {
+    STATS.lock(|cell| cell.get())
+}
@@ -0,0 +1,190 @@
+// Status LED Task
+//
+// The status LED (GPIO 25, or GPIO 15 on a Pico W) has a task of its own,
+// so blinking never holds up the cure timing. It's driven by PWM so it
+// can be dimmed as well as switched. Anything that wants to show
+// something calls `show()`, which queues the request and returns at once:
+//
+// - steady patterns (`Off`, `On`, `Repeat`) last until the next one
+// - `Flashes` plays once, then the LED goes back to its steady pattern
+//
+// While the UV is on, the LED blinks slowly at the start of a cure and
+// faster and faster towards the end (`Countdown`), so progress shows from
+// across the room. It blinks steadily while paused and double-blinks while
+// preheating.
+//
+// While idle it "breathes" - fading slowly up and down - to show the curer
+// is powered and waiting. Breathing is the lowest priority pattern: it's
+// only ever a step away from checking for a new request, so anything the
+// cure supervisor asks for takes over at once.
+
+use core::convert::Infallible;
+
+use curing_core::drivers;
+use defmt::*;
+use embassy_futures::select::{select, Either};
+#[cfg(not(feature = "pico-w"))]
+use embassy_rp::peripherals::PWM_CH4;
+#[cfg(feature = "pico-w")]
+use embassy_rp::peripherals::PWM_CH7;
+use embassy_rp::pwm::{self, Pwm, PwmPinB, Slice};
+use embassy_rp::Peripheral;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::Channel;
+use embassy_time::{Instant, Timer};
+
+use crate::config::*;
+
+/// What the status LED should do
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Led {
+    Off,
+    On,
+    /// `flashes` flashes of `flash_ms` at the start of every `period_ms`,
+    /// until told otherwise
+    Repeat { flashes: u32, flash_ms: u64, period_ms: u64 },
+    /// Blink faster as `ends_at` gets closer - from one blink every
+    /// STATUS_LED_COUNTDOWN_SLOW_MS with `total_ms` to go, down to one every
+    /// STATUS_LED_COUNTDOWN_FAST_MS at the end
+    Countdown { ends_at: Instant, total_ms: u64 },
+    /// Fade up and down, once every STATUS_LED_BREATHE_MS
+    Breathe,
+    /// `count` flashes once, then back to the steady pattern
+    Flashes { count: u32, on_ms: u64, off_ms: u64 },
+}
+
+/// The status LED: GPIO 25 is PWM slice 4 channel B...
+#[cfg(not(feature = "pico-w"))]
+pub type StatusLed = drivers::DimmableLed<LedPwm<'static, PWM_CH4>>;
+/// ...and GPIO 15 (Pico W) is slice 7 channel B
+#[cfg(feature = "pico-w")]
+pub type StatusLed = drivers::DimmableLed<LedPwm<'static, PWM_CH7>>;
+
+/// Channel B of a PWM slice, as an embedded-hal PWM output
+pub struct LedPwm<'d, T: Slice> {
+    pwm: Pwm<'d, T>,
+    config: pwm::Config,
+}
+
+impl<'d, T: Slice> LedPwm<'d, T> {
+    /// Take the slice and pin (starts dark). The default settings give a
+    /// ~1.9 kHz PWM - far too fast to see flicker.
+    pub fn new(slice: impl Peripheral<P = T> + 'd, pin: impl Peripheral<P = impl PwmPinB<T>> + 'd) -> Self {
+        let mut config = pwm::Config::default();
+        config.compare_b = 0;
+        let pwm = Pwm::new_output_b(slice, pin, config.clone());
+        Self { pwm, config }
+    }
+}
+
+impl<T: Slice> embedded_hal::pwm::ErrorType for LedPwm<'_, T> {
+    type Error = Infallible;
+}
+
+impl<T: Slice> embedded_hal::pwm::SetDutyCycle for LedPwm<'_, T> {
+    fn max_duty_cycle(&self) -> u16 {
+        self.config.top
+    }
+
+    fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Infallible> {
+        self.config.compare_b = duty;
+        self.pwm.set_config(&self.config);
+        Ok(())
+    }
+}
+
+/// Requests waiting for the LED task
+static REQUESTS: Channel<CriticalSectionRawMutex, Led, 4> = Channel::new();
+
+/// Change what the status LED shows - never waits
+pub fn show(led: Led) {
+    if REQUESTS.try_send(led).is_err() {
+        warn!("Status LED: too many requests queued - one dropped");
+    }
+}
+
+/// Background task: drive the status LED
+#[embassy_executor::task]
+pub async fn status_led_task(mut led: StatusLed) {
+    let mut steady = Led::Off;
+
+    loop {
+        let request = match steady {
+            // Blink until the next request comes in
+            Led::Repeat { flashes, flash_ms, period_ms } => {
+                match select(REQUESTS.receive(), repeat(&mut led, flashes, flash_ms, period_ms)).await {
+                    Either::First(request) => request,
+                    Either::Second(()) => continue,      // (never - repeat() doesn't stop by itself)
+                }
+            }
+            Led::Countdown { ends_at, total_ms } => {
+                match select(REQUESTS.receive(), countdown(&mut led, ends_at, total_ms)).await {
+                    Either::First(request) => request,
+                    Either::Second(()) => continue,      // (never - countdown() doesn't stop by itself)
+                }
+            }
+            Led::Breathe => match select(REQUESTS.receive(), breathe(&mut led)).await {
+                Either::First(request) => request,
+                Either::Second(()) => continue,          // (never - breathe() doesn't stop by itself)
+            },
+            _ => {
+                led.set(steady == Led::On);
+                REQUESTS.receive().await
+            }
+        };
+
+        match request {
+            Led::Flashes { count, on_ms, off_ms } => {
+                led.off();
+                flash(&mut led, count, on_ms, off_ms).await;
+            }
+            _ => steady = request,
+        }
+    }
+}
+
+/// `count` flashes, with `off_ms` after each one
+async fn flash(led: &mut StatusLed, count: u32, on_ms: u64, off_ms: u64) {
+    for _ in 0..count {
+        led.on();
+        Timer::after_millis(on_ms).await;
+        led.off();
+        Timer::after_millis(off_ms).await;
+    }
+}
+
+/// A `Led::Repeat` pattern, for ever
+async fn repeat(led: &mut StatusLed, flashes: u32, flash_ms: u64, period_ms: u64) {
+    loop {
+        flash(led, flashes, flash_ms, flash_ms).await;
+        Timer::after_millis(period_ms.saturating_sub(2 * flashes as u64 * flash_ms)).await;
+    }
+}
+
+/// A `Led::Countdown` pattern, for ever (at the fastest rate once `ends_at`
+/// has passed)
+async fn countdown(led: &mut StatusLed, ends_at: Instant, total_ms: u64) {
+    loop {
+        let remaining_ms = ends_at.saturating_duration_since(Instant::now()).as_millis().min(total_ms);
+        let period_ms = STATUS_LED_COUNTDOWN_FAST_MS
+            + (STATUS_LED_COUNTDOWN_SLOW_MS - STATUS_LED_COUNTDOWN_FAST_MS) * remaining_ms / total_ms.max(1);
+        flash(led, 1, period_ms / 2, period_ms / 2).await;
+    }
+}
+
+/// A `Led::Breathe` pattern, for ever
+async fn breathe(led: &mut StatusLed) {
+    const STEP_MS: u64 = 20;
+    let steps = (STATUS_LED_BREATHE_MS / STEP_MS).max(2);
+    loop {
+        for step in 0..steps {
+            // Up from 0 to 100 and back down...
+            let up = (step * 200 / steps) as u32;
+            let level = if up <= 100 { up } else { 200 - up };
+            // ...squared, because eyes notice changes in a dim LED far more
+            // than in a bright one - this makes the fade look even
+            led.set_percent((level * level / 100 * STATUS_LED_BREATHE_PERCENT / 100) as u8);
+            Timer::after_millis(STEP_MS).await;
+        }
+    }
+}
@@ -0,0 +1,412 @@
+// Flash Storage
+//
+// Keeps a small record of settings that must survive a power cycle - and,
+// while a cure runs, a checkpoint of how far it has got (see
+// curing-core/src/checkpoint.rs), so it can be finished after a power cut.
+//
+// The last two 4 KB sectors of flash are reserved for this (see memory/standard.x and memory/ota.x).
+// Flash can only be erased a whole sector at a time and wears out after
+// ~100,000 erases, so instead of rewriting one fixed spot we append a new
+// fixed-size record each time something changes:
+//
+//   [magic (2)] [sequence number (4)] [settings (24)] [checksum (2)]
+//
+// Checkpoints are records too, with their own magic and a checkpoint in
+// place of the settings, so they share the same wear levelling. So is the
+// API token (settings.rs) - the settings record has no room left for it -
+// and it's kept just like the settings. A Wi-Fi network set over USB serial
+// (pico-w builds) takes five kept records, each with the same generation
+// number so a save cut off half-way is spotted (the built-in network is
+// used then). Its name and password are scrambled with a key made from the
+// flash chip's unique ID, so they don't show up as plain text in a dump of
+// the flash - that's all: anyone with the board itself can unscramble them.
+//
+// At boot we scan both sectors and use the valid record with the highest
+// sequence number (and the newest checkpoint and token). The sectors are used in
+// turn; the newest settings are always kept in the sector being written
+// (copied forward when a run of checkpoints moves on), so erasing the
+// other one can't lose them. A record cut off by a power loss is skipped,
+// not erased. The record layout itself is curing-core's `RecordLog`
+// (curing-core/src/record_log.rs), where this is tested.
+//
+// Erasing a sector stops the whole chip for ~50 ms (up to ~400 ms on a
+// worn chip) - the lid and overheat checks included - so that never
+// happens during a cure: `prepare_for_cure` moves on to a fresh sector
+// before the UV comes on if the checkpoints might not fit, and a
+// checkpoint that still doesn't fit is skipped.
+//
+// The flash chip is shared: in ota builds the firmware updater (ota.rs)
+// writes new firmware to it too, so both go through `SharedFlash`.
+
+use core::cell::RefCell;
+
+use defmt::unwrap;
+use embassy_rp::flash::{Blocking, Error, Flash, ERASE_SIZE};
+use embassy_rp::peripherals::FLASH;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+
+use curing_core::checkpoint::Checkpoint;
+use curing_core::menu::MenuSettings;
+use curing_core::record_log::{LogGeometry, RecordFlash, RecordLog, PAYLOAD_SIZE, RECORD_SIZE};
+
+use crate::config::PowerOnBehavior;
+use crate::settings::{ApiToken, WifiCredentials, MAX_API_TOKEN_LENGTH, MAX_SSID_LENGTH, MAX_WIFI_PASSWORD_LENGTH};
+use crate::stats::CureStats;
+
+/// Total flash size of the Pico's W25Q16 chip (2 MB) - must match the memory/ layouts
+pub const FLASH_SIZE: usize = 2 * 1024 * 1024;
+
+/// Size of one erasable flash sector
+const SECTOR_SIZE: u32 = ERASE_SIZE as u32;
+
+/// Number of sectors reserved at the end of flash
+const STORAGE_SECTORS: u32 = 2;
+
+/// Flash offset of the first storage sector
+const STORAGE_START: u32 = FLASH_SIZE as u32 - STORAGE_SECTORS * SECTOR_SIZE;
+
+/// How the records fit in the storage sectors
+const GEOMETRY: LogGeometry = LogGeometry { sectors: STORAGE_SECTORS, slots_per_sector: SECTOR_SIZE / RECORD_SIZE as u32 };
+
+/// Marks the start of a valid record (erased flash reads as 0xFF)
+const RECORD_MAGIC: [u8; 2] = [0xC7, 0x55];
+
+/// Marks the start of a checkpoint record
+const CHECKPOINT_MAGIC: [u8; 2] = [0xC7, 0x5C];
+
+/// Marks the start of an API token record
+const TOKEN_MAGIC: [u8; 2] = [0xC7, 0x54];
+
+/// How many records a Wi-Fi network takes...
+const WIFI_RECORDS: usize = 5;
+
+/// ...each marked by its own magic
+const WIFI_MAGICS: [[u8; 2]; WIFI_RECORDS] = [[0xC7, 0x60], [0xC7, 0x61], [0xC7, 0x62], [0xC7, 0x63], [0xC7, 0x64]];
+
+/// ...and holding a generation number and this many bytes of the network
+const WIFI_PART_SIZE: usize = PAYLOAD_SIZE - 1;
+
+/// A Wi-Fi network, one record's worth after another:
+/// [name length] [name (32)] [password length] [password (63)]
+const WIFI_SIZE: usize = 2 + MAX_SSID_LENGTH + MAX_WIFI_PASSWORD_LENGTH;
+
+// The Wi-Fi network has to fit in its records
+const _: () = assert!(WIFI_SIZE <= WIFI_RECORDS * WIFI_PART_SIZE);
+
+/// How many kinds of record are kept: the settings, token and Wi-Fi network
+const KEPT: usize = 2 + WIFI_RECORDS;
+
+/// The kinds of record kept in the sector being written
+const KEPT_MAGICS: [[u8; 2]; KEPT] =
+    [RECORD_MAGIC, TOKEN_MAGIC, WIFI_MAGICS[0], WIFI_MAGICS[1], WIFI_MAGICS[2], WIFI_MAGICS[3], WIFI_MAGICS[4]];
+
+// The token and its length have to fit in a record
+const _: () = assert!(MAX_API_TOKEN_LENGTH < PAYLOAD_SIZE);
+
+// A checkpoint has to fit in a record
+const _: () = assert!(Checkpoint::SIZE <= PAYLOAD_SIZE);
+
+/// CRC-16 of the records (also checks frames in the UART protocol, uart_control.rs)
+pub use curing_core::record_log::crc16;
+
+/// The flash chip, usable from more than one place
+pub type SharedFlash = Mutex<CriticalSectionRawMutex, RefCell<Flash<'static, FLASH, Blocking, FLASH_SIZE>>>;
+
+/// Take over the flash peripheral so it can be shared (call once, at startup)
+pub fn share_flash(flash: FLASH) -> &'static SharedFlash {
+    unwrap!(cortex_m::singleton!(: SharedFlash = Mutex::new(RefCell::new(Flash::new_blocking(flash)))))
+}
+
+/// Everything the firmware remembers across power cycles
+///
+/// New fields go at the end of the payload. Records written by older
+/// firmware have zeros there, so zero must be a sensible default.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub struct PersistedState {
+    /// Selected multi-duration preset (index into DURATION_PRESETS, multi-duration builds)
+    /// None = never selected, use the default. Stored as index + 1.
+    pub preset_index: Option<u8>,
+    /// Silent mode - buzzer off, LED flashes instead
+    pub muted: bool,
+    /// Lifetime cure counters (see stats.rs)
+    pub stats: CureStats,
+    /// Settings picked in the on-device menu (see menu.rs)
+    /// None = never changed, use the config.rs values. Its silent mode
+    /// is saved as `muted`, above.
+    pub menu: Option<MenuSettings>,
+    /// What to do at power-on (see settings.rs)
+    /// None = never changed, use POWER_ON_BEHAVIOR. Stored in the bits of
+    /// `muted`'s byte above the lowest, as the payload is full.
+    pub power_on: Option<PowerOnBehavior>,
+    /// Token network commands need (see settings.rs), empty = none. Saved
+    /// in a record of its own.
+    pub api_token: ApiToken,
+    /// Wi-Fi network set over USB serial (see settings.rs), empty = the
+    /// built-in one. Saved in records of its own.
+    pub wifi: WifiCredentials,
+}
+
+impl PersistedState {
+    fn to_bytes(self) -> [u8; PAYLOAD_SIZE] {
+        let mut bytes = [0u8; PAYLOAD_SIZE];
+        bytes[0] = self.preset_index.map_or(0, |index| index + 1);
+        bytes[1] = self.muted as u8
+            | match self.power_on {
+                None => 0,
+                Some(PowerOnBehavior::Idle) => 1 << 1,
+                Some(PowerOnBehavior::Resume) => 2 << 1,
+                Some(PowerOnBehavior::AutoStart) => 3 << 1,
+            };
+        bytes[2..6].copy_from_slice(&self.stats.completed.to_le_bytes());
+        bytes[6..10].copy_from_slice(&self.stats.aborted.to_le_bytes());
+        bytes[10..14].copy_from_slice(&self.stats.uv_on_seconds.to_le_bytes());
+        bytes[14..18].copy_from_slice(&self.stats.lamp_seconds.to_le_bytes());
+        if let Some(menu) = self.menu {
+            bytes[18] = 1;
+            bytes[19..21].copy_from_slice(&(menu.cure_seconds as u16).to_le_bytes());
+            bytes[21] = menu.completion_beeps as u8;
+            bytes[22..24].copy_from_slice(&(menu.cooldown_ms as u16).to_le_bytes());
+        }
+        bytes
+    }
+
+    /// The API token record's payload: [length] [token]
+    fn token_bytes(&self) -> [u8; PAYLOAD_SIZE] {
+        let token = self.api_token.as_bytes();
+        let mut bytes = [0u8; PAYLOAD_SIZE];
+        bytes[0] = token.len() as u8;
+        bytes[1..1 + token.len()].copy_from_slice(token);
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            preset_index: bytes[0].checked_sub(1),
+            muted: bytes[1] & 1 != 0,
+            stats: CureStats {
+                completed: u32_at(bytes, 2),
+                aborted: u32_at(bytes, 6),
+                uv_on_seconds: u32_at(bytes, 10),
+                lamp_seconds: u32_at(bytes, 14),
+            },
+            menu: (bytes[18] != 0).then(|| MenuSettings {
+                cure_seconds: u16::from_le_bytes([bytes[19], bytes[20]]) as u64,
+                completion_beeps: bytes[21] as u32,
+                cooldown_ms: u16::from_le_bytes([bytes[22], bytes[23]]) as u64,
+                muted: bytes[1] & 1 != 0,
+            }),
+            power_on: match bytes[1] >> 1 {
+                1 => Some(PowerOnBehavior::Idle),
+                2 => Some(PowerOnBehavior::Resume),
+                3 => Some(PowerOnBehavior::AutoStart),
+                _ => None,
+            },
+            api_token: ApiToken::default(),
+            wifi: WifiCredentials::NONE,
+        }
+    }
+}
+
+/// A Wi-Fi network as bytes, before it's split into records
+fn wifi_to_bytes(wifi: &WifiCredentials) -> [u8; WIFI_SIZE] {
+    let mut bytes = [0u8; WIFI_SIZE];
+    let (ssid, password) = (wifi.ssid().as_bytes(), wifi.password().as_bytes());
+    bytes[0] = ssid.len() as u8;
+    bytes[1..1 + ssid.len()].copy_from_slice(ssid);
+    bytes[1 + MAX_SSID_LENGTH] = password.len() as u8;
+    bytes[2 + MAX_SSID_LENGTH..2 + MAX_SSID_LENGTH + password.len()].copy_from_slice(password);
+    bytes
+}
+
+/// The Wi-Fi network in those bytes (none if it isn't a valid one)
+fn wifi_from_bytes(bytes: &[u8; WIFI_SIZE]) -> WifiCredentials {
+    let ssid_len = (bytes[0] as usize).min(MAX_SSID_LENGTH);
+    let password_len = (bytes[1 + MAX_SSID_LENGTH] as usize).min(MAX_WIFI_PASSWORD_LENGTH);
+    let ssid = core::str::from_utf8(&bytes[1..1 + ssid_len]);
+    let password = core::str::from_utf8(&bytes[2 + MAX_SSID_LENGTH..2 + MAX_SSID_LENGTH + password_len]);
+    match (ssid, password) {
+        (Ok(ssid), Ok(password)) => WifiCredentials::new(ssid, password).unwrap_or_default(),
+        _ => WifiCredentials::NONE,
+    }
+}
+
+/// Scramble (or unscramble - it's the same) part `part` of a Wi-Fi network
+/// with a keystream from `key`
+fn scramble(key: u64, part: usize, bytes: &mut [u8]) {
+    // xorshift64, started from the key and the part number (never from 0)
+    let mut state = (key ^ (part as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15)) | 1;
+    for byte in bytes {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        *byte ^= state as u8;
+    }
+}
+
+/// The token in an API token record (none if it isn't a valid one)
+fn token_from_bytes(bytes: &[u8]) -> ApiToken {
+    let len = (bytes[0] as usize).min(MAX_API_TOKEN_LENGTH);
+    core::str::from_utf8(&bytes[1..1 + len]).ok().and_then(ApiToken::new).unwrap_or_default()
+}
+
+/// Append-only settings log in the reserved flash sectors
+pub struct Storage<'d> {
+    flash: &'d SharedFlash,
+    /// Where the record log has got to (the settings, token and Wi-Fi
+    /// network are kept)
+    log: RecordLog<KEPT>,
+    /// Contents of the newest settings and token records
+    saved: PersistedState,
+    /// The newest checkpoint, if one was ever saved
+    checkpoint: Option<Checkpoint>,
+    /// Generation number of the newest Wi-Fi network records
+    wifi_generation: u8,
+    /// What the Wi-Fi network is scrambled with (from the flash's unique ID)
+    wifi_key: u64,
+}
+
+impl<'d> Storage<'d> {
+    /// Find the newest stored records
+    pub fn new(flash: &'d SharedFlash) -> Self {
+        let mut checkpoint: Option<(u32, Checkpoint)> = None;
+        let log = RecordLog::open(&mut StorageFlash(flash), GEOMETRY, KEPT_MAGICS, |magic, sequence, payload| {
+            let newer = checkpoint.is_none_or(|(newest, _)| sequence > newest);
+            if magic == CHECKPOINT_MAGIC && newer {
+                if let Some(found) = Checkpoint::from_bytes(payload) {
+                    checkpoint = Some((sequence, found));
+                }
+            }
+        });
+        let mut saved = log.kept(RECORD_MAGIC).map_or_else(PersistedState::default, |payload| PersistedState::from_bytes(payload));
+        saved.api_token = log.kept(TOKEN_MAGIC).map_or_else(ApiToken::default, |payload| token_from_bytes(payload));
+
+        let mut id = [0u8; 8];
+        let _ = flash.lock(|flash| flash.borrow_mut().blocking_unique_id(&mut id));   // Unscrambles to nothing if it fails
+        let wifi_key = u64::from_le_bytes(id);
+        let wifi_generation = log.kept(WIFI_MAGICS[0]).map_or(0, |payload| payload[0]);
+        saved.wifi = read_wifi(&log, wifi_key).unwrap_or_default();
+        Self {
+            flash,
+            saved,
+            log,
+            checkpoint: checkpoint.map(|(_, checkpoint)| checkpoint),
+            wifi_generation,
+            wifi_key,
+        }
+    }
+
+    /// The most recently saved state (defaults if nothing was ever saved)
+    pub fn load(&self) -> PersistedState {
+        self.saved
+    }
+
+    /// Save a new state - does nothing if it hasn't changed
+    ///
+    /// Blocks for a few milliseconds (~50 ms, up to ~400 ms, when a sector
+    /// has to be erased), so only call this while idle, never during a cure.
+    pub fn save(&mut self, state: &PersistedState) -> Result<(), Error> {
+        if state.to_bytes() != self.saved.to_bytes() {
+            self.log.append(&mut StorageFlash(self.flash), RECORD_MAGIC, &state.to_bytes())?;
+        }
+        if state.api_token != self.saved.api_token {
+            self.log.append(&mut StorageFlash(self.flash), TOKEN_MAGIC, &state.token_bytes())?;
+        }
+        if state.wifi != self.saved.wifi {
+            self.save_wifi(&state.wifi)?;
+        }
+        self.saved = *state;
+        Ok(())
+    }
+
+    /// Save a Wi-Fi network, in a new generation of its records
+    fn save_wifi(&mut self, wifi: &WifiCredentials) -> Result<(), Error> {
+        self.wifi_generation = self.wifi_generation.wrapping_add(1);
+        let bytes = wifi_to_bytes(wifi);
+        for (part, magic) in WIFI_MAGICS.iter().enumerate() {
+            let mut payload = [0u8; PAYLOAD_SIZE];
+            payload[0] = self.wifi_generation;
+            let start = (part * WIFI_PART_SIZE).min(WIFI_SIZE);
+            let end = (start + WIFI_PART_SIZE).min(WIFI_SIZE);
+            payload[1..1 + end - start].copy_from_slice(&bytes[start..end]);
+            scramble(self.wifi_key, part, &mut payload[1..]);
+            self.log.append(&mut StorageFlash(self.flash), *magic, &payload)?;
+        }
+        Ok(())
+    }
+
+    /// The newest checkpoint saved (it may belong to a cure that has long
+    /// finished - see `cure_number`)
+    pub fn checkpoint(&self) -> Option<Checkpoint> {
+        self.checkpoint
+    }
+
+    /// Make room for `checkpoints` checkpoints without erasing (as many
+    /// as a sector holds, at most) - call before the UV comes on, as it
+    /// may have to erase
+    pub fn prepare_for_cure(&mut self, checkpoints: u32) -> Result<(), Error> {
+        self.log.make_room(&mut StorageFlash(self.flash), checkpoints)
+    }
+
+    /// Save where the running cure has got to - false if it was skipped
+    /// because the sector is full
+    ///
+    /// Unlike `save()`, this is called during a cure, so it only ever
+    /// writes one record (a moment) and never erases.
+    pub fn save_checkpoint(&mut self, checkpoint: &Checkpoint) -> Result<bool, Error> {
+        let mut payload = [0u8; PAYLOAD_SIZE];
+        payload[..Checkpoint::SIZE].copy_from_slice(&checkpoint.to_bytes());
+        if self.log.append_without_erase(&mut StorageFlash(self.flash), CHECKPOINT_MAGIC, &payload)?.is_none() {
+            return Ok(false);
+        }
+        self.checkpoint = Some(*checkpoint);
+        Ok(true)
+    }
+}
+
+/// The saved Wi-Fi network - None if there isn't one, or its records
+/// aren't all from the same save
+fn read_wifi(log: &RecordLog<KEPT>, key: u64) -> Option<WifiCredentials> {
+    let generation = log.kept(WIFI_MAGICS[0])?[0];
+    let mut bytes = [0u8; WIFI_SIZE];
+    for (part, magic) in WIFI_MAGICS.iter().enumerate() {
+        let payload = log.kept(*magic).filter(|payload| payload[0] == generation)?;
+        let mut data = [0u8; WIFI_PART_SIZE];
+        data.copy_from_slice(&payload[1..]);
+        scramble(key, part, &mut data);
+        let start = (part * WIFI_PART_SIZE).min(WIFI_SIZE);
+        let end = (start + WIFI_PART_SIZE).min(WIFI_SIZE);
+        bytes[start..end].copy_from_slice(&data[..end - start]);
+    }
+    Some(wifi_from_bytes(&bytes))
+}
+
+/// The storage sectors, one record slot at a time
+struct StorageFlash<'d>(&'d SharedFlash);
+
+impl RecordFlash for StorageFlash<'_> {
+    type Error = Error;
+
+    fn read(&mut self, slot: u32, record: &mut [u8; RECORD_SIZE]) -> Result<(), Error> {
+        self.0.lock(|flash| flash.borrow_mut().blocking_read(slot_offset(slot), record))
+    }
+
+    fn write(&mut self, slot: u32, record: &[u8; RECORD_SIZE]) -> Result<(), Error> {
+        self.0.lock(|flash| flash.borrow_mut().blocking_write(slot_offset(slot), record))
+    }
+
+    fn erase(&mut self, sector: u32) -> Result<(), Error> {
+        let start = STORAGE_START + sector * SECTOR_SIZE;
+        self.0.lock(|flash| flash.borrow_mut().blocking_erase(start, start + SECTOR_SIZE))
+    }
+}
+
+/// Little-endian u32 starting at `offset`
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+/// Flash offset of a record slot
+fn slot_offset(slot: u32) -> u32 {
+    STORAGE_START + slot * RECORD_SIZE as u32
+}
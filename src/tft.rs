@@ -0,0 +1,408 @@
+// ST7789 Colour TFT (st7789 builds)
+//
+// A 240x240 colour screen (the common 1.3" and 1.54" ST7789 modules) with
+// a big countdown, a progress bar, and the whole background coloured by
+// state: blue idle, purple curing, green done, red on an over-temperature
+// fault. Idle, the countdown's place shows the time of day once the clock
+// is set (IDLE_CLOCK). Wire it to SPI0 - the microSD card's bus, so it can't be combined
+// with sd-log:
+//
+//   GPIO 18 (SPI0 SCK) ── SCL / SCK
+//   GPIO 19 (SPI0 TX)  ── SDA / MOSI
+//   GPIO 17            ── CS   (leave unconnected if the module has none)
+//   GPIO 16            ── DC
+//   GPIO 21            ── RES / RST
+//   GPIO 28            ── BLK / BL (backlight - on once the screen is set up)
+//   3.3V ── VCC, GND ── GND
+//
+// Like the NeoPixel ring, the screen has its own task: the cure supervisor
+// passes on each change of state with `follow()` and never waits for the
+// screen. SPI transfers go out by DMA, so the rest of the firmware (relay
+// control included) carries on running while a frame is sent. Only the
+// parts of the screen that changed are redrawn.
+//
+// There's no font: the label and the countdown are drawn as 7-segment
+// digits (curing-core's seven_segment.rs), one rectangle per segment.
+
+use curing_core::cure::PhaseEnd;
+use curing_core::cycle::CureState;
+use curing_core::fault::FaultCode;
+use curing_core::seven_segment::{self, BLANK, COLON};
+use defmt::*;
+use embassy_futures::select::{select, Either};
+use embassy_rp::gpio::{Level, Output};
+use embassy_rp::peripherals::{DMA_CH1, PIN_16, PIN_17, PIN_18, PIN_19, PIN_21, PIN_28, SPI0};
+use embassy_rp::spi::{self, Async, Spi};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Instant, Timer};
+
+use crate::clock;
+use crate::config::*;
+
+/// How often the countdown is redrawn while a cure runs, in milliseconds
+const FRAME_MS: u64 = 200;
+
+/// What the screen should show
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Tft {
+    Idle,
+    /// Start accepted, waiting for the lid or the preheat
+    Arming,
+    /// A cure `done_ms` through its `total_ms` - counting up from now
+    /// while `running`, frozen while paused. `uv` is false in rest phases.
+    Progress { done_ms: u64, total_ms: u64, running: bool, uv: bool },
+    /// Finished - green until the next start
+    Complete,
+    /// A fault - red with its code until it's cleared
+    Fault(FaultCode),
+    /// Standby - blank, backlight off
+    Standby,
+}
+
+/// What to show next. A signal rather than a queue - only the latest matters.
+static REQUEST: Signal<CriticalSectionRawMutex, Tft> = Signal::new();
+
+/// Change what the screen shows - never waits
+pub fn show(tft: Tft) {
+    REQUEST.signal(tft);
+}
+
+/// Show the cure cycle going from `old` to `new`
+pub fn follow(old: &CureState<'_>, new: &CureState<'_>, now_ms: u64) {
+    let tft = match *new {
+        CureState::Arming { .. } => Tft::Arming,
+        CureState::Curing { plan, phase, paused, .. } => match new.progress_ms(now_ms) {
+            Some((done_ms, total_ms)) => Tft::Progress {
+                done_ms,
+                total_ms,
+                running: paused.is_none(),
+                uv: plan.phase(phase).is_some_and(|p| p.is_uv()),
+            },
+            None => return,
+        },
+        CureState::Finishing { end: PhaseEnd::Fault(fault) } | CureState::Fault(fault) => Tft::Fault(fault),
+        CureState::Finishing { end } if end.completed() => Tft::Complete,
+        CureState::Finishing { .. } => Tft::Idle,
+        // Stay green after a cure, until the next one starts
+        CureState::Idle if matches!(*old, CureState::Finishing { end } if end.completed()) => return,
+        CureState::Idle => Tft::Idle,
+    };
+    show(tft);
+}
+
+/// Background task: drive the screen
+#[embassy_executor::task]
+pub async fn tft_task(
+    spi: SPI0,
+    sck: PIN_18,
+    mosi: PIN_19,
+    cs: PIN_17,
+    dc: PIN_16,
+    reset: PIN_21,
+    backlight: PIN_28,
+    dma: DMA_CH1,
+) {
+    let mut config = spi::Config::default();
+    config.frequency = TFT_SPI_FREQUENCY_HZ;
+    let bus = Spi::new_txonly(spi, sck, mosi, dma, config);
+    let mut screen = St7789::new(bus, Output::new(cs, Level::High), Output::new(dc, Level::Low));
+    let mut reset = Output::new(reset, Level::High);
+    let mut backlight = Output::new(backlight, Level::Low);
+    screen.init(&mut reset).await;
+    backlight.set_high();
+    info!("ST7789 screen ready");
+
+    let mut showing = Tft::Idle;
+    let mut since = Instant::now();
+    let mut drawn = None;
+
+    loop {
+        drawn = screen.draw(frame(showing, since.elapsed().as_millis()), drawn).await;
+        backlight.set_level(if showing == Tft::Standby { Level::Low } else { Level::High });
+
+        // Wait for the next request - or, while a cure runs, for the next
+        // frame (idle, for the clock to move on)
+        let next = match showing {
+            Tft::Progress { running: true, .. } => select(REQUEST.wait(), Timer::after_millis(FRAME_MS)).await,
+            Tft::Idle if IDLE_CLOCK => select(REQUEST.wait(), Timer::after_secs(1)).await,
+            _ => Either::First(REQUEST.wait().await),
+        };
+        if let Either::First(request) = next {
+            showing = request;
+            since = Instant::now();
+        }
+    }
+}
+
+/* ===========================================
+   LAYOUT
+   =========================================== */
+
+/// One screenful, ready to draw
+#[derive(Clone, Copy)]
+struct Frame {
+    background: u16,
+    label: [u8; 4],
+    countdown: [u8; 4],
+    /// Progress bar fill, in pixels (0 to BAR_WIDTH)
+    bar: u16,
+}
+
+/// Width and height of the screen, in pixels
+const SIZE: u16 = 240;
+const BAR_X: u16 = 20;
+const BAR_Y: u16 = 196;
+const BAR_WIDTH: u16 = 200;
+const BAR_HEIGHT: u16 = 20;
+/// The empty part of the progress bar
+const BAR_EMPTY: u16 = rgb565((0, 0, 0));
+const FOREGROUND: u16 = rgb565((255, 255, 255));
+
+/// Label: 4 small digits across the top
+const LABEL: DigitRow = DigitRow { x: 54, y: 20, width: 26, height: 44, thickness: 6, gap: 10, colon_gap: 0 };
+/// Countdown: MM:SS, as big as fits
+const COUNTDOWN: DigitRow = DigitRow { x: 8, y: 86, width: 44, height: 88, thickness: 10, gap: 8, colon_gap: 24 };
+
+/// The screen for `showing`, `elapsed_ms` after it was asked for
+fn frame(showing: Tft, elapsed_ms: u64) -> Frame {
+    let dashes = seven_segment::text("----");
+    match showing {
+        Tft::Idle => {
+            let countdown = match clock::now() {
+                Some(time) if IDLE_CLOCK => seven_segment::hours_minutes(time.hour, time.minute, true),
+                _ => dashes,
+            };
+            Frame { background: rgb565(TFT_IDLE_COLOR), label: seven_segment::text("IdLE"), countdown, bar: 0 }
+        }
+        Tft::Arming => Frame { background: rgb565(TFT_IDLE_COLOR), label: dashes, countdown: dashes, bar: 0 },
+        Tft::Progress { done_ms, total_ms, running, uv } => {
+            let done_ms = if running { done_ms + elapsed_ms } else { done_ms }.min(total_ms);
+            let label = match (running, uv) {
+                (false, _) => "PAUS",
+                (true, true) => "CurE",
+                (true, false) => "rESt",
+            };
+            // Round up, so it shows 0:01 for the last second rather than 0:00
+            let left_seconds = (total_ms - done_ms).div_ceil(1000);
+            Frame {
+                background: rgb565(TFT_CURING_COLOR),
+                label: seven_segment::text(label),
+                countdown: seven_segment::minutes_seconds(left_seconds, true),
+                bar: (done_ms * BAR_WIDTH as u64 / total_ms.max(1)) as u16,
+            }
+        }
+        Tft::Complete => Frame {
+            background: rgb565(TFT_COMPLETE_COLOR),
+            label: seven_segment::text("donE"),
+            countdown: seven_segment::minutes_seconds(0, true),
+            bar: BAR_WIDTH,
+        },
+        Tft::Fault(fault) => Frame { background: rgb565(TFT_FAULT_COLOR), label: fault_label(fault), countdown: dashes, bar: 0 },
+        Tft::Standby => Frame { background: rgb565((0, 0, 0)), label: BLANK, countdown: BLANK, bar: 0 },
+    }
+}
+
+/// "HOt" when too hot, otherwise "Er" and the fault's code - the same
+/// number the status LED blinks
+fn fault_label(fault: FaultCode) -> [u8; 4] {
+    if fault == FaultCode::OverTemp {
+        return seven_segment::text("HOt");
+    }
+    let mut label = seven_segment::text("Er");
+    label[3] = seven_segment::digit(fault.code() as u64);
+    label
+}
+
+/// (red, green, blue) as the 16-bit colour the ST7789 takes
+const fn rgb565((r, g, b): (u8, u8, u8)) -> u16 {
+    ((r as u16 >> 3) << 11) | ((g as u16 >> 2) << 5) | (b as u16 >> 3)
+}
+
+/// Where a row of four 7-segment digits goes
+struct DigitRow {
+    x: u16,
+    y: u16,
+    width: u16,
+    height: u16,
+    thickness: u16,
+    /// Space between digits
+    gap: u16,
+    /// Extra space between the 2nd and 3rd digits, for a colon
+    colon_gap: u16,
+}
+
+impl DigitRow {
+    /// Left edge of digit `i`
+    fn digit_x(&self, i: u16) -> u16 {
+        self.x + i * (self.width + self.gap) + if i >= 2 { self.colon_gap } else { 0 }
+    }
+
+    /// The rectangles (x, y, width, height) for segments a-g of digit `i`
+    fn segments(&self, i: u16) -> [(u16, u16, u16, u16); 7] {
+        let (x, y, w, h, t) = (self.digit_x(i), self.y, self.width, self.height, self.thickness);
+        let mid = y + h / 2;
+        let half = h / 2 - t - t / 2;   // Length of a vertical segment
+        [
+            (x + t, y, w - 2 * t, t),                 // a: top
+            (x + w - t, y + t, t, half),              // b: top right
+            (x + w - t, mid + t / 2, t, half),        // c: bottom right
+            (x + t, y + h - t, w - 2 * t, t),         // d: bottom
+            (x, mid + t / 2, t, half),                // e: bottom left
+            (x, y + t, t, half),                      // f: top left
+            (x + t, mid - t / 2, w - 2 * t, t),       // g: middle
+        ]
+    }
+
+    /// The two dots of the colon
+    fn colon(&self) -> [(u16, u16, u16, u16); 2] {
+        let t = self.thickness;
+        let x = self.digit_x(1) + self.width + (self.gap + self.colon_gap - t) / 2;
+        [(x, self.y + self.height / 3 - t / 2, t, t), (x, self.y + 2 * self.height / 3 - t / 2, t, t)]
+    }
+}
+
+/* ===========================================
+   ST7789 DRIVER
+   =========================================== */
+
+/* ST7789 commands */
+const CMD_SOFTWARE_RESET: u8 = 0x01;
+const CMD_SLEEP_OUT: u8 = 0x11;
+const CMD_NORMAL_MODE: u8 = 0x13;
+const CMD_INVERSION_OFF: u8 = 0x20;
+const CMD_INVERSION_ON: u8 = 0x21;
+const CMD_DISPLAY_ON: u8 = 0x29;
+const CMD_COLUMN_RANGE: u8 = 0x2A;
+const CMD_ROW_RANGE: u8 = 0x2B;
+const CMD_WRITE_MEMORY: u8 = 0x2C;
+const CMD_MEMORY_ORDER: u8 = 0x36;
+const CMD_PIXEL_FORMAT: u8 = 0x3A;
+/// 16 bits per pixel
+const PIXEL_FORMAT_RGB565: u8 = 0x55;
+
+/// Pixels sent per DMA transfer when filling a rectangle
+const FILL_CHUNK: usize = 64;
+
+/// An ST7789 on an SPI bus
+struct St7789<'d> {
+    bus: Spi<'d, SPI0, Async>,
+    cs: Output<'d, PIN_17>,
+    /// Low = command, high = data
+    dc: Output<'d, PIN_16>,
+}
+
+impl<'d> St7789<'d> {
+    fn new(bus: Spi<'d, SPI0, Async>, cs: Output<'d, PIN_17>, dc: Output<'d, PIN_16>) -> Self {
+        Self { bus, cs, dc }
+    }
+
+    /// Reset the panel and set it up for 16-bit colour
+    async fn init(&mut self, reset: &mut Output<'_, PIN_21>) {
+        reset.set_low();
+        Timer::after_millis(10).await;
+        reset.set_high();
+        Timer::after_millis(120).await;
+
+        self.command(CMD_SOFTWARE_RESET, &[]).await;
+        Timer::after_millis(150).await;
+        self.command(CMD_SLEEP_OUT, &[]).await;
+        Timer::after_millis(10).await;
+        self.command(CMD_PIXEL_FORMAT, &[PIXEL_FORMAT_RGB565]).await;
+        self.command(CMD_MEMORY_ORDER, &[0x00]).await;
+        // Most 240x240 modules have inverted colours - see TFT_INVERT_COLORS
+        self.command(if TFT_INVERT_COLORS { CMD_INVERSION_ON } else { CMD_INVERSION_OFF }, &[]).await;
+        self.command(CMD_NORMAL_MODE, &[]).await;
+        self.command(CMD_DISPLAY_ON, &[]).await;
+    }
+
+    /// Draw `next`, redrawing only what differs from `drawn` (what's on the
+    /// screen now). Returns what's on the screen afterwards.
+    async fn draw(&mut self, next: Frame, drawn: Option<Frame>) -> Option<Frame> {
+        // A new background colour means starting from scratch
+        let old = match drawn {
+            Some(old) if old.background == next.background => old,
+            _ => {
+                self.fill(0, 0, SIZE, SIZE, next.background).await;
+                self.fill(BAR_X, BAR_Y, BAR_WIDTH, BAR_HEIGHT, BAR_EMPTY).await;
+                Frame { background: next.background, label: [0; 4], countdown: [0; 4], bar: 0 }
+            }
+        };
+
+        for i in 0..4 {
+            if next.label[i] != old.label[i] {
+                self.digit(&LABEL, i as u16, next.label[i], next.background).await;
+            }
+            if next.countdown[i] != old.countdown[i] {
+                self.digit(&COUNTDOWN, i as u16, next.countdown[i], next.background).await;
+            }
+        }
+        if (next.countdown[1] ^ old.countdown[1]) & COLON != 0 {
+            let color = if next.countdown[1] & COLON != 0 { FOREGROUND } else { next.background };
+            for (x, y, w, h) in COUNTDOWN.colon() {
+                self.fill(x, y, w, h, color).await;
+            }
+        }
+
+        // Only the part of the bar that changed
+        if next.bar > old.bar {
+            self.fill(BAR_X + old.bar, BAR_Y, next.bar - old.bar, BAR_HEIGHT, FOREGROUND).await;
+        } else if next.bar < old.bar {
+            self.fill(BAR_X + next.bar, BAR_Y, old.bar - next.bar, BAR_HEIGHT, BAR_EMPTY).await;
+        }
+        Some(next)
+    }
+
+    /// One 7-segment digit: lit segments in white, the rest background
+    async fn digit(&mut self, row: &DigitRow, i: u16, segments: u8, background: u16) {
+        for (bit, (x, y, w, h)) in row.segments(i).into_iter().enumerate() {
+            let color = if segments & (1 << bit) != 0 { FOREGROUND } else { background };
+            self.fill(x, y, w, h, color).await;
+        }
+    }
+
+    /// Fill a rectangle with one colour
+    async fn fill(&mut self, x: u16, y: u16, width: u16, height: u16, color: u16) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        let (x_end, y_end) = (x + width - 1, y + height - 1);
+        self.command(CMD_COLUMN_RANGE, &[(x >> 8) as u8, x as u8, (x_end >> 8) as u8, x_end as u8]).await;
+        self.command(CMD_ROW_RANGE, &[(y >> 8) as u8, y as u8, (y_end >> 8) as u8, y_end as u8]).await;
+
+        let mut chunk = [0u8; FILL_CHUNK * 2];
+        for pixel in chunk.chunks_exact_mut(2) {
+            pixel.copy_from_slice(&color.to_be_bytes());
+        }
+        let mut pixels = width as usize * height as usize;
+
+        self.cs.set_low();
+        self.dc.set_low();
+        self.write(&[CMD_WRITE_MEMORY]).await;
+        self.dc.set_high();
+        while pixels > 0 {
+            let n = pixels.min(FILL_CHUNK);
+            self.write(&chunk[..n * 2]).await;
+            pixels -= n;
+        }
+        self.cs.set_high();
+    }
+
+    /// A command byte followed by its parameters
+    async fn command(&mut self, command: u8, params: &[u8]) {
+        self.cs.set_low();
+        self.dc.set_low();
+        self.write(&[command]).await;
+        if !params.is_empty() {
+            self.dc.set_high();
+            self.write(params).await;
+        }
+        self.cs.set_high();
+    }
+
+    async fn write(&mut self, bytes: &[u8]) {
+        // A TX-only bus can't see the panel, so there's nothing to go wrong
+        // that would be reported here
+        let _ = self.bus.write(bytes).await;
+    }
+}
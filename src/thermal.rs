@@ -0,0 +1,175 @@
+// Chamber Over-Temperature Protection (thermistor builds)
+//
+// An NTC thermistor in the chamber is read through the RP2040's ADC:
+//
+//   3.3V ── series resistor ──┬── GPIO 26 (ADC0)
+//                             └── NTC thermistor ── GND
+//
+// The thermistor's resistance drops as it heats up, pulling the ADC reading
+// down. The Beta equation turns that resistance into a temperature.
+//
+// A background task samples it continuously. If the chamber gets hotter than
+// OVERTEMP_LIMIT_C, the main loop is told to open the relay straight away,
+// and no new cure can start until it has cooled below OVERTEMP_RESUME_C.
+// A broken or shorted thermistor counts as over-temperature too - if we
+// can't measure the temperature we must not run the UV.
+//
+// Below that there's a soft limit, HEAT_PAUSE_C: a running cure pauses when
+// the chamber reaches it and resumes once it's back below HEAT_RESUME_C.
+
+use core::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+use defmt::*;
+use embassy_rp::adc::Channel;
+use embassy_rp::gpio::Pull;
+use embassy_rp::peripherals::PIN_26;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Instant, Timer};
+
+use crate::analog;
+use crate::config::*;
+
+/// Full-scale reading of the 12-bit ADC
+const ADC_MAX: f32 = 4095.0;
+
+/// Readings this close to either end of the ADC range mean the thermistor
+/// is disconnected (near full scale) or shorted (near zero)
+const ADC_FAULT_MARGIN: u16 = 10;
+
+/// 25°C in Kelvin - the temperature THERMISTOR_NOMINAL_OHMS is specified at
+const NOMINAL_KELVIN: f32 = 298.15;
+
+/// Whether the chamber is currently too hot (or the sensor has failed)
+static TOO_HOT: AtomicBool = AtomicBool::new(false);
+
+/// Latest temperature in hundredths of a degree C (i32::MIN = no valid reading)
+static LATEST_CENTI_CELSIUS: AtomicI32 = AtomicI32::new(i32::MIN);
+
+/// Wakes the main loop whenever TOO_HOT changes
+static TEMPERATURE_CHANGED: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Whether the chamber is past the soft limit (HEAT_PAUSE_C)
+static TOO_WARM: AtomicBool = AtomicBool::new(false);
+
+/// Wakes the main loop whenever TOO_WARM changes (a signal of its own, as
+/// the main loop waits for both at once)
+static WARMTH_CHANGED: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Is the chamber currently too hot to run the UV?
+pub fn is_too_hot() -> bool {
+    TOO_HOT.load(Ordering::Relaxed)
+}
+
+/// Latest chamber temperature in hundredths of a degree C, if the sensor is working
+pub fn latest_centi_celsius() -> Option<i32> {
+    match LATEST_CENTI_CELSIUS.load(Ordering::Relaxed) {
+        i32::MIN => None,
+        centi => Some(centi),
+    }
+}
+
+/// Wait until the chamber is too hot (returns straight away if it already is)
+pub async fn wait_for_overheat() {
+    while !is_too_hot() {
+        TEMPERATURE_CHANGED.wait().await;
+    }
+}
+
+/// Wait until the chamber has cooled down enough to cure again
+pub async fn wait_until_cool() {
+    while is_too_hot() {
+        TEMPERATURE_CHANGED.wait().await;
+    }
+}
+
+/// Wait until the chamber is past the soft limit (returns straight away if
+/// it already is - never, with HEAT_PAUSE_C off)
+pub async fn wait_until_too_warm() {
+    while !TOO_WARM.load(Ordering::Relaxed) {
+        WARMTH_CHANGED.wait().await;
+    }
+}
+
+/// Wait until the chamber has cooled below HEAT_RESUME_C
+pub async fn wait_until_cooled_down() {
+    while TOO_WARM.load(Ordering::Relaxed) {
+        WARMTH_CHANGED.wait().await;
+    }
+}
+
+/// Convert a raw ADC reading to degrees Celsius (None = sensor fault)
+fn to_celsius(raw: u16) -> Option<f32> {
+    if raw < ADC_FAULT_MARGIN || raw > ADC_MAX as u16 - ADC_FAULT_MARGIN {
+        return None;
+    }
+
+    // Voltage divider: the thermistor is the bottom resistor
+    let raw = raw as f32;
+    let ohms = THERMISTOR_SERIES_OHMS as f32 * raw / (ADC_MAX - raw);
+
+    // Beta equation: 1/T = 1/T0 + ln(R/R0)/B
+    let inverse_kelvin = 1.0 / NOMINAL_KELVIN + libm::logf(ohms / THERMISTOR_NOMINAL_OHMS as f32) / THERMISTOR_BETA as f32;
+    Some(1.0 / inverse_kelvin - 273.15)
+}
+
+/// Background task: sample the thermistor and raise the over-temperature alarm
+#[embassy_executor::task]
+pub async fn thermal_monitor_task(pin: PIN_26) {
+    let mut channel = Channel::new_pin(pin, Pull::None);
+
+    let log_interval = Duration::from_secs(TEMP_LOG_INTERVAL_SECONDS);
+    let mut last_log = Instant::now();
+
+    loop {
+        let celsius = match analog::read(&mut channel).await {
+            Ok(raw) => to_celsius(raw),
+            Err(_) => None,
+        };
+
+        let centi = celsius.map_or(i32::MIN, |c| (c * 100.0) as i32);
+        LATEST_CENTI_CELSIUS.store(centi, Ordering::Relaxed);
+
+        let too_hot = match celsius {
+            Some(c) if is_too_hot() => c >= OVERTEMP_RESUME_C as f32,  // Stay tripped until properly cool
+            Some(c) => c >= OVERTEMP_LIMIT_C as f32,
+            None => true,
+        };
+
+        if too_hot != is_too_hot() {
+            TOO_HOT.store(too_hot, Ordering::Relaxed);
+            TEMPERATURE_CHANGED.signal(());
+            match (too_hot, celsius) {
+                (true, Some(c)) => error!("OVER-TEMPERATURE: chamber at {} °C (limit {} °C)", c, OVERTEMP_LIMIT_C),
+                (true, None) => error!("Thermistor fault (disconnected or shorted) - UV disabled"),
+                (false, _) => info!("Chamber cooled below {} °C - UV allowed again", OVERTEMP_RESUME_C),
+            }
+        }
+
+        // The soft limit - no reading is left to the alarm above
+        let was_warm = TOO_WARM.load(Ordering::Relaxed);
+        let too_warm = match (HEAT_PAUSE_C, celsius) {
+            (Some(_), Some(c)) if was_warm => c >= HEAT_RESUME_C as f32,
+            (Some(limit), Some(c)) => c >= limit as f32,
+            _ => false,
+        };
+
+        if too_warm != was_warm {
+            TOO_WARM.store(too_warm, Ordering::Relaxed);
+            WARMTH_CHANGED.signal(());
+            match (too_warm, celsius) {
+                (true, Some(c)) => warn!("Chamber warm: {} °C - cures pause until it's below {} °C", c, HEAT_RESUME_C),
+                _ => info!("Chamber cooled below {} °C - paused cures carry on", HEAT_RESUME_C),
+            }
+        }
+
+        if last_log.elapsed() >= log_interval {
+            if let Some(c) = celsius {
+                info!("Chamber temperature: {} °C", c);
+            }
+            last_log = Instant::now();
+        }
+
+        Timer::after_millis(TEMP_SAMPLE_INTERVAL_MS).await;
+    }
+}
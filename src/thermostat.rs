@@ -0,0 +1,70 @@
+// Cooling Fan Thermostat (fan builds)
+//
+// Switches a chamber cooling fan (through a MOSFET or relay on GPIO 12):
+// - always ON while a cure is running
+// - otherwise ON once the chamber reaches FAN_ON_C, and OFF again only when
+//   it has cooled below FAN_OFF_C. The gap between the two (hysteresis)
+//   stops the fan clicking on and off around a single threshold.
+//
+// The temperature comes from whichever sensor is built in: the thermistor
+// (`thermistor` feature) or the BME280 (`bme280` feature). With no valid
+// reading the fan stays ON - better noisy than hot.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use defmt::*;
+use embassy_futures::select::select;
+use embassy_rp::gpio::{AnyPin, Output};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_time::Timer;
+
+use crate::config::*;
+use crate::sensors::chamber_centi_celsius;
+
+/// Whether a cure is running (fan forced on)
+static CURING: AtomicBool = AtomicBool::new(false);
+
+/// Wakes the thermostat straight away when a cure starts or ends
+static CURING_CHANGED: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Tell the thermostat a cure has started (true) or ended (false)
+pub fn set_curing(curing: bool) {
+    CURING.store(curing, Ordering::Relaxed);
+    CURING_CHANGED.signal(());
+}
+
+/// Background task: switch the fan from the temperature and cure state
+#[embassy_executor::task]
+pub async fn fan_task(mut fan: Output<'static, AnyPin>) {
+    let mut fan_on = false;
+
+    loop {
+        let curing = CURING.load(Ordering::Relaxed);
+        let temperature = chamber_centi_celsius();
+
+        let want_on = match temperature {
+            _ if curing => true,
+            Some(centi) if fan_on => centi >= FAN_OFF_C * 100,  // Stay on until properly cool
+            Some(centi) => centi >= FAN_ON_C * 100,
+            None => true,                                       // No reading - fail safe
+        };
+
+        if want_on != fan_on {
+            fan_on = want_on;
+            if fan_on {
+                fan.set_high();
+                match (curing, temperature) {
+                    (true, _) => info!("Fan ON - curing"),
+                    (false, Some(centi)) => info!("Fan ON - chamber at {} °C", centi / 100),
+                    (false, None) => warn!("Fan ON - no temperature reading"),
+                }
+            } else {
+                fan.set_low();
+                info!("Fan OFF");
+            }
+        }
+
+        select(Timer::after_secs(FAN_CHECK_INTERVAL_SECONDS), CURING_CHANGED.wait()).await;
+    }
+}
@@ -0,0 +1,122 @@
+// TM1637 4-Digit Display Driver (tm1637 builds)
+//
+// The cheap 4-digit 7-segment modules (often sold as "TM1637 clock
+// display") have two signal wires:
+//
+//   GPIO 21 ── CLK
+//   GPIO 28 ── DIO
+//   3.3V ── VCC, GND ── GND
+//
+// The TM1637 talks something close to I2C, but not close enough for the
+// RP2040's I2C hardware (no address, least significant bit first), so it
+// is bit-banged here. Both lines are open-drain: a pin is either pulled
+// LOW, or let go (switched to input) and pulled HIGH by the module's own
+// resistors. The chip answers each byte by pulling DIO low - the ACK.
+//
+// A whole update (four digits) takes well under a millisecond, with the
+// CPU busy for all of it - too short to matter to the rest of the firmware.
+
+use embassy_rp::gpio::{AnyPin, Flex, Pull};
+use embassy_time::{block_for, Duration};
+
+/// "Write data, auto-increment the address"
+const CMD_DATA: u8 = 0x40;
+/// "Start at digit 0"
+const CMD_ADDRESS: u8 = 0xC0;
+/// "Display on" - plus the brightness, 0-7
+const CMD_DISPLAY_ON: u8 = 0x88;
+
+/// Half a clock period - the TM1637 manages 250 kHz at best, so keep well under
+const HALF_BIT: Duration = Duration::from_micros(5);
+
+/// A TM1637 module on two GPIO pins
+pub struct Tm1637<'d> {
+    clk: Flex<'d, AnyPin>,
+    dio: Flex<'d, AnyPin>,
+    /// 0 (dimmest) to 7 (brightest)
+    brightness: u8,
+}
+
+impl<'d> Tm1637<'d> {
+    pub fn new(clk: AnyPin, dio: AnyPin, brightness: u8) -> Self {
+        let mut clk = Flex::new(clk);
+        let mut dio = Flex::new(dio);
+        for pin in [&mut clk, &mut dio] {
+            pin.set_low();          // Output level whenever it's an output
+            pin.set_pull(Pull::Up); // Helps the module's pull-ups along
+            pin.set_as_input();     // Let go = HIGH
+        }
+        Self { clk, dio, brightness: brightness.min(7) }
+    }
+
+    /// Show four digits' worth of segments (see curing-core's seven_segment.rs)
+    ///
+    /// Returns false if the module didn't answer (not fitted, or a loose wire).
+    pub fn show(&mut self, segments: [u8; 4]) -> bool {
+        let mut acked = self.command(&[CMD_DATA]);
+        let mut frame = [CMD_ADDRESS, 0, 0, 0, 0];
+        frame[1..].copy_from_slice(&segments);
+        acked &= self.command(&frame);
+        acked &= self.command(&[CMD_DISPLAY_ON | self.brightness]);
+        acked
+    }
+
+    /// One start ... stop transfer - true if every byte was acknowledged
+    fn command(&mut self, bytes: &[u8]) -> bool {
+        // Start: DIO falls while CLK is high
+        release(&mut self.clk);
+        release(&mut self.dio);
+        block_for(HALF_BIT);
+        pull_low(&mut self.dio);
+        block_for(HALF_BIT);
+        pull_low(&mut self.clk);
+
+        let mut acked = true;
+        for &byte in bytes {
+            acked &= self.write_byte(byte);
+        }
+
+        // Stop: DIO rises while CLK is high
+        pull_low(&mut self.dio);
+        block_for(HALF_BIT);
+        release(&mut self.clk);
+        block_for(HALF_BIT);
+        release(&mut self.dio);
+        block_for(HALF_BIT);
+        acked
+    }
+
+    /// Eight bits, least significant first, then the ACK clock
+    fn write_byte(&mut self, byte: u8) -> bool {
+        for bit in 0..8 {
+            if byte & (1 << bit) != 0 {
+                release(&mut self.dio);
+            } else {
+                pull_low(&mut self.dio);
+            }
+            block_for(HALF_BIT);
+            release(&mut self.clk);
+            block_for(HALF_BIT);
+            pull_low(&mut self.clk);
+        }
+
+        // The chip pulls DIO low during the ninth clock to say "got it"
+        release(&mut self.dio);
+        block_for(HALF_BIT);
+        release(&mut self.clk);
+        block_for(HALF_BIT);
+        let acked = self.dio.is_low();
+        pull_low(&mut self.clk);
+        acked
+    }
+}
+
+/// Drive an open-drain line LOW
+fn pull_low(pin: &mut Flex<'_, AnyPin>) {
+    pin.set_as_output();
+}
+
+/// Let an open-drain line go - the pull-ups take it HIGH
+fn release(pin: &mut Flex<'_, AnyPin>) {
+    pin.set_as_input();
+}
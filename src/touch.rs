@@ -0,0 +1,156 @@
+// Touch Pad Start Button (touch-pad builds)
+//
+// A bare copper pad (or foil behind the acrylic) used as the start button,
+// with no touch module - the RP2040 senses the finger itself. Wire it:
+//
+//   3.3V ── 1 MΩ ──┬── GPIO 6
+//                  └── pad
+//
+// To take a reading the pin pulls the pad to 0 V, lets go, and counts how
+// long the resistor takes to charge it back up to a HIGH. A finger adds
+// capacitance, so the count goes up when the pad is touched. The count
+// with nobody touching (the baseline) is measured at power-on and follows
+// slow drift (temperature, humidity) from then on - so keep fingers off
+// the pad while the curer powers up.
+//
+// `TouchPad` is a normal embedded-hal input pin as far as the rest of the
+// firmware is concerned: HIGH while touched. It goes into curing-core's
+// `Button` driver just like a real button, so taps, holds and double taps
+// work the same. With a latching start input (START_INPUT) it toggles
+// instead - one touch ON, the next OFF - like a TTP223 module with its TOG
+// jumper set.
+
+use core::convert::Infallible;
+
+use embassy_rp::gpio::{AnyPin, Flex, Pull};
+use embassy_time::{block_for, Duration, Timer};
+use embedded_hal::digital::{ErrorType, InputPin};
+use embedded_hal_async::digital::Wait;
+
+use crate::config::*;
+
+/// Give up counting here - the pad is far bigger than it should be, or the
+/// resistor is missing
+const MAX_COUNT: u32 = 10_000;
+
+/// Readings averaged into one sample
+const READINGS_PER_SAMPLE: u32 = 4;
+
+/// A touch pad on a GPIO with a pull-up resistor
+pub struct TouchPad<'d> {
+    pin: Flex<'d, AnyPin>,
+    /// Average untouched count
+    baseline: u32,
+    /// The pad is touched right now
+    touched: bool,
+    /// Touches flip `level` instead of setting it
+    toggle: bool,
+    /// What the pin reports: touched, or ON/OFF in toggle mode
+    level: bool,
+}
+
+impl<'d> TouchPad<'d> {
+    /// Take the pin and measure the baseline (don't touch the pad!)
+    pub fn new(pin: AnyPin, toggle: bool) -> Self {
+        let mut pin = Flex::new(pin);
+        pin.set_pull(Pull::None);      // The 1 MΩ does the charging
+        pin.set_low();                 // Output level whenever it's an output
+        let mut pad = Self { pin, baseline: 0, touched: false, toggle, level: false };
+        let baseline = (0..8).map(|_| pad.sample()).sum::<u32>() / 8;
+        pad.baseline = baseline;
+        defmt::info!("Touch pad baseline: {} counts", pad.baseline);
+        pad
+    }
+
+    /// Take a sample and update the state. Returns the level.
+    fn poll(&mut self) -> bool {
+        let count = self.sample();
+        let touched = count > self.baseline + self.baseline * TOUCH_PAD_THRESHOLD_PERCENT / 100;
+        if !touched {
+            // Follow slow drift, 1/16th of the way each time
+            self.baseline = (self.baseline * 15 + count) / 16;
+        }
+        if touched != self.touched {
+            self.touched = touched;
+            if !self.toggle {
+                self.level = touched;
+            } else if touched {
+                self.level = !self.level;
+            }
+        }
+        self.level
+    }
+
+    /// Average charge-up count over a few readings
+    fn sample(&mut self) -> u32 {
+        (0..READINGS_PER_SAMPLE).map(|_| self.reading()).sum::<u32>() / READINGS_PER_SAMPLE
+    }
+
+    /// How long the pad takes to charge, in loop counts
+    fn reading(&mut self) -> u32 {
+        // Empty the pad...
+        self.pin.set_as_output();
+        block_for(Duration::from_micros(10));
+        // ...then let go and time the climb back up, with interrupts off so
+        // nothing stretches the count
+        cortex_m::interrupt::free(|_| {
+            self.pin.set_as_input();
+            let mut count = 0;
+            while self.pin.is_low() && count < MAX_COUNT {
+                count += 1;
+            }
+            count
+        })
+    }
+
+    /// Sample every TOUCH_PAD_POLL_MS until the level is `level`
+    async fn wait_for_level(&mut self, level: bool) {
+        while self.poll() != level {
+            Timer::after_millis(TOUCH_PAD_POLL_MS).await;
+        }
+    }
+}
+
+impl ErrorType for TouchPad<'_> {
+    type Error = Infallible;
+}
+
+impl InputPin for TouchPad<'_> {
+    fn is_high(&mut self) -> Result<bool, Infallible> {
+        Ok(self.poll())
+    }
+
+    fn is_low(&mut self) -> Result<bool, Infallible> {
+        Ok(!self.poll())
+    }
+}
+
+impl Wait for TouchPad<'_> {
+    async fn wait_for_high(&mut self) -> Result<(), Infallible> {
+        self.wait_for_level(true).await;
+        Ok(())
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Infallible> {
+        self.wait_for_level(false).await;
+        Ok(())
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Infallible> {
+        self.wait_for_level(false).await;
+        self.wait_for_level(true).await;
+        Ok(())
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Infallible> {
+        self.wait_for_level(true).await;
+        self.wait_for_level(false).await;
+        Ok(())
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Infallible> {
+        let level = self.level;
+        self.wait_for_level(!level).await;
+        Ok(())
+    }
+}
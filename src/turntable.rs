@@ -0,0 +1,155 @@
+// Turntable (turntable / dc-turntable builds)
+//
+// Spins the chamber's turntable while the UV is on. Two motor drivers exist:
+//
+// - Stepper (`--features turntable`): a stepper driver board (A4988,
+//   DRV8825, TMC2208...) on two pins: STEP (one pulse = one (micro)step)
+//   and DIR (rotation direction).
+// - DC motor (`--features dc-turntable`): a small geared DC motor switched
+//   by a logic-level MOSFET on a PWM pin, with a soft start and a run-on
+//   time after the UV goes off.
+//
+// The motor runs in its own Embassy task so it keeps going while the main
+// loop is busy timing the cure. The main loop just tells it to start or stop
+// through the TURNTABLE signal - start when the UV goes on, stop when it
+// goes off.
+
+use defmt::*;
+use embassy_futures::select::{select, Either};
+#[cfg(feature = "turntable")]
+use embassy_rp::gpio::{AnyPin, Output};
+#[cfg(feature = "dc-turntable")]
+use embassy_rp::{peripherals::{PIN_4, PWM_CH2}, pwm::{self, Pwm}};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Timer};
+#[cfg(feature = "dc-turntable")]
+use embassy_time::Instant;
+
+use crate::config::*;
+
+/// Start (true) / stop (false) requests for the turntable task
+static TURNTABLE: Signal<CriticalSectionRawMutex, bool> = Signal::new();
+
+/// Ask the turntable to start spinning
+pub fn start() {
+    TURNTABLE.signal(true);
+}
+
+/// Ask the turntable to stop
+pub fn stop() {
+    TURNTABLE.signal(false);
+}
+
+/// Background task: step the motor whenever the turntable is switched on
+#[cfg(feature = "turntable")]
+#[embassy_executor::task]
+pub async fn turntable_task(mut step: Output<'static, AnyPin>, mut dir: Output<'static, AnyPin>) {
+    // Time for one full step pulse (high + low) at the configured speed
+    let steps_per_minute = TURNTABLE_RPM * TURNTABLE_STEPS_PER_REV;
+    let half_step = Duration::from_micros(60_000_000 / steps_per_minute / 2);
+
+    if TURNTABLE_CLOCKWISE {
+        dir.set_high();
+    } else {
+        dir.set_low();
+    }
+
+    loop {
+        // Sleep until someone asks us to spin
+        if !TURNTABLE.wait().await {
+            continue;
+        }
+        info!("Turntable ON - {} RPM", TURNTABLE_RPM);
+
+        // Step until a stop request arrives. The pin is always left low, so
+        // a stop can never leave the driver halfway through a pulse.
+        loop {
+            step.set_high();
+            Timer::after(half_step).await;
+            step.set_low();
+            if let Either::Second(false) = select(Timer::after(half_step), TURNTABLE.wait()).await {
+                info!("Turntable OFF");
+                break;
+            }
+        }
+    }
+}
+
+/// PWM counter wrap value for the DC motor: 125 MHz / (6249 + 1) = 20 kHz,
+/// above human hearing so the motor doesn't whine
+#[cfg(feature = "dc-turntable")]
+const MOTOR_PWM_TOP: u16 = 6_249;
+
+/// Number of speed steps in the soft start ramp
+#[cfg(feature = "dc-turntable")]
+const RAMP_STEPS: u32 = 20;
+
+/// Set the DC motor duty cycle in percent
+#[cfg(feature = "dc-turntable")]
+fn set_motor_speed(pwm: &mut Pwm<'static, PWM_CH2>, config: &mut pwm::Config, percent: u32) {
+    config.compare_a = ((MOTOR_PWM_TOP as u32 + 1) * percent / 100) as u16;
+    pwm.set_config(config);
+}
+
+/// Background task: run the DC motor whenever the turntable is switched on
+///
+/// Ramps up to DC_MOTOR_SPEED_PERCENT over DC_MOTOR_RAMP_MS, and keeps
+/// spinning for DC_MOTOR_RUN_ON_SECONDS after a stop request. A start
+/// request during the run-on just carries on at full speed.
+#[cfg(feature = "dc-turntable")]
+#[embassy_executor::task]
+pub async fn turntable_task(slice: PWM_CH2, pin: PIN_4) {
+    // PWM slice 2 channel A drives the motor MOSFET on GPIO 4, starting stopped
+    let mut config = pwm::Config::default();
+    config.top = MOTOR_PWM_TOP;
+    config.compare_a = 0;          // 0% duty = motor stopped
+    let mut pwm = Pwm::new_output_a(slice, pin, config.clone());
+    let ramp_step = Duration::from_millis(DC_MOTOR_RAMP_MS / RAMP_STEPS as u64);
+
+    loop {
+        // Sleep until someone asks us to spin
+        if !TURNTABLE.wait().await {
+            continue;
+        }
+        info!("Turntable ON - {}% speed", DC_MOTOR_SPEED_PERCENT);
+
+        /* SOFT START */
+        // Ramping up gently stops parts sliding around on the platter
+        let mut stop_requested = false;
+        for step in 1..=RAMP_STEPS {
+            set_motor_speed(&mut pwm, &mut config, DC_MOTOR_SPEED_PERCENT * step / RAMP_STEPS);
+            if let Either::Second(false) = select(Timer::after(ramp_step), TURNTABLE.wait()).await {
+                stop_requested = true;
+                break;
+            }
+        }
+
+        'running: loop {
+            // Spin until a stop request arrives
+            if !stop_requested {
+                while TURNTABLE.wait().await {}
+            }
+            stop_requested = false;
+
+            /* RUN-ON */
+            // Keep turning a little after the UV goes off
+            let run_on_end = Instant::now() + Duration::from_secs(DC_MOTOR_RUN_ON_SECONDS);
+            loop {
+                match select(Timer::at(run_on_end), TURNTABLE.wait()).await {
+                    Either::First(_) => break 'running,
+                    Either::Second(true) => {
+                        // UV back on - carry on at full speed
+                        set_motor_speed(&mut pwm, &mut config, DC_MOTOR_SPEED_PERCENT);
+                        continue 'running;
+                    }
+                    Either::Second(false) => {}
+                }
+            }
+        }
+
+        set_motor_speed(&mut pwm, &mut config, 0);
+        info!("Turntable OFF");
+    }
+}
+
@@ -0,0 +1,206 @@
+// UART Control Link (uart-control builds)
+//
+// Lets another computer - a Raspberry Pi, a printer controller - drive the
+// curer over a 3-wire serial link on UART0: GPIO 0 (TX) to the host's RX,
+// GPIO 1 (RX) to the host's TX, and GND to GND. 3.3 V logic only!
+// Speed is UART_BAUD_RATE (config.rs), 8N1.
+//
+// Every message is one line of text, called a frame:
+//   $<seq>,<command>[,<value>...]*<crc>\r\n
+// - seq: 0-255, picked by the host; the reply carries the same number
+// - crc: CRC-16/CCITT-FALSE of everything between '$' and '*', as 4 hex digits
+//
+// Commands (case-insensitive, the same ones as the USB serial port):
+//   START  START,120  STOP  STATUS  CONFIG  STATS  SET,DEBOUNCE,80  SET,VERBOSITY,3
+//   TIME  TIME,2026-10-16T14:30:00  WIFI  WIFI,SET,<name>,<password>
+//
+// Replies:
+//   $<seq>,ACK*<crc>                       done
+//   $<seq>,ACK,curing,95*<crc>             STATUS: state[,seconds left]
+//   $<seq>,ACK,300,600,1000,50,2*<crc>     CONFIG: cure_s,max_cure_s,cooldown_ms,debounce_ms,verbosity
+//   $<seq>,ACK,42,3,12600,12600*<crc>      STATS: completed,aborted,uv_on_s,lamp_s
+//   $<seq>,ACK,2026-10-16T14:30:00*<crc>   TIME (ACK,unset if the clock isn't set)
+//   $<seq>,ACK,connected,192.168.1.23*<crc>  WIFI (ACK,disconnected when it isn't)
+//   $<seq>,NAK,<code>*<crc>                refused or not understood
+// The NAK codes are the same as the JSON protocol's, plus bad_frame,
+// bad_checksum and too_long. A frame too garbled to read gets seq "-".
+//
+// Noise protection:
+// - a frame whose checksum doesn't match is never acted on - it gets a NAK,
+//   and the host sends it again
+// - if a reply is lost and the host repeats a frame with the same seq, the
+//   saved reply is sent again WITHOUT running the command twice, so a retry
+//   can never start a second cure. Use a new seq for every new command.
+// - bytes outside a frame (line noise, a host booting up) are ignored
+
+use core::fmt::Write as _;
+
+use defmt::*;
+use embassy_rp::bind_interrupts;
+use embassy_rp::peripherals::{PIN_0, PIN_1, UART0};
+use embassy_rp::uart::{BufferedInterruptHandler, BufferedUart, Config};
+use embedded_io_async::{Read, Write};
+use heapless::String;
+
+use crate::commands::{self, Reply};
+use crate::config::*;
+use crate::settings;
+use crate::storage::crc16;
+
+bind_interrupts!(struct Irqs {
+    UART0_IRQ => BufferedInterruptHandler<UART0>;
+});
+
+/// Longest frame we accept (between '$' and the line ending)
+const MAX_FRAME_LENGTH: usize = 64;
+
+/// A reply frame, ready to send
+type Response = String<96>;
+
+/// Reads frames from the host and answers them (never returns)
+#[embassy_executor::task]
+pub async fn uart_control_task(uart: UART0, tx_pin: PIN_0, rx_pin: PIN_1) -> ! {
+    let mut tx_buffer = [0u8; 128];
+    let mut rx_buffer = [0u8; 128];
+    let mut config = Config::default();
+    config.baudrate = UART_BAUD_RATE;
+    let mut uart = BufferedUart::new(uart, Irqs, tx_pin, rx_pin, &mut tx_buffer, &mut rx_buffer, config);
+    info!("UART control link ready ({} baud)", UART_BAUD_RATE);
+
+    let mut chunk = [0u8; 32];
+    let mut frame: String<MAX_FRAME_LENGTH> = String::new();
+    let mut in_frame = false;
+    let mut garbled = false;           // Frame had a byte that can't be in one
+    let mut overflow = false;
+    let mut last: Option<(u8, Response)> = None;
+
+    loop {
+        let n = match uart.read(&mut chunk).await {
+            Ok(n) => n,
+            Err(e) => {
+                warn!("UART: receive error: {}", e);
+                in_frame = false;      // Whatever frame was coming is damaged
+                continue;
+            }
+        };
+        for &byte in &chunk[..n] {
+            match byte {
+                // A '$' always starts a new frame, dropping any broken one
+                b'$' => {
+                    frame.clear();
+                    in_frame = true;
+                    garbled = false;
+                    overflow = false;
+                }
+                b'\r' | b'\n' if in_frame => {
+                    in_frame = false;
+                    let response = if overflow {
+                        response(None, Err("too_long"))
+                    } else if garbled {
+                        response(None, Err("bad_frame"))
+                    } else {
+                        handle(&frame, &mut last)
+                    };
+                    if uart.write_all(response.as_bytes()).await.is_err() {
+                        warn!("UART: send failed");
+                    }
+                }
+                b' '..=b'~' if in_frame => {
+                    if frame.push(byte as char).is_err() {
+                        overflow = true;
+                    }
+                }
+                _ if in_frame => garbled = true,
+                _ => {}                // Noise between frames
+            }
+        }
+    }
+}
+
+/// Check one frame (without its '$'), run it and build the reply
+fn handle(frame: &str, last: &mut Option<(u8, Response)>) -> Response {
+    let Some((body, crc)) = frame.split_once('*') else {
+        return response(None, Err("bad_frame"));
+    };
+    if u16::from_str_radix(crc, 16).ok() != Some(crc16(body.as_bytes())) {
+        return response(None, Err("bad_checksum"));
+    }
+    let Some((seq, command)) = body.split_once(',') else {
+        return response(None, Err("bad_frame"));
+    };
+    let Ok(seq) = seq.parse::<u8>() else {
+        return response(None, Err("bad_frame"));
+    };
+
+    // The host didn't get our reply and sent the same frame again
+    if let Some((last_seq, last_response)) = last {
+        if *last_seq == seq {
+            return last_response.clone();
+        }
+    }
+
+    // "SET,DEBOUNCE,80" is the serial port's "set debounce 80"
+    let words: String<MAX_FRAME_LENGTH> = command.chars().map(|c| if c == ',' { ' ' } else { c }).collect();
+    let result = match commands::parse(&words) {
+        Err(e) => Err(e.code()),
+        Ok(command) => {
+            let changes_something = command.changes_something();
+            let secret = command.has_secret();
+            let result = commands::execute(command).map_err(|e| e.code());
+            if changes_something && result.is_ok() {
+                // Not the token or password itself - the log may end up anywhere
+                info!("UART: {}", if secret { "API token or Wi-Fi network changed" } else { words.as_str() });
+            }
+            result
+        }
+    };
+
+    let response = response(Some(seq), result);
+    *last = Some((seq, response.clone()));
+    response
+}
+
+/// Build a reply frame: ACK with any values, or NAK with an error code
+fn response(seq: Option<u8>, result: Result<Reply, &str>) -> Response {
+    let mut body: String<80> = String::new();
+    let _ = match seq {
+        Some(seq) => write!(body, "{}", seq),
+        None => write!(body, "-"),
+    };
+    let _ = match result {
+        Err(code) => write!(body, ",NAK,{}", code),
+        Ok(Reply::Status(status)) => write!(body, ",ACK,{}", status.name()).and_then(|_| match status.remaining_seconds() {
+            Some(seconds) => write!(body, ",{}", seconds),
+            None => Ok(()),
+        }),
+        Ok(Reply::Config) => write!(
+            body,
+            ",ACK,{},{},{},{},{}",
+            settings::cure_seconds(),
+            MAX_CURING_DURATION_SECONDS,
+            settings::cooldown_ms(),
+            settings::button_debounce_ms(),
+            settings::log_level() as u8
+        ),
+        Ok(Reply::Stats(stats)) => write!(
+            body,
+            ",ACK,{},{},{},{}",
+            stats.completed, stats.aborted, stats.uv_on_seconds, stats.lamp_seconds
+        ),
+        Ok(Reply::Mem { used, size }) => write!(body, ",ACK,{},{}", used, size),
+        Ok(Reply::Time(Some(time))) => write!(body, ",ACK,{}", time),
+        Ok(Reply::Time(None)) => write!(body, ",ACK,unset"),
+        #[cfg(feature = "gpio-diagnostics")]
+        Ok(Reply::Gpio { pin, high }) => write!(body, ",ACK,{},{}", pin, if high { "high" } else { "low" }),
+        #[cfg(feature = "pico-w")]
+        Ok(Reply::Wifi) => match crate::wifi::address() {
+            Some([a, b, c, d]) => write!(body, ",ACK,connected,{}.{}.{}.{}", a, b, c, d),
+            None => write!(body, ",ACK,disconnected"),
+        },
+        Ok(Reply::Done) | Ok(Reply::Help) => write!(body, ",ACK"),
+    };
+
+    let mut response = Response::new();
+    let _ = write!(response, "${}*{:04X}\r\n", body, crc16(body.as_bytes()));
+    response
+}
@@ -0,0 +1,145 @@
+// Log Over USB (usb-log builds)
+//
+// Normally the info!/warn! log goes to a debug probe over RTT - no probe,
+// no log. With usb-log the log goes out of the Pico's own USB socket
+// instead, on a second serial port next to the command port (usb_serial.rs):
+// the first port is for commands, the second is the log.
+//
+// defmt doesn't send text - it sends small binary frames that point at the
+// format strings kept in the firmware's ELF file, which is what makes it
+// cheap enough for a microcontroller. So the log is read on the computer
+// with `defmt-print` and that ELF file, not a plain terminal (see the
+// README, "Log Over USB").
+//
+// How it fits together:
+// - `UsbLogger` is defmt's global logger (instead of defmt-rtt). Each log
+//   line is built up as one frame, then dropped into LOG_PIPE whole - or
+//   not at all if there's no room, so the decoder never sees half a frame
+// - `log_task` (started by usb_serial.rs) copies LOG_PIPE to the log port
+//   while a computer has it open
+//
+// Lines logged before the port is opened wait in LOG_PIPE, so the start-up
+// log is there when you connect - as long as it fits in
+// USB_LOG_BUFFER_BYTES. A panic's message can't get out this way (USB has
+// stopped by then); that still needs a probe.
+
+use core::cell::RefCell;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::pipe::Pipe;
+use embassy_usb::class::cdc_acm::CdcAcmClass;
+
+use crate::config::*;
+use crate::usb_serial::{UsbDriver, MAX_PACKET_SIZE};
+
+/// Longest single log frame - longer ones are dropped
+const MAX_FRAME_BYTES: usize = 256;
+
+/// Finished frames waiting to go out of the log port
+static LOG_PIPE: Pipe<CriticalSectionRawMutex, USB_LOG_BUFFER_BYTES> = Pipe::new();
+
+/// The frame being built, and defmt's encoder for it
+static FRAME: Mutex<CriticalSectionRawMutex, RefCell<Frame>> = Mutex::new(RefCell::new(Frame::new()));
+
+/// Set while a log line is being written (defmt allows only one at a time)
+static TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// Interrupt state to put back once the log line is written
+static mut RESTORE: critical_section::RestoreState = critical_section::RestoreState::invalid();
+
+/// One log frame on its way to LOG_PIPE
+struct Frame {
+    encoder: defmt::Encoder,
+    bytes: [u8; MAX_FRAME_BYTES],
+    len: usize,
+    /// Didn't fit in `bytes` - thrown away at the end
+    too_long: bool,
+}
+
+impl Frame {
+    const fn new() -> Self {
+        Self { encoder: defmt::Encoder::new(), bytes: [0; MAX_FRAME_BYTES], len: 0, too_long: false }
+    }
+}
+
+/// Add encoded bytes to the frame
+fn push(bytes: &mut [u8; MAX_FRAME_BYTES], len: &mut usize, too_long: &mut bool, data: &[u8]) {
+    match bytes.get_mut(*len..*len + data.len()) {
+        Some(space) => {
+            space.copy_from_slice(data);
+            *len += data.len();
+        }
+        None => *too_long = true,
+    }
+}
+
+#[defmt::global_logger]
+struct UsbLogger;
+
+// Safety: defmt calls acquire, then write any number of times, then release,
+// and the critical section held in between means nothing else logs meanwhile
+unsafe impl defmt::Logger for UsbLogger {
+    fn acquire() {
+        let restore = unsafe { critical_section::acquire() };
+        if TAKEN.load(Ordering::Relaxed) {
+            // Logging from inside a log line - a bug, and nowhere to say so
+            panic!("defmt logger taken reentrantly")
+        }
+        TAKEN.store(true, Ordering::Relaxed);
+        unsafe { RESTORE = restore };
+
+        FRAME.lock(|frame| {
+            let Frame { encoder, bytes, len, too_long } = &mut *frame.borrow_mut();
+            *len = 0;
+            *too_long = false;
+            encoder.start_frame(|data| push(bytes, len, too_long, data));
+        });
+    }
+
+    unsafe fn flush() {
+        // Nothing to wait for: log_task sends the frames as soon as it can
+    }
+
+    unsafe fn release() {
+        FRAME.lock(|frame| {
+            let Frame { encoder, bytes, len, too_long } = &mut *frame.borrow_mut();
+            encoder.end_frame(|data| push(bytes, len, too_long, data));
+
+            // Whole frames only - if it doesn't fit, this line is lost
+            if !*too_long && LOG_PIPE.free_capacity() >= *len {
+                let _ = LOG_PIPE.try_write(&bytes[..*len]);
+            }
+        });
+
+        TAKEN.store(false, Ordering::Relaxed);
+        unsafe { critical_section::release(RESTORE) };
+    }
+
+    unsafe fn write(data: &[u8]) {
+        FRAME.lock(|frame| {
+            let Frame { encoder, bytes, len, too_long } = &mut *frame.borrow_mut();
+            encoder.write(data, |data| push(bytes, len, too_long, data));
+        });
+    }
+}
+
+/// Sends the log to the log port whenever a computer has it open
+#[embassy_executor::task]
+pub async fn log_task(mut class: CdcAcmClass<'static, UsbDriver>) -> ! {
+    // One byte short of a full packet, so every packet is delivered at once
+    // without an empty one after it
+    let mut packet = [0u8; MAX_PACKET_SIZE as usize - 1];
+    loop {
+        class.wait_connection().await;
+        loop {
+            let n = LOG_PIPE.read(&mut packet).await;
+            // Port closed - what was taken out of the pipe is lost, the rest
+            // waits for the next connection
+            if class.write_packet(&packet[..n]).await.is_err() {
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,339 @@
+// USB Serial Commands (usb-serial builds)
+//
+// The Pico's own USB port shows up on the computer as a serial port
+// (CDC-ACM - no driver needed on Linux, macOS or Windows 10+). Type a
+// command such as `start 120`, `stop` or `status` and press Enter, or send
+// one JSON request per line from a script - commands.rs has the full list.
+//
+// Two tasks do the work:
+// - `usb_task` runs the USB device itself (enumeration, control requests)
+// - `serial_task` reads lines, runs them with commands.rs and sends back
+//   the response: JSON requests get a JSON response, plain text gets text
+//
+// usb-log builds add a second port carrying the defmt log (see usb_log.rs).
+//
+// ota builds also take new firmware over the port (see ota.rs):
+//   update <bytes> <crc32>   answers "ready", then expects exactly <bytes>
+//                            bytes of raw firmware; <crc32> is in hex
+
+use core::fmt::{self, Write};
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_rp::bind_interrupts;
+use embassy_rp::peripherals::USB;
+use embassy_rp::usb::{Driver, InterruptHandler};
+use embassy_usb::class::cdc_acm::{CdcAcmClass, State};
+use embassy_usb::driver::EndpointError;
+use embassy_usb::{Builder, UsbDevice};
+#[cfg(feature = "ota")]
+use embassy_time::{with_timeout, Duration};
+use heapless::String;
+use static_cell::StaticCell;
+
+use crate::commands::{self, Command, CommandError, Setting};
+use crate::config::*;
+#[cfg(feature = "ota")]
+use crate::ota::{self, UploadError};
+use crate::settings;
+
+bind_interrupts!(struct Irqs {
+    USBCTRL_IRQ => InterruptHandler<USB>;
+});
+
+/// The RP2040 USB driver, as used by both tasks
+pub type UsbDriver = Driver<'static, USB>;
+
+/// Largest USB packet for a full-speed CDC-ACM endpoint
+pub const MAX_PACKET_SIZE: u16 = 64;
+
+/// Longest command line we accept - anything longer is thrown away
+const MAX_LINE_LENGTH: usize = 128;
+
+/// Give up on a firmware upload that stops for this long
+#[cfg(feature = "ota")]
+const UPLOAD_TIMEOUT_SECONDS: u64 = 10;
+
+// USB descriptors and class state have to live as long as the USB device,
+// i.e. forever - StaticCell hands out 'static buffers for them
+static DEVICE_DESCRIPTOR: StaticCell<[u8; 256]> = StaticCell::new();
+static CONFIG_DESCRIPTOR: StaticCell<[u8; 256]> = StaticCell::new();
+static BOS_DESCRIPTOR: StaticCell<[u8; 256]> = StaticCell::new();
+static CONTROL_BUF: StaticCell<[u8; 64]> = StaticCell::new();
+static CDC_STATE: StaticCell<State> = StaticCell::new();
+#[cfg(feature = "usb-log")]
+static LOG_CDC_STATE: StaticCell<State> = StaticCell::new();
+
+/// Set up the USB serial port and start its tasks
+pub fn init(spawner: &Spawner, usb: USB) {
+    let driver = Driver::new(usb, Irqs);
+
+    let mut config = embassy_usb::Config::new(USB_VENDOR_ID, USB_PRODUCT_ID);
+    config.manufacturer = Some("octo-curer");
+    config.product = Some("UV Resin Curer");
+    config.serial_number = None;
+    config.max_power = 100;
+    config.max_packet_size_0 = 64;
+
+    // Needed for Windows to bind the serial driver to a composite-class device
+    config.device_class = 0xEF;
+    config.device_sub_class = 0x02;
+    config.device_protocol = 0x01;
+    config.composite_with_iads = true;
+
+    let mut builder = Builder::new(
+        driver,
+        config,
+        DEVICE_DESCRIPTOR.init([0; 256]),
+        CONFIG_DESCRIPTOR.init([0; 256]),
+        BOS_DESCRIPTOR.init([0; 256]),
+        &mut [],                       // No Microsoft OS descriptors
+        CONTROL_BUF.init([0; 64]),
+    );
+    let class = CdcAcmClass::new(&mut builder, CDC_STATE.init(State::new()), MAX_PACKET_SIZE);
+    #[cfg(feature = "usb-log")]
+    let log_class = CdcAcmClass::new(&mut builder, LOG_CDC_STATE.init(State::new()), MAX_PACKET_SIZE);
+    let usb = builder.build();
+
+    unwrap!(spawner.spawn(usb_task(usb)));
+    unwrap!(spawner.spawn(serial_task(class)));
+    #[cfg(feature = "usb-log")]
+    unwrap!(spawner.spawn(crate::usb_log::log_task(log_class)));
+    info!("USB serial port ready");
+}
+
+/// Keeps the USB device running (never returns)
+#[embassy_executor::task]
+async fn usb_task(mut usb: UsbDevice<'static, UsbDriver>) -> ! {
+    usb.run().await
+}
+
+/// Reads command lines from the serial port and answers them
+#[embassy_executor::task]
+async fn serial_task(mut class: CdcAcmClass<'static, UsbDriver>) -> ! {
+    loop {
+        // Nothing to do until a terminal opens the port
+        class.wait_connection().await;
+        info!("USB serial connected");
+        let _ = session(&mut class).await;
+        info!("USB serial disconnected");
+    }
+}
+
+/// One terminal session - returns when the port is closed
+async fn session(class: &mut CdcAcmClass<'static, UsbDriver>) -> Result<(), EndpointError> {
+    let mut packet = [0u8; MAX_PACKET_SIZE as usize];
+    let mut line: String<MAX_LINE_LENGTH> = String::new();
+    let mut overflow = false;
+
+    loop {
+        let n = class.read_packet(&mut packet).await?;
+        for &byte in &packet[..n] {
+            match byte {
+                b'\r' | b'\n' => {
+                    #[cfg(feature = "ota")]
+                    if !overflow && is_update_command(&line) {
+                        receive_firmware(class, &line).await?;
+                        line.clear();
+                        break;                 // The rest of this packet was just the line ending
+                    }
+                    if overflow {
+                        send(class, "error: line too long\r\n").await?;
+                    } else if !line.trim().is_empty() {
+                        let reply = handle(&line);
+                        send(class, &reply).await?;
+                    }
+                    line.clear();
+                    overflow = false;
+                }
+                // Only plain printable text - anything else can't be a command
+                b' '..=b'~' => {
+                    if line.push(byte as char).is_err() {
+                        overflow = true;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Is this line an `update` command? (ota builds)
+#[cfg(feature = "ota")]
+fn is_update_command(line: &str) -> bool {
+    line.split_whitespace().next().map_or(false, |word| word.eq_ignore_ascii_case("update"))
+}
+
+/// `update <bytes> <crc32>`: receive new firmware into flash, then restart
+/// into it once it's verified and the curer is idle
+#[cfg(feature = "ota")]
+async fn receive_firmware(class: &mut CdcAcmClass<'static, UsbDriver>, line: &str) -> Result<(), EndpointError> {
+    let mut words = line.split_whitespace().skip(1);
+    let length = words.next().and_then(|word| word.parse().ok());
+    let crc = words.next().and_then(ota::parse_crc);
+    let (Some(length), Some(crc), None) = (length, crc, words.next()) else {
+        return send(class, "error: usage: update <bytes> <crc32 in hex>\r\n").await;
+    };
+
+    let mut upload = match ota::Upload::begin(length, crc) {
+        Ok(upload) => upload,
+        Err(e) => return send_upload_error(class, e).await,
+    };
+    send(class, "ready\r\n").await?;
+
+    let mut packet = [0u8; MAX_PACKET_SIZE as usize];
+    while upload.remaining() > 0 {
+        let n = match with_timeout(Duration::from_secs(UPLOAD_TIMEOUT_SECONDS), class.read_packet(&mut packet)).await {
+            Ok(result) => result?,
+            Err(_) => return send_upload_error(class, UploadError::Incomplete).await,
+        };
+        if let Err(e) = upload.write(&packet[..n]) {
+            return send_upload_error(class, e).await;
+        }
+    }
+    if let Err(e) = upload.finish() {
+        return send_upload_error(class, e).await;
+    }
+
+    info!("USB serial: firmware uploaded");
+    send(class, "ok - restarting into the new firmware when idle\r\n").await?;
+    ota::restart_when_idle().await
+}
+
+#[cfg(feature = "ota")]
+async fn send_upload_error(class: &mut CdcAcmClass<'static, UsbDriver>, error: UploadError) -> Result<(), EndpointError> {
+    warn!("USB serial: firmware upload failed: {}", error.code());
+    let mut reply: String<64> = String::new();
+    let _ = write!(reply, "error: {}\r\n", error.message());
+    send(class, &reply).await
+}
+
+/// Run one command line and build the reply - JSON in, JSON out;
+/// plain text in, plain text out
+fn handle(line: &str) -> String<512> {
+    let line = line.trim();
+    let mut reply = String::new();
+    let _ = if line.starts_with('{') {
+        let (id, command) = commands::parse_json(line);
+        let result = command.map_err(|e| e.code()).and_then(|command| run(command).map_err(|e| e.code()));
+        commands::write_json_reply(&mut reply, id, result).and_then(|_| write!(reply, "\r\n"))
+    } else {
+        match commands::parse(line) {
+            Err(e) => write!(reply, "error: {}\r\n", e.message()),
+            Ok(command) => match run(command) {
+                Err(e) => write!(reply, "error: {}\r\n", e.message()),
+                Ok(result) => write_text(&mut reply, result),
+            },
+        }
+    };
+    reply
+}
+
+/// Run a command, logging what a remote user changed
+fn run(command: Command) -> Result<commands::Reply, CommandError> {
+    let log = match command {
+        Command::Start(_) => Some("start"),
+        Command::Stop => Some("stop"),
+        Command::Set(Setting::Debounce, _) => Some("set debounce"),
+        Command::Set(Setting::Verbosity, _) => Some("set verbosity"),
+        Command::Set(Setting::CureTime, _) => Some("set cure"),
+        Command::Set(Setting::Cooldown, _) => Some("set cooldown"),
+        Command::Set(Setting::Beeps, _) => Some("set beeps"),
+        Command::Set(Setting::Silent, _) => Some("set silent"),
+        Command::Set(Setting::PowerOn, _) => Some("set poweron"),
+        Command::SetToken(_) => Some("API token changed"),
+        #[cfg(feature = "pico-w")]
+        Command::SetWifi(_) => Some("Wi-Fi network changed"),
+        Command::SetTime(_) => Some("set time"),
+        _ => None,
+    };
+    let result = commands::execute(command);
+    if let (Some(what), Ok(_)) = (log, &result) {
+        info!("USB serial: {}", what);
+    }
+    result
+}
+
+/// Plain-text response, e.g. "curing, 95 s left (done at 14:32:10)"
+fn write_text(reply: &mut String<512>, result: commands::Reply) -> fmt::Result {
+    match result {
+        commands::Reply::Done => write!(reply, "ok\r\n"),
+        commands::Reply::Status(status) => match (status.remaining_seconds(), status.ends_at()) {
+            (Some(seconds), Some(time)) => write!(
+                reply,
+                "{}, {} s left (done at {:02}:{:02}:{:02})\r\n",
+                status.name(),
+                seconds,
+                time.hour,
+                time.minute,
+                time.second
+            ),
+            (Some(seconds), None) => write!(reply, "{}, {} s left\r\n", status.name(), seconds),
+            _ => write!(reply, "{}\r\n", status.name()),
+        },
+        commands::Reply::Config => write!(
+            reply,
+            "cure time: {} s\r\nmax cure time: {} s\r\ncooldown: {} ms\r\nbeeps: {}\r\nsilent: {}\r\npower-on: {}\r\nAPI token: {}\r\ndebounce: {} ms\r\nverbosity: {}\r\n",
+            settings::cure_seconds(),
+            MAX_CURING_DURATION_SECONDS,
+            settings::cooldown_ms(),
+            settings::completion_beeps(),
+            if crate::buzzer::is_muted() { "on" } else { "off" },
+            settings::power_on_name(settings::power_on_behavior()),
+            if settings::api_token().is_empty() { "none" } else { "set" },
+            settings::button_debounce_ms(),
+            settings::log_level() as u8
+        ),
+        commands::Reply::Stats(stats) => write!(
+            reply,
+            "completed cures: {}\r\naborted cures: {}\r\nUV time: {} h {} min\r\nlamp hours: {}{}\r\n",
+            stats.completed,
+            stats.aborted,
+            stats.uv_on_hours(),
+            stats.uv_on_seconds % 3600 / 60,
+            stats.lamp_hours(),
+            if stats.lamp_service_due() { " - service the lamp" } else { "" }
+        ),
+        commands::Reply::Mem { used, size } => {
+            write!(reply, "stack: {} of {} bytes used at most ({}%)\r\n", used, size, used * 100 / size)
+        }
+        commands::Reply::Time(Some(time)) => write!(reply, "{}\r\n", time),
+        commands::Reply::Time(None) => write!(reply, "clock not set - use 'time YYYY-MM-DD HH:MM:SS'\r\n"),
+        #[cfg(feature = "gpio-diagnostics")]
+        commands::Reply::Gpio { pin, high } => write!(reply, "GPIO {}: {}\r\n", pin, if high { "high" } else { "low" }),
+        #[cfg(feature = "pico-w")]
+        commands::Reply::Wifi => {
+            let saved = !settings::wifi_credentials().is_empty();
+            write!(reply, "network: {} ({})\r\n", crate::wifi::network_name(), if saved { "saved" } else { "built in" })?;
+            match crate::wifi::address() {
+                Some([a, b, c, d]) => write!(reply, "connected: yes, {}.{}.{}.{}\r\n", a, b, c, d),
+                None => write!(reply, "connected: no\r\n"),
+            }
+        }
+        commands::Reply::Help => {
+            write!(
+                reply,
+                "commands:\r\n  start [seconds]\r\n  stop\r\n  status\r\n  config\r\n  stats\r\n  mem\r\n  time [YYYY-MM-DD HH:MM:SS]\r\n  set debounce <ms>\r\n  set verbosity <0-3>\r\n  set cure <seconds>\r\n  set cooldown <ms>\r\n  set beeps <1-10>\r\n  set silent <0|1>\r\n  set poweron <idle|resume|autostart>\r\n  token <token|off>\r\n  help\r\n"
+            )?;
+            #[cfg(feature = "pico-w")]
+            write!(reply, "  wifi [set <name> <password> | clear]\r\n")?;
+            #[cfg(feature = "ota")]
+            write!(reply, "  update <bytes> <crc32>\r\n")?;
+            #[cfg(feature = "gpio-diagnostics")]
+            write!(reply, "  gpio read|release <pin>\r\n  gpio set <pin> high|low\r\n  gpio pulse <pin> <ms>\r\n")?;
+            Ok(())
+        }
+    }
+}
+
+/// Write text to the port in packet-sized pieces
+async fn send(class: &mut CdcAcmClass<'static, UsbDriver>, text: &str) -> Result<(), EndpointError> {
+    for chunk in text.as_bytes().chunks(MAX_PACKET_SIZE as usize) {
+        class.write_packet(chunk).await?;
+    }
+    // A full-size final packet needs an empty one after it to be delivered
+    if text.len() % MAX_PACKET_SIZE as usize == 0 {
+        class.write_packet(&[]).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,422 @@
+// UV LED Output Drivers
+//
+// Everything that switches the UV LEDs goes through the `UvSwitch` trait, so
+// main.rs doesn't care what hardware is doing the switching. Pick the driver
+// that matches your hardware with a Cargo feature (all use GPIO 10):
+//
+// - SRD-05VDC relay (default): the common blue active-low relay module,
+//   using the "pin kill" trick - see `FlexPinRelay` below. Set
+//   RELAY_ACTIVE_LOW = false in config.rs for a module that closes on HIGH.
+// - Active-high relay (`--features relay-active-high`): relay boards that
+//   close when the input is driven HIGH.
+// - Solid state relay (`--features relay-ssr`): SSR input driven HIGH = on.
+//   Silent and switches instantly, so there's no settling delay.
+//   (These two are the generic `PinRelay` from curing-core/src/drivers.rs,
+//   which works on any embedded-hal output pin.)
+// - PWM MOSFET (`--features pwm-mosfet`): logic-level MOSFET gate driven by
+//   an RP2040 PWM slice. Silent, and the UV intensity can be set with
+//   UV_INTENSITY_PERCENT in config.rs.
+//
+// Adding support for other hardware means writing one more struct with
+// `on()`, `off()` and `settle_time_ms()` and an `impl UvSwitch` (and a
+// `UvDriver` line) - main.rs stays the same.
+//
+// Whichever driver is built in belongs to the relay task: the rest of the
+// firmware asks for the UV with `set()`, and `wait_until_off()` waits
+// until it's off and the relay contacts have had time to open.
+//
+// Relay feedback (`--features relay-feedback`): the SRD relay is
+// double-throw, so one contact is spare. Wire it to GPIO 3 so the relay
+// task can see whether the relay *really* switched:
+//
+//   relay COM ── GND
+//   relay NO (or NC) ── GPIO 3   (internal pull-up: contact closed = LOW)
+//
+// Only do this when the spare contact isn't carrying the LED supply - on
+// a single-pole relay, take it from a second pole (a DPDT relay) instead.
+// If the relay doesn't follow a command within RELAY_FEEDBACK_TIMEOUT_MS
+// it's stuck: stuck open ends the cure with fault code 4 (see fault.rs),
+// stuck closed keeps retrying the pin kill (and nothing new starts) until
+// it lets go, sounding code 4 (four long beeps). The feedback is passed
+// around as an `Option` like the lid switch: `None` in builds without it.
+// Every switch is also timed, from driving the coil to the contact moving,
+// and logged (verbosity 3); if the times creep up by RELAY_SLOWDOWN_PERCENT
+// it warns that the relay is wearing out (curing-core/src/relay_timing.rs).
+//
+// If the firmware panics, panic.rs cuts GPIO 10 straight through the pad
+// registers, whichever driver is built in.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use defmt::*;
+#[cfg(feature = "relay-feedback")]
+use core::cell::RefCell;
+
+#[cfg(feature = "relay-feedback")]
+use embassy_time::{with_timeout, Duration, Instant};
+#[cfg(any(feature = "relay-active-high", feature = "relay-ssr"))]
+use curing_core::drivers::{PinRelay, PinState};
+#[cfg(feature = "relay-feedback")]
+use curing_core::fault::FaultCode;
+#[cfg(feature = "relay-feedback")]
+use curing_core::relay_timing::SwitchTimes;
+use embassy_rp::gpio::{AnyPin, Input};
+#[cfg(not(any(feature = "pwm-mosfet", feature = "relay-active-high", feature = "relay-ssr")))]
+use embassy_rp::gpio::{Flex, Level};
+#[cfg(any(feature = "relay-active-high", feature = "relay-ssr"))]
+use embassy_rp::gpio::Output;
+#[cfg(feature = "pwm-mosfet")]
+use embassy_rp::{peripherals::{PIN_10, PWM_CH5}, pwm::{self, Pwm}};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+#[cfg(feature = "relay-feedback")]
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::signal::Signal;
+use embassy_time::Timer;
+
+use crate::config::*;
+#[cfg(feature = "relay-feedback")]
+use crate::buzzer::{self, AudioEvent};
+#[cfg(feature = "relay-feedback")]
+use crate::events::{self, Event};
+
+pub use curing_core::drivers::UvSwitch;
+
+#[cfg(any(
+    all(feature = "pwm-mosfet", feature = "relay-active-high"),
+    all(feature = "pwm-mosfet", feature = "relay-ssr"),
+    all(feature = "relay-active-high", feature = "relay-ssr"),
+))]
+compile_error!("Pick only one UV output driver feature: pwm-mosfet, relay-active-high or relay-ssr");
+
+/// The driver picked by the Cargo features
+#[cfg(not(any(feature = "pwm-mosfet", feature = "relay-active-high", feature = "relay-ssr")))]
+pub type UvDriver = FlexPinRelay<'static>;
+#[cfg(feature = "relay-active-high")]
+pub type UvDriver = ActiveHighRelay;
+#[cfg(feature = "relay-ssr")]
+pub type UvDriver = SolidStateRelay;
+#[cfg(feature = "pwm-mosfet")]
+pub type UvDriver = PwmMosfet<'static>;
+
+/// Relay feedback contact input, or None when there is none
+pub type RelayFeedback = Option<Input<'static, AnyPin>>;
+
+/// The UV state asked for (true = on). A signal rather than a queue: only
+/// the latest request matters, and an OFF can never be lost to a full queue.
+static REQUEST: Signal<CriticalSectionRawMutex, bool> = Signal::new();
+
+/// The UV is off and the relay has settled
+static DARK: AtomicBool = AtomicBool::new(true);
+
+/// Signalled each time the UV has gone dark
+static WENT_DARK: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Switch the UV LEDs on or off - never waits
+pub fn set(on: bool) {
+    DARK.store(false, Ordering::Relaxed);    // Until the relay task says it is
+    #[cfg(feature = "relay-feedback")]
+    if on {
+        STUCK_OPEN.reset();                  // Forget a report from an earlier cure
+    }
+    REQUEST.signal(on);
+}
+
+/// Is the UV off, with the relay contacts open?
+#[cfg_attr(not(feature = "dormant"), allow(dead_code))]
+pub fn is_dark() -> bool {
+    DARK.load(Ordering::Relaxed)
+}
+
+/// Wait until the UV is off and the relay contacts have opened
+pub async fn wait_until_off() {
+    while !DARK.load(Ordering::Relaxed) {
+        WENT_DARK.wait().await;
+    }
+}
+
+/// Background task: switch the UV output as asked
+#[embassy_executor::task]
+pub async fn relay_task(mut uv: UvDriver, mut feedback: RelayFeedback) {
+    // Welded shut before we've even started?
+    if switched(&mut feedback, false).await.is_none() {
+        relay_stuck(true);
+    }
+    loop {
+        if REQUEST.wait().await {
+            uv.on();
+            match switched(&mut feedback, true).await {
+                Some(us) => switch_time(true, us),
+                None => relay_stuck(false),
+            }
+        } else {
+            uv.off();
+            let mut warned = false;
+            loop {
+                if let Some(us) = switched(&mut feedback, false).await {
+                    if !warned {
+                        switch_time(false, us);    // (Not timed from a retry)
+                    }
+                    break;
+                }
+                if REQUEST.signaled() {
+                    break;
+                }
+                if !warned {
+                    relay_stuck(true);
+                    warned = true;
+                }
+                uv.off();                      // Kill the pin again and keep checking
+            }
+            Timer::after_millis(uv.settle_time_ms()).await;  // Allow relay time to settle
+            // Still off? (an ON may have come in meanwhile)
+            if !REQUEST.signaled() {
+                DARK.store(true, Ordering::Relaxed);
+                WENT_DARK.signal(());
+            }
+        }
+    }
+}
+
+/* ===========================================
+   RELAY FEEDBACK (relay-feedback)
+   =========================================== */
+
+/// The relay didn't close when the UV was switched on (relay-feedback builds)
+#[cfg(feature = "relay-feedback")]
+static STUCK_OPEN: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// How long the relay has been taking to close and to open
+#[cfg(feature = "relay-feedback")]
+static SWITCH_TIMES: Mutex<CriticalSectionRawMutex, RefCell<[SwitchTimes; 2]>> =
+    Mutex::new(RefCell::new([SwitchTimes::new(RELAY_SLOWDOWN_PERCENT); 2]));
+
+/// Did the relay follow the command (`closed` = it should now be closed)?
+/// Waits up to RELAY_FEEDBACK_TIMEOUT_MS for the feedback contact to agree,
+/// and says how many microseconds that took (call straight after switching)
+/// - None if it never did.
+#[cfg(feature = "relay-feedback")]
+async fn switched(feedback: &mut RelayFeedback, closed: bool) -> Option<u32> {
+    let commanded = Instant::now();
+    let Some(contact) = feedback else {
+        return Some(0);
+    };
+    // COM is on GND, so the contact reads LOW while it's made
+    let contact_made = match RELAY_FEEDBACK_CONTACT {
+        FeedbackContact::NormallyOpen => closed,
+        FeedbackContact::NormallyClosed => !closed,
+    };
+    let settled = async {
+        loop {
+            if contact.is_low() == contact_made {
+                // Let the contact bounce die down before believing it
+                let moved = Instant::now();
+                Timer::after_millis(RELAY_FEEDBACK_DEBOUNCE_MS).await;
+                if contact.is_low() == contact_made {
+                    return moved;
+                }
+            }
+            if contact_made {
+                contact.wait_for_low().await;
+            } else {
+                contact.wait_for_high().await;
+            }
+        }
+    };
+    let moved = with_timeout(Duration::from_millis(RELAY_FEEDBACK_TIMEOUT_MS), settled).await.ok()?;
+    Some(moved.saturating_duration_since(commanded).as_micros() as u32)
+}
+
+/// Without feedback, take the relay's word for it
+#[cfg(not(feature = "relay-feedback"))]
+async fn switched(_feedback: &mut RelayFeedback, _closed: bool) -> Option<u32> {
+    Some(0)
+}
+
+/// Log how long a switch took (`closed` = it closed), and warn if the
+/// relay is slowing down
+#[cfg(feature = "relay-feedback")]
+fn switch_time(closed: bool, us: u32) {
+    events::record(Event::RelaySwitched { closed, us });
+    let slowing = SWITCH_TIMES.lock(|times| {
+        let times = &mut times.borrow_mut()[closed as usize];
+        times.record(us).then(|| (times.average_us(), times.baseline_us().unwrap_or(0)))
+    });
+    if let Some((average_us, baseline_us)) = slowing {
+        events::record(Event::RelaySlowing { closed, average_us, baseline_us });
+    }
+}
+
+#[cfg(not(feature = "relay-feedback"))]
+fn switch_time(_closed: bool, _us: u32) {}
+
+/// Report a stuck relay. Stuck open ends the cure with fault code 4 (the
+/// main loop's fault handler takes it from there); stuck closed can't be
+/// fixed from here, so sound code 4 straight away
+#[cfg(feature = "relay-feedback")]
+fn relay_stuck(closed: bool) {
+    events::record(Event::RelayStuck { closed });
+    if closed {
+        buzzer::play_count(AudioEvent::Fault, FaultCode::RelayStuck.code());
+    } else {
+        STUCK_OPEN.signal(());
+    }
+}
+
+#[cfg(not(feature = "relay-feedback"))]
+fn relay_stuck(_closed: bool) {}
+
+/// Wait until the relay fails to close for the UV (relay-feedback builds)
+#[cfg(feature = "relay-feedback")]
+pub async fn wait_for_stuck_open() {
+    STUCK_OPEN.wait().await
+}
+
+/* ===========================================
+   SRD-05VDC-SL-C RELAY (default)
+   =========================================== */
+
+/// Pin level that closes the relay (RELAY_ACTIVE_LOW in config.rs)
+#[cfg(not(any(feature = "pwm-mosfet", feature = "relay-active-high", feature = "relay-ssr")))]
+const RELAY_CLOSED: Level = if RELAY_ACTIVE_LOW { Level::Low } else { Level::High };
+
+/// Pin level that leaves the relay open
+#[cfg(not(any(feature = "pwm-mosfet", feature = "relay-active-high", feature = "relay-ssr")))]
+const RELAY_OPEN: Level = if RELAY_ACTIVE_LOW { Level::High } else { Level::Low };
+
+/// Relay driver for the SRD-05VDC-SL-C module (active LOW by default)
+///
+/// The key discovery of this project: driving the relay pin to its "off"
+/// level doesn't reliably open the relay. Setting the pin to INPUT mode
+/// (high-impedance) completely "kills" it - no voltage, relay opens every
+/// time. A `Flex` pin lets us switch between output (relay closed) and
+/// input (relay open). The "on" level follows RELAY_ACTIVE_LOW.
+#[cfg(not(any(feature = "pwm-mosfet", feature = "relay-active-high", feature = "relay-ssr")))]
+pub struct FlexPinRelay<'d> {
+    relay: Flex<'d, AnyPin>,
+}
+
+#[cfg(not(any(feature = "pwm-mosfet", feature = "relay-active-high", feature = "relay-ssr")))]
+impl<'d> FlexPinRelay<'d> {
+    /// Take the relay pin and make sure the relay is open
+    ///
+    /// When the Pico powers on, GPIO pins can be in undefined states, so this
+    /// ensures the relay is definitely OFF at startup.
+    pub async fn new(relay_pin: AnyPin) -> FlexPinRelay<'d> {
+        let mut relay = Flex::new(relay_pin);
+
+        info!("Performing startup relay reset to ensure LEDs are OFF...");
+        relay.set_as_input();          // First set to high-impedance (guaranteed OFF)
+        Timer::after_millis(RELAY_SETTLE_TIME_MS).await;  // Wait for relay to settle
+        relay.set_level(RELAY_OPEN);   // Open level first, so the output can't glitch ON
+        relay.set_as_output();         // Then set as output for control
+        info!("Relay reset complete ({}) - LEDs confirmed OFF", if RELAY_ACTIVE_LOW { "active LOW" } else { "active HIGH" });
+
+        Self { relay }
+    }
+}
+
+#[cfg(not(any(feature = "pwm-mosfet", feature = "relay-active-high", feature = "relay-ssr")))]
+impl UvSwitch for FlexPinRelay<'_> {
+    fn on(&mut self) {
+        // SRD-05VDC-SL-C relay: LOW = closed = UV LEDs ON (HIGH when RELAY_ACTIVE_LOW is false)
+        self.relay.set_level(RELAY_CLOSED);  // Level first, then drive it
+        self.relay.set_as_output();    // Ensure pin is in output mode - closes the relay
+    }
+
+    fn off(&mut self) {
+        self.relay.set_as_input();     // High-impedance = no voltage = relay opens
+    }
+
+    fn settle_time_ms(&self) -> u64 {
+        RELAY_SETTLE_TIME_MS
+    }
+}
+
+/* ===========================================
+   ACTIVE-HIGH RELAY (relay-active-high)
+   =========================================== */
+
+/// Relay board that closes when its input is driven HIGH
+///
+/// These boards have a transistor or optocoupler on the input, so a plain
+/// push-pull output switches them reliably - no pin kill needed.
+#[cfg(feature = "relay-active-high")]
+pub type ActiveHighRelay = PinRelay<Output<'static, AnyPin>>;
+
+/// Take the relay pin and open the relay (LOW = UV LEDs off)
+#[cfg(feature = "relay-active-high")]
+pub async fn active_high_relay(pin: Output<'static, AnyPin>) -> ActiveHighRelay {
+    let relay = PinRelay::new(pin, PinState::High, RELAY_SETTLE_TIME_MS);
+    Timer::after_millis(RELAY_SETTLE_TIME_MS).await;
+    info!("Active-high relay ready - LEDs confirmed OFF");
+    relay
+}
+
+/* ===========================================
+   SOLID STATE RELAY (relay-ssr)
+   =========================================== */
+
+/// Solid state relay (e.g. a DC-DC SSR module) - input HIGH = UV on
+#[cfg(feature = "relay-ssr")]
+pub type SolidStateRelay = PinRelay<Output<'static, AnyPin>>;
+
+/// Take the SSR input pin and switch it off (LOW = UV LEDs off)
+#[cfg(feature = "relay-ssr")]
+pub async fn solid_state_relay(pin: Output<'static, AnyPin>) -> SolidStateRelay {
+    let ssr = PinRelay::new(pin, PinState::High, 0);    // No contacts - off is instant
+    info!("Solid state relay ready - LEDs confirmed OFF");
+    ssr
+}
+
+/* ===========================================
+   PWM MOSFET (pwm-mosfet)
+   =========================================== */
+
+/// PWM counter wrap value: 125 MHz / (9999 + 1) = 12.5 kHz, well above
+/// anything visible, and 1% of duty cycle = 100 counts
+#[cfg(feature = "pwm-mosfet")]
+const PWM_TOP: u16 = 9_999;
+
+/// PWM driver for a logic-level MOSFET on GPIO 10 (PWM slice 5, channel A)
+///
+/// Gate HIGH = UV on. Fit a pull-down resistor on the gate so the MOSFET
+/// stays off while the Pico is booting.
+#[cfg(feature = "pwm-mosfet")]
+pub struct PwmMosfet<'d> {
+    pwm: Pwm<'d, PWM_CH5>,
+    config: pwm::Config,
+}
+
+#[cfg(feature = "pwm-mosfet")]
+impl<'d> PwmMosfet<'d> {
+    /// Take the PWM slice and pin, starting with the UV off
+    pub async fn new(slice: PWM_CH5, pin: PIN_10) -> PwmMosfet<'d> {
+        let mut config = pwm::Config::default();
+        config.top = PWM_TOP;
+        config.compare_a = 0;          // 0% duty = UV off
+        let pwm = Pwm::new_output_a(slice, pin, config.clone());
+        info!("PWM MOSFET driver ready at {}% intensity - LEDs confirmed OFF", UV_INTENSITY_PERCENT);
+        Self { pwm, config }
+    }
+}
+
+#[cfg(feature = "pwm-mosfet")]
+impl UvSwitch for PwmMosfet<'_> {
+    /// Drive the MOSFET at UV_INTENSITY_PERCENT duty cycle
+    fn on(&mut self) {
+        // compare = top + 1 keeps the output high for the whole period (100%)
+        let period = PWM_TOP as u32 + 1;
+        self.config.compare_a = (period * UV_INTENSITY_PERCENT / 100) as u16;
+        self.pwm.set_config(&self.config);
+    }
+
+    /// 0% duty cycle
+    fn off(&mut self) {
+        self.config.compare_a = 0;
+        self.pwm.set_config(&self.config);
+    }
+
+    fn settle_time_ms(&self) -> u64 {
+        0                              // MOSFET switches in microseconds
+    }
+}
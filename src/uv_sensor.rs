@@ -0,0 +1,73 @@
+// UV Intensity Sensor (uv-sensor builds)
+//
+// A GUVA-S12SD UV sensor module in the chamber, facing the UV LEDs:
+//
+//   GPIO 27 (ADC1) ── SIG
+//   3.3V ── VCC, GND ── GND
+//
+// Its output voltage rises with UV intensity (roughly 0.1 V per UV index
+// point in sunlight - far more under a curing lamp). A background task
+// samples it all the time; while a UV phase runs, the main loop watches
+// the readings with `wait_for_lamp_failure`. If the reading stays near
+// zero for UV_SENSOR_FAULT_MS after the UV was switched on, the LEDs
+// aren't lighting - a dead lamp, a broken wire or a relay that didn't
+// close - and the cure is aborted rather than pretending to cure.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use defmt::*;
+use embassy_rp::adc::Channel;
+use embassy_rp::gpio::Pull;
+use embassy_rp::peripherals::PIN_27;
+use embassy_time::{Duration, Instant, Timer};
+
+use crate::analog;
+use crate::config::*;
+
+/// ADC reference voltage in millivolts
+const ADC_REFERENCE_MV: u32 = 3300;
+
+/// Full-scale reading of the 12-bit ADC
+const ADC_MAX: u32 = 4095;
+
+/// Latest sensor output in millivolts
+static LATEST_MV: AtomicU32 = AtomicU32::new(0);
+
+/// Latest sensor output in millivolts
+pub fn millivolts() -> u32 {
+    LATEST_MV.load(Ordering::Relaxed)
+}
+
+/// Background task: sample the sensor
+#[embassy_executor::task]
+pub async fn uv_sensor_task(pin: PIN_27) {
+    let mut channel = Channel::new_pin(pin, Pull::None);
+    loop {
+        if let Ok(raw) = analog::read(&mut channel).await {
+            LATEST_MV.store(raw as u32 * ADC_REFERENCE_MV / ADC_MAX, Ordering::Relaxed);
+        }
+        Timer::after_millis(UV_SENSOR_SAMPLE_MS).await;
+    }
+}
+
+/// Watch the sensor while the UV is on: returns the last reading (mV) if
+/// it stays below UV_SENSOR_MIN_MV for UV_SENSOR_FAULT_MS - the lamp isn't
+/// lighting. Call it when the UV comes on; it never returns while the lamp
+/// works. Logs the intensity every UV_SENSOR_LOG_INTERVAL_SECONDS.
+pub async fn wait_for_lamp_failure() -> u32 {
+    let mut lit_at = Instant::now();   // Counts as lit until proven otherwise
+    let mut last_log = Instant::now();
+    loop {
+        Timer::after_millis(UV_SENSOR_SAMPLE_MS).await;
+        let mv = millivolts();
+        if mv >= UV_SENSOR_MIN_MV {
+            lit_at = Instant::now();
+        } else if lit_at.elapsed() >= Duration::from_millis(UV_SENSOR_FAULT_MS) {
+            return mv;
+        }
+        if last_log.elapsed() >= Duration::from_secs(UV_SENSOR_LOG_INTERVAL_SECONDS) {
+            info!("UV intensity: {} mV", mv);
+            last_log = Instant::now();
+        }
+    }
+}
@@ -0,0 +1,534 @@
+// Web Interface (pico-w builds)
+//
+// A tiny HTTP server on port WEB_PORT with two faces:
+//
+// The page, for people: what the curer is doing, the time left, the chamber
+// temperature and humidity (with a sensor), and buttons to start or abort a
+// cure (one start button per preset in multi-duration builds). It refreshes
+// itself every few seconds.
+//   GET    /                 the page
+//   POST   /start            start a cure of the normal length
+//   POST   /start?preset=2   start a cure with the third preset
+//   POST   /stop             abort the running cure
+//
+// A JSON REST API, for scripts and integrations (e.g. OctoPrint plugins):
+//   GET    /status           {"ok":true,"state":"curing","remaining_s":95}
+//   POST   /cure             start a cure - optional body {"seconds":120}
+//   DELETE /cure             abort the running cure
+//   GET    /config           {"ok":true,"config":{...}}
+//   PUT    /config           change a setting - body e.g. {"debounce_ms":80} or {"cure_s":120}
+//                            (cure_s, cooldown_ms, beeps, silent and power_on are saved to flash)
+//   GET    /stats            {"ok":true,"stats":{"completed":42,"aborted":3,"uv_on_s":12600,...}}
+// API responses are the same JSON as the USB serial protocol, with an HTTP
+// status code to match (200/202 OK, 400 bad request, 401 no token, 409 refused).
+//
+// Once an API token is set (the serial `token` command), anything that
+// changes something - a start, a stop, a setting, a firmware upload - needs
+// it, sent as "Authorization: Bearer <token>". The page's buttons get a 401
+// that makes the browser ask for a user name and password: the password is
+// the token, the user name can be anything. Reading the status never needs it.
+//
+// Firmware updates, in ota builds (see ota.rs):
+//   GET    /firmware         a page to pick a .bin file and upload it
+//   POST   /firmware         the raw firmware as the body, with its CRC-32
+//                            in an X-Firmware-CRC32 header (hex)
+// The upload is streamed straight into flash, so it can be far bigger than
+// any buffer here. The curer restarts into it once it's verified and idle.
+//
+// Everything goes through commands.rs like the USB serial commands, so all
+// the same checks apply (busy, safety maximum, latching switch...).
+// One connection is handled at a time, which is plenty here.
+
+use core::fmt::Write;
+
+use defmt::*;
+use embassy_net::tcp::TcpSocket;
+use embassy_net::Stack;
+use embassy_time::Duration;
+use heapless::String;
+
+use serde::Deserialize;
+
+use crate::commands::{self, Command, CommandError, ParseError, Reply, Setting};
+use crate::config::*;
+#[cfg(feature = "ota")]
+use crate::ota::{self, UploadError};
+use crate::remote;
+use crate::settings;
+use crate::wifi::NetDriver;
+
+/// Seconds between automatic page refreshes
+const REFRESH_SECONDS: u32 = 2;
+
+/// Give up on a client that stops sending or receiving for this long
+const SOCKET_TIMEOUT_SECONDS: u64 = 10;
+
+/// Serves the web page (never returns)
+#[embassy_executor::task]
+pub async fn web_server_task(stack: &'static Stack<NetDriver>) -> ! {
+    let mut rx_buffer = [0u8; 1024];
+    let mut tx_buffer = [0u8; 2048];
+    let mut request = [0u8; 1024];
+
+    loop {
+        let mut socket = TcpSocket::new(stack, &mut rx_buffer, &mut tx_buffer);
+        socket.set_timeout(Some(Duration::from_secs(SOCKET_TIMEOUT_SECONDS)));
+
+        if let Err(e) = socket.accept(WEB_PORT).await {
+            warn!("Web: accept failed: {}", e);
+            continue;
+        }
+
+        // Read the headers, then as much body as Content-Length promises
+        let mut len = 0;
+        let mut wanted = request.len();
+        let mut body_start = None;
+        while len < wanted {
+            match socket.read(&mut request[len..wanted]).await {
+                Ok(0) | Err(_) => break,
+                Ok(n) => len += n,
+            }
+            body_start = find(&request[..len], b"\r\n\r\n").map(|i| i + 4);
+            if let Some(body_start) = body_start {
+                if is_firmware_upload(&request[..len]) {
+                    break;                     // Far too big to buffer - streamed below
+                }
+                let body_len = content_length(&request[..body_start]);
+                wanted = (body_start + body_len).min(request.len());
+            }
+        }
+
+        #[cfg(feature = "ota")]
+        if let (true, Some(body_start)) = (is_firmware_upload(&request[..len]), body_start) {
+            upload_firmware(&mut socket, &request[..body_start], &request[body_start..len]).await;
+            continue;
+        }
+
+        let response = respond(&request[..len]);
+        if write_all(&mut socket, response.as_bytes()).await.is_err() {
+            warn!("Web: client went away");
+        }
+        socket.close();
+        let _ = socket.flush().await;
+    }
+}
+
+/// Build the response to one request
+fn respond(request: &[u8]) -> String<2048> {
+    let line = request.split(|&b| b == b'\r' || b == b'\n').next().unwrap_or(&[]);
+    let line = core::str::from_utf8(line).unwrap_or("");
+    let mut parts = line.split(' ');
+    let method = parts.next().unwrap_or("");
+    let target = parts.next().unwrap_or("");
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let headers_end = find(request, b"\r\n\r\n");
+    let body = headers_end.map_or(&[][..], |i| &request[i + 4..]);
+    let mut decoded = [0u8; 64];
+    let token = request_token(&request[..headers_end.unwrap_or(request.len())], &mut decoded);
+
+    if matches!(path, "/status" | "/cure" | "/config" | "/stats") {
+        return api(method, path, body, token);
+    }
+
+    let mut response = String::new();
+    let _ = match (method, path) {
+        ("GET", "/") => write_page(&mut response),
+        #[cfg(feature = "ota")]
+        ("GET", "/firmware") => write_firmware_page(&mut response),
+        ("POST", "/start") | ("POST", "/stop") => {
+            let command = if path == "/stop" {
+                Command::Stop
+            } else {
+                match start_seconds(query) {
+                    Some(seconds) => Command::Start(seconds),
+                    None => return error_page(400, "Bad Request", "unknown preset"),
+                }
+            };
+            match commands::authorize(&command, token).and_then(|_| commands::execute(command)) {
+                Ok(_) => {
+                    info!("Web: {}", path);
+                    // Back to the page, which now shows the new state
+                    write!(response, "HTTP/1.1 303 See Other\r\nLocation: /\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                }
+                Err(CommandError::Unauthorized) => return log_in_page(),
+                Err(e) => return error_page(409, "Conflict", e.message()),
+            }
+        }
+        _ => return error_page(404, "Not Found", "no such page"),
+    };
+    response
+}
+
+/// The JSON API: run the request and answer with a status code and JSON
+fn api(method: &str, path: &str, body: &[u8], token: Option<&[u8]>) -> String<2048> {
+    let command = match (method, path) {
+        ("GET", "/status") => Ok(Command::Status),
+        ("POST", "/cure") => parse_body::<CureBody>(body).map(|cure| Command::Start(cure.seconds)).map_err(|e| (400, e.code())),
+        ("DELETE", "/cure") => Ok(Command::Stop),
+        ("GET", "/config") => Ok(Command::Config),
+        ("PUT", "/config") => match parse_body::<ConfigBody>(body).and_then(|config| config.setting()) {
+            Ok((setting, value)) => Ok(Command::Set(setting, value)),
+            Err(e) => Err((400, e.code())),
+        },
+        ("GET", "/stats") => Ok(Command::Stats),
+        _ => Err((405, "method_not_allowed")),
+    };
+    let result = command.and_then(|command| {
+        // A setting that can't be changed is a bad request; a refused start
+        // or stop is a conflict with what the curer is doing
+        let setting = matches!(command, Command::Set(..));
+        commands::authorize(&command, token)
+            .and_then(|_| commands::execute(command))
+            .map(|reply| if setting { Reply::Config } else { reply })   // Answer with the new settings
+            .map_err(|e| match e {
+                CommandError::Unauthorized => (401, e.code()),
+                _ if setting => (400, e.code()),
+                _ => (409, e.code()),
+            })
+    });
+
+    let code = match &result {
+        Ok(_) if matches!(method, "POST" | "DELETE") => 202,   // Accepted - the main loop acts on it
+        Ok(_) => 200,
+        Err((code, _)) => *code,
+    };
+    if result.is_ok() && code == 202 {
+        info!("Web API: {} {}", method, path);
+    }
+
+    let mut json: String<256> = String::new();
+    let _ = commands::write_json_reply(&mut json, None, result.map_err(|(_, error)| error));
+    let reason = match code {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        405 => "Method Not Allowed",
+        _ => "Conflict",
+    };
+    let mut response = String::new();
+    let _ = write!(
+        response,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+        code,
+        reason,
+        json.len(),
+        if code == 401 { "WWW-Authenticate: Bearer\r\n" } else { "" },
+        json
+    );
+    response
+}
+
+/// Body of POST /cure (may be empty)
+#[derive(Deserialize)]
+struct CureBody {
+    seconds: Option<u64>,
+}
+
+/// Body of PUT /config (one setting at a time)
+#[derive(Deserialize)]
+struct ConfigBody<'a> {
+    debounce_ms: Option<u64>,
+    verbosity: Option<u64>,
+    cure_s: Option<u64>,
+    cooldown_ms: Option<u64>,
+    beeps: Option<u64>,
+    silent: Option<bool>,
+    power_on: Option<&'a str>,
+}
+
+impl ConfigBody<'_> {
+    /// The one setting the body changes, and its new value
+    fn setting(&self) -> Result<(Setting, u64), ParseError> {
+        let power_on = match self.power_on {
+            Some(name) => Some(settings::power_on_named(name).ok_or(ParseError::UnknownChoice)? as u64),
+            None => None,
+        };
+        let mut given = [
+            self.debounce_ms.map(|ms| (Setting::Debounce, ms)),
+            self.verbosity.map(|level| (Setting::Verbosity, level)),
+            self.cure_s.map(|seconds| (Setting::CureTime, seconds)),
+            self.cooldown_ms.map(|ms| (Setting::Cooldown, ms)),
+            self.beeps.map(|beeps| (Setting::Beeps, beeps)),
+            self.silent.map(|silent| (Setting::Silent, silent as u64)),
+            power_on.map(|value| (Setting::PowerOn, value)),
+        ]
+        .into_iter()
+        .flatten();
+        match (given.next(), given.next()) {
+            (Some(setting), None) => Ok(setting),
+            (None, _) => Err(ParseError::MissingValue),
+            (Some(_), Some(_)) => Err(ParseError::TooManyArguments),
+        }
+    }
+}
+
+/// Parse a JSON request body - an empty body counts as `{}`
+fn parse_body<'a, T: Deserialize<'a>>(body: &'a [u8]) -> Result<T, ParseError> {
+    let body = if body.iter().all(u8::is_ascii_whitespace) { &b"{}"[..] } else { body };
+    serde_json_core::from_slice(body).map(|(value, _)| value).map_err(|_| ParseError::BadJson)
+}
+
+/// The API token a request came with: "Authorization: Bearer <token>", or
+/// the password of "Authorization: Basic ..." - what a browser sends once
+/// it has asked for a user name and password (the user name is ignored).
+/// `decoded` holds the Basic one.
+fn request_token<'a>(headers: &'a [u8], decoded: &'a mut [u8; 64]) -> Option<&'a [u8]> {
+    let (scheme, value) = header(headers, "authorization")?.split_once(' ')?;
+    if scheme.eq_ignore_ascii_case("bearer") {
+        Some(value.trim().as_bytes())
+    } else if scheme.eq_ignore_ascii_case("basic") {
+        let user_and_password = base64_decode(value.trim(), decoded)?;
+        let colon = user_and_password.iter().position(|&b| b == b':')?;
+        Some(&user_and_password[colon + 1..])
+    } else {
+        None
+    }
+}
+
+/// Decode base64 into `out` - None if it isn't base64, or doesn't fit
+fn base64_decode<'a>(text: &str, out: &'a mut [u8]) -> Option<&'a [u8]> {
+    let mut len = 0;
+    let mut bits: u32 = 0;
+    let mut held = 0;
+    for c in text.bytes().take_while(|&c| c != b'=') {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        bits = (bits << 6 | value as u32) & 0xFFFF;
+        held += 6;
+        if held >= 8 {
+            held -= 8;
+            *out.get_mut(len)? = (bits >> held) as u8;
+            len += 1;
+        }
+    }
+    Some(&out[..len])
+}
+
+/// POST /firmware is handled by `upload_firmware` (ota builds), not `respond`
+fn is_firmware_upload(request: &[u8]) -> bool {
+    cfg!(feature = "ota") && request.starts_with(b"POST /firmware ")
+}
+
+/// POST /firmware: stream the body into flash and answer with JSON, then
+/// restart into the new firmware once the curer is idle
+#[cfg(feature = "ota")]
+async fn upload_firmware(socket: &mut TcpSocket<'_>, headers: &[u8], received: &[u8]) {
+    let mut decoded = [0u8; 64];
+    let result = match settings::api_token_matches(request_token(headers, &mut decoded)) {
+        true => receive_firmware(socket, headers, received).await,
+        false => Err(UploadError::Unauthorized),
+    };
+    let (code, reason) = match &result {
+        Ok(()) => (200, "OK"),
+        Err(UploadError::Unauthorized) => (401, "Unauthorized"),
+        Err(UploadError::Busy) => (409, "Conflict"),
+        Err(UploadError::FlashError) => (500, "Internal Server Error"),
+        Err(_) => (400, "Bad Request"),
+    };
+    match &result {
+        Ok(()) => info!("Web: firmware uploaded"),
+        Err(e) => warn!("Web: firmware upload failed: {}", e.code()),
+    }
+
+    let mut json: String<64> = String::new();
+    let _ = commands::write_json_reply(&mut json, None, result.as_ref().map(|_| Reply::Done).map_err(|e| e.code()));
+    let mut response: String<256> = String::new();
+    let _ = write!(
+        response,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        code,
+        reason,
+        json.len(),
+        json
+    );
+    let _ = write_all(socket, response.as_bytes()).await;
+    socket.close();
+    let _ = socket.flush().await;
+
+    if result.is_ok() {
+        ota::restart_when_idle().await;
+    }
+}
+
+/// Copy the firmware from the socket into flash (`received` = the part that
+/// arrived with the headers)
+#[cfg(feature = "ota")]
+async fn receive_firmware(socket: &mut TcpSocket<'_>, headers: &[u8], received: &[u8]) -> Result<(), UploadError> {
+    let crc = header(headers, "x-firmware-crc32").and_then(ota::parse_crc).ok_or(UploadError::MissingChecksum)?;
+    let mut upload = ota::Upload::begin(content_length(headers), crc)?;
+    upload.write(received)?;
+
+    let mut buffer = [0u8; 512];
+    while upload.remaining() > 0 {
+        match socket.read(&mut buffer).await {
+            Ok(0) | Err(_) => return Err(UploadError::Incomplete),
+            Ok(n) => upload.write(&buffer[..n])?,
+        }
+    }
+    upload.finish()
+}
+
+/// The upload page: works out the file's CRC-32 in the browser and sends it
+/// with the raw file, just like the curl example in the README
+#[cfg(feature = "ota")]
+fn write_firmware_page(response: &mut String<2048>) -> core::fmt::Result {
+    let body = "<!DOCTYPE html><html><head><meta charset=\"utf-8\">\
+         <meta name=\"viewport\" content=\"width=device-width\"><title>Firmware Update</title></head>\
+         <body style=\"font-family:sans-serif;text-align:center\"><h1>Firmware Update</h1>\
+         <p><input type=\"file\" id=\"file\" accept=\".bin\"></p><p><button onclick=\"upload()\">Upload</button></p>\
+         <p id=\"result\"></p><a href=\"/\">Back</a><script>\
+         async function upload(){\
+         let data=new Uint8Array(await file.files[0].arrayBuffer()),crc=-1;\
+         for(let b of data){crc^=b;for(let k=0;k<8;k++)crc=crc>>>1^(crc&1?0xEDB88320:0)}\
+         result.textContent='Uploading...';\
+         let r=await fetch('/firmware',{method:'POST',body:data,\
+         headers:{'X-Firmware-CRC32':((crc^-1)>>>0).toString(16)}});\
+         let j=await r.json();\
+         result.textContent=j.ok?'Done - the curer restarts into it when idle':'Failed: '+j.error}\
+         </script></body></html>";
+    write!(
+        response,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )
+}
+
+/// Position of `needle` in `haystack`
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// The Content-Length header's value (0 if missing)
+fn content_length(headers: &[u8]) -> usize {
+    header(headers, "content-length").and_then(|value| value.parse().ok()).unwrap_or(0)
+}
+
+/// A header's value, if the request has it (`name` in lower case)
+fn header<'a>(headers: &'a [u8], name: &str) -> Option<&'a str> {
+    core::str::from_utf8(headers)
+        .ok()?
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(header, _)| header.trim().eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim())
+}
+
+/// Curing time for a start request: the preset picked in the query string,
+/// or None for the normal time. Outer None = bad preset.
+fn start_seconds(query: &str) -> Option<Option<u64>> {
+    match query.strip_prefix("preset=") {
+        None => Some(None),
+        #[cfg(feature = "multi-duration")]
+        Some(preset) => {
+            let index: usize = preset.parse().ok()?;
+            DURATION_PRESETS.get(index).map(|&seconds| Some(seconds))
+        }
+        #[cfg(not(feature = "multi-duration"))]
+        Some(_) => None,
+    }
+}
+
+/// The status page
+fn write_page(response: &mut String<2048>) -> core::fmt::Result {
+    let status = remote::status();
+    let mut body: String<1536> = String::new();
+    write!(
+        body,
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\">\
+         <meta name=\"viewport\" content=\"width=device-width\">\
+         <meta http-equiv=\"refresh\" content=\"{}\"><title>UV Curer</title></head>\
+         <body style=\"font-family:sans-serif;text-align:center\"><h1>UV Resin Curer</h1>\
+         <p style=\"font-size:2em\">{}",
+        REFRESH_SECONDS,
+        status.name()
+    )?;
+    if let Some(seconds) = status.remaining_seconds() {
+        write!(body, " - {}:{:02} left", seconds / 60, seconds % 60)?;
+    }
+    if let Some(time) = status.ends_at() {
+        write!(body, " (ends {:02}:{:02})", time.hour, time.minute)?;
+    }
+    write!(body, "</p>")?;
+
+    #[cfg(any(feature = "thermistor", feature = "bme280"))]
+    if let Some(centi) = crate::sensors::chamber_centi_celsius() {
+        let sign = if centi < 0 { "-" } else { "" };
+        write!(body, "<p>Chamber {}{}.{} °C", sign, centi.abs() / 100, centi.abs() % 100 / 10)?;
+        #[cfg(feature = "bme280")]
+        if let Some(reading) = crate::climate::latest() {
+            write!(body, ", {} %RH", reading.centi_percent_rh / 100)?;
+        }
+        write!(body, "</p>")?;
+    }
+
+    #[cfg(feature = "multi-duration")]
+    for (index, (name, seconds)) in PRESET_NAMES.iter().zip(DURATION_PRESETS.iter()).enumerate() {
+        write!(
+            body,
+            "<form method=\"post\" action=\"/start?preset={}\"><button>Start {} ({} s)</button></form>",
+            index, name, seconds
+        )?;
+    }
+    #[cfg(not(feature = "multi-duration"))]
+    write!(body, "<form method=\"post\" action=\"/start\"><button>Start</button></form>")?;
+
+    write!(body, "<form method=\"post\" action=\"/stop\"><button>Abort</button></form>")?;
+    #[cfg(feature = "ota")]
+    write!(body, "<p><a href=\"/firmware\">Firmware update</a></p>")?;
+    write!(body, "</body></html>")?;
+
+    write!(
+        response,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )
+}
+
+/// 401 for a page button pressed without the API token: the browser asks
+/// for a user name and password (the token), then sends the press again
+fn log_in_page() -> String<2048> {
+    let body = "<!DOCTYPE html><html><body><p>This curer needs its API token - give it as the password</p><a href=\"/\">Back</a></body></html>";
+    let mut response = String::new();
+    let _ = write!(
+        response,
+        "HTTP/1.1 401 Unauthorized\r\nWWW-Authenticate: Basic realm=\"{}\"\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        MDNS_HOSTNAME,
+        body.len(),
+        body
+    );
+    response
+}
+
+/// A short error page with a link back
+fn error_page(code: u16, reason: &str, message: &str) -> String<2048> {
+    let mut body: String<256> = String::new();
+    let _ = write!(body, "<!DOCTYPE html><html><body><p>{}</p><a href=\"/\">Back</a></body></html>", message);
+    let mut response = String::new();
+    let _ = write!(
+        response,
+        "HTTP/1.1 {} {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        code,
+        reason,
+        body.len(),
+        body
+    );
+    response
+}
+
+/// Send the whole buffer (a TCP write may take only part of it)
+async fn write_all(socket: &mut TcpSocket<'_>, mut data: &[u8]) -> Result<(), embassy_net::tcp::Error> {
+    while !data.is_empty() {
+        let n = socket.write(data).await?;
+        data = &data[n..];
+    }
+    Ok(())
+}
@@ -0,0 +1,233 @@
+// Pico W Wi-Fi (pico-w builds)
+//
+// The Pico W has a CYW43439 Wi-Fi chip wired to the RP2040 over a PIO-driven
+// SPI bus on GPIO 23 (power), 24 (data), 25 (chip select) and 29 (clock).
+// GPIO 25 is the onboard LED on a plain Pico, so pico-w builds move the
+// status LED to GPIO 15.
+//
+// Bringing Wi-Fi up takes a few seconds (firmware upload, join, DHCP), so it
+// all happens in `wifi_task` and the curer is usable from the button the
+// whole time. Once the network is up, the web interface (web.rs) and the
+// mDNS responder (mdns.rs) start, plus the MQTT client (mqtt.rs) in mqtt
+// builds. In ota builds, getting this far also marks freshly updated
+// firmware as good (ota.rs).
+//
+// After that the task keeps watch: every LINK_CHECK_SECONDS it checks the
+// link is still up, and if the access point has gone away (or rebooted) it
+// rejoins, waiting a little longer after each failed try. The web server,
+// mDNS and MQTT just wait for the network meanwhile (MQTT reconnects to
+// the broker by itself), and nothing here ever waits on the cure - or the
+// cure on it. `is_connected()` tells the display.
+//
+// The network is the one set over USB serial (`wifi set`, saved in flash -
+// see settings.rs and storage.rs) if there is one, else the built-in
+// WIFI_SSID/WIFI_PASSWORD. Setting a new one calls `rejoin()`, which leaves
+// the old network and joins the new one straight away.
+//
+// The chip needs two firmware blobs at build time - see
+// cyw43-firmware/README.md for where to get them.
+
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use cyw43_pio::PioSpi;
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_futures::select::{select, Either};
+use embassy_net::{Config, Stack, StackResources};
+use embassy_rp::bind_interrupts;
+use embassy_rp::clocks::RoscRng;
+use embassy_rp::gpio::{Level, Output};
+use embassy_rp::peripherals::{DMA_CH0, PIN_23, PIN_24, PIN_25, PIN_29, PIO0};
+use embassy_rp::pio::{InterruptHandler, Pio};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_time::Timer;
+use heapless::String;
+use rand_core::RngCore;
+use static_cell::StaticCell;
+
+use crate::config::*;
+use crate::events::{self, Event};
+use crate::settings;
+use crate::{mdns, web};
+
+bind_interrupts!(struct Irqs {
+    PIO0_IRQ_0 => InterruptHandler<PIO0>;
+});
+
+/// How long to wait before trying to join the network again...
+const JOIN_RETRY_SECONDS: u64 = 5;
+
+/// ...doubling after each failed try, up to this
+const JOIN_RETRY_MAX_SECONDS: u64 = 60;
+
+/// How often to check the network is still there
+const LINK_CHECK_SECONDS: u64 = 5;
+
+/// On the network (joined, with an address)?
+static CONNECTED: AtomicBool = AtomicBool::new(false);
+
+/// Our IPv4 address while connected, as a big-endian number
+static ADDRESS: AtomicU32 = AtomicU32::new(0);
+
+/// Set when a new network was set, to join instead
+static REJOIN: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// The Wi-Fi chip's network driver, as used by the network stack
+pub type NetDriver = cyw43::NetDriver<'static>;
+
+/// SPI link to the Wi-Fi chip
+type WifiSpi = PioSpi<'static, PIN_25, PIO0, 0, DMA_CH0>;
+
+static WIFI_STATE: StaticCell<cyw43::State> = StaticCell::new();
+static STACK: StaticCell<Stack<NetDriver>> = StaticCell::new();
+
+/// Sockets the network stack needs: DHCP, the web server, mDNS, and MQTT if built in
+const SOCKETS: usize = if cfg!(feature = "mqtt") { 4 } else { 3 };
+static RESOURCES: StaticCell<StackResources<SOCKETS>> = StaticCell::new();
+
+/// Bring up Wi-Fi and the network, then start the web interface, mDNS (and MQTT)
+#[embassy_executor::task]
+pub async fn wifi_task(
+    spawner: Spawner,
+    pwr_pin: PIN_23,
+    dio_pin: PIN_24,
+    cs_pin: PIN_25,
+    clk_pin: PIN_29,
+    pio: PIO0,
+    dma: DMA_CH0,
+) {
+    let firmware = include_bytes!("../cyw43-firmware/43439A0.bin");
+    let clm = include_bytes!("../cyw43-firmware/43439A0_clm.bin");
+
+    let pwr = Output::new(pwr_pin, Level::Low);
+    let cs = Output::new(cs_pin, Level::High);
+    let mut pio = Pio::new(pio, Irqs);
+    let spi = PioSpi::new(&mut pio.common, pio.sm0, pio.irq0, cs, dio_pin, clk_pin, dma);
+
+    let state = WIFI_STATE.init(cyw43::State::new());
+    let (net_device, mut control, runner) = cyw43::new(state, pwr, spi, firmware).await;
+    unwrap!(spawner.spawn(cyw43_task(runner)));
+
+    control.init(clm).await;
+    control.set_power_management(cyw43::PowerManagementMode::PowerSave).await;
+    // The chip drops multicast frames unless told otherwise - let mDNS through
+    if control.add_multicast_address(mdns::MDNS_MAC).await.is_err() {
+        warn!("Wi-Fi: can't enable mDNS multicast");
+    }
+
+    // DHCP gives us an address; the seed randomises TCP port/sequence numbers
+    let seed = RoscRng.next_u64();
+    let stack = &*STACK.init(Stack::new(
+        net_device,
+        Config::dhcpv4(Default::default()),
+        RESOURCES.init(StackResources::new()),
+        seed,
+    ));
+    unwrap!(spawner.spawn(net_task(stack)));
+
+    join(&mut control, stack).await;
+
+    // Wi-Fi works, so new firmware can be uploaded again if need be - tell
+    // the bootloader to keep this one (ota builds, see ota.rs)
+    #[cfg(feature = "ota")]
+    crate::ota::mark_booted();
+
+    unwrap!(spawner.spawn(web::web_server_task(stack)));
+    unwrap!(spawner.spawn(mdns::mdns_task(stack)));
+    #[cfg(feature = "mqtt")]
+    unwrap!(spawner.spawn(crate::mqtt::mqtt_task(stack)));
+
+    // From now on, rejoin whenever the network goes away, or a new one is set
+    loop {
+        match select(Timer::after_secs(LINK_CHECK_SECONDS), REJOIN.wait()).await {
+            Either::First(_) if stack.is_link_up() => continue,
+            Either::First(_) => events::record(Event::WifiLost),
+            Either::Second(_) => control.leave().await,
+        }
+        CONNECTED.store(false, Ordering::Relaxed);
+        ADDRESS.store(0, Ordering::Relaxed);
+        let attempts = join(&mut control, stack).await;
+        events::record(Event::WifiJoined { attempts });
+    }
+}
+
+/// Join the network, trying until it works and DHCP has given us an
+/// address - returns how many tries it took. A new network set meanwhile
+/// is tried straight away.
+async fn join(control: &mut cyw43::Control<'static>, stack: &Stack<NetDriver>) -> u32 {
+    let mut attempts = 1;
+    let mut wait_seconds = JOIN_RETRY_SECONDS;
+    loop {
+        let saved = settings::wifi_credentials();
+        let (ssid, password) = match saved.is_empty() {
+            true => (WIFI_SSID, WIFI_PASSWORD),
+            false => (saved.ssid(), saved.password()),
+        };
+        if ssid.is_empty() {
+            warn!("Wi-Fi: no network set - use 'wifi set <name> <password>' over USB serial");
+            REJOIN.wait().await;
+            continue;
+        }
+
+        info!("Wi-Fi: joining '{}'...", ssid);
+        match control.join_wpa2(ssid, password).await {
+            Ok(()) => break,
+            Err(e) => warn!("Wi-Fi: join failed (status {}) - retrying in {} s", e.status, wait_seconds),
+        }
+        match select(Timer::after_secs(wait_seconds), REJOIN.wait()).await {
+            Either::First(_) => wait_seconds = (wait_seconds * 2).min(JOIN_RETRY_MAX_SECONDS),
+            Either::Second(_) => wait_seconds = JOIN_RETRY_SECONDS,   // A new network - start again
+        }
+        attempts += 1;
+    }
+
+    stack.wait_config_up().await;
+    if let Some(config) = stack.config_v4() {
+        info!("Wi-Fi: connected - web interface at http://{}/", config.address.address());
+        ADDRESS.store(u32::from_be_bytes(config.address.address().0), Ordering::Relaxed);
+    }
+    CONNECTED.store(true, Ordering::Relaxed);
+    attempts
+}
+
+/// Leave the network and join the one now set (call after
+/// `settings::set_wifi_credentials`)
+#[cfg_attr(not(feature = "usb-serial"), allow(dead_code))]  // Only set over USB serial
+pub fn rejoin() {
+    REJOIN.signal(());
+}
+
+/// The network being used: the one set over USB serial, or the built-in one
+#[cfg_attr(not(feature = "usb-serial"), allow(dead_code))]
+pub fn network_name() -> String<{ settings::MAX_SSID_LENGTH }> {
+    let saved = settings::wifi_credentials();
+    let mut name = String::new();
+    let _ = name.push_str(if saved.is_empty() { WIFI_SSID } else { saved.ssid() });
+    name
+}
+
+/// Our IPv4 address, while connected
+#[cfg_attr(not(feature = "usb-serial"), allow(dead_code))]
+pub fn address() -> Option<[u8; 4]> {
+    let address = ADDRESS.load(Ordering::Relaxed);
+    (address != 0).then(|| address.to_be_bytes())
+}
+
+/// On the network right now?
+#[cfg_attr(not(any(feature = "tm1637", feature = "hd44780")), allow(dead_code))]  // Only the display asks
+pub fn is_connected() -> bool {
+    CONNECTED.load(Ordering::Relaxed)
+}
+
+/// Talks to the Wi-Fi chip (never returns)
+#[embassy_executor::task]
+async fn cyw43_task(runner: cyw43::Runner<'static, Output<'static, PIN_23>, WifiSpi>) -> ! {
+    runner.run().await
+}
+
+/// Runs the network stack (never returns)
+#[embassy_executor::task]
+async fn net_task(stack: &'static Stack<NetDriver>) -> ! {
+    stack.run().await
+}